@@ -0,0 +1,215 @@
+//! Expansion of command aliases from the `[aliases]` section of `cli.toml`
+//!
+//! Alias is a command-line which replaces the alias name. It may refer to
+//! the arguments following the alias name as `$1`, `$2` ... or `$@` (all
+//! of them), if there are no such references arguments are appended.
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use clap::{App, ArgSettings, IntoApp};
+
+use crate::config;
+use crate::options::RawOptions;
+use crate::typos;
+
+
+fn takes_value(app: &App, name: &OsString) -> bool {
+    let name = name.to_string_lossy();
+    let arg = if let Some(long) = name.strip_prefix("--") {
+        if long.contains('=') {
+            return false;
+        }
+        app.get_arguments().find(|a| a.get_long() == Some(long))
+    } else {
+        // for clustered short flags `-abc` only the last one may have
+        // a separate value
+        let last = match name.chars().last() {
+            Some(c) => c,
+            None => return false,
+        };
+        if name.chars().skip(1).take_while(|c| *c != last).any(|c| {
+            app.get_arguments().any(|a| a.get_short() == Some(c)
+                && a.is_set(ArgSettings::TakesValue))
+        }) {
+            return false;
+        }
+        app.get_arguments().find(|a| a.get_short() == Some(last))
+    };
+    arg.map(|a| a.is_set(ArgSettings::TakesValue)).unwrap_or(false)
+}
+
+/// Returns position of the subcommand name, skipping global options
+pub fn subcommand_pos(app: &App, args: &[OsString]) -> Option<usize> {
+    let mut iter = args.iter().enumerate().skip(1);
+    while let Some((idx, arg)) = iter.next() {
+        if arg == "--" {
+            return None;
+        }
+        let is_option = arg.to_str()
+            .map(|a| a.starts_with('-') && a != "-")
+            .unwrap_or(false);
+        if !is_option {
+            return Some(idx);
+        }
+        if takes_value(app, arg) {
+            iter.next();
+        }
+    }
+    None
+}
+
+fn arg_str(arg: &OsString) -> anyhow::Result<&str> {
+    arg.to_str().ok_or_else(|| {
+        anyhow::anyhow!("alias argument {:?} is not valid unicode", arg)
+    })
+}
+
+/// Replaces placeholders in the alias command-line by the arguments
+fn substitute(name: &str, template: &str, args: &[OsString])
+    -> anyhow::Result<Vec<OsString>>
+{
+    let words = shell_words::split(template).map_err(|e| {
+        anyhow::anyhow!("cannot parse alias {:?}: {}", name, e)
+    })?;
+    let mut output = Vec::with_capacity(words.len() + args.len());
+    let mut used = vec![false; args.len()];
+    let mut has_placeholders = false;
+    for word in words {
+        if word == "$@" {
+            has_placeholders = true;
+            used.iter_mut().for_each(|u| *u = true);
+            output.extend(args.iter().cloned());
+            continue;
+        }
+        if !word.contains('$') {
+            output.push(word.into());
+            continue;
+        }
+        let mut result = String::with_capacity(word.len());
+        let mut whole = None;
+        let mut chars = word.char_indices().peekable();
+        while let Some((pos, c)) = chars.next() {
+            if c != '$' {
+                result.push(c);
+                continue;
+            }
+            let mut end = pos + 1;
+            while chars.peek().map(|(_, d)| d.is_ascii_digit())
+                .unwrap_or(false)
+            {
+                end += 1;
+                chars.next();
+            }
+            if end == pos + 1 {
+                result.push(c);
+                continue;
+            }
+            let num: usize = word[pos+1..end].parse()?;
+            let arg = num.checked_sub(1).and_then(|idx| args.get(idx))
+                .ok_or_else(|| anyhow::anyhow!(
+                    "alias {:?} requires at least {} argument(s)",
+                    name, num))?;
+            has_placeholders = true;
+            used[num-1] = true;
+            if pos == 0 && end == word.len() {
+                whole = Some(arg.clone());
+            } else {
+                result.push_str(arg_str(arg)?);
+            }
+        }
+        output.push(whole.unwrap_or_else(|| result.into()));
+    }
+    if has_placeholders {
+        if let Some(idx) = used.iter().position(|u| !*u) {
+            anyhow::bail!("unexpected argument {:?} for alias {:?}",
+                          args[idx], name);
+        }
+    } else {
+        output.extend(args.iter().cloned());
+    }
+    Ok(output)
+}
+
+fn expand_with(args: Vec<OsString>, aliases: &BTreeMap<String, String>)
+    -> anyhow::Result<Vec<OsString>>
+{
+    let app = RawOptions::into_app();
+    let pos = match subcommand_pos(&app, &args) {
+        Some(pos) => pos,
+        None => return Ok(args),
+    };
+    let name = match args[pos].to_str() {
+        Some(name) => name,
+        None => return Ok(args),
+    };
+    let template = match aliases.get(name) {
+        Some(template) => template,
+        None => return Ok(args),
+    };
+    let expanded = substitute(name, template, &args[pos+1..])?;
+    let mut result = args[..pos].to_vec();
+    result.extend(expanded);
+    Ok(result)
+}
+
+/// Expands an alias if the subcommand is not one of the built-in ones
+///
+/// Mistyped subcommands are corrected first, if the user confirms.
+pub fn expand(args: Vec<OsString>) -> anyhow::Result<Vec<OsString>> {
+    let app = RawOptions::into_app();
+    let builtin = subcommand_pos(&app, &args)
+        .and_then(|pos| args[pos].to_str())
+        .map(|name| {
+            app.get_subcommands().any(|cmd| cmd.get_name() == name)
+        })
+        .unwrap_or(true);
+    if builtin {
+        return Ok(typos::fix_command(args, &BTreeMap::new()));
+    }
+    // warnings are reported once options are parsed
+    let config = config::get_config();
+    let args = typos::fix_command(args, &config.aliases);
+    expand_with(args, &config.aliases)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::ffi::OsString;
+
+    use super::{expand_with, substitute};
+
+    fn args(items: &[&str]) -> Vec<OsString> {
+        items.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(substitute("mig", "migration create --non-interactive",
+                              &args(&["--allow-empty"])).unwrap(),
+                   args(&["migration", "create", "--non-interactive",
+                          "--allow-empty"]));
+        assert_eq!(substitute("q", "query 'SELECT $1' --db=$2",
+                              &args(&["User", "main"])).unwrap(),
+                   args(&["query", "SELECT User", "--db=main"]));
+        assert_eq!(substitute("all", "query $@",
+                              &args(&["1", "2"])).unwrap(),
+                   args(&["query", "1", "2"]));
+        assert!(substitute("q", "query $2", &args(&["1"])).is_err());
+        assert!(substitute("q", "query $1", &args(&["1", "2"])).is_err());
+    }
+
+    #[test]
+    fn global_options() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert("mig".to_string(),
+                       "migration create --non-interactive".to_string());
+        assert_eq!(expand_with(args(&["edgedb", "-I", "inst", "mig"]),
+                               &aliases).unwrap(),
+                   args(&["edgedb", "-I", "inst", "migration", "create",
+                          "--non-interactive"]));
+        assert_eq!(expand_with(args(&["edgedb", "-c", "mig"]),
+                               &aliases).unwrap(),
+                   args(&["edgedb", "-c", "mig"]));
+    }
+}
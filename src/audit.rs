@@ -0,0 +1,205 @@
+//! Local log of operations which modify instances or databases
+//!
+//! Inside of a project the log is stored in the project's directory in
+//! the config dir (so each project has its own log), otherwise in the
+//! global `audit.jsonl`. The log is only appended to.
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{Write, BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use once_cell::sync::Lazy;
+use prettytable::{Table, Row, Cell};
+use serde::{Serialize, Deserialize};
+
+use crate::commands;
+use crate::dry_run;
+use crate::options::{Options, Command, Log};
+use crate::platform::config_dir;
+use crate::project;
+use crate::server;
+use crate::table;
+use crate::testing;
+
+
+static REVISION: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Record {
+    #[serde(with="humantime_serde")]
+    timestamp: SystemTime,
+    user: String,
+    command: String,
+    instance: Option<String>,
+    revision: Option<String>,
+}
+
+fn log_path() -> anyhow::Result<PathBuf> {
+    if let Some(project_dir) = project::project_dir_opt(None)? {
+        let stash = project::stash_path(&project_dir)?;
+        if stash.exists() {
+            return Ok(stash.join("audit.jsonl"));
+        }
+    }
+    Ok(config_dir()?.join("audit.jsonl"))
+}
+
+/// Records migration revision to be saved with the current operation
+pub fn revision_applied(revision: &str) {
+    *REVISION.lock().unwrap() = Some(revision.into());
+}
+
+/// Returns instance name if command modifies an instance or database
+fn mutated_instance(cmd: &Command) -> Option<Option<&str>> {
+    use server::options::Command as Server;
+    use project::options::Command as Project;
+    use testing::options::Command as Test;
+    use testing::options::FixtureAction;
+
+    match cmd {
+        Command::Common(cmd) => commands::mutating_command(cmd)
+            .map(|_| None),
+        Command::CreateSuperuserRole(_) | Command::AlterRole(_)
+        | Command::DropRole(_) | Command::WipeDatabase(_) => Some(None),
+        Command::Server(cmd) => match &cmd.subcommand {
+            Server::Init(c) => Some(Some(&c.name)),
+            Server::Destroy(c) => Some(Some(&c.name)),
+            Server::Upgrade(c) => Some(c.name.as_deref()),
+            Server::SetPort(c) => Some(Some(&c.name)),
+            Server::SetListenAddress(c) => Some(Some(&c.name)),
+            Server::ResetPassword(c) => Some(Some(&c.name)),
+            _ => None,
+        },
+        Command::Project(cmd) => match &cmd.subcommand {
+            Project::Init(_) | Project::Unlink(_) | Project::Relink(_)
+            => Some(None),
+            // only prints or writes files, no instance state is changed
+            Project::GenerateCi(_) | Project::GenerateCompose(_) => None,
+        },
+        Command::Test(cmd) => match &cmd.subcommand {
+            Test::Fixture(f) => match f.subcommand {
+                FixtureAction::Reset(_) => Some(None),
+                FixtureAction::Save(_) => None,
+            },
+            Test::Snapshots(_) => None,
+        },
+        Command::Insert(_) | Command::Update(_) | Command::Delete(_)
+        | Command::Queries(_) | Command::Undo(_) => Some(None),
+        _ => None,
+    }
+}
+
+fn command_line() -> String {
+    let mut args = Vec::new();
+    let mut hide_next = false;
+    for arg in env::args_os().skip(1).map(OsString::into_string) {
+        let arg = arg.unwrap_or_else(|a| a.to_string_lossy().into());
+        if hide_next {
+            args.push("<hidden>".into());
+            hide_next = false;
+        } else if arg.starts_with("--dsn=") {
+            args.push("--dsn=<hidden>".into());
+        } else {
+            hide_next = arg == "--dsn";
+            args.push(arg);
+        }
+    }
+    shell_words::join(&args)
+}
+
+fn append(record: &Record) -> anyhow::Result<()> {
+    let path = log_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    fs::OpenOptions::new().create(true).append(true).open(&path)
+        .and_then(|mut f| f.write_all(&line))
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(())
+}
+
+/// Appends the command to the audit log if it's a mutating one
+pub fn record(options: &Options) {
+    let cmd = match &options.subcommand {
+        Some(_) if dry_run::is_enabled() => return,
+        Some(cmd) => cmd,
+        None => return,
+    };
+    let instance = match mutated_instance(cmd) {
+        Some(instance) => instance,
+        None => return,
+    };
+    let instance = instance.map(|name| name.to_string()).or_else(|| {
+        options.conn_sources.iter()
+            .find(|s| s.name == "instance" || s.name == "address")
+            .map(|s| s.value.trim().to_string())
+    });
+    let record = Record {
+        timestamp: SystemTime::now(),
+        user: whoami::username(),
+        command: command_line(),
+        instance,
+        revision: REVISION.lock().unwrap().take(),
+    };
+    if let Err(e) = append(&record) {
+        log::warn!("Cannot write audit log: {:#}", e);
+    }
+}
+
+pub fn print_log(cmd: &Log) -> anyhow::Result<()> {
+    let path = log_path()?;
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("No operations recorded yet");
+            return Ok(());
+        }
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!("cannot read {}", path.display())
+            });
+        }
+    };
+    let mut records = Vec::new();
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let record: Record = serde_json::from_str(&line?)
+            .with_context(|| format!("{}:{}: invalid record",
+                                     path.display(), idx + 1))?;
+        records.push(record);
+    }
+    if let Some(limit) = cmd.limit {
+        let skip = records.len().saturating_sub(limit);
+        records.drain(..skip);
+    }
+    if cmd.json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    table.add_row(Row::new(vec![
+        table::header_cell("Time"),
+        table::header_cell("User"),
+        table::header_cell("Instance"),
+        table::header_cell("Revision"),
+        table::header_cell("Command"),
+    ]));
+    for record in records {
+        table.add_row(Row::new(vec![
+            Cell::new(&humantime::format_rfc3339_seconds(record.timestamp)
+                      .to_string()),
+            Cell::new(&record.user),
+            Cell::new(record.instance.as_deref().unwrap_or("")),
+            Cell::new(record.revision.as_deref().unwrap_or("")),
+            Cell::new(&record.command),
+        ]));
+    }
+    table.printstd();
+    Ok(())
+}
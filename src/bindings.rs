@@ -0,0 +1,215 @@
+//! Variables of the interactive shell set from query results by `\gset`
+//!
+//! Values are stored as EdgeQL literals of their type. A reference to the
+//! variable (`:name`) is replaced by the literal when the query is sent,
+//! references in strings and comments are left intact.
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use bigdecimal::BigDecimal;
+use edgedb_protocol::value::Value;
+use edgeql_parser::helpers::quote_string;
+use edgeql_parser::tokenizer::{TokenStream, Kind, SpannedToken};
+use num_bigint::BigInt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+
+static NAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap()
+});
+
+pub type Variables = BTreeMap<String, String>;
+
+fn typed(type_name: &str, text: &str) -> String {
+    format!("<{}>{}", type_name, quote_string(text))
+}
+
+fn items(items: &[Value]) -> anyhow::Result<Vec<String>> {
+    items.iter().map(literal).collect()
+}
+
+/// EdgeQL expression which evaluates to the value
+pub fn literal(value: &Value) -> anyhow::Result<String> {
+    use Value as V;
+    let text = match value {
+        V::Nothing => "{}".into(),
+        V::Uuid(u) => typed("uuid", &u.to_string()),
+        V::Str(s) => quote_string(s),
+        V::Bytes(b) => {
+            let escaped = b.iter()
+                .map(|b| format!("\\x{:02x}", b))
+                .collect::<String>();
+            format!("b'{}'", escaped)
+        }
+        V::Int16(v) => typed("int16", &v.to_string()),
+        V::Int32(v) => typed("int32", &v.to_string()),
+        V::Int64(v) => typed("int64", &v.to_string()),
+        V::Float32(v) => typed("float32", &v.to_string()),
+        V::Float64(v) => typed("float64", &v.to_string()),
+        V::BigInt(v) => {
+            let v: BigInt = v.into();
+            typed("bigint", &v.to_string())
+        }
+        V::Decimal(v) => {
+            let v: BigDecimal = v.into();
+            typed("decimal", &v.to_string())
+        }
+        V::Bool(v) => v.to_string(),
+        V::Datetime(t) => typed("datetime", &format!("{:?}", t)),
+        V::LocalDatetime(t) => {
+            typed("cal::local_datetime", &format!("{:?}", t))
+        }
+        V::LocalDate(d) => typed("cal::local_date", &format!("{:?}", d)),
+        V::LocalTime(t) => typed("cal::local_time", &format!("{:?}", t)),
+        V::Duration(d) => typed("duration", &d.to_string()),
+        V::Json(d) => format!("to_json({})", quote_string(d)),
+        // enum type isn't known, the string is cast implicitly
+        // in comparisons
+        V::Enum(v) => quote_string(v),
+        V::Set(values) => format!("{{{}}}", items(values)?.join(", ")),
+        V::Array(values) if values.is_empty() => {
+            anyhow::bail!("type of an empty array is unknown");
+        }
+        V::Array(values) => format!("[{}]", items(values)?.join(", ")),
+        V::Tuple(values) if values.len() == 1 => {
+            format!("({},)", literal(&values[0])?)
+        }
+        V::Tuple(values) => format!("({})", items(values)?.join(", ")),
+        V::NamedTuple { shape, fields } => {
+            let fields = shape.elements.iter().zip(fields)
+                .map(|(el, value)| {
+                    Ok(format!("{} := {}", el.name, literal(value)?))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            format!("({})", fields.join(", "))
+        }
+        V::Object { .. } => {
+            anyhow::bail!("nested objects can't be stored, \
+                           select their properties instead");
+        }
+    };
+    Ok(text)
+}
+
+/// Variables for the single row of the query result
+///
+/// Fields of objects and named tuples are stored as `NAME_FIELD`, other
+/// values are stored as `NAME`.
+pub fn bind(name: &str, row: &Value)
+    -> anyhow::Result<Vec<(String, String)>>
+{
+    if !NAME.is_match(name) {
+        anyhow::bail!("invalid variable name {:?}", name);
+    }
+    match row {
+        Value::Object { shape, fields } => {
+            let mut vars = Vec::with_capacity(fields.len());
+            for (el, value) in shape.elements.iter().zip(fields) {
+                if el.flag_link_property || el.name.starts_with("__") {
+                    continue;
+                }
+                let text = match value {
+                    Some(value) => literal(value)?,
+                    None => "{}".into(),
+                };
+                vars.push((format!("{}_{}", name, el.name), text));
+            }
+            Ok(vars)
+        }
+        Value::NamedTuple { shape, fields } => {
+            shape.elements.iter().zip(fields)
+                .map(|(el, value)| {
+                    Ok((format!("{}_{}", name, el.name), literal(value)?))
+                })
+                .collect()
+        }
+        _ => Ok(vec![(name.into(), literal(row)?)]),
+    }
+}
+
+fn is_name(token: &SpannedToken) -> bool {
+    matches!(token.token.kind, Kind::Ident | Kind::Keyword)
+}
+
+/// Replaces references to the known variables by their values
+///
+/// Unknown references are kept, so that the server reports them.
+pub fn interpolate<'x>(text: &'x str, vars: &Variables) -> Cow<'x, str> {
+    if vars.is_empty() || !text.contains(':') {
+        return text.into();
+    }
+    let tokens = TokenStream::new(text).collect::<Result<Vec<_>, _>>();
+    let tokens = match tokens {
+        Ok(tokens) => tokens,
+        // syntax errors are reported by the server
+        Err(_) => return text.into(),
+    };
+    let mut output = String::with_capacity(text.len());
+    let mut start = 0;
+    for pair in tokens.windows(2) {
+        let (colon, name) = (&pair[0], &pair[1]);
+        if colon.token.kind != Kind::Colon || !is_name(name)
+            || colon.end.offset != name.start.offset
+        {
+            continue;
+        }
+        if let Some(value) = vars.get(name.token.value) {
+            output.push_str(&text[start..colon.start.offset as usize]);
+            output.push_str(value);
+            start = name.end.offset as usize;
+        }
+    }
+    if start == 0 {
+        return text.into();
+    }
+    output.push_str(&text[start..]);
+    output.into()
+}
+
+#[cfg(test)]
+mod test {
+    use edgedb_protocol::value::Value;
+
+    use super::{bind, interpolate, literal, Variables};
+
+    fn vars() -> Variables {
+        let mut vars = Variables::new();
+        vars.insert("name".into(), "'Alice'".into());
+        vars.insert("n".into(), "<int64>'7'".into());
+        vars
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(literal(&Value::Int16(-5)).unwrap(), "<int16>'-5'");
+        assert_eq!(literal(&Value::Bool(true)).unwrap(), "true");
+        assert_eq!(literal(&Value::Bytes(b"a'".to_vec().into())).unwrap(),
+                   r"b'\x61\x27'");
+        assert_eq!(literal(&Value::Array(vec![
+                       Value::Int64(1), Value::Int64(2),
+                   ])).unwrap(),
+                   "[<int64>'1', <int64>'2']");
+        assert_eq!(literal(&Value::Tuple(vec![Value::Bool(false)])).unwrap(),
+                   "(false,)");
+        assert!(literal(&Value::Array(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn bind_scalar() {
+        assert_eq!(bind("x", &Value::Int64(1)).unwrap(),
+                   vec![("x".to_string(), "<int64>'1'".to_string())]);
+        assert!(bind("1x", &Value::Int64(1)).is_err());
+    }
+
+    #[test]
+    fn references() {
+        assert_eq!(interpolate("SELECT User FILTER .name = :name", &vars()),
+                   "SELECT User FILTER .name = 'Alice'");
+        assert_eq!(interpolate("SELECT ':name' ++ :other", &vars()),
+                   "SELECT ':name' ++ :other");
+        assert_eq!(interpolate("SELECT User { name := :name }", &vars()),
+                   "SELECT User { name := 'Alice' }");
+        assert_eq!(interpolate("SELECT x: n", &vars()), "SELECT x: n");
+    }
+}
@@ -0,0 +1,227 @@
+//! Rough classification of EdgeQL statements by their keywords
+//!
+//! This works on tokens only and is used for client-side safety checks,
+//! it's not a replacement for access control on the server.
+//!
+//! The read-only mode of the process is also kept here, so every path
+//! sending statements checks the same flag.
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use edgeql_parser::tokenizer::{TokenStream, Kind};
+use once_cell::sync::OnceCell;
+
+use crate::hint::HintExt;
+
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+/// Set by `--read-only`, the mode can't be turned off in this case
+static FORCED: OnceCell<bool> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Danger {
+    UnfilteredDelete,
+    UnfilteredUpdate,
+    Drop,
+}
+
+/// Reserved keywords which start or are a part of mutating statements
+const MUTATING_KEYWORDS: &[&str] = &[
+    "insert", "update", "delete", "create", "alter", "drop", "populate",
+];
+
+/// Returns lowercase keywords and identifiers of the statement
+fn words(statement: &str) -> Vec<(Kind, String)> {
+    let mut result = Vec::new();
+    for token in TokenStream::new(statement) {
+        match token {
+            Ok(tok) => match tok.token.kind {
+                kind @ Kind::Keyword | kind @ Kind::Ident => {
+                    result.push((kind, tok.token.value.to_lowercase()));
+                }
+                _ => {}
+            },
+            // the server will report the error
+            Err(_) => break,
+        }
+    }
+    result
+}
+
+/// Returns lowercase reserved keywords of the statement
+fn keywords(statement: &str) -> Vec<String> {
+    words(statement).into_iter()
+        .filter(|(kind, _)| *kind == Kind::Keyword)
+        .map(|(_, word)| word)
+        .collect()
+}
+
+/// Detects statements which modify data, schema or configuration
+///
+/// Session-level statements (`SET ALIAS`, `CONFIGURE SESSION`,
+/// transaction control) are not considered mutating.
+pub fn is_mutating(statement: &str) -> bool {
+    let words = words(statement);
+    for (idx, (kind, word)) in words.iter().enumerate() {
+        if *kind != Kind::Keyword {
+            continue;
+        }
+        let next = words.get(idx+1).map(|(_, w)| &w[..]);
+        match &word[..] {
+            w if MUTATING_KEYWORDS.contains(&w) => return true,
+            "configure" if next != Some("session") => return true,
+            "start" | "commit" if next == Some("migration") => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Detects `START TRANSACTION` without any transaction modes
+pub fn is_plain_start_transaction(statement: &str) -> bool {
+    let words = words(statement);
+    words.len() == 2 && words[0].1 == "start" && words[1].1 == "transaction"
+}
+
+/// Detects statements which control the transaction, e.g. `COMMIT`
+pub fn is_transaction_control(statement: &str) -> bool {
+    let words = words(statement);
+    let first = words.get(0).map(|(_, w)| &w[..]);
+    let second = words.get(1).map(|(_, w)| &w[..]);
+    match first {
+        Some("start") => second == Some("transaction"),
+        Some("commit") | Some("rollback") => second != Some("migration"),
+        Some("declare") | Some("release") => second == Some("savepoint"),
+        _ => false,
+    }
+}
+
+/// Returns an error if the statement is mutating
+pub fn check_read_only(statement: &str) -> anyhow::Result<()> {
+    if is_mutating(statement) {
+        return Err(anyhow::anyhow!(
+            "statement modifies data, schema or configuration, \
+             but the session is read-only"))
+            .hint("Run without `--read-only` to allow it")?;
+    }
+    Ok(())
+}
+
+pub fn init_read_only(enabled: bool, forced: bool) {
+    READ_ONLY.store(enabled || forced, Ordering::SeqCst);
+    FORCED.set(forced).ok();
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::SeqCst)
+}
+
+/// Changes the read-only mode, for `\set read-only`
+pub fn set_read_only(enabled: bool) -> anyhow::Result<()> {
+    if !enabled && FORCED.get().copied().unwrap_or(false) {
+        anyhow::bail!("the session was started with `--read-only`, \
+                       it can't be turned off");
+    }
+    READ_ONLY.store(enabled, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Returns an error if the session is read-only and the statement is
+/// mutating
+pub fn guard(statement: &str) -> anyhow::Result<()> {
+    if is_read_only() {
+        check_read_only(statement)?;
+    }
+    Ok(())
+}
+
+/// Returns an error if the session is read-only, for commands which
+/// always modify the database
+pub fn guard_command(name: &str) -> anyhow::Result<()> {
+    if is_read_only() {
+        return Err(anyhow::anyhow!(
+            "`{}` modifies the database, but the session is read-only",
+            name))
+            .hint("Run without `--read-only` to allow it")?;
+    }
+    Ok(())
+}
+
+/// Detects statements which delete or modify a lot of data
+pub fn danger(statement: &str) -> Option<Danger> {
+    let keywords = keywords(statement);
+    let has = |kw: &str| keywords.iter().any(|k| k == kw);
+    if has("drop") {
+        Some(Danger::Drop)
+    } else if has("delete") && !has("filter") {
+        Some(Danger::UnfilteredDelete)
+    } else if has("update") && !has("filter") {
+        Some(Danger::UnfilteredUpdate)
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for Danger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Danger::UnfilteredDelete => {
+                "Statement deletes objects without a FILTER clause.".fmt(f)
+            }
+            Danger::UnfilteredUpdate => {
+                "Statement updates objects without a FILTER clause.".fmt(f)
+            }
+            Danger::Drop => "Statement drops part of the schema.".fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{danger, is_mutating, is_plain_start_transaction, Danger};
+    use super::is_transaction_control;
+
+    #[test]
+    fn dangerous() {
+        assert_eq!(danger("DELETE User"), Some(Danger::UnfilteredDelete));
+        assert_eq!(danger("delete User filter .name = 'x'"), None);
+        assert_eq!(danger("UPDATE User SET { name := 'x' }"),
+                   Some(Danger::UnfilteredUpdate));
+        assert_eq!(danger("DROP TYPE User"), Some(Danger::Drop));
+        assert_eq!(danger("SELECT User { name }"), None);
+        assert_eq!(danger("SELECT 'delete'"), None);
+    }
+
+    #[test]
+    fn mutating() {
+        assert!(is_mutating("INSERT User { name := 'x' }"));
+        assert!(is_mutating("WITH x := 1 SELECT (UPDATE User SET {})"));
+        assert!(is_mutating("CREATE TYPE Foo"));
+        assert!(is_mutating("CONFIGURE SYSTEM SET listen_port := 1"));
+        assert!(is_mutating("START MIGRATION TO {}"));
+        assert!(!is_mutating("CONFIGURE SESSION SET x := '1MB'"));
+        assert!(!is_mutating("SELECT User FILTER .name = 'insert'"));
+        assert!(!is_mutating("START TRANSACTION"));
+        assert!(!is_mutating("SET ALIAS foo AS MODULE default"));
+    }
+
+    #[test]
+    fn plain_start_transaction() {
+        assert!(is_plain_start_transaction("START TRANSACTION"));
+        assert!(is_plain_start_transaction("start transaction;"));
+        assert!(!is_plain_start_transaction(
+            "START TRANSACTION ISOLATION SERIALIZABLE"));
+        assert!(!is_plain_start_transaction("START MIGRATION TO {}"));
+    }
+
+    #[test]
+    fn transaction_control() {
+        assert!(is_transaction_control("START TRANSACTION"));
+        assert!(is_transaction_control("commit;"));
+        assert!(is_transaction_control("ROLLBACK TO SAVEPOINT x"));
+        assert!(is_transaction_control("DECLARE SAVEPOINT x"));
+        assert!(!is_transaction_control("COMMIT MIGRATION"));
+        assert!(!is_transaction_control("START MIGRATION TO {}"));
+        assert!(!is_transaction_control("SELECT 'commit'"));
+    }
+}
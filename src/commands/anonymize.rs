@@ -0,0 +1,408 @@
+//! Rewriting of dump data blocks according to masking rules
+//!
+//! Data blocks contain rows in PostgreSQL binary `COPY` format, columns
+//! are described by the object shape descriptor stored in the dump header.
+//! Only tables of object types are rewritten, multi properties and link
+//! tables are passed through as is.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use bytes::Bytes;
+use edgedb_protocol::codec;
+use edgedb_protocol::descriptors::{Descriptor, TypePos};
+use edgedb_protocol::encoding::Decode;
+use edgeql_parser::preparser::full_statement;
+use edgeql_parser::tokenizer::Kind;
+use fn_error_context::context;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use serde::Deserialize;
+use sha2::{Sha256, Digest};
+use uuid::Uuid;
+
+use crate::commands::dump_format::{Reader, BLOCK_DATA, write_sized};
+use crate::commands::dump_format::{block_attributes, block_id};
+use crate::commands::remap::{tokenize, ident_value, statement_type};
+
+
+const COPY_SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all="kebab-case")]
+pub enum Action {
+    /// Replace the value by its (stable) hash
+    Hash,
+    /// Replace the value by `<hash>@example.com`
+    Email,
+    /// Replace the value by a random string of the same length
+    Random,
+    /// Replace the value by an empty set
+    Null,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(rename="type")]
+    pub type_name: String,
+    pub property: String,
+    pub action: Action,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(rename="rule", default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+pub struct Anonymizer {
+    /// Action for each column of the tables which are rewritten
+    tables: HashMap<Uuid, Vec<Option<Action>>>,
+}
+
+#[context("error reading anonymization rules {}", path.display())]
+pub fn read_rules(path: &Path) -> anyhow::Result<Rules> {
+    let text = fs::read_to_string(path)?;
+    let mut toml = toml::de::Deserializer::new(&text);
+    Ok(serde_path_to_error::deserialize(&mut toml)?)
+}
+
+fn is_str(descriptors: &[Descriptor], pos: TypePos) -> bool {
+    match descriptors.get(pos.0 as usize) {
+        Some(Descriptor::BaseScalar(s)) => s.id == codec::STD_STR,
+        Some(Descriptor::Scalar(s)) => is_str(descriptors, s.base_type_pos),
+        _ => false,
+    }
+}
+
+fn decode_descriptors(data: &[u8]) -> anyhow::Result<Vec<Descriptor>> {
+    let mut cur = Cursor::new(Bytes::copy_from_slice(data));
+    let mut descriptors = Vec::new();
+    while (cur.position() as usize) < data.len() {
+        descriptors.push(Descriptor::decode(&mut cur)?);
+    }
+    Ok(descriptors)
+}
+
+/// Required pointers and bases of an object type, from the schema DDL
+#[derive(Debug, Default)]
+struct TypeInfo {
+    required: HashSet<String>,
+    bases: Vec<String>,
+}
+
+fn type_info(ddl: &str) -> anyhow::Result<HashMap<String, TypeInfo>> {
+    let mut types = HashMap::<String, TypeInfo>::new();
+    let mut offset = 0;
+    while offset < ddl.len() {
+        let len = full_statement(ddl[offset..].as_bytes(), None)
+            .unwrap_or(ddl.len() - offset);
+        let statement = &ddl[offset..offset+len];
+        offset += len;
+        let name = match statement_type(statement)? {
+            Some(name) => name,
+            None => continue,
+        };
+        let info = types.entry(name).or_default();
+        let tokens = tokenize(statement)?;
+        let word = |idx: usize| tokens.get(idx)
+            .filter(|t| matches!(t.token.kind, Kind::Ident | Kind::Keyword))
+            .map(|t| t.token.value.to_lowercase());
+        let mut depth = 0_i32;
+        for (idx, tok) in tokens.iter().enumerate() {
+            match tok.token.kind {
+                Kind::OpenBrace => depth += 1,
+                Kind::CloseBrace => depth -= 1,
+                _ => {}
+            }
+            match word(idx).as_deref() {
+                Some("extending") if depth == 0 => {
+                    // qualified names up to the body
+                    let mut iter = tokens[idx+1..].iter()
+                        .take_while(|t| !matches!(t.token.kind,
+                            Kind::OpenBrace | Kind::Semicolon));
+                    while let (Some(module), Some(_), Some(name)) =
+                        (iter.next(), iter.next(), iter.next())
+                    {
+                        info.bases.push(format!("{}::{}",
+                            ident_value(module.token.value),
+                            ident_value(name.token.value)));
+                        iter.next();  // comma
+                    }
+                }
+                // `CREATE REQUIRED [SINGLE | MULTI] PROPERTY name`
+                Some("required") if depth == 1 => {
+                    let mut next = idx + 1;
+                    if matches!(word(next).as_deref(),
+                                Some("single") | Some("multi"))
+                    {
+                        next += 1;
+                    }
+                    if matches!(word(next).as_deref(),
+                                Some("property") | Some("link"))
+                    {
+                        if let Some(tok) = tokens.get(next + 1) {
+                            info.required.insert(
+                                ident_value(tok.token.value).into());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(types)
+}
+
+/// Whether the pointer is required in the type or in any of its bases
+fn is_required(types: &HashMap<String, TypeInfo>, type_name: &str,
+    pointer: &str)
+    -> bool
+{
+    let mut queue = vec![type_name];
+    let mut seen = HashSet::new();
+    while let Some(name) = queue.pop() {
+        if !seen.insert(name) {
+            continue;
+        }
+        if let Some(info) = types.get(name) {
+            if info.required.contains(pointer) {
+                return true;
+            }
+            queue.extend(info.bases.iter().map(|b| &b[..]));
+        }
+    }
+    false
+}
+
+impl Rules {
+    /// Matches the rules against the types listed in the dump header
+    pub fn prepare(&self, header: &[u8]) -> anyhow::Result<Anonymizer> {
+        let mut buf = Reader::new(header);
+        let num_attributes = buf.u16()?;
+        for _ in 0..num_attributes {
+            buf.u16()?;
+            buf.sized()?;
+        }
+        buf.u16()?;  // major version
+        buf.u16()?;  // minor version
+        let ddl = std::str::from_utf8(buf.sized()?)?;
+        let types = type_info(ddl)?;
+        let mut type_names = HashMap::new();
+        for _ in 0..buf.u32()? {
+            let name = buf.string()?;
+            buf.string()?;  // type class
+            type_names.insert(buf.uuid()?, name);
+        }
+        let mut tables = HashMap::new();
+        for _ in 0..buf.u32()? {
+            let id = buf.uuid()?;
+            let description = buf.sized()?;
+            for _ in 0..buf.u16()? {
+                buf.uuid()?;
+            }
+            let type_name = match type_names.get(&id) {
+                Some(name) => *name,
+                None => continue,
+            };
+            let rules = self.rules.iter()
+                .filter(|r| r.type_name == type_name)
+                .collect::<Vec<_>>();
+            if rules.is_empty() {
+                continue;
+            }
+            let descriptors = decode_descriptors(description)?;
+            let shape = match descriptors.last() {
+                Some(Descriptor::ObjectShape(shape)) => shape,
+                _ => anyhow::bail!("unexpected descriptor of {}", type_name),
+            };
+            let mut columns = vec![None; shape.elements.len()];
+            for rule in rules {
+                let idx = shape.elements.iter()
+                    .position(|el| el.name == rule.property)
+                    .ok_or_else(|| anyhow::anyhow!(
+                        "type {} has no single property {:?}",
+                        type_name, rule.property))?;
+                let element = &shape.elements[idx];
+                if rule.action != Action::Null &&
+                    !is_str(&descriptors, element.type_pos)
+                {
+                    anyhow::bail!("property {}.{} is not a string, \
+                        only `null` action can be applied",
+                        type_name, rule.property);
+                }
+                if rule.action == Action::Null &&
+                    is_required(&types, type_name, &rule.property)
+                {
+                    anyhow::bail!("property {}.{} is required, \
+                        `null` action can't be applied",
+                        type_name, rule.property);
+                }
+                columns[idx] = Some(rule.action);
+            }
+            tables.insert(id, columns);
+        }
+        for rule in &self.rules {
+            if !type_names.values().any(|name| *name == rule.type_name) {
+                log::warn!("Type {:?} from anonymization rules \
+                    is not found in the dump", rule.type_name);
+            }
+        }
+        Ok(Anonymizer { tables })
+    }
+}
+
+fn hash(value: &[u8]) -> String {
+    hex::encode(&Sha256::digest(value)[..8])
+}
+
+fn apply(action: Action, value: &[u8]) -> Option<Vec<u8>> {
+    match action {
+        Action::Hash => Some(hash(value).into_bytes()),
+        Action::Email => Some(format!("{}@example.com", hash(value)).into()),
+        Action::Random => {
+            let len = String::from_utf8_lossy(value).chars().count();
+            Some(rand::thread_rng().sample_iter(&Alphanumeric)
+                .take(len).collect())
+        }
+        Action::Null => None,
+    }
+}
+
+fn rewrite_rows(columns: &[Option<Action>], data: &[u8])
+    -> anyhow::Result<Vec<u8>>
+{
+    let mut buf = Reader::new(data);
+    let mut out = Vec::with_capacity(data.len());
+    if data.starts_with(COPY_SIGNATURE) {
+        out.extend(buf.bytes(COPY_SIGNATURE.len())?);
+        out.extend(buf.bytes(4)?);  // flags
+        let ext_len = buf.u32()?;
+        out.extend(&ext_len.to_be_bytes());
+        out.extend(buf.bytes(ext_len as usize)?);
+    }
+    while !buf.is_empty() {
+        let num_fields = buf.i16()?;
+        out.extend(&num_fields.to_be_bytes());
+        if num_fields == -1 {  // trailer
+            continue;
+        }
+        if num_fields as usize != columns.len() {
+            anyhow::bail!("expected {} columns, got {}",
+                          columns.len(), num_fields);
+        }
+        for action in columns {
+            let len = buf.i32()?;
+            let value = if len < 0 { None } else {
+                Some(buf.bytes(len as usize)?)
+            };
+            let value = match (action, value) {
+                (Some(action), Some(value)) => apply(*action, value),
+                (_, value) => value.map(|v| v.to_vec()),
+            };
+            match value {
+                Some(value) => {
+                    out.extend(&(value.len() as i32).to_be_bytes());
+                    out.extend(&value);
+                }
+                None => out.extend(&(-1i32).to_be_bytes()),
+            }
+        }
+    }
+    Ok(out)
+}
+
+impl Anonymizer {
+    /// Returns the data of a dump block with masked values
+    pub fn rewrite_block(&self, block: Bytes) -> anyhow::Result<Bytes> {
+        let attributes = block_attributes(&block)?;
+        let id = block_id(&attributes)?;
+        let columns = match id.and_then(|id| self.tables.get(&id)) {
+            Some(columns) => columns,
+            None => return Ok(block.clone()),
+        };
+        let mut out = Vec::with_capacity(block.len());
+        out.extend(&(attributes.len() as u16).to_be_bytes());
+        for (key, value) in attributes {
+            let rewritten;
+            let value = if key == BLOCK_DATA {
+                rewritten = rewrite_rows(columns, value)?;
+                &rewritten[..]
+            } else {
+                value
+            };
+            out.extend(&key.to_be_bytes());
+            write_sized(&mut out, value);
+        }
+        Ok(out.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, rewrite_rows, type_info, is_required, COPY_SIGNATURE};
+
+    fn row(values: &[Option<&[u8]>]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(&(values.len() as i16).to_be_bytes());
+        for value in values {
+            match value {
+                Some(value) => {
+                    buf.extend(&(value.len() as i32).to_be_bytes());
+                    buf.extend(*value);
+                }
+                None => buf.extend(&(-1i32).to_be_bytes()),
+            }
+        }
+        buf
+    }
+
+    #[test]
+    fn rows() {
+        let mut data = COPY_SIGNATURE.to_vec();
+        data.extend(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend(row(&[Some(b"id"), Some(b"john@gmail.com"),
+                          Some(b"John"), Some(b"secret")]));
+        data.extend(&(-1i16).to_be_bytes());
+        let columns = [None, Some(Action::Email),
+                       Some(Action::Random), Some(Action::Null)];
+        let out = rewrite_rows(&columns, &data).unwrap();
+        let header_len = COPY_SIGNATURE.len() + 8;
+        assert_eq!(out[..header_len], data[..header_len]);
+        assert_eq!(&out[out.len()-2..], &(-1i16).to_be_bytes());
+        let email_len = 16 + "@example.com".len();
+        assert_eq!(out.len(), data.len() + email_len
+                   - b"john@gmail.com".len() - b"secret".len());
+        let masked = &out[header_len..];
+        assert_eq!(&masked[..2], &4i16.to_be_bytes());
+        assert_eq!(&masked[2..8], b"\0\0\0\x02id");
+        assert_eq!(&masked[8..12], &(email_len as i32).to_be_bytes());
+        assert!(masked[12..12+email_len].ends_with(b"@example.com"));
+        let name = &masked[12+email_len..];
+        assert_eq!(&name[..4], &4i32.to_be_bytes());
+        assert_ne!(&name[4..8], b"John");
+        assert_eq!(&name[8..12], &(-1i32).to_be_bytes());
+    }
+
+    #[test]
+    fn required() {
+        let types = type_info(
+            "CREATE ABSTRACT TYPE default::Named {\n\
+                 CREATE REQUIRED SINGLE PROPERTY name -> std::str;\n\
+             };\n\
+             CREATE TYPE default::User EXTENDING default::Named {\n\
+                 CREATE REQUIRED PROPERTY email -> std::str {\n\
+                     CREATE CONSTRAINT std::exclusive;\n\
+                 };\n\
+                 CREATE PROPERTY note -> std::str;\n\
+             };\n").unwrap();
+        assert!(is_required(&types, "default::User", "email"));
+        assert!(is_required(&types, "default::User", "name"));
+        assert!(!is_required(&types, "default::User", "note"));
+        assert!(!is_required(&types, "default::Named", "email"));
+    }
+}
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::collections::{BTreeSet, BTreeMap};
+use std::time::Duration;
 
 use anyhow;
 use clap::{self, Clap, IntoApp};
@@ -8,13 +9,20 @@ use once_cell::sync::Lazy;
 use prettytable::{Table, Row, Cell};
 use regex::Regex;
 
+use crate::bindings;
+use crate::classify;
 use crate::commands::Options;
+use crate::non_interactive;
 use crate::repl;
 use crate::print::style::Styler;
 use crate::prompt;
+use crate::question;
 use crate::commands::execute;
+use crate::commands::help;
+use crate::commands::watch;
 use crate::commands::parser::{Backslash, BackslashCmd, Setting};
 use crate::table;
+use crate::verbosity::Verbosity;
 
 
 pub static CMD_CACHE: Lazy<CommandCache> = Lazy::new(|| CommandCache::new());
@@ -24,9 +32,11 @@ pub enum ExecuteResult {
     Skip,
     Quit,
     Input(String),
+    /// Statements to execute, generated by the previous query
+    Statements(Vec<String>),
 }
 
-const HELP: &str = r###"
+pub const HELP: &str = r###"
 Introspection
   (options: -v = verbose, -s = show system objects, -I = case-sensitive match)
   \d [-v] NAME             describe schema object
@@ -45,11 +55,31 @@ Introspection
                            (alias: \list-casts)
   \li [-Isv] [PATTERN]     list indexes
                            (alias: \list-indexes)
+  \lf [-sI] [PATTERN]      list functions
+                           (alias: \list-functions)
+  \lx [-I] [PATTERN]       list extensions
+                           (alias: \list-extensions)
   \list-ports              list ports
 
+  psql-style shortcuts: \dt (object types), \ds (scalar types),
+  \df (functions), \di (indexes), \dx (extensions)
+
 Operations
   \dump FILENAME           dump current database into a file
   \restore FILENAME        restore the database from file into the current one
+  \diff FILENAME           re-run last query and compare the result with
+                           JSON saved in a file
+  \gexec                   execute each string returned by the last query
+                           as a statement, after a confirmation
+  \gset NAME               store the single row returned by the last query
+                           in variables, reference them as `:NAME` or
+                           `:NAME_FIELD` in the following queries
+  \watch [INTERVAL]        re-run last query every INTERVAL (default 2s),
+                           highlighting changes, until interrupted
+  \extension list          list available extensions
+  \extension enable NAME   enable (or `disable`) extension in the database
+  \extension install NAME  check the extension is shipped with the server
+                           and enable it
 
 Editing
   \s, \history             show history
@@ -57,14 +87,22 @@ Editing
                            output as the input
 
 Settings
+  \mode [edgeql|json TYPE] show or switch input language; in `json` mode
+                           each JSON document ending with `;` is inserted
+                           as an object of TYPE, links are set by the ids
+                           of the targets (SQL input is not supported, the
+                           server has no SQL adapter)
   \set [OPTION [VALUE]]    how/change setting, type \set for listing
                            all available options
+  \settings                show current values of all settings
 
 Connection
   \c, \connect [DBNAME]    Connect to database DBNAME
 
 Help
   \?, \h, \help            Show help on backslash commands
+  \help TOPIC              Show help on a backslash command, EdgeQL keyword
+                           or function, or search help for words
   \set                     Show setting descriptions (without arguments)
   \q, \quit, \exit, Ctrl+D Quit REPL
 "###;
@@ -270,6 +308,13 @@ impl CommandCache {
         aliases.insert("la", "list-aliases");
         aliases.insert("lc", "list-casts");
         aliases.insert("li", "list-indexes");
+        aliases.insert("lf", "list-functions");
+        aliases.insert("lx", "list-extensions");
+        aliases.insert("dt", "list-object-types");
+        aliases.insert("ds", "list-scalar-types");
+        aliases.insert("df", "list-functions");
+        aliases.insert("di", "list-indexes");
+        aliases.insert("dx", "list-extensions");
         aliases.insert("s", "history");
         aliases.insert("e", "edit");
         aliases.insert("c", "connect");
@@ -314,6 +359,18 @@ impl CommandCache {
                 ExpandStrings(Default::default()),
                 HistorySize(Default::default()),
                 PrintStats(Default::default()),
+                Prompt(Default::default()),
+                PasteTransaction(Default::default()),
+                IdleKeepalive(Default::default()),
+                SpillThreshold(Default::default()),
+                NullDisplay(Default::default()),
+                VectorDisplay(Default::default()),
+                NumberFormat(Default::default()),
+                SafeMode(Default::default()),
+                ReadOnly(Default::default()),
+                StatementTimeout(Default::default()),
+                TransactionIsolation(Default::default()),
+                Deferrable(Default::default()),
             ].into_iter().map(|setting| {
                 let cmd = setting_cmd.remove(&setting.name())
                     .expect("all settings have cmd");
@@ -363,16 +420,18 @@ pub fn backslashify_help<'x>(text: &'x str) -> Cow<'x, str> {
 }
 
 pub fn parse(s: &str) -> Result<Backslash, ParseError> {
+    use crate::commands::parser::Help;
     use Item::*;
 
     let mut arguments = Vec::new();
+    let mut help = false;
     for token in Parser::new(s) {
         match token.item {
             Command(x) => {
+                // not passed to clap which has its own `help` command
                 if x == "\\?" || x == "\\h" || x == "\\help" {
-                    return Ok(Backslash {
-                        command: BackslashCmd::Help,
-                    })
+                    help = true;
+                    continue;
                 }
                 if let Some(cmd) = CMD_CACHE.aliases.get(&x[1..]) {
                     arguments.push(cmd.to_string())
@@ -398,6 +457,11 @@ pub fn parse(s: &str) -> Result<Backslash, ParseError> {
             }
         }
     }
+    if help {
+        return Ok(Backslash {
+            command: BackslashCmd::Help(Help { topic: arguments }),
+        });
+    }
     Backslash::try_parse_from(arguments)
     .map_err(|e| ParseError {
         help: e.kind == clap::ErrorKind::DisplayHelp,
@@ -474,6 +538,51 @@ pub fn get_setting(s: &Setting, prompt: &repl::State) -> Cow<'static, str> {
         PrintStats(_) => {
             prompt.print_stats.as_str().into()
         }
+        Prompt(_) => {
+            format!("{:?}", prompt.prompt_template).into()
+        }
+        PasteTransaction(_) => {
+            bool_str(prompt.paste_transaction).into()
+        }
+        IdleKeepalive(_) => {
+            prompt.idle_keepalive.to_string().into()
+        }
+        SpillThreshold(_) => {
+            if let Some(value) = prompt.print.spill_threshold {
+                (value >> 20).to_string().into()
+            } else {
+                "0  # never spill".into()
+            }
+        }
+        NullDisplay(_) => {
+            format!("{:?}", prompt.print.null_display.as_deref()
+                .unwrap_or("{}")).into()
+        }
+        VectorDisplay(_) => {
+            prompt.print.vector_display.as_str().into()
+        }
+        NumberFormat(_) => {
+            prompt.print.number_format.as_str().into()
+        }
+        SafeMode(_) => {
+            bool_str(prompt.safe_mode).into()
+        }
+        ReadOnly(_) => {
+            bool_str(prompt.read_only).into()
+        }
+        StatementTimeout(_) => {
+            if let Some(value) = prompt.statement_timeout {
+                humantime::format_duration(value).to_string().into()
+            } else {
+                "0  # no timeout".into()
+            }
+        }
+        TransactionIsolation(_) => {
+            prompt.transaction_isolation.as_str().into()
+        }
+        Deferrable(_) => {
+            bool_str(prompt.deferrable).into()
+        }
      }
 }
 
@@ -503,22 +612,39 @@ pub async fn execute(cmd: &BackslashCmd, prompt: &mut repl::State)
 
     let options = Options {
         command_line: false,
-        styler: Some(Styler::dark_256()),
+        styler: Some(Styler::configured()),
         conn_params: prompt.conn_params.clone(),
+        verbosity: Verbosity::Normal,
     };
     match cmd {
-        Help => {
+        Help(h) if h.topic.is_empty() => {
             print!("{}", HELP);
             Ok(Skip)
         }
+        Help(h) => {
+            help(&h.topic.join(" "))?;
+            Ok(Skip)
+        }
         Common(ref cmd) => {
+            if prompt.safe_mode {
+                if let Some(danger) = execute::statement(cmd)
+                    .and_then(|s| classify::danger(&s))
+                {
+                    let q = question::Confirm::new_dangerous(
+                        format!("{} Do you really want to run it?", danger));
+                    if !q.ask()? {
+                        eprintln!("Command cancelled.");
+                        return Ok(Skip);
+                    }
+                }
+            }
             prompt.soft_reconnect().await?;
             let cli = prompt.connection.as_mut()
                 .expect("connection established");
             execute::common(cli, cmd, &options).await?;
             Ok(Skip)
         }
-        Set(SetCommand {setting: None}) => {
+        Set(SetCommand {setting: None}) | Settings => {
             list_settings(prompt);
             Ok(Skip)
         }
@@ -560,6 +686,71 @@ pub async fn execute(cmd: &BackslashCmd, prompt: &mut repl::State)
                 PrintStats(v) => {
                     prompt.print_stats = v.value.expect("only writes here");
                 }
+                Prompt(v) => {
+                    let template = v.value.clone().expect("only set here");
+                    repl::validate_prompt(&template)?;
+                    prompt.prompt_template = template;
+                }
+                PasteTransaction(b) => {
+                    prompt.paste_transaction = b.unwrap_value();
+                }
+                IdleKeepalive(c) => {
+                    prompt.idle_keepalive = c.value.expect("only set here");
+                }
+                SpillThreshold(c) => {
+                    let value = c.value.expect("only set here");
+                    if value == 0 {
+                        prompt.print.spill_threshold = None;
+                    } else {
+                        prompt.print.spill_threshold = Some(value << 20);
+                    }
+                }
+                NullDisplay(v) => {
+                    let value = v.value.clone().expect("only set here");
+                    if value == "{}" {
+                        prompt.print.null_display = None;
+                    } else {
+                        prompt.print.null_display = Some(value);
+                    }
+                }
+                VectorDisplay(v) => {
+                    prompt.print.vector_display = v.value
+                        .expect("only set here");
+                }
+                NumberFormat(v) => {
+                    prompt.print.number_format = v.value
+                        .expect("only set here");
+                }
+                SafeMode(b) => {
+                    prompt.safe_mode = b.unwrap_value();
+                }
+                ReadOnly(b) => {
+                    let value = b.unwrap_value();
+                    classify::set_read_only(value)?;
+                    prompt.read_only = value;
+                }
+                StatementTimeout(c) => {
+                    let value = c.value.expect("only set here");
+                    if value == Duration::from_secs(0) {
+                        prompt.statement_timeout = None;
+                    } else {
+                        prompt.statement_timeout = Some(value);
+                    }
+                    // new connections are configured on connect
+                    let query = prompt.statement_timeout_query();
+                    if let Some(conn) = &mut prompt.connection {
+                        if conn.is_consistent() {
+                            conn.execute(&query).await?;
+                        }
+                    }
+                }
+                TransactionIsolation(v) => {
+                    prompt.transaction_isolation = v.value
+                        .expect("only set here");
+                }
+                Deferrable(b) => {
+                    prompt.deferrable = b.unwrap_value();
+                }
             }
             Ok(Skip)
         }
@@ -597,6 +788,102 @@ pub async fn execute(cmd: &BackslashCmd, prompt: &mut repl::State)
                 | prompt::Input::Eof => Ok(Skip),
             }
         }
+        Diff(c) => {
+            let query = match prompt.last_query {
+                Some(ref query) => query.clone(),
+                None => {
+                    eprintln!("== there is no previous query ==");
+                    return Ok(Skip);
+                }
+            };
+            prompt.soft_reconnect().await?;
+            let cli = prompt.connection.as_mut()
+                .expect("connection established");
+            non_interactive::diff_with(cli, &query, &c.path).await?;
+            Ok(Skip)
+        }
+        Gexec => {
+            let result = match prompt.last_result.take() {
+                Some(result) => result,
+                None if matches!(prompt.output_mode,
+                    repl::OutputMode::Json | repl::OutputMode::JsonElements)
+                => {
+                    eprintln!("== results aren't kept in JSON modes ==");
+                    return Ok(Skip);
+                }
+                None => {
+                    eprintln!("== there is no previous result ==");
+                    return Ok(Skip);
+                }
+            };
+            match result.statements {
+                Some(statements) => Ok(Statements(statements.into_lines()?)),
+                None => {
+                    anyhow::bail!("\\gexec requires the previous query \
+                                   to return a set of at most {} strings",
+                                  repl::MAX_GEXEC_STATEMENTS);
+                }
+            }
+        }
+        Watch(c) => {
+            let query = match prompt.last_query {
+                Some(ref query) => query.clone(),
+                None => {
+                    eprintln!("== there is no previous query ==");
+                    return Ok(Skip);
+                }
+            };
+            prompt.soft_reconnect().await?;
+            let cli = prompt.connection.as_mut()
+                .expect("connection established");
+            watch(cli, &query, c.interval).await?;
+            Ok(Skip)
+        }
+        Gset(c) => {
+            let row = match &prompt.last_result {
+                Some(repl::LastResult { rows: 1, first: Some(row), .. })
+                => row,
+                Some(result) => {
+                    anyhow::bail!("\\gset requires the previous query \
+                                   to return a single row, got {}",
+                                  result.rows);
+                }
+                None => {
+                    eprintln!("== there is no previous result ==");
+                    return Ok(Skip);
+                }
+            };
+            for (name, value) in bindings::bind(&c.name, row)? {
+                prompt.variables.insert(name, value);
+            }
+            Ok(Skip)
+        }
+        Mode(c) => {
+            match (c.mode.as_deref(), &c.type_name) {
+                (None, _) => match prompt.input_language {
+                    repl::InputLanguage::EdgeQL => println!("edgeql"),
+                    repl::InputLanguage::JsonInsert(ref name) => {
+                        println!("json {}", name)
+                    }
+                },
+                (Some("json"), Some(name)) => {
+                    let name = if name.contains("::") {
+                        name.clone()
+                    } else {
+                        format!("default::{}", name)
+                    };
+                    prompt.input_language =
+                        repl::InputLanguage::JsonInsert(name);
+                }
+                (Some("json"), None) => {
+                    eprintln!("Error: type name is required for `json` mode");
+                }
+                (Some(_), _) => {
+                    prompt.input_language = repl::InputLanguage::EdgeQL;
+                }
+            }
+            Ok(Skip)
+        }
         Exit => Ok(Quit),
     }
 }
@@ -0,0 +1,147 @@
+use anyhow::Context;
+use async_std::prelude::StreamExt;
+use edgedb_client::client::Connection;
+use edgedb_protocol::value::Value;
+use edgeql_parser::helpers::quote_name;
+
+use crate::commands::Options;
+use crate::commands::insert::{full_type_name, quote_type, introspect};
+use crate::non_interactive::query_json_value;
+use crate::options::{Update, Delete, BulkOptions};
+use crate::question;
+
+
+enum Action<'a> {
+    Update(&'a [String]),
+    Delete,
+}
+
+impl Action<'_> {
+    fn verb(&self) -> &'static str {
+        match self {
+            Action::Update(_) => "Updated",
+            Action::Delete => "Deleted",
+        }
+    }
+    fn statement(&self, type_name: &str) -> String {
+        let filter = "FILTER .id IN \
+            <uuid>array_unpack(<array<str>><json>$0)";
+        match self {
+            Action::Update(assignments) => {
+                format!("SELECT count((UPDATE {} {} SET {{ {} }}))",
+                        type_name, filter, assignments.join(", "))
+            }
+            Action::Delete => {
+                format!("SELECT count((DELETE {} {}))", type_name, filter)
+            }
+        }
+    }
+}
+
+async fn matching_ids(conn: &mut Connection, type_name: &str, filter: &str)
+    -> anyhow::Result<Vec<String>>
+{
+    let mut rows = conn.query::<String>(
+        &format!("SELECT <str>(SELECT {} FILTER {}).id", type_name, filter),
+        &Value::empty_tuple(),
+    ).await.context("error selecting objects")?;
+    let mut ids = Vec::new();
+    while let Some(id) = rows.next().await.transpose()? {
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+async fn print_sample(conn: &mut Connection, name: &str, ids: &[String],
+    size: usize)
+    -> anyhow::Result<()>
+{
+    let info = introspect(conn, name).await?;
+    let mut shape = vec!["id".to_string()];
+    shape.extend(info.properties.iter().map(|p| quote_name(&p.name)));
+    let ids = ids.iter().take(size)
+        .map(|id| format!("<uuid>'{}'", id))
+        .collect::<Vec<_>>();
+    let sample = query_json_value(conn, &format!(
+        "SELECT {} {{ {} }} FILTER .id IN {{ {} }}",
+        quote_type(name), shape.join(", "), ids.join(", "))).await?;
+    if let Some(items) = sample.as_array() {
+        for item in items {
+            println!("{}", item);
+        }
+    }
+    Ok(())
+}
+
+async fn run(options: &Options, type_name: &str, filter: &str,
+    action: Action<'_>, bulk: &BulkOptions, confirm: bool)
+    -> anyhow::Result<()>
+{
+    let mut conn = options.conn_params.connect().await?;
+    let name = full_type_name(type_name);
+    let quoted = quote_type(&name);
+    let ids = matching_ids(&mut conn, &quoted, filter).await?;
+    eprintln!("{} object(s) of {} match the filter", ids.len(), name);
+    if ids.is_empty() {
+        return Ok(());
+    }
+    if bulk.preview {
+        print_sample(&mut conn, &name, &ids, bulk.sample_size).await?;
+        if ids.len() > bulk.sample_size {
+            eprintln!("... and {} more", ids.len() - bulk.sample_size);
+        }
+    }
+    if confirm {
+        let q = question::Confirm::new_dangerous(format!(
+            "{} {} object(s)?",
+            match action {
+                Action::Update(_) => "Update",
+                Action::Delete => "Delete",
+            },
+            ids.len()));
+        if !q.ask()? {
+            eprintln!("Canceled");
+            return Ok(());
+        }
+    }
+    let statement = action.statement(&quoted);
+    let mut done = 0;
+    for chunk in ids.chunks(bulk.batch_size) {
+        let data = serde_json::to_string(chunk)?;
+        let mut rows = conn.query::<i64>(&statement,
+            &Value::Tuple(vec![Value::Json(data)])).await?;
+        while let Some(num) = rows.next().await.transpose()? {
+            done += num;
+        }
+        eprintln!("{} {}/{}", action.verb(), done, ids.len());
+    }
+    Ok(())
+}
+
+pub async fn update(options: &Options, cmd: &Update) -> anyhow::Result<()> {
+    run(options, &cmd.type_name, &cmd.filter,
+        Action::Update(&cmd.assignments), &cmd.bulk, cmd.bulk.preview).await
+}
+
+pub async fn delete(options: &Options, cmd: &Delete) -> anyhow::Result<()> {
+    // deleted objects can't be restored, so it's confirmed by default
+    run(options, &cmd.type_name, &cmd.filter, Action::Delete, &cmd.bulk,
+        cmd.bulk.preview || !cmd.non_interactive).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::Action;
+
+    #[test]
+    fn statements() {
+        let set = vec!["archived := true".into(), "note := 'x'".into()];
+        assert_eq!(Action::Update(&set).statement("default::User"),
+            "SELECT count((UPDATE default::User \
+             FILTER .id IN <uuid>array_unpack(<array<str>><json>$0) \
+             SET { archived := true, note := 'x' }))");
+        assert_eq!(Action::Delete.statement("default::User"),
+            "SELECT count((DELETE default::User \
+             FILTER .id IN <uuid>array_unpack(<array<str>><json>$0)))");
+    }
+}
@@ -0,0 +1,469 @@
+//! Offline checks of EdgeQL files, intended to be run by editors on save
+//!
+//! Files are tokenized and brackets and statement terminators are checked
+//! without connecting to the server. Inside a project, names of object
+//! types after `INSERT`, `UPDATE`, `DELETE` and `IS` are also checked
+//! against the types declared in the schema files.
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use anyhow::Context;
+use edgeql_parser::tokenizer::{TokenStream, Kind, SpannedToken};
+use serde::Serialize;
+
+use crate::commands::ExitCode;
+use crate::migrations::template;
+use crate::options::Check;
+use crate::project;
+use crate::typos::closest;
+
+
+/// Types which can be referred to without a module
+const STD_TYPES: &[&str] = &[
+    "BaseObject", "Object", "FreeObject", "anytype", "anyscalar", "anyint",
+    "anyfloat", "anyreal", "str", "bool", "bytes", "uuid", "json", "int16",
+    "int32", "int64", "float32", "float64", "bigint", "decimal",
+    "datetime", "duration", "sequence", "array", "tuple",
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all="lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+struct Problem {
+    severity: Severity,
+    message: String,
+    hint: Option<String>,
+    span: (usize, usize),
+}
+
+/// Line and column are 1-based, column and offset are in bytes
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    line: usize,
+    column: usize,
+    offset: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct Diagnostic<'a> {
+    file: &'a str,
+    severity: Severity,
+    message: &'a str,
+    #[serde(skip_serializing_if="Option::is_none")]
+    hint: Option<&'a str>,
+    start: Position,
+    end: Position,
+}
+
+#[derive(Serialize, Debug)]
+struct Report<'a> {
+    ok: bool,
+    diagnostics: Vec<Diagnostic<'a>>,
+}
+
+impl Problem {
+    fn error(message: impl Into<String>, span: (usize, usize)) -> Problem {
+        Problem {
+            severity: Severity::Error,
+            message: message.into(),
+            hint: None,
+            span,
+        }
+    }
+    fn warning(message: impl Into<String>, span: (usize, usize))
+        -> Problem
+    {
+        Problem {
+            severity: Severity::Warning,
+            ..Problem::error(message, span)
+        }
+    }
+}
+
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map(|p| p + 1).unwrap_or(0);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: offset - line_start + 1,
+        offset,
+    }
+}
+
+fn span(token: &SpannedToken) -> (usize, usize) {
+    (token.start.offset as usize, token.end.offset as usize)
+}
+
+/// Offset of the first token after `offset`
+fn skip_insignificant(text: &str, mut offset: usize) -> usize {
+    loop {
+        let tail = &text[offset..];
+        let trimmed = tail.trim_start();
+        offset += tail.len() - trimmed.len();
+        if !trimmed.starts_with('#') {
+            return offset;
+        }
+        offset += trimmed.find('\n').unwrap_or(trimmed.len());
+    }
+}
+
+fn closing(open: &str) -> &'static str {
+    match open {
+        "(" => ")",
+        "[" => "]",
+        _ => "}",
+    }
+}
+
+fn is_word(token: &SpannedToken, word: &str) -> bool {
+    matches!(token.token.kind, Kind::Ident | Kind::Keyword)
+        && token.token.value.eq_ignore_ascii_case(word)
+}
+
+fn tokenize<'a>(text: &'a str, problems: &mut Vec<Problem>)
+    -> Vec<SpannedToken<'a>>
+{
+    let mut tokens: Vec<SpannedToken> = Vec::new();
+    for item in TokenStream::new(text) {
+        match item {
+            Ok(token) => tokens.push(token),
+            Err(e) => {
+                let last = tokens.last().map(|t| span(t).1).unwrap_or(0);
+                let start = skip_insignificant(text, last);
+                let end = text[start..].find('\n')
+                    .map(|p| start + p)
+                    .unwrap_or_else(|| text.len());
+                let message = e.to_string().lines()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(": ");
+                problems.push(Problem::error(message, (start, end)));
+                break;
+            }
+        }
+    }
+    tokens
+}
+
+fn check_structure(text: &str, tokens: &[SpannedToken],
+                   problems: &mut Vec<Problem>)
+{
+    let mut brackets: Vec<&SpannedToken> = Vec::new();
+    let mut statement_start = None;
+    for token in tokens {
+        match token.token.kind {
+            Kind::OpenParen | Kind::OpenBracket | Kind::OpenBrace => {
+                brackets.push(token);
+            }
+            Kind::CloseParen | Kind::CloseBracket | Kind::CloseBrace => {
+                match brackets.pop() {
+                    Some(open)
+                    if closing(open.token.value) == token.token.value => {}
+                    Some(open) => {
+                        let start = position(text, span(open).0);
+                        problems.push(Problem {
+                            hint: Some(format!("`{}` is opened at {}:{}",
+                                open.token.value,
+                                start.line, start.column)),
+                            ..Problem::error(format!(
+                                "unexpected `{}`, expected `{}`",
+                                token.token.value,
+                                closing(open.token.value)),
+                                span(token))
+                        });
+                        return;
+                    }
+                    None => {
+                        problems.push(Problem::error(
+                            format!("unexpected `{}`, no bracket to close",
+                                    token.token.value),
+                            span(token)));
+                        return;
+                    }
+                }
+            }
+            Kind::Semicolon if brackets.is_empty() => {
+                if statement_start.take().is_none() {
+                    problems.push(Problem::warning(
+                        "empty statement", span(token)));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        statement_start.get_or_insert(span(token).0);
+    }
+    if let Some(open) = brackets.last() {
+        problems.push(Problem::error(
+            format!("`{}` is never closed", open.token.value),
+            span(open)));
+    } else if let (Some(start), Some(last)) = (statement_start, tokens.last())
+    {
+        problems.push(Problem::warning(
+            "statement is not terminated by `;`",
+            (start, span(last).1)));
+    }
+}
+
+/// Name of the type starting at the token and the span of the name
+fn type_name(tokens: &[SpannedToken]) -> Option<(String, (usize, usize))> {
+    let mut name = Vec::new();
+    let mut iter = tokens.iter();
+    let first = iter.next().filter(|t| t.token.kind == Kind::Ident)?;
+    name.push(first.token.value);
+    let mut end = span(first).1;
+    while let (Some(ns), Some(ident)) = (iter.next(), iter.next()) {
+        if ns.token.kind != Kind::Namespace || ident.token.kind != Kind::Ident
+        {
+            break;
+        }
+        name.push(ident.token.value);
+        end = span(ident).1;
+    }
+    Some((name.join("::"), (span(first).0, end)))
+}
+
+fn check_types(tokens: &[SpannedToken], types: &BTreeSet<String>,
+               problems: &mut Vec<Problem>)
+{
+    // aliases and iterator variables which may shadow types
+    let local = tokens.windows(2)
+        .filter_map(|pair| {
+            if is_word(&pair[0], "FOR") {
+                Some(pair[1].token.value)
+            } else if pair[1].token.kind == Kind::Assign {
+                Some(pair[0].token.value)
+            } else {
+                None
+            }
+        })
+        .collect::<BTreeSet<_>>();
+    for (idx, token) in tokens.iter().enumerate() {
+        if !["INSERT", "UPDATE", "DELETE", "IS"].iter()
+            .any(|w| is_word(token, w))
+        {
+            continue;
+        }
+        let (name, name_span) = match type_name(&tokens[idx+1..]) {
+            Some(found) => found,
+            None => continue,
+        };
+        let full_name = if name.contains("::") {
+            name.clone()
+        } else if local.contains(&name[..])
+            || STD_TYPES.contains(&&name[..])
+        {
+            continue;
+        } else {
+            format!("default::{}", name)
+        };
+        let module = &full_name[..full_name.rfind("::").unwrap_or(0) + 2];
+        let declared = types.iter().any(|t| t.starts_with(module));
+        if !declared || types.contains(&full_name) {
+            // types of std and other modules are not known
+            continue;
+        }
+        let hint = closest(&full_name, types.iter().map(|t| &t[..]))
+            .map(|found| format!("did you mean `{}`?",
+                found.trim_start_matches("default::")));
+        problems.push(Problem {
+            hint,
+            ..Problem::warning(
+                format!("type `{}` is not declared in the schema", name),
+                name_span)
+        });
+    }
+}
+
+/// Names of the types declared in the schema, with modules
+fn schema_types(text: &str, types: &mut BTreeSet<String>) {
+    let tokens = match TokenStream::new(text).collect::<Result<Vec<_>, _>>()
+    {
+        Ok(tokens) => tokens,
+        // reported by `edgedb migration create`
+        Err(_) => return,
+    };
+    let mut modules: Vec<(&str, usize)> = Vec::new();
+    let mut depth = 0_usize;
+    for (idx, token) in tokens.iter().enumerate() {
+        match token.token.kind {
+            Kind::OpenBrace => depth += 1,
+            Kind::CloseBrace => {
+                depth = depth.saturating_sub(1);
+                if modules.last().map(|(_, d)| *d == depth).unwrap_or(false)
+                {
+                    modules.pop();
+                }
+            }
+            _ => {}
+        }
+        let next = match tokens.get(idx+1) {
+            Some(next) if next.token.kind == Kind::Ident => next,
+            _ => continue,
+        };
+        if is_word(token, "module") {
+            modules.push((next.token.value, depth));
+        } else if is_word(token, "type") || is_word(token, "alias") {
+            let module = modules.last().map(|(m, _)| *m).unwrap_or("default");
+            types.insert(format!("{}::{}", module, next.token.value));
+        }
+    }
+}
+
+fn read_schema(dir: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let variables = template::project_variables()?;
+    let mut types = BTreeSet::new();
+    for item in fs::read_dir(dir)? {
+        let path = item?.path();
+        if path.extension().map(|e| e == "esdl").unwrap_or(false) {
+            let text = fs::read_to_string(&path)?;
+            match template::expand_file(&text, variables.as_ref()) {
+                Ok(expanded) => schema_types(&expanded.text, &mut types),
+                // reported by `edgedb migration create`
+                Err(_) => schema_types(&text, &mut types),
+            }
+        }
+    }
+    Ok(types)
+}
+
+fn check_text(text: &str, types: Option<&BTreeSet<String>>)
+    -> Vec<Problem>
+{
+    let mut problems = Vec::new();
+    let tokens = tokenize(text, &mut problems);
+    check_structure(text, &tokens, &mut problems);
+    if let Some(types) = types {
+        check_types(&tokens, types, &mut problems);
+    }
+    problems.sort_by_key(|p| p.span);
+    problems
+}
+
+fn read_input(file: &Path, stdin_ok: bool) -> anyhow::Result<String> {
+    if file == Path::new("-")
+        || (stdin_ok && !atty::is(atty::Stream::Stdin))
+    {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)
+            .context("cannot read stdin")?;
+        return Ok(text);
+    }
+    fs::read_to_string(file)
+        .with_context(|| format!("cannot read {}", file.display()))
+}
+
+pub fn check(cmd: &Check) -> anyhow::Result<()> {
+    if cmd.stdin_ok && cmd.files.len() > 1 {
+        anyhow::bail!("`--stdin-ok` requires a single file");
+    }
+    let types = match project::project_dir_opt(None)? {
+        Some(dir) if dir.join("dbschema").exists() => {
+            Some(read_schema(&dir.join("dbschema"))
+                .context("cannot read schema files")?)
+        }
+        _ => None,
+    };
+    let mut files = Vec::with_capacity(cmd.files.len());
+    for file in &cmd.files {
+        let text = read_input(file, cmd.stdin_ok)?;
+        let problems = check_text(&text, types.as_ref());
+        files.push((file.display().to_string(), text, problems));
+    }
+    let mut diagnostics = Vec::new();
+    for (name, text, problems) in &files {
+        for problem in problems {
+            diagnostics.push(Diagnostic {
+                file: name,
+                severity: problem.severity,
+                message: &problem.message,
+                hint: problem.hint.as_deref(),
+                start: position(text, problem.span.0),
+                end: position(text, problem.span.1),
+            });
+        }
+    }
+    let ok = diagnostics.iter().all(|d| d.severity != Severity::Error);
+    if cmd.json {
+        let report = Report { ok, diagnostics };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for d in &diagnostics {
+            let severity = match d.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            eprintln!("{}:{}:{}: {}: {}", d.file,
+                      d.start.line, d.start.column, severity, d.message);
+            if let Some(hint) = d.hint {
+                eprintln!("  Hint: {}", hint);
+            }
+        }
+    }
+    if !ok {
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::{check_text, schema_types, Severity};
+
+    fn messages(text: &str) -> Vec<(Severity, String, (usize, usize))> {
+        check_text(text, None).into_iter()
+            .map(|p| (p.severity, p.message, p.span))
+            .collect()
+    }
+
+    #[test]
+    fn brackets() {
+        assert_eq!(messages("SELECT (1 + 2;"), vec![
+            (Severity::Error, "`(` is never closed".into(), (7, 8)),
+        ]);
+        assert_eq!(messages("SELECT [1, 2);")[0].1,
+                   "unexpected `)`, expected `]`");
+        assert_eq!(messages("SELECT 1);")[0].1,
+                   "unexpected `)`, no bracket to close");
+        assert!(messages("SELECT User { name };").is_empty());
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(messages("SELECT 1;\nSELECT 2"), vec![
+            (Severity::Warning,
+             "statement is not terminated by `;`".into(), (10, 18)),
+        ]);
+        assert_eq!(messages("SELECT 1;;")[0].1, "empty statement");
+        assert!(messages("# comment only\n").is_empty());
+    }
+
+    #[test]
+    fn types() {
+        let mut types = BTreeSet::new();
+        schema_types("module default { type User; } \
+                      module other { abstract type Named; }", &mut types);
+        assert!(types.contains("default::User"));
+        assert!(types.contains("other::Named"));
+        let problems = check_text(
+            "INSERT Usr { name := 'x' }; \
+             DELETE other::Named; \
+             SELECT x[IS User]; \
+             FOR u IN {1} UNION (UPDATE u SET {});",
+            Some(&types));
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].message,
+                   "type `Usr` is not declared in the schema");
+        assert_eq!(problems[0].hint.as_deref(), Some("did you mean `User`?"));
+    }
+}
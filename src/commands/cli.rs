@@ -1,34 +1,70 @@
 use async_std::task;
 
-use crate::options::{Options, Command};
+use crate::audit;
+use crate::classify;
+use crate::options::{Options, Command, ConnectionCommand};
+use crate::options::CliConfigCommand;
 use crate::commands::parser::Common;
 use crate::non_interactive;
 use crate::commands;
+use crate::generate;
+use crate::history;
 use crate::migrations;
+use crate::schema;
+use crate::output_style;
 use crate::self_install;
 use crate::self_upgrade;
 use crate::server;
+use crate::setup_wizard;
 use crate::project;
+use crate::queries;
+use crate::telemetry;
+use crate::testing;
+use crate::version_check;
 use crate::print::style::Styler;
 
 
+/// Name of the command if it always modifies the database, commands
+/// running arbitrary statements check them one by one
+fn mutating_command(cmd: &Command) -> Option<&'static str> {
+    match cmd {
+        Command::AlterRole(_) => Some("alter-role"),
+        Command::CreateSuperuserRole(_) => Some("create-superuser-role"),
+        Command::DropRole(_) => Some("drop-role"),
+        Command::Insert(_) => Some("insert"),
+        Command::Update(_) => Some("update"),
+        Command::Delete(_) => Some("delete"),
+        Command::WipeDatabase(_) => Some("wipe-database"),
+        _ => None,
+    }
+}
+
 pub fn main(options: Options) -> Result<(), anyhow::Error> {
     let cmdopt = commands::Options {
         command_line: true,
-        styler: if atty::is(atty::Stream::Stdout) {
-            Some(Styler::dark_256())
+        styler: if output_style::colors(atty::Stream::Stdout) {
+            Some(Styler::configured())
         } else {
             None
         },
         conn_params: options.conn_params.clone(),
+        verbosity: options.verbosity,
     };
-    match options.subcommand.as_ref().expect("subcommand is present") {
+    let subcommand = options.subcommand.as_ref()
+        .expect("subcommand is present");
+    if let Some(name) = mutating_command(subcommand) {
+        classify::guard_command(name)?;
+    }
+    let result: anyhow::Result<()> = match subcommand {
         Command::Common(cmd) => {
             match cmd {
                 Common::MigrationLog(mlog) if mlog.from_fs => {
                     // no need for connection
                     task::block_on(migrations::log_fs(&cmdopt, &mlog)).into()
                 }
+                Common::MigrationPlan(plan) => {
+                    task::block_on(migrations::plan(&cmdopt, plan)).into()
+                }
                 cmd => {
                     task::block_on(async {
                         let mut conn = options.conn_params.connect().await?;
@@ -46,6 +82,63 @@ pub fn main(options: Options) -> Result<(), anyhow::Error> {
         Command::Project(cmd) => {
             project::main(cmd)
         }
+        Command::Test(cmd) => {
+            testing::main(cmd, &options)
+        }
+        Command::History(cmd) => {
+            history::main(cmd)
+        }
+        Command::Generate(cmd) => {
+            generate::main(cmd, &options)
+        }
+        Command::Schema(cmd) => {
+            schema::main(cmd, &options)
+        }
+        Command::Queries(cmd) => {
+            queries::main(cmd, &options)
+        }
+        Command::Doctor(cmd) => {
+            server::doctor::doctor(cmd)
+        }
+        Command::Undo(cmd) => {
+            server::trash::undo(cmd)
+        }
+        Command::Log(cmd) => {
+            audit::print_log(cmd)
+        }
+        Command::Connection(c) => match c.subcommand {
+            ConnectionCommand::Explain => commands::explain(&options),
+        },
+        Command::Config(c) => match c.subcommand {
+            CliConfigCommand::Doctor => commands::config_doctor(&options),
+            CliConfigCommand::Setup => setup_wizard::setup(),
+        },
+        Command::Wait(w) => {
+            task::block_on(commands::wait(&cmdopt, w)).into()
+        }
+        Command::Insert(i) => {
+            task::block_on(commands::insert(&cmdopt, i)).into()
+        }
+        Command::Update(u) => {
+            task::block_on(commands::bulk::update(&cmdopt, u)).into()
+        }
+        Command::Delete(d) => {
+            task::block_on(commands::bulk::delete(&cmdopt, d)).into()
+        }
+        Command::Sample(s) => {
+            task::block_on(commands::sample(&options, s)).into()
+        }
+        Command::Check(c) => commands::check(c),
+        Command::Locate(c) => commands::locate(c),
+        Command::Listen(l) => {
+            task::block_on(commands::listen(&options, l)).into()
+        }
+        Command::Stats(s) => {
+            task::block_on(commands::stats(&cmdopt, s)).into()
+        }
+        Command::WipeDatabase(w) => {
+            task::block_on(commands::wipe_database(&cmdopt, w)).into()
+        }
         Command::CreateSuperuserRole(opt) => {
             task::block_on(async {
                 let mut conn = options.conn_params.connect().await?;
@@ -71,6 +164,37 @@ pub fn main(options: Options) -> Result<(), anyhow::Error> {
         Command::Query(q) => {
             task::block_on(async {
                 let mut conn = options.conn_params.connect().await?;
+                if let Some(interval) = q.watch {
+                    if q.queries.len() != 1 {
+                        anyhow::bail!("`--watch` requires exactly one query");
+                    }
+                    return commands::watch(
+                        &mut conn, &q.queries[0], interval).await;
+                }
+                if let Some(path) = &q.diff_with {
+                    if q.queries.len() != 1 {
+                        anyhow::bail!("`--diff-with` requires \
+                                       exactly one query");
+                    }
+                    let same = non_interactive::diff_with(
+                        &mut conn, &q.queries[0], path).await?;
+                    if !same {
+                        return Err(commands::ExitCode::new(1))?;
+                    }
+                    return Ok(());
+                }
+                if q.all_databases {
+                    let options = &options;
+                    return commands::fan_out::run(&mut conn, &cmdopt,
+                        q.parallel,
+                        |mut conn, _| async move {
+                            for query in &q.queries {
+                                non_interactive::query(
+                                    &mut conn, query, options).await?;
+                            }
+                            Ok(())
+                        }).await;
+                }
                 for query in &q.queries {
                     non_interactive::query(&mut conn, query, &options).await?;
                 }
@@ -84,7 +208,17 @@ pub fn main(options: Options) -> Result<(), anyhow::Error> {
             self_install::gen_completions(s)
         }
         Command::SelfUpgrade(s) => {
-            self_upgrade::main(s)
+            self_upgrade::main(s, options.verbosity)
         }
+        Command::Version(v) => {
+            version_check::show_version(v)
+        }
+        Command::Telemetry(t) => {
+            telemetry::main(&options, t)
+        }
+    };
+    if result.is_ok() {
+        audit::record(&options);
     }
+    result
 }
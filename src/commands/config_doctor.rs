@@ -0,0 +1,66 @@
+use prettytable::{Table, Row, Cell};
+
+use crate::commands::ExitCode;
+use crate::options::Options;
+use crate::output_style::OutputStyle;
+use crate::table;
+
+
+pub fn config_doctor(options: &Options) -> anyhow::Result<()> {
+    let config = &options.config;
+    println!("Config files:");
+    for (source, exists) in &config.files {
+        if *exists {
+            println!("  {}", source);
+        } else {
+            println!("  {} (not found)", source);
+        }
+    }
+
+    let mut rows = vec![
+        ("output-format".to_string(), match config.output_format {
+            Some(format) => format.as_str().to_string(),
+            None => "<default or json-elements if not interactive>".into(),
+        }),
+        ("output-style".into(), match config.output_style {
+            OutputStyle::Default => "default".into(),
+            OutputStyle::Plain => "plain".into(),
+        }),
+        ("color-theme".into(), config.color_theme.as_str().into()),
+        ("version-check".into(), config.version_check.to_string()),
+        ("telemetry".into(), config.telemetry.as_str().into()),
+        ("read-only".into(), config.read_only.to_string()),
+        ("shell.prompt".into(), config.shell.prompt.clone()
+            .unwrap_or_else(|| "<default>".into())),
+        ("shell.safe-mode".into(), config.shell.safe_mode.to_string()),
+    ];
+    for (type_name, rule) in &config.display {
+        rows.push((format!("display.{:?}", type_name),
+                   rule.template.clone().unwrap_or_else(|| "{value}".into())));
+    }
+    for (name, command) in &config.aliases {
+        rows.push((format!("aliases.{}", name), command.clone()));
+    }
+
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    table.set_titles(Row::new(["Setting", "Value", "Source"]
+        .iter().map(|x| table::header_cell(x)).collect()));
+    for (name, value) in rows {
+        table.add_row(Row::new(vec![
+            Cell::new(&name),
+            Cell::new(&value),
+            Cell::new(&config.source(&name).to_string()),
+        ]));
+    }
+    table.printstd();
+
+    if !config.warnings.is_empty() {
+        println!("Found {} problem(s):", config.warnings.len());
+        for warning in &config.warnings {
+            println!("  {}", warning);
+        }
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
@@ -12,9 +12,12 @@ use edgedb_protocol::value::Value;
 use edgedb_client::client::Connection;
 
 use crate::platform::tmp_file_name;
+use crate::protocol_trace;
 use crate::commands::Options;
-use crate::commands::list_databases::get_databases;
+use crate::commands::anonymize::{self, Rules};
+use crate::commands::fan_out;
 use crate::commands::parser::{Dump as DumpOptions, DumpFormat};
+use crate::warnings;
 
 
 type Output = Box<dyn Write + Unpin + Send>;
@@ -62,6 +65,9 @@ pub async fn dump(cli: &mut Connection, general: &Options,
     options: &DumpOptions)
     -> Result<(), anyhow::Error>
 {
+    let rules = options.anonymize.as_ref()
+        .map(|path| anonymize::read_rules(path))
+        .transpose()?;
     if options.all {
         if let Some(dformat) = options.format {
             if dformat != DumpFormat::Dir {
@@ -70,16 +76,21 @@ pub async fn dump(cli: &mut Connection, general: &Options,
         } else {
             anyhow::bail!("`--format=dir` is required when using `--all`");
         }
-        dump_all(cli, general, options.path.as_ref()).await
+        dump_all(cli, general, options.path.as_ref(), options.parallel,
+                 false, rules.as_ref()).await
     } else {
         if options.format.is_some() {
             anyhow::bail!("`--format` is reserved for dump using `--all`");
         }
-        dump_db(cli, general, options.path.as_ref()).await
+        if options.parallel {
+            anyhow::bail!("`--parallel` requires `--all`");
+        }
+        dump_db(cli, general, options.path.as_ref(), rules.as_ref()).await
     }
 }
 
-async fn dump_db(cli: &mut Connection, _options: &Options, filename: &Path)
+async fn dump_db(cli: &mut Connection, _options: &Options, filename: &Path,
+    rules: Option<&Rules>)
     -> Result<(), anyhow::Error>
 {
     let mut seq = cli.start_sequence().await?;
@@ -89,20 +100,31 @@ async fn dump_db(cli: &mut Connection, _options: &Options, filename: &Path)
           \x00\x00\x00\x00\x00\x00\x00\x01"
         ).await?;
 
-    seq.send_messages(&[
+    seq.send_messages(protocol_trace::sent(&[
         ClientMessage::Dump(Dump {
             headers: Default::default(),
         }),
         ClientMessage::Sync,
-    ]).await?;
+    ])).await?;
 
     let mut header_buf = Vec::with_capacity(25);
-    let msg = seq.message().await?;
+    let mut anonymizer = None;
+    let msg = loop {
+        let msg = seq.message().await?;
+        protocol_trace::received(&msg);
+        match msg {
+            ServerMessage::LogMessage(msg) => warnings::handle(&msg)?,
+            msg => break msg,
+        }
+    };
     match msg {
         ServerMessage::DumpHeader(packet) => {
             // this is ensured because length in the protocol is u32 too
             assert!(packet.data.len() <= u32::max_value() as usize);
 
+            if let Some(rules) = rules {
+                anonymizer = Some(rules.prepare(&packet.data)?);
+            }
             header_buf.truncate(0);
             header_buf.push(b'H');
             header_buf.extend(
@@ -124,12 +146,16 @@ async fn dump_db(cli: &mut Connection, _options: &Options, filename: &Path)
     }
     loop {
         let msg = seq.message().await?;
+        protocol_trace::received(&msg);
         match msg {
             ServerMessage::CommandComplete(..) => {
                 seq.expect_ready().await?;
                 break;
             }
-            ServerMessage::DumpBlock(packet) => {
+            ServerMessage::DumpBlock(mut packet) => {
+                if let Some(anonymizer) = &anonymizer {
+                    packet.data = anonymizer.rewrite_block(packet.data)?;
+                }
                 // this is ensured because length in the protocol is u32 too
                 assert!(packet.data.len() <= u32::max_value() as usize);
 
@@ -147,6 +173,7 @@ async fn dump_db(cli: &mut Connection, _options: &Options, filename: &Path)
                 return Err(anyhow::anyhow!(err)
                     .context("Error receiving dump block"));
             }
+            ServerMessage::LogMessage(msg) => warnings::handle(&msg)?,
             _ => {
                 return Err(anyhow::anyhow!(
                     "WARNING: unsolicited message {:?}", msg));
@@ -173,10 +200,14 @@ async fn get_text(cli: &mut Connection, query: &str)
     Ok(text)
 }
 
-pub async fn dump_all(cli: &mut Connection, options: &Options, dir: &Path)
+/// Dumps the instance config, roles and every database into `dir`
+///
+/// With `stop_on_error` databases are dumped one by one and the first
+/// failure is returned, otherwise all databases are attempted.
+pub async fn dump_all(cli: &mut Connection, options: &Options, dir: &Path,
+    parallel: bool, stop_on_error: bool, rules: Option<&Rules>)
     -> Result<(), anyhow::Error>
 {
-    let databases = get_databases(cli).await?;
     let config = get_text(cli, "DESCRIBE SYSTEM CONFIG").await?;
     let roles = get_text(cli, "DESCRIBE ROLES").await?;
 
@@ -195,14 +226,13 @@ pub async fn dump_all(cli: &mut Connection, options: &Options, dir: &Path)
     }
     guard.commit().await?;
 
-    let mut conn_params = options.conn_params.clone();
-    for database in &databases {
-        let mut db_conn = conn_params
-            .modify(|p| { p.database(database); })
-            .connect().await?;
-        let filename = dir.join(urlencoding::encode(database) + ".dump");
-        dump_db(&mut db_conn, options, &filename).await?;
+    let dump = |mut db_conn: Connection, database: String| async move {
+        let filename = dir.join(urlencoding::encode(&database) + ".dump");
+        dump_db(&mut db_conn, options, &filename, rules).await
+    };
+    if stop_on_error {
+        fan_out::run_until_failure(cli, options, dump).await
+    } else {
+        fan_out::run(cli, options, parallel, dump).await
     }
-
-    Ok(())
 }
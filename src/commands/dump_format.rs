@@ -0,0 +1,90 @@
+//! Helpers for reading and writing packets of the dump file
+use std::convert::TryInto;
+
+use uuid::Uuid;
+
+
+pub const BLOCK_ID: u16 = 110;
+pub const BLOCK_DATA: u16 = 112;
+
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Reader<'a> {
+        Reader { buf }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+    pub fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.buf)
+    }
+    pub fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.buf.len() < len {
+            anyhow::bail!("unexpected end of data");
+        }
+        let (data, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(data)
+    }
+    pub fn u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+    pub fn i16(&mut self) -> anyhow::Result<i16> {
+        Ok(i16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+    pub fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+    pub fn i32(&mut self) -> anyhow::Result<i32> {
+        Ok(i32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+    pub fn uuid(&mut self) -> anyhow::Result<Uuid> {
+        Ok(Uuid::from_slice(self.bytes(16)?)?)
+    }
+    pub fn sized(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+    pub fn string(&mut self) -> anyhow::Result<&'a str> {
+        Ok(std::str::from_utf8(self.sized()?)?)
+    }
+}
+
+pub fn write_sized(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend(&(data.len() as u32).to_be_bytes());
+    out.extend(data);
+}
+
+/// Returns schema DDL stored in the dump header
+pub fn schema_ddl(header: &[u8]) -> anyhow::Result<&str> {
+    let mut buf = Reader::new(header);
+    for _ in 0..buf.u16()? {
+        buf.u16()?;
+        buf.sized()?;
+    }
+    buf.bytes(4)?;  // major and minor version
+    buf.string()
+}
+
+/// Returns key-value attributes of a data block
+pub fn block_attributes(block: &[u8]) -> anyhow::Result<Vec<(u16, &[u8])>> {
+    let mut buf = Reader::new(block);
+    let mut attributes = Vec::new();
+    for _ in 0..buf.u16()? {
+        let key = buf.u16()?;
+        attributes.push((key, buf.sized()?));
+    }
+    Ok(attributes)
+}
+
+/// Returns id of the type or pointer the data block belongs to
+pub fn block_id(attributes: &[(u16, &[u8])]) -> anyhow::Result<Option<Uuid>> {
+    let id = attributes.iter()
+        .find(|(key, _)| *key == BLOCK_ID)
+        .map(|(_, value)| Uuid::from_slice(value))
+        .transpose()?;
+    Ok(id)
+}
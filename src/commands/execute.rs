@@ -2,16 +2,46 @@ use edgeql_parser::helpers::quote_name;
 use edgedb_client::client::Connection;
 use edgedb_client::server_params::PostgresAddress;
 
+use crate::classify;
 use crate::commands::{self, Options};
-use crate::commands::parser::Common;
+use crate::commands::parser::{Common, ExtensionCommand};
 use crate::print;
 use crate::migrations;
 
 
+/// Name of the command if it always modifies the database
+pub fn mutating_command(cmd: &Common) -> Option<&'static str> {
+    use Common::*;
+    match cmd {
+        CreateDatabase(_) => Some("create-database"),
+        Configure(_) => Some("configure"),
+        Extension(c) => match c.command {
+            ExtensionCommand::List => None,
+            ExtensionCommand::Install(_) => Some("extension install"),
+            ExtensionCommand::Enable(_) => Some("extension enable"),
+            ExtensionCommand::Disable(_) => Some("extension disable"),
+        },
+        Restore(_) => Some("restore"),
+        Migrate(_) => Some("migrate"),
+        _ => None,
+    }
+}
+
+/// The statement run by the command, for the checks applied to queries
+pub fn statement(cmd: &Common) -> Option<String> {
+    match cmd {
+        Common::Extension(ext) => commands::extension::statement(ext),
+        _ => None,
+    }
+}
+
 pub async fn common(cli: &mut Connection, cmd: &Common, options: &Options)
     -> Result<(), anyhow::Error>
 {
     use Common::*;
+    if let Some(name) = mutating_command(cmd) {
+        classify::guard_command(name)?;
+    }
     match cmd {
         ListAliases(c) => {
             commands::list_aliases(cli, &options,
@@ -43,6 +73,14 @@ pub async fn common(cli: &mut Connection, cmd: &Common, options: &Options)
             commands::list_modules(cli, &options,
                 &c.pattern, c.case_sensitive).await?;
         }
+        ListFunctions(c) => {
+            commands::list_functions(cli, &options,
+                &c.pattern, c.system, c.case_sensitive).await?;
+        }
+        ListExtensions(c) => {
+            commands::list_extensions(cli, &options,
+                &c.pattern, c.case_sensitive).await?;
+        }
         ListRoles(c) => {
             commands::list_roles(cli, &options,
                 &c.pattern, c.case_sensitive).await?;
@@ -73,6 +111,9 @@ pub async fn common(cli: &mut Connection, cmd: &Common, options: &Options)
         Configure(c) => {
             commands::configure(cli, &options, c).await?;
         }
+        Extension(c) => {
+            commands::extension(cli, &options, c).await?;
+        }
         CreateDatabase(c) => {
             print::completion(&cli.execute(
                 &format!("CREATE DATABASE {}", quote_name(&c.database_name))
@@ -81,6 +122,12 @@ pub async fn common(cli: &mut Connection, cmd: &Common, options: &Options)
         CreateMigration(params) => {
             migrations::create(cli, &options, params).await?;
         }
+        Migrate(params) if params.all_databases => {
+            commands::fan_out::run(cli, &options, params.parallel,
+                |mut conn, _| async move {
+                    migrations::migrate(&mut conn, &options, params).await
+                }).await?;
+        }
         Migrate(params) => {
             migrations::migrate(cli, &options, params).await?;
         }
@@ -90,6 +137,12 @@ pub async fn common(cli: &mut Connection, cmd: &Common, options: &Options)
         MigrationLog(params) => {
             migrations::log(cli, &options, params).await?;
         }
+        MigrationPlan(params) => {
+            migrations::plan(&options, params).await?;
+        }
+        MigrationCheck(params) => {
+            migrations::check(cli, &options, params).await?;
+        }
     }
     Ok(())
 }
@@ -1,5 +1,32 @@
+//! Exit codes of the command-line tool
+//!
+//! Codes below 10 are specific to the command, e.g. `edgedb migration
+//! status` exits with 2 and 3, and are returned with `ExitCode`. Errors
+//! which aren't handled by the command are mapped to the codes below by
+//! the class of the error.
 use std::process::exit;
 
+use edgedb_protocol::error_response::ErrorResponse;
+
+use crate::bug::Bug;
+use crate::commands::fan_out::PartialSuccess;
+use crate::connect::ConnectionFailed;
+use crate::hint::HintedError;
+use crate::interactive::{Interrupted, QueryError};
+use crate::migrations::MigrationConflict;
+use crate::variables::Canceled;
+
+
+pub const FAILURE: i32 = 1;
+pub const CONNECTION_FAILED: i32 = 10;
+pub const QUERY_ERROR: i32 = 11;
+pub const MIGRATION_CONFLICT: i32 = 12;
+pub const BUG: i32 = 13;
+pub const PARTIAL_SUCCESS: i32 = 14;
+/// Command succeeded but warnings were printed with `--fail-on=warnings`
+pub const WARNINGS: i32 = 15;
+/// Interrupted by Ctrl+C or canceled at a prompt
+pub const ABORTED: i32 = 130;
 
 #[derive(Debug, thiserror::Error)]
 #[error("Exit with status {}", _0)]
@@ -16,3 +43,28 @@ impl ExitCode {
         exit(self.code())
     }
 }
+
+/// Exit code for the error, the outermost classified error wins
+pub fn error_code(err: &anyhow::Error) -> i32 {
+    for item in err.chain() {
+        if let Some(e) = item.downcast_ref::<ExitCode>() {
+            return e.code();
+        } else if let Some(e) = item.downcast_ref::<HintedError>() {
+            // the chain continues with the source of the hinted error
+            return error_code(&e.error);
+        } else if item.is::<Bug>() {
+            return BUG;
+        } else if item.is::<ConnectionFailed>() {
+            return CONNECTION_FAILED;
+        } else if item.is::<MigrationConflict>() {
+            return MIGRATION_CONFLICT;
+        } else if item.is::<PartialSuccess>() {
+            return PARTIAL_SUCCESS;
+        } else if item.is::<Interrupted>() || item.is::<Canceled>() {
+            return ABORTED;
+        } else if item.is::<QueryError>() || item.is::<ErrorResponse>() {
+            return QUERY_ERROR;
+        }
+    }
+    FAILURE
+}
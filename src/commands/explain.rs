@@ -0,0 +1,32 @@
+use prettytable::{Table, Row, Cell};
+
+use crate::options::Options;
+use crate::table;
+
+
+pub fn explain(options: &Options) -> anyhow::Result<()> {
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    table.set_titles(Row::new(["Parameter", "Value", "Source"]
+        .iter().map(|x| table::header_cell(x)).collect()));
+    for param in &options.conn_sources {
+        table.add_row(Row::new(vec![
+            Cell::new(param.name),
+            Cell::new(&param.value),
+            Cell::new(&param.source.to_string()),
+        ]));
+    }
+    if !table.is_empty() {
+        table.printstd();
+    }
+    match options.conn_params.get() {
+        Ok(params) => {
+            println!("Resolved address: {}", params.get_addr());
+            Ok(())
+        }
+        Err(e) => {
+            Err(anyhow::anyhow!(e)
+                .context("connection parameters cannot be resolved"))
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use async_std::prelude::StreamExt;
+
+use edgeql_parser::helpers::quote_name;
+use prettytable::{Table, Row, Cell};
+
+use edgedb_derive::Queryable;
+use edgedb_protocol::value::Value;
+use crate::classify;
+use crate::commands::Options;
+use crate::commands::parser::{Extension, ExtensionCommand};
+use crate::hint::HintExt;
+use crate::print;
+use edgedb_client::client::Connection;
+use crate::table;
+
+
+#[derive(Queryable)]
+struct Package {
+    name: String,
+    version: String,
+    enabled: bool,
+}
+
+async fn list(cli: &mut Connection, options: &Options)
+    -> Result<(), anyhow::Error>
+{
+    let mut items = cli.query::<Package>(r###"
+        WITH MODULE sys
+        SELECT ExtensionPackage {
+            name,
+            version := <str>.version.major ++ '.' ++ <str>.version.minor,
+            enabled := EXISTS (
+                SELECT schema::Extension
+                FILTER .name = ExtensionPackage.name
+            ),
+        }
+        ORDER BY .name;
+    "###, &Value::empty_tuple()).await?;
+    if !options.command_line || atty::is(atty::Stream::Stdout) {
+        let mut table = Table::new();
+        table.set_format(*table::FORMAT);
+        table.set_titles(Row::new(
+            ["Name", "Version", "Enabled"]
+            .iter().map(|x| table::header_cell(x)).collect()));
+        while let Some(item) = items.next().await.transpose()? {
+            table.add_row(Row::new(vec![
+                Cell::new(&item.name),
+                Cell::new(&item.version),
+                Cell::new(if item.enabled { "yes" } else { "no" }),
+            ]));
+        }
+        if table.is_empty() {
+            eprintln!("No extensions are available in this server");
+        } else {
+            table.printstd();
+        }
+    } else {
+        while let Some(item) = items.next().await.transpose()? {
+            println!("{}\t{}\t{}", item.name, item.version, item.enabled);
+        }
+    }
+    Ok(())
+}
+
+/// DDL run by the command
+pub fn statement(ext: &Extension) -> Option<String> {
+    match &ext.command {
+        ExtensionCommand::List => None,
+        ExtensionCommand::Install(e) | ExtensionCommand::Enable(e) => {
+            Some(format!("CREATE EXTENSION {};", quote_name(&e.name)))
+        }
+        ExtensionCommand::Disable(e) => {
+            Some(format!("DROP EXTENSION {};", quote_name(&e.name)))
+        }
+    }
+}
+
+async fn install(cli: &mut Connection, name: &str, statement: &str)
+    -> Result<(), anyhow::Error>
+{
+    let mut items = cli.query::<Package>(r###"
+        WITH MODULE sys
+        SELECT ExtensionPackage {
+            name,
+            version := <str>.version.major ++ '.' ++ <str>.version.minor,
+            enabled := EXISTS (
+                SELECT schema::Extension
+                FILTER .name = ExtensionPackage.name
+            ),
+        }
+        FILTER .name = <str>$0;
+    "###, &Value::Tuple(vec![Value::Str(name.into())])).await?;
+    let mut packages = Vec::new();
+    while let Some(item) = items.next().await.transpose()? {
+        packages.push(item);
+    }
+    let package = match packages.pop() {
+        Some(package) => package,
+        None => {
+            return Err(anyhow::anyhow!(
+                "extension {:?} is not shipped with this server", name))
+                .hint("Extension packages are installed with the server, \
+                       upgrade the instance with `edgedb server upgrade` \
+                       to a version which includes it")?;
+        }
+    };
+    if package.enabled {
+        eprintln!("Extension {} {} is already enabled",
+                  package.name, package.version);
+        return Ok(());
+    }
+    print::completion(&cli.execute(statement).await?);
+    eprintln!("Extension {} {} is enabled, no restart is required",
+              package.name, package.version);
+    Ok(())
+}
+
+pub async fn extension(cli: &mut Connection, options: &Options,
+    ext: &Extension)
+    -> Result<(), anyhow::Error>
+{
+    let statement = match statement(ext) {
+        Some(statement) => statement,
+        None => return list(cli, options).await,
+    };
+    classify::guard(&statement)?;
+    match &ext.command {
+        ExtensionCommand::Install(e) => {
+            install(cli, &e.name, &statement).await?;
+        }
+        _ => print::completion(&cli.execute(&statement).await?),
+    }
+    Ok(())
+}
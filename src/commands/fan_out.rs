@@ -0,0 +1,142 @@
+//! Running a command against every database of the instance
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use anyhow::Context as _;
+use edgedb_client::client::Connection;
+
+use crate::commands::Options;
+use crate::commands::list_databases::get_databases;
+
+
+/// Command succeeded on some of the databases and failed on the others
+#[derive(Debug, thiserror::Error)]
+#[error("{failed} of {total} databases failed")]
+pub struct PartialSuccess {
+    pub failed: usize,
+    pub total: usize,
+}
+
+/// Polls all futures concurrently within the current task
+struct JoinAll<F: Future> {
+    futures: Vec<Option<Pin<Box<F>>>>,
+    results: Vec<Option<F::Output>>,
+}
+
+impl<F: Future> JoinAll<F> {
+    fn new(futures: impl IntoIterator<Item=F>) -> JoinAll<F> {
+        let futures = futures.into_iter()
+            .map(|f| Some(Box::pin(f)))
+            .collect::<Vec<_>>();
+        let results = futures.iter().map(|_| None).collect();
+        JoinAll { futures, results }
+    }
+}
+
+impl<F: Future> Future for JoinAll<F> {
+    type Output = Vec<F::Output>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut done = true;
+        let pairs = this.futures.iter_mut().zip(this.results.iter_mut());
+        for (future, result) in pairs {
+            if let Some(fut) = future {
+                let poll = fut.as_mut().poll(cx);
+                match poll {
+                    Poll::Ready(value) => {
+                        *result = Some(value);
+                        *future = None;
+                    }
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        if done {
+            Poll::Ready(this.results.drain(..)
+                .map(|r| r.expect("all futures are complete"))
+                .collect())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+async fn run_one<F, Fut>(options: &Options, database: &str, f: &F)
+    -> anyhow::Result<()>
+    where F: Fn(Connection, String) -> Fut,
+          Fut: Future<Output=anyhow::Result<()>>,
+{
+    let mut conn_params = options.conn_params.clone();
+    conn_params.modify(|p| { p.database(database); });
+    let conn = conn_params.connect().await?;
+    f(conn, database.into()).await
+}
+
+fn print_summary(results: &[(String, anyhow::Result<()>)]) {
+    let width = results.iter().map(|(name, _)| name.len()).max()
+        .unwrap_or(0);
+    eprintln!("Summary:");
+    for (name, result) in results {
+        match result {
+            Ok(()) => eprintln!("  {:width$}  OK", name, width=width),
+            Err(e) => {
+                eprintln!("  {:width$}  FAILED: {:#}", name, e, width=width)
+            }
+        }
+    }
+}
+
+/// Runs `f` with a connection to each non-builtin database
+///
+/// Databases are processed one by one unless `parallel` is set. Failure on
+/// one database doesn't stop the others, the error is reported in the
+/// summary printed at the end.
+pub async fn run<F, Fut>(cli: &mut Connection, options: &Options,
+    parallel: bool, f: F)
+    -> anyhow::Result<()>
+    where F: Fn(Connection, String) -> Fut,
+          Fut: Future<Output=anyhow::Result<()>>,
+{
+    let databases = get_databases(cli).await?;
+    if databases.is_empty() {
+        eprintln!("No databases found.");
+        return Ok(());
+    }
+    let results = if parallel {
+        let outcomes = JoinAll::new(databases.iter()
+            .map(|name| run_one(options, name, &f))).await;
+        databases.into_iter().zip(outcomes).collect::<Vec<_>>()
+    } else {
+        let mut results = Vec::with_capacity(databases.len());
+        for name in databases {
+            eprintln!("Database {}:", name);
+            let result = run_one(options, &name, &f).await;
+            results.push((name, result));
+        }
+        results
+    };
+    print_summary(&results);
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    if failed == results.len() {
+        anyhow::bail!("all {} databases failed", failed);
+    } else if failed > 0 {
+        return Err(PartialSuccess { failed, total: results.len() })?;
+    }
+    Ok(())
+}
+
+/// Runs `f` with a connection to each non-builtin database in turn and
+/// stops at the first failure
+pub async fn run_until_failure<F, Fut>(cli: &mut Connection,
+    options: &Options, f: F)
+    -> anyhow::Result<()>
+    where F: Fn(Connection, String) -> Fut,
+          Fut: Future<Output=anyhow::Result<()>>,
+{
+    for name in get_databases(cli).await? {
+        run_one(options, &name, &f).await
+            .with_context(|| format!("database {:?} failed", name))?;
+    }
+    Ok(())
+}
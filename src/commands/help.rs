@@ -0,0 +1,235 @@
+//! Documentation shown by `\help TOPIC`
+//!
+//! Backslash commands are described by the `\help` output itself, EdgeQL
+//! keywords and standard library functions come from the snapshot in
+//! `help_topics.txt`. A topic is looked up by name first, then the words
+//! of the query are searched in the text of all topics.
+use std::collections::{BTreeMap, BTreeSet};
+
+use once_cell::sync::Lazy;
+
+use crate::commands::backslash::HELP;
+use crate::pager;
+use crate::typos::closest;
+
+
+const TOPICS: &str = include_str!("help_topics.txt");
+const MAX_RESULTS: usize = 10;
+const DESCRIPTION_COLUMN: usize = 27;
+
+static INDEX: Lazy<Index> = Lazy::new(|| Index::new(HELP, TOPICS));
+
+#[derive(Debug)]
+struct Topic {
+    kind: &'static str,
+    names: Vec<String>,
+    text: String,
+}
+
+struct Index {
+    topics: Vec<Topic>,
+    /// Lowercase names of the topics
+    names: BTreeMap<String, usize>,
+    /// Lowercase words of the text and names
+    words: BTreeMap<String, BTreeSet<usize>>,
+}
+
+fn words(text: &str) -> impl Iterator<Item=String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| w.len() > 1)
+        .map(|w| w.to_lowercase())
+}
+
+fn backslash_topics(help: &str) -> Vec<Topic> {
+    let mut topics: Vec<Topic> = Vec::new();
+    let mut current = None;
+    let indent = " ".repeat(DESCRIPTION_COLUMN);
+    for line in help.lines() {
+        if line.starts_with("  \\") {
+            let split = DESCRIPTION_COLUMN.min(line.len());
+            let mut names = line[..split].split_whitespace()
+                .filter(|w| w.starts_with('\\'))
+                .map(|w| w.trim_end_matches(',').to_string())
+                .collect::<Vec<_>>();
+            if let Some(alias) = line[split..].find("(alias: ") {
+                let alias = &line[split+alias+"(alias: ".len()..];
+                names.push(alias.trim_end_matches(')').to_string());
+            }
+            // e.g. `\extension list` and `\extension enable`
+            let existing = topics.iter()
+                .position(|t| t.names.contains(&names[0]));
+            let idx = match existing {
+                Some(idx) => idx,
+                None => {
+                    topics.push(Topic {
+                        kind: "backslash command",
+                        names,
+                        text: String::new(),
+                    });
+                    topics.len() - 1
+                }
+            };
+            topics[idx].text.push_str(line.trim_start());
+            topics[idx].text.push('\n');
+            current = Some(idx);
+        } else if line.starts_with(&indent) {
+            if let Some(idx) = current {
+                let line = &line[DESCRIPTION_COLUMN..];
+                if let Some(alias) = line.trim().strip_prefix("(alias: ") {
+                    topics[idx].names.push(
+                        alias.trim_end_matches(')').to_string());
+                }
+                // first lines are unindented
+                topics[idx].text.push_str(&indent[2..]);
+                topics[idx].text.push_str(line);
+                topics[idx].text.push('\n');
+            }
+        } else {
+            current = None;
+        }
+    }
+    topics
+}
+
+fn snapshot_topics(text: &str) -> Vec<Topic> {
+    let mut topics = Vec::new();
+    for chunk in text.split("\n## ").skip(1) {
+        let (header, body) = match chunk.find('\n') {
+            Some(pos) => (&chunk[..pos], chunk[pos+1..].trim_end()),
+            None => (chunk, ""),
+        };
+        let (kind, name) = match header.find(' ') {
+            Some(pos) => (&header[..pos], header[pos+1..].trim()),
+            None => continue,
+        };
+        let mut names = vec![name.to_string()];
+        if kind == "function" {
+            if let Some(pos) = name.rfind("::") {
+                names.push(name[pos+2..].to_string());
+            }
+        }
+        topics.push(Topic {
+            kind: match kind {
+                "keyword" => "EdgeQL keyword",
+                "function" => "function",
+                _ => "topic",
+            },
+            names,
+            text: format!("{}\n", body),
+        });
+    }
+    topics
+}
+
+impl Index {
+    fn new(help: &str, snapshot: &str) -> Index {
+        let mut topics = snapshot_topics(snapshot);
+        topics.extend(backslash_topics(help));
+        let mut names = BTreeMap::new();
+        let mut index = BTreeMap::new();
+        for (idx, topic) in topics.iter().enumerate() {
+            for name in &topic.names {
+                // short function names may clash, the first one wins
+                names.entry(name.to_lowercase()).or_insert(idx);
+            }
+            let text = topic.names.iter().map(|n| &n[..])
+                .chain(Some(&topic.text[..]));
+            for word in text.flat_map(words) {
+                index.entry(word).or_insert_with(BTreeSet::new).insert(idx);
+            }
+        }
+        Index { topics, names, words: index }
+    }
+    fn find(&self, query: &str) -> Option<&Topic> {
+        let name = query.trim().trim_end_matches("()").to_lowercase();
+        self.names.get(&name)
+            .or_else(|| self.names.get(&format!("\\{}", name)))
+            .map(|&idx| &self.topics[idx])
+    }
+    fn search(&self, query: &str) -> Vec<&Topic> {
+        let mut scores = BTreeMap::new();
+        for word in words(query) {
+            for &idx in self.words.get(&word).into_iter().flatten() {
+                let in_name = self.topics[idx].names.iter()
+                    .any(|n| words(n).any(|w| w == word));
+                let score = if in_name { 3 } else { 1 };
+                *scores.entry(idx).or_insert(0) += score;
+            }
+        }
+        let mut found = scores.into_iter().collect::<Vec<_>>();
+        found.sort_by_key(|&(idx, score)| (-score, idx));
+        found.into_iter()
+            .take(MAX_RESULTS)
+            .map(|(idx, _)| &self.topics[idx])
+            .collect()
+    }
+    fn similar_name(&self, query: &str) -> Option<&str> {
+        closest(&query.to_lowercase(), self.names.keys().map(|n| &n[..]))
+    }
+}
+
+impl Topic {
+    fn summary(&self) -> &str {
+        self.text.lines().next().unwrap_or("").trim()
+    }
+    fn render(&self) -> String {
+        format!("{} ({})\n\n{}", self.names.join(", "), self.kind,
+                self.text)
+    }
+}
+
+/// Shows the topic, or the list of topics matching the query
+pub fn help(query: &str) -> anyhow::Result<()> {
+    if let Some(topic) = INDEX.find(query) {
+        return pager::page(&topic.render());
+    }
+    let found = INDEX.search(query);
+    if found.is_empty() {
+        match INDEX.similar_name(query) {
+            Some(name) => {
+                println!("No help on `{}`, did you mean `{}`?",
+                         query, name);
+            }
+            None => println!("No help on `{}`", query),
+        }
+        return Ok(());
+    }
+    let width = found.iter().map(|t| t.names[0].len()).max().unwrap_or(0);
+    let mut text = format!("Topics matching `{}`:\n", query);
+    for topic in found {
+        text.push_str(&format!("  {:width$}  {}\n",
+                               topic.names[0], topic.summary(),
+                               width=width));
+    }
+    text.push_str("Type `\\help NAME` to read the topic\n");
+    pager::page(&text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HELP, TOPICS, Index};
+
+    #[test]
+    fn names() {
+        let index = Index::new(HELP, TOPICS);
+        assert_eq!(index.find("len()").unwrap().names[0], "std::len");
+        assert_eq!(index.find("std::LEN").unwrap().names[0], "std::len");
+        assert_eq!(index.find("select").unwrap().kind, "EdgeQL keyword");
+        assert_eq!(index.find("\\c").unwrap().names[1], "\\connect");
+        assert_eq!(index.find("gset").unwrap().names[0], "\\gset");
+        assert!(index.find("\\lt").unwrap().names
+                .contains(&"\\list-object-types".to_string()));
+        assert!(index.find("\\extension").unwrap().text.contains("enable"));
+        assert!(index.find("nothing").is_none());
+    }
+
+    #[test]
+    fn search() {
+        let index = Index::new(HELP, TOPICS);
+        let found = index.search("regular expression");
+        assert!(found.iter().any(|t| t.names[0] == "std::re_test"));
+        assert_eq!(index.search("transaction")[0].names[0],
+                   "START TRANSACTION");
+        assert!(index.search("xyzzy").is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+
+use anyhow::Context;
+use async_std::prelude::StreamExt;
+use edgedb_client::client::Connection;
+use edgedb_protocol::value::Value;
+use edgeql_parser::helpers::{quote_name, quote_string};
+use serde::Deserialize;
+use serde_json::{Map, Value as Json};
+
+use crate::commands::{ExitCode, Options};
+use crate::options::Insert;
+
+
+#[derive(Deserialize, Debug)]
+pub struct Pointer {
+    pub name: String,
+    pub target: String,
+    /// Names of ancestors of the target (for scalar types)
+    pub ancestors: Vec<String>,
+    pub required: bool,
+    pub multi: bool,
+    pub has_default: bool,
+    pub computed: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TypeInfo {
+    pub name: String,
+    pub properties: Vec<Pointer>,
+    pub links: Vec<Pointer>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    String,
+    Number,
+    Bool,
+    Any,
+}
+
+struct Item {
+    line: usize,
+    data: Map<String, Json>,
+}
+
+/// Adds `default::` module if name is not qualified
+pub fn full_type_name(name: &str) -> String {
+    if name.contains("::") {
+        name.into()
+    } else {
+        format!("default::{}", name)
+    }
+}
+
+/// Quotes each part of the qualified name
+pub fn quote_type(name: &str) -> String {
+    name.split("::").map(quote_name).collect::<Vec<_>>().join("::")
+}
+
+pub async fn introspect(conn: &mut Connection, name: &str)
+    -> anyhow::Result<TypeInfo>
+{
+    let mut rows = conn.query_json(r###"
+        WITH MODULE schema
+        SELECT ObjectType {
+            name,
+            properties: {
+                name,
+                target := .target.name,
+                ancestors := array_agg(
+                    .target[IS ScalarType].ancestors.name),
+                required,
+                multi := .cardinality = Cardinality.Many,
+                has_default := EXISTS .default,
+                computed := EXISTS .expr,
+            } FILTER .name NOT IN {'id', '__type__'},
+            links: {
+                name,
+                target := .target.name,
+                ancestors := <array<str>>[],
+                required,
+                multi := .cardinality = Cardinality.Many,
+                has_default := EXISTS .default,
+                computed := EXISTS .expr,
+            } FILTER .name != '__type__',
+        }
+        FILTER .name = <str>$0
+        "###, &Value::Tuple(vec![Value::Str(name.into())])).await?;
+    let mut types = Vec::<TypeInfo>::new();
+    while let Some(row) = rows.next().await.transpose()? {
+        types.extend(serde_json::from_str::<Vec<TypeInfo>>(&row)
+            .context("cannot decode introspection result")?);
+    }
+    types.pop()
+        .ok_or_else(|| anyhow::anyhow!("object type {:?} is not found", name))
+}
+
+fn scalar_kind(ptr: &Pointer) -> Kind {
+    let names = std::iter::once(&ptr.target).chain(&ptr.ancestors);
+    for name in names {
+        match &name[..] {
+            "std::bool" => return Kind::Bool,
+            "std::int16" | "std::int32" | "std::int64"
+            | "std::float32" | "std::float64"
+            | "std::bigint" | "std::decimal"
+            => return Kind::Number,
+            "std::str" | "std::uuid" | "std::datetime" | "std::duration"
+            | "cal::local_datetime" | "cal::local_date" | "cal::local_time"
+            | "std::anyenum"
+            => return Kind::String,
+            _ => {}
+        }
+    }
+    Kind::Any
+}
+
+fn check_kind(name: &str, kind: Kind, value: &Json) -> Result<(), String> {
+    let valid = match (kind, value) {
+        (Kind::Any, _) => true,
+        (Kind::String, Json::String(_)) => true,
+        (Kind::Number, Json::Number(_)) => true,
+        (Kind::Bool, Json::Bool(_)) => true,
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid value of {:?}: expected {}, got {}",
+                    name, kind_name(kind), value))
+    }
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::String => "a string",
+        Kind::Number => "a number",
+        Kind::Bool => "a boolean",
+        Kind::Any => "any value",
+    }
+}
+
+/// Checks item against the schema, link keys are converted to strings
+pub fn validate(info: &TypeInfo, data: &mut Map<String, Json>)
+    -> Result<(), String>
+{
+    for (name, value) in data.iter_mut() {
+        let (ptr, is_link) = if let Some(p) = info.properties.iter()
+            .find(|p| &p.name == name)
+        {
+            (p, false)
+        } else if let Some(p) = info.links.iter().find(|p| &p.name == name) {
+            (p, true)
+        } else {
+            return Err(format!("{} has no property or link {:?}",
+                               info.name, name));
+        };
+        if value.is_null() {
+            if ptr.required {
+                return Err(format!("{:?} is required", name));
+            }
+            continue;
+        }
+        let kind = if is_link { Kind::String } else { scalar_kind(ptr) };
+        let values = if ptr.multi {
+            match value {
+                Json::Array(items) => items.iter_mut().collect(),
+                _ => return Err(format!("{:?} must be an array", name)),
+            }
+        } else {
+            vec![value]
+        };
+        for value in values {
+            if is_link {
+                // keys of linked objects are compared as strings
+                if let Json::Number(num) = value {
+                    *value = Json::String(num.to_string());
+                }
+            }
+            check_kind(name, kind, value)?;
+        }
+    }
+    for ptr in info.properties.iter().chain(&info.links) {
+        if ptr.required && !ptr.has_default && !data.contains_key(&ptr.name)
+        {
+            return Err(format!("{:?} is required", ptr.name));
+        }
+    }
+    Ok(())
+}
+
+/// Shape of the INSERT setting the fields from the JSON object in `item`
+///
+/// Only the fields present in the items are set, so that omitted ones
+/// get their default values.
+fn insert_shape(info: &TypeInfo, fields: &[String], link_by: &str)
+    -> String
+{
+    let mut shape = Vec::new();
+    for field in fields {
+        let value = format!("json_get(item, {})", quote_string(field));
+        if let Some(prop) = info.properties.iter().find(|p| &p.name == field)
+        {
+            let typ = quote_type(&prop.target);
+            if prop.multi {
+                shape.push(format!("{} := array_unpack(<array<{}>>{})",
+                                   quote_name(field), typ, value));
+            } else {
+                shape.push(format!("{} := <{}>{}",
+                                   quote_name(field), typ, value));
+            }
+        } else if let Some(link) = info.links.iter()
+            .find(|p| &p.name == field)
+        {
+            let target = quote_type(&link.target);
+            let key = format!("<str>.{}", quote_name(link_by));
+            if link.multi {
+                shape.push(format!(
+                    "{} := (SELECT DETACHED {} \
+                     FILTER {} IN array_unpack(<array<str>>{}))",
+                    quote_name(field), target, key, value));
+            } else {
+                shape.push(format!(
+                    "{} := (SELECT DETACHED {} FILTER {} = <str>{} LIMIT 1)",
+                    quote_name(field), target, key, value));
+            }
+        }
+    }
+    shape.join(",\n    ")
+}
+
+/// Builds a query which inserts items from the JSON array in `$0`
+fn insert_query(info: &TypeInfo, fields: &[String], link_by: &str)
+    -> String
+{
+    format!("SELECT count((\n\
+        FOR item IN {{json_array_unpack(<json>$0)}}\n\
+        UNION (INSERT {} {{\n    {}\n}})\n))",
+        quote_type(&info.name), insert_shape(info, fields, link_by))
+}
+
+/// Builds a query which inserts the JSON object in `$0` and returns its id
+pub fn insert_object_query(info: &TypeInfo, fields: &[String],
+    link_by: &str)
+    -> String
+{
+    format!("WITH item := <json>$0\n\
+        SELECT (INSERT {} {{\n    {}\n}}) {{ id }}",
+        quote_type(&info.name), insert_shape(info, fields, link_by))
+}
+
+async fn insert_batch(conn: &mut Connection, query: &str, items: &[&Item])
+    -> anyhow::Result<i64>
+{
+    let data = Json::Array(items.iter()
+        .map(|item| Json::Object(item.data.clone()))
+        .collect());
+    let mut rows = conn.query::<i64>(query,
+        &Value::Tuple(vec![Value::Json(data.to_string())])).await?;
+    let mut count = 0;
+    while let Some(num) = rows.next().await.transpose()? {
+        count += num;
+    }
+    Ok(count)
+}
+
+fn read_input(insert: &Insert) -> anyhow::Result<Box<dyn BufRead>> {
+    if insert.from_json.to_str() == Some("-") {
+        Ok(Box::new(BufReader::new(io::stdin())))
+    } else {
+        let file = fs::File::open(&insert.from_json)
+            .with_context(|| format!("cannot open {}",
+                                     insert.from_json.display()))?;
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+pub async fn insert(options: &Options, insert: &Insert)
+    -> anyhow::Result<()>
+{
+    let mut conn = options.conn_params.connect().await?;
+    let info = introspect(&mut conn, &full_type_name(&insert.type_name))
+        .await?;
+    let input = read_input(insert)?;
+    let mut errors = 0;
+    let mut inserted = 0;
+    let mut batch = Vec::new();
+    let mut lines = input.lines().enumerate().peekable();
+    while let Some((idx, line)) = lines.next() {
+        let line_no = idx + 1;
+        let line = line.context("error reading input")?;
+        if !line.trim().is_empty() {
+            let parsed = serde_json::from_str::<Json>(&line)
+                .map_err(|e| e.to_string())
+                .and_then(|value| match value {
+                    Json::Object(data) => Ok(data),
+                    _ => Err("expected a JSON object".into()),
+                })
+                .and_then(|mut data| {
+                    validate(&info, &mut data).map(|()| data)
+                });
+            match parsed {
+                Ok(data) => batch.push(Item { line: line_no, data }),
+                Err(e) => {
+                    eprintln!("line {}: {}", line_no, e);
+                    errors += 1;
+                }
+            }
+        }
+        if batch.len() >= insert.batch_size || lines.peek().is_none() {
+            let (num, err) = flush(&mut conn, &info, insert, &batch).await?;
+            inserted += num;
+            errors += err;
+            batch.clear();
+            eprintln!("Inserted {} objects", inserted);
+        }
+    }
+    eprintln!("Done: {} inserted, {} failed", inserted, errors);
+    if errors > 0 {
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
+
+/// Inserts items grouped by the set of fields, returns number of
+/// inserted objects and number of failed items
+async fn flush(conn: &mut Connection, info: &TypeInfo, insert: &Insert,
+    batch: &[Item])
+    -> anyhow::Result<(i64, usize)>
+{
+    let mut groups = BTreeMap::<Vec<String>, Vec<&Item>>::new();
+    for item in batch {
+        let fields = item.data.keys().cloned().collect();
+        groups.entry(fields).or_default().push(item);
+    }
+    let mut inserted = 0;
+    let mut errors = 0;
+    for (fields, items) in groups {
+        let query = insert_query(info, &fields, &insert.link_by);
+        match insert_batch(conn, &query, &items).await {
+            Ok(num) => inserted += num,
+            Err(e) if items.len() == 1 => {
+                eprintln!("line {}: {:#}", items[0].line, e);
+                errors += 1;
+            }
+            Err(_) => {
+                // find out which lines fail
+                for item in items {
+                    match insert_batch(conn, &query, &[item]).await {
+                        Ok(num) => inserted += num,
+                        Err(e) => {
+                            eprintln!("line {}: {:#}", item.line, e);
+                            errors += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok((inserted, errors))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TypeInfo, Pointer, validate, insert_query};
+    use super::insert_object_query;
+
+    fn pointer(name: &str, target: &str, required: bool, multi: bool)
+        -> Pointer
+    {
+        Pointer {
+            name: name.into(),
+            target: target.into(),
+            ancestors: Vec::new(),
+            required,
+            multi,
+            has_default: false,
+            computed: false,
+        }
+    }
+
+    fn user() -> TypeInfo {
+        TypeInfo {
+            name: "default::User".into(),
+            properties: vec![
+                pointer("email", "std::str", true, false),
+                pointer("age", "std::int64", false, false),
+            ],
+            links: vec![pointer("friends", "default::User", false, true)],
+        }
+    }
+
+    fn check(text: &str) -> Result<serde_json::Value, String> {
+        let mut data = match serde_json::from_str(text).unwrap() {
+            serde_json::Value::Object(data) => data,
+            _ => unreachable!(),
+        };
+        validate(&user(), &mut data)?;
+        Ok(serde_json::Value::Object(data))
+    }
+
+    #[test]
+    fn validation() {
+        assert!(check(r#"{"email": "a@example.com", "age": 7}"#).is_ok());
+        assert_eq!(check(r#"{"email": "a@example.com", "friends": [1]}"#)
+                   .unwrap()["friends"][0], "1");
+        assert!(check(r#"{"age": 7}"#).unwrap_err().contains("required"));
+        assert!(check(r#"{"email": "x", "age": "7"}"#).is_err());
+        assert!(check(r#"{"email": "x", "friends": "y"}"#).is_err());
+        assert!(check(r#"{"email": "x", "nickname": "y"}"#).is_err());
+    }
+
+    #[test]
+    fn query() {
+        let fields = vec!["email".into(), "friends".into()];
+        assert_eq!(insert_query(&user(), &fields, "email"), "\
+SELECT count((
+FOR item IN {json_array_unpack(<json>$0)}
+UNION (INSERT default::User {
+    email := <std::str>json_get(item, 'email'),
+    friends := (SELECT DETACHED default::User \
+FILTER <str>.email IN array_unpack(<array<str>>json_get(item, 'friends')))
+})
+))");
+    }
+
+    #[test]
+    fn object_query() {
+        let fields = vec!["email".into()];
+        assert_eq!(insert_object_query(&user(), &fields, "id"), "\
+WITH item := <json>$0
+SELECT (INSERT default::User {
+    email := <std::str>json_get(item, 'email')
+}) { id }");
+    }
+}
@@ -0,0 +1,94 @@
+use async_std::prelude::StreamExt;
+
+use prettytable::{Table, Row, Cell};
+
+use edgedb_derive::Queryable;
+use crate::commands::Options;
+use crate::commands::filter;
+use edgedb_client::client::Connection;
+use crate::table;
+
+
+#[derive(Queryable)]
+struct Function {
+    name: String,
+    arguments: String,
+    return_type: String,
+    volatility_str: String,
+}
+
+pub async fn list_functions<'x>(cli: &mut Connection, options: &Options,
+    pattern: &Option<String>, system: bool, case_sensitive: bool)
+    -> Result<(), anyhow::Error>
+{
+    let pat = filter::pattern_to_value(pattern, case_sensitive);
+    let filter = match (pattern, system) {
+        (None, true) => "",
+        (None, false) => {
+            r#"FILTER NOT
+                re_test("^(?:std|schema|math|sys|cfg|cal|stdgraphql)::",
+                .name)"#
+        }
+        (Some(_), true) => "FILTER re_test(<str>$0, .name)",
+        (Some(_), false) => {
+            r#"FILTER re_test(<str>$0, .name) AND
+                NOT re_test("^(?:std|schema|math|sys|cfg|cal|stdgraphql)::",
+                .name)"#
+        }
+    };
+
+    let query = &format!(r###"
+        WITH MODULE schema
+        SELECT Function {{
+            name,
+            arguments := array_join(array_agg(
+                .params.name ++ ': ' ++ .params.type.name
+            ), ', '),
+            return_type := (
+                'SET OF ' IF .return_typemod = TypeModifier.SetOfType ELSE
+                'OPTIONAL ' IF .return_typemod = TypeModifier.OptionalType
+                ELSE ''
+            ) ++ .return_type.name,
+            volatility_str := <str>.volatility,
+        }}
+        {filter}
+        ORDER BY .name THEN .arguments;
+    "###, filter=filter);
+
+    let mut items = cli.query::<Function>(&query, &pat).await?;
+    if !options.command_line || atty::is(atty::Stream::Stdout) {
+        let term_width = term_size::dimensions_stdout()
+            .map(|(w, _h)| w).unwrap_or(80);
+        let arguments_width = (term_width-10) / 3;
+        let mut table = Table::new();
+        table.set_format(*table::FORMAT);
+        table.set_titles(Row::new(
+            ["Name", "Arguments", "Returns", "Volatility"]
+            .iter().map(|x| table::header_cell(x)).collect()));
+        while let Some(item) = items.next().await.transpose()? {
+            table.add_row(Row::new(vec![
+                Cell::new(&item.name),
+                Cell::new(&textwrap::fill(&item.arguments, arguments_width)),
+                Cell::new(&item.return_type),
+                Cell::new(&item.volatility_str),
+            ]));
+        }
+        if table.is_empty() {
+            if let Some(pattern) = pattern {
+                eprintln!("No functions found matching {:?}", pattern);
+            } else if !system {
+                eprintln!("No user-defined functions found. {}",
+                    if options.command_line { "Try --system" }
+                    else { r"Try \df -s" });
+            }
+        } else {
+            table.printstd();
+        }
+    } else {
+        while let Some(item) = items.next().await.transpose()? {
+            println!("{}\t{}\t{}\t{}", item.name, item.arguments,
+                item.return_type, item.volatility_str);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+//! Printing changes of a query result as a stream of events
+//!
+//! The server has no notifications yet, so the query of the channel is
+//! polled and consecutive results are compared. Each change is printed as
+//! a JSON object on a separate line (NDJSON). Lost connections are
+//! re-established, changes made meanwhile are reported after reconnect.
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+use async_std::task;
+use edgedb_client::client::Connection;
+use edgedb_protocol::error_response::ErrorResponse;
+use serde_json::{json, Value};
+
+use crate::classify;
+use crate::hint::HintExt;
+use crate::non_interactive::query_json_value;
+use crate::options::{Options, Listen};
+
+
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq)]
+enum Event<'a> {
+    Insert(&'a Value),
+    Update(&'a Value),
+    Delete(&'a Value),
+}
+
+fn object_id(value: &Value) -> Option<&str> {
+    value.as_object()
+        .and_then(|obj| obj.get("id"))
+        .and_then(|id| id.as_str())
+}
+
+/// Changes between two results of the query
+///
+/// Objects are matched by `id`, so changed objects are reported as
+/// updates. Rows without `id` can only be inserted or deleted.
+fn events<'a>(old: &'a [Value], new: &'a [Value]) -> Vec<Event<'a>> {
+    let by_id = old.iter().chain(new).all(|v| object_id(v).is_some());
+    let mut result = Vec::new();
+    if by_id {
+        let index = |items: &'a [Value]| items.iter()
+            .filter_map(|v| Some((object_id(v)?, v)))
+            .collect::<HashMap<_, _>>();
+        let (old_ids, new_ids) = (index(old), index(new));
+        for item in new {
+            match object_id(item).and_then(|id| old_ids.get(id)) {
+                Some(prev) if *prev != item => {
+                    result.push(Event::Update(item));
+                }
+                Some(_) => {}
+                None => result.push(Event::Insert(item)),
+            }
+        }
+        for item in old {
+            if !object_id(item).map(|id| new_ids.contains_key(id))
+                .unwrap_or(false)
+            {
+                result.push(Event::Delete(item));
+            }
+        }
+    } else {
+        let mut remaining = old.iter().collect::<Vec<_>>();
+        for item in new {
+            match remaining.iter().position(|o| *o == item) {
+                Some(idx) => {
+                    remaining.remove(idx);
+                }
+                None => result.push(Event::Insert(item)),
+            }
+        }
+        result.extend(remaining.into_iter().map(Event::Delete));
+    }
+    result
+}
+
+fn print_event(channel: &str, event: &Event) -> anyhow::Result<()> {
+    let (kind, data) = match event {
+        Event::Insert(data) => ("insert", data),
+        Event::Update(data) => ("update", data),
+        Event::Delete(data) => ("delete", data),
+    };
+    let line = json!({
+        "channel": channel,
+        "event": kind,
+        "timestamp": humantime::format_rfc3339_millis(SystemTime::now())
+            .to_string(),
+        "data": data,
+    });
+    let mut out = io::stdout();
+    writeln!(out, "{}", line)?;
+    out.flush()?;
+    Ok(())
+}
+
+async fn poll(conn: &mut Connection, channel: &str, query: &str,
+              interval: Duration, last: &mut Option<Vec<Value>>)
+    -> anyhow::Result<()>
+{
+    loop {
+        let items = match query_json_value(conn, query).await? {
+            Value::Array(items) => items,
+            _ => anyhow::bail!("non-array returned in JSON mode"),
+        };
+        if let Some(old) = last {
+            for event in events(old, &items) {
+                print_event(channel, &event)?;
+            }
+        }
+        *last = Some(items);
+        task::sleep(interval).await;
+    }
+}
+
+pub async fn listen(options: &Options, cmd: &Listen) -> anyhow::Result<()> {
+    let query = match (&cmd.query, options.config.listeners.get(&cmd.channel))
+    {
+        (Some(query), _) | (None, Some(query)) => query.clone(),
+        (None, None) => {
+            return Err(anyhow::anyhow!(
+                "unknown channel {:?}", cmd.channel))
+                .hint("Define the query of the channel in the `[listeners]` \
+                       section of `cli.toml` or `edgedb.toml`, \
+                       or specify it with `--query`")?;
+        }
+    };
+    classify::guard(&query)?;
+    // unless `--initial` is given, the first result is only a baseline
+    let mut last = if cmd.initial { Some(Vec::new()) } else { None };
+    let mut delay = cmd.interval;
+    loop {
+        let err = match options.conn_params.connect().await {
+            Ok(mut conn) => {
+                delay = cmd.interval;
+                let err = poll(&mut conn, &cmd.channel, &query,
+                               cmd.interval, &mut last).await
+                    .expect_err("polling never stops successfully");
+                // errors of the query and of the output leave the
+                // connection usable, only lost connections are retried
+                if conn.is_consistent() {
+                    return Err(err);
+                }
+                err
+            }
+            // e.g. authentication errors are not going to go away
+            Err(e) if e.chain().any(|e| e.is::<ErrorResponse>()) => {
+                return Err(e);
+            }
+            Err(e) => e,
+        };
+        log::warn!("Connection lost: {:#}. Reconnecting in {}",
+                   err, humantime::format_duration(delay));
+        task::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{events, Event};
+
+    #[test]
+    fn by_id() {
+        let old = vec![
+            json!({"id": "1", "name": "a"}),
+            json!({"id": "2", "name": "b"}),
+        ];
+        let new = vec![
+            json!({"id": "2", "name": "c"}),
+            json!({"id": "3", "name": "d"}),
+        ];
+        assert_eq!(events(&old, &new), vec![
+            Event::Update(&new[0]),
+            Event::Insert(&new[1]),
+            Event::Delete(&old[0]),
+        ]);
+        assert_eq!(events(&new, &new), vec![]);
+    }
+
+    #[test]
+    fn rows() {
+        let old = vec![json!(1), json!(2), json!(2)];
+        let new = vec![json!(2), json!(3)];
+        assert_eq!(events(&old, &new), vec![
+            Event::Insert(&new[1]),
+            Event::Delete(&old[0]),
+            Event::Delete(&old[2]),
+        ]);
+    }
+}
@@ -0,0 +1,287 @@
+//! Finding definitions in the schema files, for editors and shell users
+//!
+//! Schema files are only tokenized, so definitions are recognized by the
+//! keywords preceding the names, without connecting to the server.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use edgeql_parser::tokenizer::{TokenStream, Kind, SpannedToken};
+
+use crate::hint::HintExt;
+use crate::migrations::template;
+use crate::options::Locate;
+use crate::project;
+use crate::typos::closest;
+
+
+#[derive(Debug, PartialEq)]
+struct Definition {
+    /// Full name, pointers are named `module::Type.pointer`
+    name: String,
+    kind: &'static str,
+    /// Offset of the name in the text
+    offset: usize,
+}
+
+fn is_word(token: &SpannedToken, word: &str) -> bool {
+    matches!(token.token.kind, Kind::Ident | Kind::Keyword)
+        && token.token.value.eq_ignore_ascii_case(word)
+}
+
+/// Line and column of the offset, 1-based
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map(|p| p + 1).unwrap_or(0);
+    (before.matches('\n').count() + 1, before.len() - line_start + 1)
+}
+
+/// Possibly qualified name starting at the token
+fn name_at(tokens: &[SpannedToken]) -> Option<String> {
+    let mut name = Vec::new();
+    let mut iter = tokens.iter();
+    name.push(iter.next().filter(|t| t.token.kind == Kind::Ident)?
+              .token.value);
+    while let (Some(ns), Some(ident)) = (iter.next(), iter.next()) {
+        if ns.token.kind != Kind::Namespace || ident.token.kind != Kind::Ident
+        {
+            break;
+        }
+        name.push(ident.token.value);
+    }
+    Some(name.join("::"))
+}
+
+fn definitions(text: &str) -> Vec<Definition> {
+    let tokens = match TokenStream::new(text).collect::<Result<Vec<_>, _>>()
+    {
+        Ok(tokens) => tokens,
+        // reported by `edgedb migration create`
+        Err(_) => return Vec::new(),
+    };
+    let mut result = Vec::new();
+    // modules and object types with the depth of their braces
+    let mut modules: Vec<(String, usize)> = Vec::new();
+    let mut types: Vec<(String, usize)> = Vec::new();
+    let mut depth = 0_usize;
+    for (idx, token) in tokens.iter().enumerate() {
+        match token.token.kind {
+            Kind::OpenBrace => depth += 1,
+            Kind::CloseBrace => {
+                depth = depth.saturating_sub(1);
+                for stack in &mut [&mut modules, &mut types] {
+                    while stack.last().map(|(_, d)| *d >= depth)
+                        .unwrap_or(false)
+                    {
+                        stack.pop();
+                    }
+                }
+            }
+            // declaration of a type without a body
+            Kind::Semicolon => {
+                if types.last().map(|(_, d)| *d == depth).unwrap_or(false) {
+                    types.pop();
+                }
+            }
+            _ => {}
+        }
+        let name = match name_at(&tokens[idx+1..]) {
+            Some(name) => name,
+            None => continue,
+        };
+        let prev = idx.checked_sub(1).map(|i| &tokens[i]);
+        let is_abstract = prev.map(|t| is_word(t, "abstract")
+                                   || is_word(t, "inheritable"))
+            .unwrap_or(false);
+        let module = modules.last().map(|(m, _)| &m[..]).unwrap_or("default");
+        let qualified = if name.contains("::") {
+            name.clone()
+        } else {
+            format!("{}::{}", module, name)
+        };
+        let kind = if is_word(token, "module") {
+            modules.push((name, depth));
+            continue;
+        } else if is_word(token, "type") {
+            if prev.map(|t| is_word(t, "scalar")).unwrap_or(false) {
+                "scalar type"
+            } else {
+                types.push((qualified.clone(), depth));
+                "type"
+            }
+        } else if is_word(token, "alias") {
+            "alias"
+        } else if is_word(token, "function") {
+            "function"
+        } else if is_abstract && is_word(token, "constraint") {
+            "constraint"
+        } else if is_abstract && is_word(token, "annotation") {
+            "annotation"
+        } else if is_word(token, "property") || is_word(token, "link") {
+            let kind = if is_word(token, "link") {
+                "link"
+            } else {
+                "property"
+            };
+            match types.last() {
+                Some((typ, _)) if !is_abstract => {
+                    result.push(Definition {
+                        name: format!("{}.{}", typ, name),
+                        kind,
+                        offset: tokens[idx+1].start.offset as usize,
+                    });
+                    continue;
+                }
+                _ if is_abstract => kind,
+                _ => continue,
+            }
+        } else {
+            continue;
+        };
+        result.push(Definition {
+            name: qualified,
+            kind,
+            offset: tokens[idx+1].start.offset as usize,
+        });
+    }
+    result
+}
+
+fn schema_dir(cmd: &Locate) -> anyhow::Result<PathBuf> {
+    if let Some(dir) = &cmd.schema_dir {
+        return Ok(dir.clone());
+    }
+    match project::project_dir_opt(None)? {
+        Some(dir) => Ok(dir.join("dbschema")),
+        None => Ok(PathBuf::from("dbschema")),
+    }
+}
+
+/// Definitions with their files, lines and columns
+fn read_schema(dir: &Path)
+    -> anyhow::Result<Vec<(PathBuf, usize, usize, Definition)>>
+{
+    let mut files = Vec::new();
+    for item in fs::read_dir(dir)
+        .with_context(|| format!("cannot read {}", dir.display()))?
+    {
+        let path = item?.path();
+        if path.extension().map(|e| e == "esdl").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    let variables = template::project_variables()?;
+    let mut result = Vec::new();
+    for path in files {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+        let expanded = template::expand_file(&text, variables.as_ref())
+            .with_context(|| format!("cannot expand {}", path.display()))?;
+        for def in definitions(&expanded.text) {
+            let (line, column) = position(&text,
+                                          expanded.source_offset(def.offset));
+            result.push((path.clone(), line, column, def));
+        }
+    }
+    Ok(result)
+}
+
+/// Whether the name, with the module optionally omitted, refers to the
+/// definition
+fn matches(name: &str, definition: &str) -> bool {
+    if name == definition {
+        return true;
+    }
+    let base = name.split('.').next().unwrap_or(name);
+    !base.contains("::") && definition.ends_with(&format!("::{}", name))
+}
+
+pub fn locate(cmd: &Locate) -> anyhow::Result<()> {
+    let all = read_schema(&schema_dir(cmd)?)?;
+    let found = all.iter()
+        .filter(|(_, _, _, d)| matches(&cmd.name, &d.name))
+        .collect::<Vec<_>>();
+    if found.is_empty() {
+        let err = anyhow::anyhow!("{:?} is not defined in the schema files",
+                                  cmd.name);
+        let full = if cmd.name.contains("::") {
+            cmd.name.clone()
+        } else {
+            format!("default::{}", cmd.name)
+        };
+        let names = all.iter().map(|(_, _, _, d)| &d.name[..]);
+        return match closest(&full, names) {
+            Some(similar) => Err(err)
+                .with_hint(|| format!("did you mean `{}`?", similar))
+                .map_err(Into::into),
+            None => Err(err),
+        };
+    }
+    for (path, line, column, def) in found {
+        if cmd.verbose {
+            println!("{}:{}:{}: {} {}", path.display(),
+                     line, column, def.kind, def.name);
+        } else {
+            println!("{}:{}:{}", path.display(), line, column);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{definitions, matches, position};
+
+    #[test]
+    fn schema() {
+        let text = r###"
+module default {
+    abstract annotation note;
+    scalar type Status extending enum<Active, Blocked>;
+    type User {
+        required property name -> str {
+            constraint exclusive;
+            annotation title := 'Name';
+        };
+        multi link friends -> User;
+    }
+    alias Admins := User;
+    function greet(u: User) -> str using ('hi ' ++ u.name);
+}
+module app {
+    type Post;
+}
+type other::Comment;
+"###;
+        let defs = definitions(text);
+        let names = defs.iter()
+            .map(|d| {
+                let (line, column) = position(text, d.offset);
+                (d.kind, &d.name[..], line, column)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![
+            ("annotation", "default::note", 3, 25),
+            ("scalar type", "default::Status", 4, 17),
+            ("type", "default::User", 5, 10),
+            ("property", "default::User.name", 6, 27),
+            ("link", "default::User.friends", 10, 20),
+            ("alias", "default::Admins", 12, 11),
+            ("function", "default::greet", 13, 14),
+            ("type", "app::Post", 16, 10),
+            ("type", "other::Comment", 18, 6),
+        ]);
+    }
+
+    #[test]
+    fn names() {
+        assert!(matches("User", "default::User"));
+        assert!(matches("User.name", "default::User.name"));
+        assert!(matches("Post", "app::Post"));
+        assert!(matches("app::Post", "app::Post"));
+        assert!(!matches("default::Post", "app::Post"));
+        assert!(!matches("User", "default::AppUser"));
+        assert!(!matches("User", "default::User.name"));
+    }
+}
@@ -1,34 +1,65 @@
-mod exit;
+mod anonymize;
+mod check;
 mod configure;
+mod config_doctor;
 mod describe;
 mod dump;
+mod dump_format;
 mod execute;
+mod explain;
+mod extension;
+mod help;
+mod fan_out;
 mod filter;
-mod helpers;
 mod list;
+mod listen;
 mod list_aliases;
 mod list_casts;
 mod list_databases;
+mod list_extensions;
+mod list_functions;
 mod list_indexes;
 mod list_modules;
 mod list_object_types;
 mod list_ports;
 mod list_roles;
 mod list_scalar_types;
+mod locate;
 mod psql;
+mod remap;
 mod restore;
 mod roles;
+mod sample;
+mod stats;
+mod wait;
+mod watch;
+mod wipe;
 pub mod backslash;
+pub mod bulk;
 pub mod cli;
+pub mod exit;
+pub mod helpers;
+pub mod insert;
 pub mod options;
 pub mod parser;
 
+pub use self::check::check;
+pub use self::locate::locate;
 pub use self::configure::configure;
+pub use self::config_doctor::config_doctor;
 pub use self::dump::{dump, dump_all};
 pub use self::describe::describe;
+pub use self::execute::mutating_command;
+pub use self::explain::explain;
+pub use self::extension::extension;
+pub use self::help::help;
+pub use self::insert::insert;
 pub use self::list_aliases::list_aliases;
+pub use self::listen::listen;
 pub use self::list_casts::list_casts;
 pub use self::list_databases::list_databases;
+pub use self::list_extensions::list_extensions;
+pub use self::list_functions::list_functions;
 pub use self::list_indexes::list_indexes;
 pub use self::list_modules::list_modules;
 pub use self::list_object_types::list_object_types;
@@ -38,4 +69,9 @@ pub use self::list_scalar_types::list_scalar_types;
 pub use self::options::Options;
 pub use self::restore::{restore, restore_all};
 pub use self::psql::psql;
+pub use self::sample::sample;
+pub use self::stats::stats;
 pub use self::exit::ExitCode;
+pub use self::wait::wait;
+pub use self::watch::watch;
+pub use self::wipe::{wipe_database, recreate_database};
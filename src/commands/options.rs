@@ -1,9 +1,11 @@
 use crate::print::style::Styler;
 use crate::connect::Connector;
+use crate::verbosity::Verbosity;
 
 
 pub struct Options {
     pub command_line: bool,
     pub styler: Option<Styler>,
     pub conn_params: Connector,
+    pub verbosity: Verbosity,
 }
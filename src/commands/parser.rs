@@ -1,6 +1,8 @@
 use clap::{Clap, AppSettings, ArgSettings, ValueHint};
 use std::path::PathBuf;
+use std::time::Duration;
 
+use crate::print;
 use crate::repl;
 
 
@@ -33,8 +35,14 @@ pub enum Common {
     ListRoles(ListRoles),
     /// Display list of modules defined in the schema
     ListModules(ListModules),
+    /// Display list of functions defined in the schema
+    ListFunctions(ListTypes),
+    /// Display list of extensions enabled in the database
+    ListExtensions(ListExtensions),
     /// Modify database configuration
     Configure(Configure),
+    /// Manage extensions of the current database
+    Extension(Extension),
     /// Describe a named database object
     Describe(Describe),
     /// Create a database backup
@@ -49,6 +57,10 @@ pub enum Common {
     ShowStatus(ShowStatus),
     /// Show all migration versions
     MigrationLog(MigrationLog),
+    /// Split the latest migration into phases for a rolling deploy
+    MigrationPlan(MigrationPlan),
+    /// Check migrations and the schema (intended for CI)
+    MigrationCheck(MigrationCheck),
 }
 
 #[derive(Clap, Clone, Debug)]
@@ -63,12 +75,22 @@ pub struct Backslash {
 pub enum BackslashCmd {
     #[clap(flatten)]
     Common(Common),
-    Help,
+    Help(Help),
     LastError,
     History,
     Connect(Connect),
     Edit(Edit),
+    Diff(Diff),
+    /// Execute each string returned by the previous query as a statement
+    Gexec,
+    /// Store the single row returned by the previous query in variables
+    Gset(Gset),
+    /// Re-run the previous query on the interval until interrupted
+    Watch(Watch),
+    Mode(Mode),
     Set(SetCommand),
+    /// Show current values of all settings
+    Settings,
     Exit,
 }
 
@@ -88,6 +110,7 @@ pub enum Setting {
     /// Print all errors with maximum verbosity
     VerboseErrors(SettingBool),
     /// Set implicit LIMIT. Defaults to 100, specify 0 to disable.
+    #[clap(name="implicit-limit", alias="limit")]
     Limit(Limit),
     /// Set output mode. One of: json, json-elements, default, tab-separated
     OutputMode(OutputMode),
@@ -97,6 +120,41 @@ pub enum Setting {
     HistorySize(SettingUsize),
     /// Print statistics on each query
     PrintStats(PrintStats),
+    /// Set prompt template, e.g. `{database}{tx}> `. Variables:
+    /// {database}, {user}, {addr}, {tx}, {mode}, {error} and colors
+    /// {red}, {green}, {yellow}, {blue}, {magenta}, {cyan}, {gray},
+    /// {bold}, {reset}
+    Prompt(SettingString),
+    /// Run multiple statements entered at once in a single transaction
+    PasteTransaction(SettingBool),
+    /// Seconds of inactivity before a keep-alive query is sent to the
+    /// server. Defaults to 60, specify 0 to disable.
+    IdleKeepalive(SettingUsize),
+    /// Size (in MiB) of JSON output kept in memory before it is buffered
+    /// in a temporary file. Defaults to 64, specify 0 to disable.
+    SpillThreshold(SettingUsize),
+    /// Marker shown for missing values instead of `{}`,
+    /// e.g. `\set null-display '<empty>'`
+    NullDisplay(SettingString),
+    /// Show only first components, dimensionality and norm of long
+    /// float arrays (embeddings): `full` or `truncated`
+    VectorDisplay(VectorDisplay),
+    /// Digit grouping of numbers: `default`, `underscore` (1_234_567),
+    /// `comma` (1,234,567) or `locale`
+    NumberFormat(NumberFormat),
+    /// Ask for confirmation before running DELETE or UPDATE without
+    /// FILTER and DROP statements
+    SafeMode(SettingBool),
+    /// Reject statements which modify data, schema or configuration
+    ReadOnly(SettingBool),
+    /// Abort statements running longer than this, e.g. `5s` or `1m`.
+    /// Specify 0 to disable.
+    StatementTimeout(SettingDuration),
+    /// Isolation level of started transactions: `serializable` or
+    /// `repeatable-read`
+    TransactionIsolation(TransactionIsolation),
+    /// Start transactions as DEFERRABLE
+    Deferrable(SettingBool),
 }
 
 #[derive(Clap, Clone, Debug, Default)]
@@ -125,6 +183,12 @@ pub struct SettingUsize {
     pub value: Option<usize>,
 }
 
+#[derive(Clap, Clone, Debug, Default)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct SettingString {
+    pub value: Option<String>,
+}
+
 #[derive(Clap, Clone, Debug)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 #[clap(setting=AppSettings::TrailingVarArg)]
@@ -133,6 +197,49 @@ pub struct Edit {
     pub entry: Option<isize>,
 }
 
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Mode {
+    /// Input language: `edgeql` or `json` (SQL input is not supported,
+    /// the server has no SQL adapter)
+    #[clap(possible_values=&["edgeql", "json"][..])]
+    pub mode: Option<String>,
+    /// Object type to insert JSON documents into (for `json` mode)
+    pub type_name: Option<String>,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Diff {
+    /// JSON file with a previously saved query result
+    #[clap(value_hint=ValueHint::FilePath)]
+    pub path: PathBuf,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Help {
+    /// Name of a backslash command, keyword or function, or words to
+    /// search for
+    pub topic: Vec<String>,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Watch {
+    /// Interval between the runs
+    #[clap(default_value="2s", parse(try_from_str=humantime::parse_duration))]
+    pub interval: Duration,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Gset {
+    /// Variable name, fields of an object or a named tuple are stored
+    /// as NAME_FIELD
+    pub name: String,
+}
+
 #[derive(Clap, Clone, Debug, Default)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub struct OutputMode {
@@ -142,6 +249,34 @@ pub struct OutputMode {
     pub mode: Option<repl::OutputMode>,
 }
 
+#[derive(Clap, Clone, Debug, Default)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct VectorDisplay {
+    #[clap(possible_values=&["full", "truncated"][..])]
+    pub value: Option<print::VectorDisplay>,
+}
+
+#[derive(Clap, Clone, Debug, Default)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct NumberFormat {
+    #[clap(possible_values=&["default", "underscore", "comma", "locale"][..])]
+    pub value: Option<print::NumberFormat>,
+}
+
+#[derive(Clap, Clone, Debug, Default)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct SettingDuration {
+    #[clap(parse(try_from_str=humantime::parse_duration))]
+    pub value: Option<Duration>,
+}
+
+#[derive(Clap, Clone, Debug, Default)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct TransactionIsolation {
+    #[clap(possible_values=&["serializable", "repeatable-read"][..])]
+    pub value: Option<repl::TransactionIsolation>,
+}
+
 #[derive(Clap, Clone, Debug, Default)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub struct PrintStats {
@@ -221,6 +356,14 @@ pub struct ListModules {
     pub case_sensitive: bool,
 }
 
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct ListExtensions {
+    pub pattern: Option<String>,
+    #[clap(long, short='I')]
+    pub case_sensitive: bool,
+}
+
 #[derive(Clap, Clone, Debug)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub struct Describe {
@@ -229,6 +372,14 @@ pub struct Describe {
     pub verbose: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DdlCategory {
+    Extensions,
+    Aliases,
+    Functions,
+    Modules,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DumpFormat {
     Dir,
@@ -243,13 +394,22 @@ pub struct Dump {
     pub path: PathBuf,
     /// Dump all databases and the server configuration. `path` is a directory
     /// in this case
-    #[clap(long)]
+    #[clap(long, alias="all-databases", alias="all-branches")]
     pub all: bool,
+    /// Dump databases concurrently (with `--all`)
+    #[clap(long)]
+    pub parallel: bool,
 
     /// Choose dump format. For normal dumps this parameter should be omitted.
     /// For `--all` only `--format=dir` is required.
     #[clap(long, possible_values=&["dir"][..])]
     pub format: Option<DumpFormat>,
+
+    /// Mask data according to the rules in a TOML file. Each `[[rule]]`
+    /// has a `type`, a `property` and an `action`, which is one of
+    /// `hash`, `email`, `random` or `null`
+    #[clap(long, value_hint=ValueHint::FilePath)]
+    pub anonymize: Option<PathBuf>,
 }
 
 #[derive(Clap, Clone, Debug)]
@@ -272,6 +432,33 @@ pub struct Restore {
     /// Verbose output
     #[clap(long, short='v')]
     pub verbose: bool,
+
+    /// Restore objects of module `old` into module `new`
+    #[clap(long, value_name="old:new", number_of_values=1)]
+    pub rename_module: Vec<ModuleRename>,
+
+    /// Do not restore the type (fully qualified name as in the dump) and
+    /// its data. Types which link to or extend it must be skipped too
+    #[clap(long, value_name="module::Type", number_of_values=1)]
+    pub skip_type: Vec<String>,
+
+    /// Number of object types the server loads concurrently. Types are
+    /// loaded in an order which respects links between them.
+    #[clap(long, default_value="1", validator(jobs_number))]
+    pub jobs: u16,
+}
+
+fn jobs_number(value: &str) -> Result<(), String> {
+    match value.parse::<u16>() {
+        Ok(0) | Err(_) => Err("expected a number from 1 to 65535".into()),
+        Ok(_) => Ok(()),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleRename {
+    pub old: String,
+    pub new: String,
 }
 
 #[derive(Clap, Clone, Debug)]
@@ -281,6 +468,36 @@ pub struct Configure {
     pub command: ConfigureCommand,
 }
 
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Extension {
+    #[clap(subcommand)]
+    pub command: ExtensionCommand,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub enum ExtensionCommand {
+    /// List extensions available in the server and whether they are
+    /// enabled in the current database
+    List,
+    /// Install an extension package and enable it in the current database.
+    /// Packages are shipped with the server, so the server version of the
+    /// instance must include the extension
+    Install(ExtensionName),
+    /// Enable an extension in the current database
+    Enable(ExtensionName),
+    /// Disable an extension in the current database
+    Disable(ExtensionName),
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct ExtensionName {
+    /// Name of the extension, e.g. `graphql`
+    pub name: String,
+}
+
 #[derive(Clap, Clone, Debug)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub enum ConfigureCommand {
@@ -494,6 +711,18 @@ pub struct CreateMigration {
     /// data-only migrations)
     #[clap(long)]
     pub allow_empty: bool,
+    /// Message describing the migration, stored in the migration file
+    /// and shown by `migration log`
+    #[clap(short='m', long)]
+    pub message: Option<String>,
+    /// Only put changes of the schema of these kinds (comma-separated) into
+    /// the migration, the rest of the changes are left for the next one.
+    /// Questions are not asked, like with `--non-interactive`
+    #[clap(long, use_delimiter=true,
+           possible_values=&[
+               "extensions", "aliases", "functions", "modules",
+           ][..])]
+    pub only: Vec<DdlCategory>,
     /// Print queries executed
     #[clap(long, setting=ArgSettings::Hidden)]
     pub debug_print_queries: bool,
@@ -518,6 +747,19 @@ pub struct Migrate {
     /// revisions are applied on top.
     #[clap(long)]
     pub to_revision: Option<String>,
+
+    /// Before applying each migration, look for existing objects which
+    /// violate constraints it adds or properties it makes required. Stop
+    /// without applying anything if such objects are found.
+    #[clap(long)]
+    pub check_data: bool,
+
+    /// Apply migrations to every database of the instance
+    #[clap(long, alias="all-branches")]
+    pub all_databases: bool,
+    /// Migrate databases concurrently (with `--all-databases`)
+    #[clap(long, requires="all-databases")]
+    pub parallel: bool,
 }
 
 #[derive(Clap, Clone, Debug)]
@@ -555,6 +797,45 @@ pub struct MigrationLog {
     /// Show maximum N revisions (default is unlimited)
     #[clap(long)]
     pub limit: Option<usize>,
+
+    /// Show local and applied revisions as a graph, newest first, with
+    /// messages, timestamps and current heads. Applied revisions are not
+    /// shown with `--from-fs`
+    #[clap(long)]
+    pub graph: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct MigrationCheck {
+    #[clap(flatten)]
+    pub cfg: MigrationConfig,
+
+    /// Fail if migrations not applied to the database yet contain
+    /// non-additive changes (drops, renames, new requirements)
+    #[clap(long)]
+    pub require_additive: bool,
+
+    /// Print results as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct MigrationPlan {
+    #[clap(flatten)]
+    pub cfg: MigrationConfig,
+
+    /// Split the migration into additive changes, backfill script and
+    /// destructive changes, so it can be applied without downtime
+    #[clap(long)]
+    pub online: bool,
+
+    /// Directory to write phase migrations and the runbook to
+    #[clap(long, value_hint=ValueHint::DirPath,
+           default_value="./dbschema/plan")]
+    pub out: PathBuf,
 }
 
 impl Setting {
@@ -564,11 +845,23 @@ impl Setting {
             InputMode(_) => "input-mode",
             ImplicitProperties(_) => "implicit-properties",
             VerboseErrors(_) => "verbose-errors",
-            Limit(_) => "limit",
+            Limit(_) => "implicit-limit",
             HistorySize(_) => "history-size",
             OutputMode(_) => "output-mode",
             ExpandStrings(_) => "expand-strings",
             PrintStats(_) => "print-stats",
+            Prompt(_) => "prompt",
+            PasteTransaction(_) => "paste-transaction",
+            IdleKeepalive(_) => "idle-keepalive",
+            SpillThreshold(_) => "spill-threshold",
+            NullDisplay(_) => "null-display",
+            VectorDisplay(_) => "vector-display",
+            NumberFormat(_) => "number-format",
+            SafeMode(_) => "safe-mode",
+            ReadOnly(_) => "read-only",
+            StatementTimeout(_) => "statement-timeout",
+            TransactionIsolation(_) => "transaction-isolation",
+            Deferrable(_) => "deferrable",
         }
     }
     pub fn is_show(&self) -> bool {
@@ -583,6 +876,18 @@ impl Setting {
             OutputMode(a) => a.mode.is_none(),
             ExpandStrings(a) => a.value.is_none(),
             PrintStats(a) => a.value.is_none(),
+            Prompt(a) => a.value.is_none(),
+            PasteTransaction(a) => a.value.is_none(),
+            IdleKeepalive(a) => a.value.is_none(),
+            SpillThreshold(a) => a.value.is_none(),
+            NullDisplay(a) => a.value.is_none(),
+            VectorDisplay(a) => a.value.is_none(),
+            NumberFormat(a) => a.value.is_none(),
+            SafeMode(a) => a.value.is_none(),
+            ReadOnly(a) => a.value.is_none(),
+            StatementTimeout(a) => a.value.is_none(),
+            TransactionIsolation(a) => a.value.is_none(),
+            Deferrable(a) => a.value.is_none(),
         }
     }
 }
@@ -606,3 +911,41 @@ impl std::str::FromStr for DumpFormat {
         }
     }
 }
+
+impl std::str::FromStr for DdlCategory {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<DdlCategory, anyhow::Error> {
+        use DdlCategory::*;
+        match s {
+            "extensions" => Ok(Extensions),
+            "aliases" => Ok(Aliases),
+            "functions" => Ok(Functions),
+            "modules" => Ok(Modules),
+            _ => Err(anyhow::anyhow!("unsupported category {:?}", s)),
+        }
+    }
+}
+
+impl DdlCategory {
+    pub fn as_str(&self) -> &'static str {
+        use DdlCategory::*;
+        match self {
+            Extensions => "extensions",
+            Aliases => "aliases",
+            Functions => "functions",
+            Modules => "modules",
+        }
+    }
+}
+
+impl std::str::FromStr for ModuleRename {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<ModuleRename, anyhow::Error> {
+        match s.split_once(':') {
+            Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+                Ok(ModuleRename { old: old.into(), new: new.into() })
+            }
+            _ => Err(anyhow::anyhow!("expected `old:new`, got {:?}", s)),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Rewriting of the dump header for restoring into a reorganized schema
+//!
+//! The header contains the schema DDL, names of the types (the server uses
+//! them to match data blocks with the restored schema) and descriptors of
+//! the data blocks. Renaming a module rewrites both the DDL and type names,
+//! skipping a type removes its DDL, its descriptors and its data blocks.
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use edgeql_parser::helpers::quote_name;
+use edgeql_parser::preparser::full_statement;
+use edgeql_parser::tokenizer::{TokenStream, Kind, SpannedToken};
+use uuid::Uuid;
+
+use crate::commands::dump_format::{Reader, write_sized};
+use crate::commands::dump_format::{block_attributes, block_id};
+use crate::commands::parser::ModuleRename;
+use crate::hint::HintExt;
+
+
+pub fn tokenize(text: &str) -> anyhow::Result<Vec<SpannedToken>> {
+    TokenStream::new(text)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("cannot parse schema in dump: {}", e))
+}
+
+pub fn ident_value(value: &str) -> &str {
+    value.strip_prefix('`').and_then(|v| v.strip_suffix('`'))
+        .unwrap_or(value)
+}
+
+fn is_name(token: &SpannedToken) -> bool {
+    matches!(token.token.kind, Kind::Ident | Kind::Keyword)
+}
+
+fn rename_type(name: &str, renames: &[ModuleRename]) -> String {
+    for rename in renames {
+        if let Some(tail) = name.strip_prefix(&format!("{}::", rename.old)) {
+            return format!("{}::{}", rename.new, tail);
+        }
+    }
+    name.into()
+}
+
+/// Replaces module names in qualified names and module declarations
+fn rename_modules(ddl: &str, renames: &[ModuleRename])
+    -> anyhow::Result<String>
+{
+    let tokens = tokenize(ddl)?;
+    let mut output = String::with_capacity(ddl.len());
+    let mut start = 0;
+    for (idx, tok) in tokens.iter().enumerate() {
+        if !is_name(tok) {
+            continue;
+        }
+        let qualified = tokens.get(idx+1)
+            .map(|t| t.token.kind == Kind::Namespace)
+            .unwrap_or(false);
+        let declaration = idx > 0 &&
+            tokens[idx-1].token.value.eq_ignore_ascii_case("module");
+        if !qualified && !declaration {
+            continue;
+        }
+        let value = ident_value(tok.token.value);
+        if let Some(rename) = renames.iter().find(|r| r.old == value) {
+            output.push_str(&ddl[start..tok.start.offset as usize]);
+            output.push_str(&quote_name(&rename.new));
+            start = tok.end.offset as usize;
+        }
+    }
+    output.push_str(&ddl[start..]);
+    Ok(output)
+}
+
+/// Returns name of the type that DDL statement creates or alters
+pub fn statement_type(statement: &str) -> anyhow::Result<Option<String>> {
+    let tokens = tokenize(statement)?;
+    let mut iter = tokens.iter().map(|t| t.token.value).peekable();
+    match iter.next() {
+        Some(kw) if kw.eq_ignore_ascii_case("create")
+                 || kw.eq_ignore_ascii_case("alter") => {}
+        _ => return Ok(None),
+    }
+    if iter.peek().map(|kw| kw.eq_ignore_ascii_case("abstract"))
+        .unwrap_or(false)
+    {
+        iter.next();
+    }
+    match iter.next() {
+        Some(kw) if kw.eq_ignore_ascii_case("type") => {}
+        _ => return Ok(None),
+    }
+    match (iter.next(), iter.next(), iter.next()) {
+        (Some(module), Some("::"), Some(name)) => {
+            Ok(Some(format!("{}::{}",
+                            ident_value(module), ident_value(name))))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Qualified names mentioned in the statement
+fn referenced_names(statement: &str) -> anyhow::Result<Vec<String>> {
+    let tokens = tokenize(statement)?;
+    Ok(tokens.windows(3)
+        .filter(|w| is_name(&w[0]) && w[1].token.kind == Kind::Namespace
+                    && is_name(&w[2]))
+        .map(|w| format!("{}::{}", ident_value(w[0].token.value),
+                         ident_value(w[2].token.value)))
+        .collect())
+}
+
+/// Drops the definitions of the skipped types
+///
+/// Definitions which refer to the skipped types (e.g. links to them or
+/// types extending them) are rejected, as they can't be restored without
+/// the types.
+fn skip_types(ddl: &str, skip: &HashSet<&str>) -> anyhow::Result<String> {
+    let mut output = String::with_capacity(ddl.len());
+    let mut offset = 0;
+    while offset < ddl.len() {
+        let len = full_statement(ddl[offset..].as_bytes(), None)
+            .unwrap_or(ddl.len() - offset);
+        let statement = &ddl[offset..offset+len];
+        match statement_type(statement)? {
+            Some(name) if skip.contains(&name[..]) => {
+                log::info!(target: "edgedb::restore",
+                    "Skipping definition of {}", name);
+            }
+            owner => {
+                let names = referenced_names(statement)?;
+                if let Some(name) = names.iter()
+                    .find(|n| skip.contains(&n[..]))
+                {
+                    let owner = owner.unwrap_or_else(|| {
+                        statement.trim().lines().next()
+                            .unwrap_or("").to_string()
+                    });
+                    return Err(anyhow::anyhow!(
+                        "{} refers to the skipped type {}", owner, name))
+                        .with_hint(|| format!(
+                            "skip the dependent type too with \
+                             `--skip-type={}`", owner))?;
+                }
+                output.push_str(statement);
+            }
+        }
+        offset += len;
+    }
+    Ok(output)
+}
+
+/// Returns new header and ids of objects which data should be skipped
+pub fn rewrite_header(header: &[u8], renames: &[ModuleRename],
+    skip_type: &[String])
+    -> anyhow::Result<(Bytes, HashSet<Uuid>)>
+{
+    let skip = skip_type.iter().map(|s| &s[..]).collect::<HashSet<_>>();
+    let mut buf = Reader::new(header);
+    let mut output = Vec::with_capacity(header.len());
+
+    let num_attributes = buf.u16()?;
+    output.extend(&num_attributes.to_be_bytes());
+    for _ in 0..num_attributes {
+        output.extend(&buf.u16()?.to_be_bytes());
+        write_sized(&mut output, buf.sized()?);
+    }
+    output.extend(buf.bytes(4)?);  // major and minor version
+
+    let ddl = std::str::from_utf8(buf.sized()?)?;
+    let ddl = rename_modules(&skip_types(ddl, &skip)?, renames)?;
+    write_sized(&mut output, ddl.as_bytes());
+
+    let mut skipped = HashSet::new();
+    let mut found = HashSet::new();
+    let mut types = Vec::new();
+    for _ in 0..buf.u32()? {
+        let name = buf.string()?;
+        let class = buf.sized()?;
+        let id = buf.uuid()?;
+        if skip.contains(name) {
+            found.insert(name);
+            skipped.insert(id);
+        } else {
+            types.push((rename_type(name, renames), class, id));
+        }
+    }
+    for name in &skip {
+        if !found.contains(name) {
+            anyhow::bail!("type {:?} is not found in the dump", name);
+        }
+    }
+    output.extend(&(types.len() as u32).to_be_bytes());
+    for (name, class, id) in types {
+        write_sized(&mut output, name.as_bytes());
+        write_sized(&mut output, class);
+        output.extend(id.as_bytes());
+    }
+
+    let mut descriptors = Vec::new();
+    for _ in 0..buf.u32()? {
+        let id = buf.uuid()?;
+        let description = buf.sized()?;
+        let dependencies = (0..buf.u16()?)
+            .map(|_| buf.uuid())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // pointer tables depend on their source type
+        if skipped.contains(&id) ||
+            dependencies.iter().any(|dep| skipped.contains(dep))
+        {
+            skipped.insert(id);
+        } else {
+            descriptors.push((id, description, dependencies));
+        }
+    }
+    output.extend(&(descriptors.len() as u32).to_be_bytes());
+    for (id, description, dependencies) in descriptors {
+        output.extend(id.as_bytes());
+        write_sized(&mut output, description);
+        output.extend(&(dependencies.len() as u16).to_be_bytes());
+        for dep in dependencies {
+            output.extend(dep.as_bytes());
+        }
+    }
+    output.extend(buf.rest());
+    Ok((output.into(), skipped))
+}
+
+pub fn is_skipped(block: &[u8], skipped: &HashSet<Uuid>)
+    -> anyhow::Result<bool>
+{
+    if skipped.is_empty() {
+        return Ok(false);
+    }
+    let attributes = block_attributes(block)?;
+    Ok(block_id(&attributes)?.map(|id| skipped.contains(&id))
+       .unwrap_or(false))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{rename_modules, skip_types};
+    use crate::commands::parser::ModuleRename;
+
+    #[test]
+    fn rename() {
+        let renames = vec![ModuleRename {
+            old: "default".into(),
+            new: "app".into(),
+        }];
+        assert_eq!(rename_modules(
+            "CREATE MODULE default IF NOT EXISTS;\n\
+             CREATE TYPE default::User {\n\
+                 CREATE LINK friends -> default::User;\n\
+                 CREATE PROPERTY name -> std::str;\n\
+             };", &renames).unwrap(),
+            "CREATE MODULE app IF NOT EXISTS;\n\
+             CREATE TYPE app::User {\n\
+                 CREATE LINK friends -> app::User;\n\
+                 CREATE PROPERTY name -> std::str;\n\
+             };");
+    }
+
+    #[test]
+    fn skip() {
+        let mut skip = HashSet::new();
+        skip.insert("foo::Legacy");
+        assert_eq!(skip_types(
+            "CREATE MODULE foo IF NOT EXISTS;\n\
+             CREATE ABSTRACT TYPE foo::Legacy;\n\
+             CREATE TYPE foo::Current;\n\
+             ALTER TYPE foo::Legacy {\n\
+                 CREATE PROPERTY name -> std::str;\n\
+             };\n", &skip).unwrap(),
+            "CREATE MODULE foo IF NOT EXISTS;\n\
+             CREATE TYPE foo::Current;\n");
+    }
+
+    #[test]
+    fn skip_dependents() {
+        let mut skip = HashSet::new();
+        skip.insert("foo::Legacy");
+        let err = skip_types(
+            "CREATE TYPE foo::Legacy;\n\
+             CREATE TYPE foo::Current {\n\
+                 CREATE LINK old -> foo::Legacy;\n\
+             };\n", &skip).unwrap_err();
+        assert_eq!(err.to_string(),
+                   "foo::Current refers to the skipped type foo::Legacy");
+        assert!(skip_types(
+            "CREATE TYPE foo::Legacy;\n\
+             CREATE TYPE foo::Newer EXTENDING foo::Legacy;\n", &skip)
+            .is_err());
+        skip.insert("foo::Newer");
+        assert_eq!(skip_types(
+            "CREATE TYPE foo::Legacy;\n\
+             CREATE TYPE foo::Newer EXTENDING foo::Legacy;\n", &skip)
+            .unwrap(), "");
+    }
+}
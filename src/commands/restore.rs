@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::ffi::OsString;
 use std::slice;
@@ -12,6 +12,7 @@ use async_std::io::{self, Read, prelude::ReadExt};
 use async_std::future::{timeout, pending};
 use async_std::prelude::{FutureExt, StreamExt};
 use bytes::{Bytes, BytesMut, BufMut};
+use uuid::Uuid;
 
 use edgeql_parser::helpers::quote_name;
 use edgedb_protocol::client_message::{ClientMessage, Restore, RestoreBlock};
@@ -21,10 +22,15 @@ use edgedb_protocol ::server_message::{ErrorResponse};
 use edgeql_parser::preparser::{is_empty};
 
 use crate::commands::Options;
-use crate::commands::parser::{Restore as RestoreCmd};
+use crate::protocol_trace;
+use crate::commands::dump_format;
+use crate::commands::parser::{Restore as RestoreCmd, ModuleRename};
+use crate::commands::remap;
+use crate::dry_run;
 use edgedb_client::client::{Connection, Writer};
 use edgedb_client::reader::Reader;
 use crate::statement::{ReadStatement, EndOfFile};
+use crate::warnings;
 
 type Input = Box<dyn Read + Unpin + Send>;
 
@@ -117,10 +123,10 @@ async fn restore_db<'x>(cli: &mut Connection, options: &Options,
     params: &RestoreCmd)
     -> Result<(), anyhow::Error>
 {
-    use PacketType::*;
     let RestoreCmd {
         allow_non_empty, path: ref filename,
         all: _, verbose: _,
+        ref rename_module, ref skip_type, jobs,
     } = *params;
     if !allow_non_empty {
         if is_empty_db(cli).await.context("Error checking DB emptyness")? {
@@ -135,43 +141,24 @@ async fn restore_db<'x>(cli: &mut Connection, options: &Options,
         }
     }
 
-    // TODO(tailhook) check that DB is empty
-    let file_ctx = &|| format!("Failed to read dump {}", filename.display());
-    let mut input = if filename.to_str() == Some("-") {
-        Box::new(io::stdin()) as Input
-    } else {
-        fs::File::open(filename).await
-        .map(Box::new)
-        .with_context(file_ctx)?
-        as Input
-    };
-    let mut buf = [0u8; 17+8];
-    input.read_exact(&mut buf).await
-        .context("Cannot read header")
-        .with_context(file_ctx)?;
-    if &buf[..17] != b"\xFF\xD8\x00\x00\xD8EDGEDB\x00DUMP\x00" {
-        Err(anyhow::anyhow!("File is not an edgedb dump"))
-        .with_context(file_ctx)?
-    }
-    let version = i64::from_be_bytes(buf[17..].try_into().unwrap());
-    if version == 0 || version > MAX_SUPPORTED_DUMP_VER {
-        Err(anyhow::anyhow!("Unsupported dump version {}", version))
-        .with_context(file_ctx)?
+    let (mut input, header, skipped) = open_dump(
+        filename.as_ref(), rename_module, skip_type).await?;
+    if dry_run::is_enabled() {
+        return print_plan(&mut input, filename.as_ref(),
+                          &header, &skipped).await;
     }
-    let header = read_packet(&mut input, Header).await.with_context(file_ctx)?
-        .ok_or_else(|| anyhow::anyhow!("Dump is empty"))
-                       .with_context(file_ctx)?;
     let start_headers = Instant::now();
     let mut seq = cli.start_sequence().await?;
-    seq.send_messages(&[
+    seq.send_messages(protocol_trace::sent(&[
         ClientMessage::Restore(Restore {
             headers: HashMap::new(),
-            jobs: 1,
+            jobs,
             data: header,
         })
-    ]).await?;
+    ])).await?;
     loop {
         let msg = seq.message().await?;
+        protocol_trace::received(&msg);
         match msg {
             ServerMessage::RestoreReady(_) => {
                 log::info!(target: "edgedb::restore",
@@ -183,6 +170,7 @@ async fn restore_db<'x>(cli: &mut Connection, options: &Options,
                 return Err(anyhow::anyhow!(err)
                     .context("Error initiating restore protocol"));
             }
+            ServerMessage::LogMessage(msg) => warnings::handle(&msg)?,
             _ => {
                 return Err(anyhow::anyhow!(
                     "WARNING: unsolicited message {:?}", msg));
@@ -190,7 +178,7 @@ async fn restore_db<'x>(cli: &mut Connection, options: &Options,
         }
     }
     let result = send_blocks(&mut seq.writer, &mut input,
-                             filename.as_ref())
+                             filename.as_ref(), &skipped)
         .race(wait_response(&mut seq.reader, start_headers))
         .await;
     if let Err(..) = result {
@@ -201,8 +189,78 @@ async fn restore_db<'x>(cli: &mut Connection, options: &Options,
     result
 }
 
+async fn open_dump(filename: &Path, rename_module: &[ModuleRename],
+    skip_type: &[String])
+    -> anyhow::Result<(Input, Bytes, HashSet<Uuid>)>
+{
+    use PacketType::*;
+
+    let file_ctx = &|| format!("Failed to read dump {}", filename.display());
+    let mut input = if filename.to_str() == Some("-") {
+        Box::new(io::stdin()) as Input
+    } else {
+        fs::File::open(filename).await
+        .map(Box::new)
+        .with_context(file_ctx)?
+        as Input
+    };
+    let mut buf = [0u8; 17+8];
+    input.read_exact(&mut buf).await
+        .context("Cannot read header")
+        .with_context(file_ctx)?;
+    if &buf[..17] != b"\xFF\xD8\x00\x00\xD8EDGEDB\x00DUMP\x00" {
+        Err(anyhow::anyhow!("File is not an edgedb dump"))
+        .with_context(file_ctx)?
+    }
+    let version = i64::from_be_bytes(buf[17..].try_into().unwrap());
+    if version == 0 || version > MAX_SUPPORTED_DUMP_VER {
+        Err(anyhow::anyhow!("Unsupported dump version {}", version))
+        .with_context(file_ctx)?
+    }
+    let header = read_packet(&mut input, Header).await.with_context(file_ctx)?
+        .ok_or_else(|| anyhow::anyhow!("Dump is empty"))
+                       .with_context(file_ctx)?;
+    let (header, skipped) = if rename_module.is_empty() && skip_type.is_empty()
+    {
+        (header, HashSet::new())
+    } else {
+        remap::rewrite_header(&header, rename_module, skip_type)
+            .context("cannot rewrite dump header")
+            .with_context(file_ctx)?
+    };
+    Ok((input, header, skipped))
+}
+
+/// Prints schema and amount of data which would be restored
+async fn print_plan(input: &mut Input, filename: &Path, header: &[u8],
+    skipped: &HashSet<Uuid>)
+    -> anyhow::Result<()>
+{
+    let ddl = dump_format::schema_ddl(header)
+        .with_context(|| format!("Failed to read dump {}",
+                                 filename.display()))?;
+    dry_run::perform(format_args!("apply schema from {}:\n{}",
+                                  filename.display(), ddl));
+    let mut blocks = 0;
+    let mut bytes = 0;
+    while
+        let Some(data) = read_packet(input, PacketType::Block).await
+            .with_context(|| format!("Failed to read dump {}",
+                                     filename.display()))?
+    {
+        if remap::is_skipped(&data, skipped)? {
+            continue;
+        }
+        blocks += 1;
+        bytes += data.len();
+    }
+    dry_run::perform(format_args!("restore {} data blocks ({} bytes)",
+                                  blocks, bytes));
+    Ok(())
+}
+
 async fn send_blocks(writer: &mut Writer<'_>, input: &mut Input,
-    filename: &Path)
+    filename: &Path, skipped: &HashSet<Uuid>)
     -> Result<(), anyhow::Error>
 {
     use PacketType::*;
@@ -213,11 +271,15 @@ async fn send_blocks(writer: &mut Writer<'_>, input: &mut Input,
             .with_context(|| format!("Failed to read dump {}",
                                      filename.display()))?
     {
-        writer.send_messages(&[
+        if remap::is_skipped(&data, skipped)? {
+            continue;
+        }
+        writer.send_messages(protocol_trace::sent(&[
             ClientMessage::RestoreBlock(RestoreBlock { data })
-        ]).await?;
+        ])).await?;
     }
-    writer.send_messages(&[ClientMessage::RestoreEof]).await?;
+    writer.send_messages(protocol_trace::sent(&[ClientMessage::RestoreEof]))
+        .await?;
     log::info!(target: "edgedb::restore",
         "Blocks sent in {:?}", start_blocks.elapsed());
 
@@ -236,6 +298,7 @@ async fn wait_response(reader: &mut Reader<'_>, start: Instant)
 {
     loop {
         let msg = reader.message().await?;
+        protocol_trace::received(&msg);
         match msg {
             ServerMessage::CommandComplete(_) => {
                 log::info!(target: "edgedb::restore",
@@ -245,6 +308,7 @@ async fn wait_response(reader: &mut Reader<'_>, start: Instant)
             ServerMessage::ErrorResponse(err) => {
                 return Err(anyhow::anyhow!(err));
             }
+            ServerMessage::LogMessage(msg) => warnings::handle(&msg)?,
             _ => {
                 return Err(anyhow::anyhow!(
                     "WARNING: unsolicited message {:?}", msg));
@@ -275,6 +339,9 @@ async fn apply_init(cli: &mut Connection, path: &Path) -> anyhow::Result<()> {
         let stmt = str::from_utf8(&stmt[..])
             .context("can't decode statement")?;
         if !is_empty(stmt) {
+            if !dry_run::perform(format_args!("execute {}", stmt.trim())) {
+                continue;
+            }
             log::trace!("Executing {:?}", stmt);
             cli.execute(&stmt).await
                 .with_context(|| format!("failed statement {:?}", stmt))?;
@@ -305,6 +372,14 @@ pub async fn restore_all<'x>(cli: &mut Connection, options: &Options,
         let database = path_to_database_name(&path)?;
         log::debug!("Restoring database {:?}", database);
         let create_db = format!("CREATE DATABASE {}", quote_name(&database));
+        if dry_run::is_enabled() {
+            dry_run::perform(format_args!("execute {}", create_db));
+            let (mut input, header, skipped) = open_dump(
+                path.as_ref(), &params.rename_module, &params.skip_type,
+            ).await?;
+            print_plan(&mut input, path.as_ref(), &header, &skipped).await?;
+            continue;
+        }
         let db_error = match cli.execute(create_db).await {
             Ok(_) => None,
             Err(e) => {
@@ -0,0 +1,84 @@
+use edgeql_parser::helpers::quote_name;
+
+use crate::commands::insert::{full_type_name, quote_type, introspect};
+use crate::commands::insert::TypeInfo;
+use crate::non_interactive;
+use crate::options::{Options, Sample};
+
+
+/// Builds a query selecting stored properties and ids of linked objects
+fn sample_query(info: &TypeInfo, limit: u64, random: bool) -> String {
+    let mut shape = vec!["id".to_string()];
+    for prop in info.properties.iter().filter(|p| !p.computed) {
+        shape.push(quote_name(&prop.name).into());
+    }
+    for link in info.links.iter().filter(|p| !p.computed) {
+        if link.multi {
+            shape.push(format!("{}: {{ id }} LIMIT {}",
+                               quote_name(&link.name), limit));
+        } else {
+            shape.push(format!("{}: {{ id }}", quote_name(&link.name)));
+        }
+    }
+    let mut query = format!("SELECT {} {{\n    {}\n}}",
+        quote_type(&info.name), shape.join(",\n    "));
+    if random {
+        query.push_str("\nORDER BY random()");
+    }
+    query.push_str(&format!("\nLIMIT {}", limit));
+    query
+}
+
+pub async fn sample(options: &Options, cmd: &Sample) -> anyhow::Result<()> {
+    let mut conn = options.conn_params.connect().await?;
+    let info = introspect(&mut conn, &full_type_name(&cmd.type_name)).await?;
+    let query = sample_query(&info, cmd.limit, cmd.random);
+    log::debug!("Sample query: {}", query);
+    non_interactive::query(&mut conn, &query, options).await
+}
+
+#[cfg(test)]
+mod test {
+    use crate::commands::insert::{TypeInfo, Pointer};
+    use super::sample_query;
+
+    fn pointer(name: &str, target: &str, multi: bool, computed: bool)
+        -> Pointer
+    {
+        Pointer {
+            name: name.into(),
+            target: target.into(),
+            ancestors: Vec::new(),
+            required: false,
+            multi,
+            has_default: false,
+            computed,
+        }
+    }
+
+    #[test]
+    fn query() {
+        let info = TypeInfo {
+            name: "default::User".into(),
+            properties: vec![
+                pointer("email", "std::str", false, false),
+                pointer("domain", "std::str", false, true),
+                pointer("select", "std::str", false, false),
+            ],
+            links: vec![
+                pointer("friends", "default::User", true, false),
+                pointer("manager", "default::User", false, false),
+            ],
+        };
+        assert_eq!(sample_query(&info, 5, true), "\
+SELECT default::User {
+    id,
+    email,
+    `select`,
+    friends: { id } LIMIT 5,
+    manager: { id }
+}
+ORDER BY random()
+LIMIT 5");
+    }
+}
@@ -0,0 +1,166 @@
+use async_std::prelude::StreamExt;
+use edgedb_client::client::Connection;
+use edgedb_protocol::value::Value;
+use edgeql_parser::helpers::quote_name;
+use prettytable::{Table, Row, Cell};
+use prettytable::format::Alignment;
+use serde::Serialize;
+
+use crate::commands::Options;
+use crate::commands::insert::{quote_type, introspect};
+use crate::options::{Stats, StatsSort};
+use crate::table;
+
+
+#[derive(Serialize, Debug)]
+struct TypeStats {
+    name: String,
+    count: i64,
+    /// Approximate size in bytes (with `--sizes`)
+    #[serde(skip_serializing_if="Option::is_none")]
+    size: Option<i64>,
+}
+
+async fn user_types(cli: &mut Connection) -> anyhow::Result<Vec<String>> {
+    let mut items = cli.query::<String>(r###"
+        WITH MODULE schema
+        SELECT ObjectType.name
+        FILTER NOT .is_abstract
+            AND NOT .is_compound_type
+            AND NOT .is_from_alias
+            AND NOT re_test(
+                "^(?:std|schema|math|sys|cfg|cal|stdgraphql)::",
+                .name)
+        ORDER BY .name
+    "###, &Value::empty_tuple()).await?;
+    let mut names = Vec::new();
+    while let Some(name) = items.next().await.transpose()? {
+        names.push(name);
+    }
+    Ok(names)
+}
+
+async fn query_single(cli: &mut Connection, query: &str, name: &str)
+    -> anyhow::Result<i64>
+{
+    let mut items = cli.query::<i64>(query,
+        &Value::Tuple(vec![Value::Str(name.into())])).await?;
+    let mut result = 0;
+    while let Some(value) = items.next().await.transpose()? {
+        result = value;
+    }
+    Ok(result)
+}
+
+/// Counts objects of exactly this type, excluding subtypes, so that
+/// objects are not counted twice
+pub async fn count_objects(cli: &mut Connection, name: &str)
+    -> anyhow::Result<i64>
+{
+    query_single(cli, &format!(
+        "SELECT count((SELECT {} FILTER .__type__.name = <str>$0))",
+        quote_type(name)), name).await
+}
+
+/// Estimates size by the length of JSON representation of the stored
+/// properties
+async fn estimate_size(cli: &mut Connection, name: &str)
+    -> anyhow::Result<i64>
+{
+    let info = introspect(cli, name).await?;
+    let mut shape = vec!["id".to_string()];
+    shape.extend(info.properties.iter()
+        .filter(|p| !p.computed)
+        .map(|p| quote_name(&p.name).to_string()));
+    query_single(cli, &format!(
+        "SELECT sum(len(to_str(<json>(\
+            SELECT {} {{ {} }} FILTER .__type__.name = <str>$0))))",
+        quote_type(name), shape.join(", ")), name).await
+}
+
+fn format_size(bytes: i64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn sort(items: &mut Vec<TypeStats>, order: StatsSort) {
+    match order {
+        StatsSort::Name => items.sort_by(|a, b| a.name.cmp(&b.name)),
+        StatsSort::Count => items.sort_by(|a, b| b.count.cmp(&a.count)
+            .then_with(|| a.name.cmp(&b.name))),
+        StatsSort::Size => items.sort_by(|a, b| b.size.cmp(&a.size)
+            .then_with(|| b.count.cmp(&a.count))
+            .then_with(|| a.name.cmp(&b.name))),
+    }
+}
+
+pub async fn stats(options: &Options, cmd: &Stats) -> anyhow::Result<()> {
+    if cmd.sort == StatsSort::Size && !cmd.sizes {
+        anyhow::bail!("`--sort=size` requires `--sizes`");
+    }
+    let mut cli = options.conn_params.connect().await?;
+    let mut items = Vec::new();
+    for name in user_types(&mut cli).await? {
+        let count = count_objects(&mut cli, &name).await?;
+        let size = if cmd.sizes && count > 0 {
+            Some(estimate_size(&mut cli, &name).await?)
+        } else if cmd.sizes {
+            Some(0)
+        } else {
+            None
+        };
+        items.push(TypeStats { name, count, size });
+    }
+    sort(&mut items, cmd.sort);
+    if cmd.json {
+        println!("{}", serde_json::to_string_pretty(&items)?);
+        return Ok(());
+    }
+    if items.is_empty() {
+        eprintln!("No user-defined object types found");
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    let mut titles = vec!["Type", "Objects"];
+    if cmd.sizes {
+        titles.push("Approx. Size");
+    }
+    table.set_titles(Row::new(
+        titles.iter().map(|x| table::header_cell(x)).collect()));
+    for item in &items {
+        let mut row = vec![
+            Cell::new(&item.name),
+            Cell::new_align(&item.count.to_string(), Alignment::RIGHT),
+        ];
+        if let Some(size) = item.size {
+            row.push(Cell::new_align(&format_size(size), Alignment::RIGHT));
+        }
+        table.add_row(Row::new(row));
+    }
+    table.printstd();
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::format_size;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+}
@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use async_std::future::timeout;
+use async_std::task;
+
+use crate::commands::{ExitCode, Options};
+use crate::commands::parser::ShowStatus;
+use crate::migrations;
+use crate::options::Wait;
+
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn timed_out(wait: &Wait, what: &str) -> anyhow::Error {
+    anyhow::anyhow!("{} after {}",
+        what, humantime::format_duration(wait.timeout))
+}
+
+pub async fn wait(options: &Options, wait: &Wait) -> anyhow::Result<()> {
+    let deadline = Instant::now() + wait.timeout;
+    let remaining = || deadline.saturating_duration_since(Instant::now());
+
+    let mut conn_params = options.conn_params.clone();
+    conn_params.modify(|p| { p.wait_until_available(remaining()); });
+    let mut conn = timeout(remaining(), conn_params.connect()).await
+        .map_err(|_| timed_out(wait, "instance is not ready"))?
+        .context("instance is not ready")?;
+
+    if wait.migrations {
+        let status = ShowStatus { cfg: wait.cfg.clone(), quiet: true };
+        loop {
+            match migrations::status(&mut conn, options, &status).await {
+                Ok(()) => break,
+                // migrations are not applied (yet)
+                Err(e) if e.is::<ExitCode>() => {}
+                Err(e) => return Err(e),
+            }
+            if remaining() < POLL_INTERVAL {
+                return Err(timed_out(wait, "migrations are not applied"));
+            }
+            task::sleep(POLL_INTERVAL).await;
+        }
+    }
+    if options.verbosity.with_flags(wait.quiet, false).show_progress() {
+        eprintln!("Instance is ready.");
+    }
+    Ok(())
+}
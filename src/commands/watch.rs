@@ -0,0 +1,91 @@
+//! Re-running a query on an interval, like `watch(1)`
+//!
+//! The result is redrawn on every run. Lines which differ from the
+//! previous run are highlighted, and the header tells how many values
+//! have changed.
+use std::time::{Duration, SystemTime};
+
+use async_std::task;
+use colorful::Colorful;
+use edgedb_client::client::Connection;
+
+use crate::classify;
+use crate::hint::HintExt;
+use crate::non_interactive::query_json_value;
+use crate::output_style;
+use crate::print::{self, diff};
+
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+struct Run {
+    value: serde_json::Value,
+    lines: Vec<String>,
+}
+
+fn render(value: &serde_json::Value) -> anyhow::Result<Vec<String>> {
+    let mut cfg = print::Config::new();
+    if let Some((w, _h)) = term_size::dimensions_stdout() {
+        cfg.max_width(w);
+    }
+    let items = value.as_array().map(|a| &a[..]).unwrap_or(&[]);
+    let text = print::json_to_string(items, &cfg)?;
+    Ok(text.lines().map(|line| line.to_string()).collect())
+}
+
+fn status(previous: Option<&Run>, value: &serde_json::Value) -> String {
+    match previous.map(|prev| diff::diff(&prev.value, value).len()) {
+        None => String::new(),
+        Some(0) => ", no changes".into(),
+        Some(1) => ", 1 value changed".into(),
+        Some(n) => format!(", {} values changed", n),
+    }
+}
+
+/// Runs the query until interrupted, redrawing the result each time
+pub async fn watch(conn: &mut Connection, query: &str, interval: Duration)
+    -> anyhow::Result<()>
+{
+    // repeating a modification is never intended, this also covers
+    // read-only sessions and the safe mode
+    if classify::is_mutating(query) {
+        return Err(anyhow::anyhow!(
+            "watch only re-runs queries which don't modify data, \
+             schema or configuration"))
+            .hint("Run the statement once, without watching it")?;
+    }
+    let is_tty = atty::is(atty::Stream::Stdout);
+    let colors = output_style::colors(atty::Stream::Stdout);
+    let mut previous: Option<Run> = None;
+    loop {
+        let value = query_json_value(conn, query).await?;
+        let lines = render(&value)?;
+        if is_tty {
+            print!("{}", CLEAR_SCREEN);
+        } else if previous.is_some() {
+            println!();
+        }
+        let header = format!("Every {}: {}  [{}{}]",
+            humantime::format_duration(interval),
+            query.trim(),
+            humantime::format_rfc3339_seconds(SystemTime::now()),
+            status(previous.as_ref(), &value));
+        if colors {
+            println!("{}", header.dark_gray());
+        } else {
+            println!("{}", header);
+        }
+        for (idx, line) in lines.iter().enumerate() {
+            let changed = previous.as_ref()
+                .map(|prev| prev.lines.get(idx) != Some(line))
+                .unwrap_or(false);
+            if changed && colors {
+                println!("{}", line.clone().yellow().bold());
+            } else {
+                println!("{}", line);
+            }
+        }
+        previous = Some(Run { value, lines });
+        task::sleep(interval).await;
+    }
+}
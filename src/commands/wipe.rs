@@ -0,0 +1,298 @@
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use edgedb_client::client::Connection;
+use edgedb_protocol::value::Value;
+use edgeql_parser::helpers::{quote_name, quote_string};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::classify;
+use crate::commands::Options;
+use crate::commands::insert::quote_type;
+use crate::commands::list_databases::get_databases;
+use crate::commands::stats::count_objects;
+use crate::dry_run;
+use crate::hint::HintExt;
+use crate::non_interactive::query_json_value;
+use crate::options::WipeDatabase;
+use crate::question;
+
+
+#[derive(Deserialize, Debug)]
+struct TypeInfo {
+    name: String,
+    ancestors: Vec<String>,
+    /// Names of the link targets, including members of union targets
+    targets: Vec<String>,
+}
+
+fn pattern(glob: &str) -> anyhow::Result<Regex> {
+    let glob = if glob.contains("::") {
+        glob.to_string()
+    } else {
+        format!("default::{}", glob)
+    };
+    let re = regex::escape(&glob).replace(r"\*", ".*");
+    Ok(Regex::new(&format!("^{}$", re))?)
+}
+
+fn links_to(source: &TypeInfo, target: &TypeInfo) -> bool {
+    source.targets.iter()
+        .any(|t| *t == target.name || target.ancestors.contains(t))
+}
+
+/// Groups of types in the order of deletion
+///
+/// Objects which are linked to are deleted after the objects linking to
+/// them. Types linking to each other in a cycle are deleted by a single
+/// statement.
+fn delete_order(types: &[&TypeInfo]) -> Vec<Vec<String>> {
+    let mut left = types.to_vec();
+    let mut order = Vec::new();
+    while !left.is_empty() {
+        let (free, linked): (Vec<_>, Vec<_>) = left.iter().copied()
+            .partition(|target| {
+                !left.iter().any(|source| {
+                    source.name != target.name && links_to(source, target)
+                })
+            });
+        if free.is_empty() {
+            order.push(left.iter().map(|t| t.name.clone()).collect());
+            break;
+        }
+        for item in free {
+            order.push(vec![item.name.clone()]);
+        }
+        left = linked;
+    }
+    order
+}
+
+fn statement(group: &[String]) -> String {
+    let types = group.iter().map(|t| quote_type(t)).collect::<Vec<_>>();
+    let names = group.iter().map(|t| quote_string(t)).collect::<Vec<_>>();
+    format!("SELECT count((DELETE {{ {} }} \
+             FILTER .__type__.name IN {{ {} }}))",
+             types.join(", "), names.join(", "))
+}
+
+async fn types(cli: &mut Connection) -> anyhow::Result<Vec<TypeInfo>> {
+    let data = query_json_value(cli, r###"
+        WITH MODULE schema
+        SELECT ObjectType {
+            name,
+            ancestors := .ancestors.name,
+            targets := (
+                .links.target.name
+                UNION .links.target[IS ObjectType].union_of.name
+            ),
+        }
+        FILTER NOT .is_abstract
+            AND NOT .is_compound_type
+            AND NOT .is_from_alias
+            AND NOT re_test(
+                "^(?:std|schema|math|sys|cfg|cal|stdgraphql)::",
+                .name)
+        ORDER BY .name
+    "###).await?;
+    Ok(serde_json::from_value(data)?)
+}
+
+async fn delete(cli: &mut Connection, order: &[Vec<String>])
+    -> anyhow::Result<i64>
+{
+    let mut deleted = 0;
+    cli.execute("START TRANSACTION").await?;
+    for group in order {
+        let result = cli.query_row::<i64>(&statement(group),
+            &Value::empty_tuple()).await;
+        match result {
+            Ok(num) => deleted += num,
+            Err(e) => {
+                cli.execute("ROLLBACK").await.ok();
+                return Err(e.context(format!(
+                        "cannot delete objects of {}", group.join(", "))))
+                    .hint("objects of the types which are kept may link to \
+                           the deleted ones, no objects were deleted")?;
+            }
+        }
+    }
+    cli.execute("COMMIT").await?;
+    Ok(deleted)
+}
+
+/// Drops the database and creates an empty one with the same name
+pub async fn recreate_database(options: &Options, mut cli: Connection,
+    database: &str)
+    -> anyhow::Result<()>
+{
+    classify::guard_command("DROP DATABASE")?;
+    let other = get_databases(&mut cli).await?
+        .into_iter()
+        .find(|name| name != database);
+    let other = match other {
+        Some(other) => other,
+        None => {
+            return Err(anyhow::anyhow!(
+                "cannot drop the only database of the instance"))
+                .hint("use `--data-only` or create another database")?;
+        }
+    };
+    // the database can't be dropped while connected to it
+    drop(cli);
+    let mut conn_params = options.conn_params.clone();
+    conn_params.modify(|p| { p.database(&other); });
+    let mut cli = conn_params.connect().await?;
+    // make sure the database can be created again before dropping it,
+    // DDL on databases can't be run in a transaction
+    let probe = format!("__edgedb_wipe_{}", std::process::id());
+    cli.execute(&format!("CREATE DATABASE {}", quote_name(&probe))).await
+        .context("cannot create databases, the database is kept")?;
+    cli.execute(&format!("DROP DATABASE {}", quote_name(&probe))).await?;
+    cli.execute(&format!("DROP DATABASE {}", quote_name(database))).await?;
+    cli.execute(&format!("CREATE DATABASE {}", quote_name(database))).await
+        .with_context(|| format!(
+            "database {:?} is dropped but can't be created again", database))
+        .with_hint(|| format!(
+            "create it with `edgedb create-database {}` and restore \
+             the data from a dump", database))?;
+    Ok(())
+}
+
+pub async fn wipe_database(options: &Options, cmd: &WipeDatabase)
+    -> anyhow::Result<()>
+{
+    let data_only = cmd.data_only || !cmd.types.is_empty();
+    let patterns = cmd.types.iter()
+        .map(|p| pattern(p))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut cli = options.conn_params.connect().await?;
+    let database = cli.query_row::<String>(
+        "SELECT sys::get_current_database()",
+        &Value::empty_tuple()).await?;
+    let all = types(&mut cli).await?;
+    let selected = all.iter()
+        .filter(|t| patterns.is_empty()
+                    || patterns.iter().any(|p| p.is_match(&t.name)))
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        eprintln!("No object types match");
+        return Ok(());
+    }
+
+    let mut total = 0;
+    for typ in &selected {
+        let count = count_objects(&mut cli, &typ.name).await?;
+        eprintln!("{:>10} {}", count, typ.name);
+        total += count;
+    }
+    let names = selected.iter().map(|t| &t.name).collect::<BTreeSet<_>>();
+    let referencing = all.iter()
+        .filter(|s| !names.contains(&s.name))
+        .filter(|s| selected.iter().any(|t| links_to(s, t)))
+        .map(|s| &s.name[..])
+        .collect::<Vec<_>>();
+    if !referencing.is_empty() {
+        eprintln!("Note: objects of {} may link to the deleted objects",
+                  referencing.join(", "));
+    }
+    if data_only {
+        eprintln!("{} object(s) will be deleted from database {:?}, \
+                   the schema is kept", total, database);
+    } else {
+        eprintln!("Database {:?} will be dropped and created again, \
+                   the schema and {} object(s) will be lost",
+                   database, total);
+    }
+
+    let order = delete_order(&selected);
+    if dry_run::is_enabled() {
+        if data_only {
+            for group in &order {
+                dry_run::perform(format_args!("run `{}`", statement(group)));
+            }
+        } else {
+            dry_run::perform(format_args!(
+                "drop and create database {:?}", database));
+        }
+        return Ok(());
+    }
+    if !cmd.non_interactive {
+        let q = question::Confirm::new_dangerous(format!(
+            "Wipe database {:?}?", database));
+        if !q.ask()? {
+            eprintln!("Canceled");
+            return Ok(());
+        }
+    }
+    if data_only {
+        let deleted = delete(&mut cli, &order).await?;
+        eprintln!("Deleted {} object(s)", deleted);
+    } else {
+        recreate_database(options, cli, &database).await?;
+        eprintln!("Database {:?} is wiped", database);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TypeInfo, delete_order, pattern, statement};
+
+    fn typ(name: &str, ancestors: &[&str], targets: &[&str]) -> TypeInfo {
+        TypeInfo {
+            name: name.into(),
+            ancestors: ancestors.iter().map(|x| x.to_string()).collect(),
+            targets: targets.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn order() {
+        let types = vec![
+            typ("default::Comment", &[], &["default::Post", "default::User"]),
+            typ("default::Post", &[], &["default::User"]),
+            typ("default::User", &["default::Named"], &["default::User"]),
+            typ("default::Tag", &["default::Named"], &[]),
+            typ("default::Label", &[], &["default::Named"]),
+        ];
+        let refs = types.iter().collect::<Vec<_>>();
+        assert_eq!(delete_order(&refs), vec![
+            vec!["default::Comment"],
+            vec!["default::Label"],
+            vec!["default::Post"],
+            vec!["default::Tag"],
+            vec!["default::User"],
+        ]);
+    }
+
+    #[test]
+    fn cycle() {
+        let types = vec![
+            typ("default::A", &[], &["default::B"]),
+            typ("default::B", &[], &["default::A"]),
+            typ("default::C", &[], &["default::A"]),
+        ];
+        let refs = types.iter().collect::<Vec<_>>();
+        assert_eq!(delete_order(&refs), vec![
+            vec!["default::C"],
+            vec!["default::A", "default::B"],
+        ]);
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(pattern("app::*").unwrap().is_match("app::User"));
+        assert!(!pattern("app::*").unwrap().is_match("default::User"));
+        assert!(pattern("User").unwrap().is_match("default::User"));
+        assert!(!pattern("User").unwrap().is_match("default::UserX"));
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(statement(&["default::A".into(), "default::B".into()]),
+            "SELECT count((DELETE { default::A, default::B } \
+             FILTER .__type__.name IN { 'default::A', 'default::B' }))");
+    }
+}
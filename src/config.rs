@@ -0,0 +1,453 @@
+//! Settings of the command-line tool
+//!
+//! Settings are merged from several layers, each overriding the previous
+//! one: system-wide `cli.toml`, user's `cli.toml`, `[cli]` section of the
+//! project's `edgedb.toml`, environment variables and command-line options.
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use fn_error_context::context;
+
+use crate::env_file;
+use crate::output_style::OutputStyle;
+use crate::platform::config_dir;
+use crate::print::display::{DisplayRule, SCALAR_TYPES};
+use crate::print::style::ColorTheme;
+use crate::project;
+use crate::repl::OutputMode;
+use crate::telemetry;
+
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all="kebab-case")]
+struct SrcConfig {
+    #[serde(default)]
+    output_format: Option<String>,
+    #[serde(default)]
+    output_style: Option<String>,
+    #[serde(default)]
+    color_theme: Option<String>,
+    #[serde(default)]
+    version_check: Option<bool>,
+    #[serde(default)]
+    telemetry: Option<String>,
+    #[serde(default)]
+    read_only: Option<bool>,
+    #[serde(default)]
+    shell: SrcShell,
+    #[serde(default)]
+    display: BTreeMap<String, SrcDisplayRule>,
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    listeners: BTreeMap<String, String>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, toml::Value>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all="kebab-case")]
+struct SrcShell {
+    #[serde(default)]
+    prompt: Option<String>,
+    #[serde(default)]
+    safe_mode: Option<bool>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, toml::Value>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all="kebab-case")]
+struct SrcDisplayRule {
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    replace: Vec<(String, String)>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, toml::Value>,
+}
+
+/// Only the `[cli]` section of `edgedb.toml` is read here, the rest is
+/// project configuration
+#[derive(serde::Deserialize)]
+struct SrcProject {
+    #[serde(default)]
+    cli: Option<SrcConfig>,
+    #[serde(flatten)]
+    _project: BTreeMap<String, toml::Value>,
+}
+
+/// Where a setting comes from
+#[derive(Debug, Clone)]
+pub enum Source {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    /// `[cli]` section of `edgedb.toml`
+    Project(PathBuf),
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+/// Settings of the command-line tool itself (not of the server)
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Output format of queries, by default depends on whether the shell
+    /// is interactive
+    pub output_format: Option<OutputMode>,
+    pub output_style: OutputStyle,
+    pub color_theme: ColorTheme,
+    pub version_check: bool,
+    pub telemetry: telemetry::Mode,
+    pub read_only: bool,
+    pub shell: Shell,
+    /// Display rules for scalar types, keyed by type name
+    pub display: BTreeMap<String, DisplayRule>,
+    /// Command-lines which replace the alias name, keyed by alias name
+    pub aliases: BTreeMap<String, String>,
+    /// Queries polled by `edgedb listen`, keyed by channel name
+    pub listeners: BTreeMap<String, String>,
+    /// Where each of the settings comes from, keyed by setting name.
+    /// Settings with default values are not listed
+    pub sources: BTreeMap<String, Source>,
+    /// Config files which were looked up, and whether each of them exists
+    pub files: Vec<(Source, bool)>,
+    /// Problems found in config files and environment variables, such
+    /// settings are ignored
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Shell {
+    pub prompt: Option<String>,
+    pub safe_mode: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            output_format: None,
+            output_style: OutputStyle::Default,
+            color_theme: ColorTheme::Dark,
+            version_check: false,
+            telemetry: telemetry::Mode::Disabled,
+            read_only: false,
+            shell: Shell::default(),
+            display: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            listeners: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            files: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Source {
+    pub fn path(&self) -> Option<&Path> {
+        use Source::*;
+        match self {
+            System(path) | User(path) | Project(path) => Some(path),
+            Default | Env(_) | Flag(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Source::*;
+        match self {
+            Default => write!(f, "default"),
+            System(path) => write!(f, "system config {}", path.display()),
+            User(path) => write!(f, "user config {}", path.display()),
+            Project(path) => write!(f, "project config {}", path.display()),
+            Env(name) => match env_file::origin(name) {
+                Some(path) => write!(f, "{} in {}", name, path.display()),
+                None => write!(f, "environment variable {}", name),
+            },
+            Flag(name) => write!(f, "option {}", name),
+        }
+    }
+}
+
+pub fn path() -> anyhow::Result<PathBuf> {
+    Ok(config_dir()?.join("cli.toml"))
+}
+
+pub fn system_path() -> PathBuf {
+    if cfg!(windows) {
+        env::var_os("PROGRAMDATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("C:\\ProgramData"))
+            .join("EdgeDB").join("cli.toml")
+    } else {
+        PathBuf::from("/etc/edgedb/cli.toml")
+    }
+}
+
+#[context("error reading CLI config `{}`", path.display())]
+fn read(path: &Path) -> anyhow::Result<SrcConfig> {
+    let text = fs::read_to_string(&path)?;
+    let mut toml = toml::de::Deserializer::new(&text);
+    Ok(serde_path_to_error::deserialize(&mut toml)?)
+}
+
+#[context("error reading `[cli]` section of `{}`", path.display())]
+fn read_project(path: &Path) -> anyhow::Result<SrcConfig> {
+    let text = fs::read_to_string(&path)?;
+    let mut toml = toml::de::Deserializer::new(&text);
+    let val: SrcProject = serde_path_to_error::deserialize(&mut toml)?;
+    Ok(val.cli.unwrap_or_default())
+}
+
+impl Config {
+    pub fn set_source(&mut self, name: &str, source: Source) {
+        self.sources.insert(name.into(), source);
+    }
+    pub fn source(&self, name: &str) -> &Source {
+        self.sources.get(name).unwrap_or(&Source::Default)
+    }
+    fn warn_extra(&mut self, extra: &BTreeMap<String, toml::Value>,
+                  prefix: &str, source: &Source)
+    {
+        for key in extra.keys() {
+            self.warnings.push(format!("Unknown config option `{}{}` in {}",
+                                       prefix, key.escape_default(), source));
+        }
+    }
+    fn read_file(&mut self, source: Source) {
+        let path = source.path().expect("file source").to_path_buf();
+        if !path.exists() {
+            self.files.push((source, false));
+            return;
+        }
+        let val = match source {
+            Source::Project(_) => read_project(&path),
+            _ => read(&path),
+        };
+        match val {
+            Ok(val) => self.apply(val, &source),
+            Err(e) => self.warnings.push(format!("{:#}", e)),
+        }
+        self.files.push((source, true));
+    }
+    fn apply(&mut self, val: SrcConfig, source: &Source) {
+        self.warn_extra(&val.extra, "", source);
+        self.warn_extra(&val.shell.extra, "shell.", source);
+        if let Some(format) = val.output_format {
+            self.set_output_format(&format, source);
+        }
+        if let Some(style) = val.output_style {
+            self.set_output_style(&style, source);
+        }
+        if let Some(theme) = val.color_theme {
+            self.set_color_theme(&theme, source);
+        }
+        if let Some(version_check) = val.version_check {
+            self.version_check = version_check;
+            self.set_source("version-check", source.clone());
+        }
+        if let Some(mode) = val.telemetry {
+            match mode.parse() {
+                // a repository must not opt in everyone who clones it
+                Ok(_) if matches!(source, Source::Project(_)) => {
+                    self.warnings.push(format!(
+                        "Ignoring `telemetry` from {}: it can only be set \
+                         in the user configuration", source));
+                }
+                Ok(mode) => {
+                    self.telemetry = mode;
+                    self.set_source("telemetry", source.clone());
+                }
+                Err(e) => self.warnings.push(format!(
+                    "Ignoring `telemetry` from {}: {:#}", source, e)),
+            }
+        }
+        if let Some(read_only) = val.read_only {
+            self.read_only = read_only;
+            self.set_source("read-only", source.clone());
+        }
+        if let Some(prompt) = val.shell.prompt {
+            self.shell.prompt = Some(prompt);
+            self.set_source("shell.prompt", source.clone());
+        }
+        if let Some(safe_mode) = val.shell.safe_mode {
+            self.shell.safe_mode = safe_mode;
+            self.set_source("shell.safe-mode", source.clone());
+        }
+        for (type_name, rule) in val.display {
+            let prefix = format!("display.{:?}.", type_name);
+            self.warn_extra(&rule.extra, &prefix, source);
+            if !SCALAR_TYPES.contains(&&type_name[..]) {
+                self.warnings.push(format!(
+                    "Display rule for unsupported type {:?} in {}, \
+                    supported: {}",
+                    type_name, source, SCALAR_TYPES.join(", ")));
+                continue;
+            }
+            if let Some(template) = &rule.template {
+                if !template.contains("{value}") {
+                    self.warnings.push(format!(
+                        "Template of display rule for {:?} in {} \
+                        doesn't contain `{{value}}`", type_name, source));
+                }
+            }
+            self.set_source(&format!("display.{:?}", type_name),
+                            source.clone());
+            self.display.insert(type_name, DisplayRule {
+                template: rule.template,
+                replace: rule.replace,
+            });
+        }
+        for (name, command) in val.aliases {
+            self.set_source(&format!("aliases.{}", name), source.clone());
+            self.aliases.insert(name, command);
+        }
+        for (channel, query) in val.listeners {
+            self.set_source(&format!("listeners.{}", channel), source.clone());
+            self.listeners.insert(channel, query);
+        }
+    }
+    fn set_output_format(&mut self, value: &str, source: &Source) {
+        match value.parse() {
+            Ok(format) => {
+                self.output_format = Some(format);
+                self.set_source("output-format", source.clone());
+            }
+            Err(e) => self.warnings.push(format!(
+                "Ignoring `output-format` from {}: {:#}", source, e)),
+        }
+    }
+    fn set_output_style(&mut self, value: &str, source: &Source) {
+        match value.parse() {
+            Ok(style) => {
+                self.output_style = style;
+                self.set_source("output-style", source.clone());
+            }
+            Err(e) => self.warnings.push(format!(
+                "Ignoring `output-style` from {}: {:#}", source, e)),
+        }
+    }
+    fn set_color_theme(&mut self, value: &str, source: &Source) {
+        match value.parse() {
+            Ok(theme) => {
+                self.color_theme = theme;
+                self.set_source("color-theme", source.clone());
+            }
+            Err(e) => self.warnings.push(format!(
+                "Ignoring `color-theme` from {}: {:#}", source, e)),
+        }
+    }
+    fn read_env(&mut self) {
+        if let Ok(value) = env::var("EDGEDB_OUTPUT_FORMAT") {
+            self.set_output_format(&value,
+                                   &Source::Env("EDGEDB_OUTPUT_FORMAT"));
+        }
+        if let Ok(value) = env::var("EDGEDB_OUTPUT_STYLE") {
+            self.set_output_style(&value, &Source::Env("EDGEDB_OUTPUT_STYLE"));
+        }
+        if let Ok(value) = env::var("EDGEDB_COLOR_THEME") {
+            self.set_color_theme(&value, &Source::Env("EDGEDB_COLOR_THEME"));
+        }
+        if env::var_os("EDGEDB_NO_VERSION_CHECK")
+            .map(|x| !x.is_empty()).unwrap_or(false)
+        {
+            self.version_check = false;
+            self.set_source("version-check",
+                            Source::Env("EDGEDB_NO_VERSION_CHECK"));
+        }
+        if let Ok(prompt) = env::var("EDGEDB_SHELL_PROMPT") {
+            self.shell.prompt = Some(prompt);
+            self.set_source("shell.prompt",
+                            Source::Env("EDGEDB_SHELL_PROMPT"));
+        }
+    }
+}
+
+/// Reads all config files and environment variables. Missing files mean
+/// defaults, errors are reported in `warnings` of the returned config
+pub fn get_config() -> Config {
+    let mut config = Config::default();
+    config.read_file(Source::System(system_path()));
+    match path() {
+        Ok(path) => config.read_file(Source::User(path)),
+        Err(e) => config.warnings.push(format!("{:#}", e)),
+    }
+    match project::project_dir_opt(None) {
+        Ok(Some(dir)) => {
+            config.read_file(Source::Project(dir.join("edgedb.toml")));
+        }
+        Ok(None) => {}
+        Err(e) => config.warnings.push(format!(
+            "error searching for `edgedb.toml`: {:#}", e)),
+    }
+    config.read_env();
+    config
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::repl::OutputMode;
+    use crate::telemetry;
+    use super::{Config, SrcConfig, Source};
+
+    fn apply(config: &mut Config, text: &str, source: Source) {
+        let val: SrcConfig = toml::from_str(text).unwrap();
+        config.apply(val, &source);
+    }
+
+    #[test]
+    fn layers() {
+        let user = Source::User(PathBuf::from("cli.toml"));
+        let project = Source::Project(PathBuf::from("edgedb.toml"));
+        let mut config = Config::default();
+        apply(&mut config, r#"
+            output-format = "json"
+            [shell]
+            safe-mode = true
+            [aliases]
+            mig = "migration create"
+        "#, user);
+        apply(&mut config, r#"
+            output-format = "tab-separated"
+            read-only = true
+            [aliases]
+            up = "migrate"
+            telemetry = "local-only"
+            [listeners]
+            users = "SELECT User { name }"
+        "#, project);
+        assert!(matches!(config.output_format,
+                         Some(OutputMode::TabSeparated)));
+        assert!(config.read_only);
+        assert!(config.shell.safe_mode);
+        assert!(!config.version_check);
+        assert_eq!(config.aliases.len(), 2);
+        assert!(matches!(config.source("listeners.users"),
+                         Source::Project(_)));
+        assert!(matches!(config.source("output-format"),
+                         Source::Project(_)));
+        assert!(matches!(config.source("shell.safe-mode"), Source::User(_)));
+        assert!(matches!(config.source("version-check"), Source::Default));
+        assert_eq!(config.telemetry, telemetry::Mode::Disabled);
+        assert_eq!(config.warnings.len(), 1);
+    }
+
+    #[test]
+    fn warnings() {
+        let mut config = Config::default();
+        apply(&mut config, r#"
+            output-format = "yaml"
+            telemetry = "yes"
+            colour = true
+        "#, Source::User(PathBuf::from("cli.toml")));
+        assert!(config.output_format.is_none());
+        assert_eq!(config.telemetry, telemetry::Mode::Disabled);
+        assert_eq!(config.warnings.len(), 3);
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use async_std::future::{timeout, pending};
@@ -6,9 +8,15 @@ use async_std::prelude::FutureExt;
 use edgedb_client::Builder;
 use edgedb_client::client::Connection;
 
+use crate::env_file;
 use crate::hint::ArcError;
 
 
+/// Error of establishing a connection, the message is kept as is
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct ConnectionFailed(pub anyhow::Error);
+
 #[derive(Debug, Clone)]
 pub struct Connector {
     params: Result<Builder, ArcError>,
@@ -27,6 +35,7 @@ impl Connector {
         return params.connect()
             .race(self.print_warning(params))
             .await
+            .map_err(|e| ConnectionFailed(e).into())
     }
 
     async fn print_warning(&self, params: &Builder)
@@ -41,3 +50,47 @@ impl Connector {
         self.params.as_ref().map_err(Clone::clone)
     }
 }
+
+/// Where a connection parameter comes from
+#[derive(Debug, Clone)]
+pub enum Source {
+    Flag(&'static str),
+    Env(&'static str),
+    /// Project directory which is linked to the instance
+    Project(PathBuf),
+    Credentials(PathBuf),
+    Default,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamSource {
+    pub name: &'static str,
+    pub value: String,
+    pub source: Source,
+}
+
+impl ParamSource {
+    pub fn new(name: &'static str, value: impl ToString, source: Source)
+        -> ParamSource
+    {
+        ParamSource { name, value: value.to_string(), source }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Source::*;
+        match self {
+            Flag(name) => write!(f, "option {}", name),
+            Env(name) => match env_file::origin(name) {
+                Some(path) => write!(f, "{} in {}", name, path.display()),
+                None => write!(f, "environment variable {}", name),
+            },
+            Project(path) => write!(f, "project {}", path.display()),
+            Credentials(path) => {
+                write!(f, "credentials file {}", path.display())
+            }
+            Default => write!(f, "default"),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::fs;
+use std::io;
 use std::path::PathBuf;
 
 use async_std::task;
@@ -15,3 +17,24 @@ pub fn path(name: &str) -> anyhow::Result<PathBuf> {
     Ok(home_dir()?.join(".edgedb").join("credentials")
         .join(format!("{}.json", name)))
 }
+
+/// Names of the instances which have credentials
+pub fn all_instance_names() -> anyhow::Result<Vec<String>> {
+    let dir = home_dir()?.join(".edgedb").join("credentials");
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map(|e| e == "json").unwrap_or(false) {
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
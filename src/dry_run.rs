@@ -0,0 +1,34 @@
+//! Global `--dry-run` mode
+//!
+//! Destructive commands describe each action through this module: in
+//! dry-run mode the action is printed instead of being performed.
+use std::fmt;
+use std::process::Command;
+
+use once_cell::sync::OnceCell;
+
+
+static ENABLED: OnceCell<bool> = OnceCell::new();
+
+pub fn init(enabled: bool) {
+    ENABLED.set(enabled).ok();
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Returns `true` if the action should be performed, prints it otherwise
+pub fn perform(action: impl fmt::Display) -> bool {
+    if is_enabled() {
+        eprintln!("Would {}", action);
+        false
+    } else {
+        true
+    }
+}
+
+/// Returns `true` if the command should be run, prints it otherwise
+pub fn run(cmd: &Command) -> bool {
+    perform(format_args!("run {:?}", cmd))
+}
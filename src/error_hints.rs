@@ -0,0 +1,208 @@
+//! Suggestions for common query errors
+//!
+//! Names from the error message are compared with the names in the
+//! database schema to find the likely intended one, other errors get a
+//! generic suggestion.
+use std::collections::BTreeMap;
+
+use edgedb_client::client::Connection;
+use edgedb_protocol::error_response::ErrorResponse;
+use edgedb_protocol::server_message::TransactionState;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::non_interactive::query_json_value;
+use crate::repl;
+use crate::typos::closest;
+
+
+const SYSTEM_MODULES: &str =
+    "^(?:std|schema|math|sys|cfg|cal|stdgraphql)::";
+
+static UNKNOWN_TYPE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+    r"^(?:object type or alias|object type|type|schema item) ",
+    r"'([\w:]+)' does not exist")).unwrap());
+static UNKNOWN_POINTER: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r"^object type '([\w:]+)' has no link or property '(\w+)'").unwrap());
+static CARDINALITY: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r"more than one element returned by an expression").unwrap());
+
+#[derive(Debug, PartialEq)]
+enum Problem<'a> {
+    UnknownType(&'a str),
+    UnknownPointer { type_name: &'a str, pointer: &'a str },
+    Cardinality,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectType {
+    name: String,
+    pointers: Vec<Pointer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pointer {
+    name: String,
+}
+
+/// Pointer names keyed by the qualified name of the object type
+type Names = BTreeMap<String, Vec<String>>;
+
+fn parse(message: &str) -> Option<Problem> {
+    if let Some(caps) = UNKNOWN_POINTER.captures(message) {
+        return Some(Problem::UnknownPointer {
+            type_name: caps.get(1)?.as_str(),
+            pointer: caps.get(2)?.as_str(),
+        });
+    }
+    if let Some(caps) = UNKNOWN_TYPE.captures(message) {
+        return Some(Problem::UnknownType(caps.get(1)?.as_str()));
+    }
+    if CARDINALITY.is_match(message) {
+        return Some(Problem::Cardinality);
+    }
+    None
+}
+
+fn short_name(name: &str) -> &str {
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+fn suggest(problem: &Problem, names: &Names) -> Option<String> {
+    match *problem {
+        Problem::UnknownType(name) if !name.contains("::") => {
+            let same = names.keys()
+                .filter(|full| short_name(full) == name)
+                .collect::<Vec<_>>();
+            if let [full] = &same[..] {
+                return Some(format!(
+                    "type is defined in another module, \
+                     did you mean `{}`?", full));
+            }
+            let found = closest(name, names.keys().map(|k| short_name(k)))?;
+            let full = names.keys().find(|k| short_name(k) == found)?;
+            Some(format!("did you mean `{}`?", full))
+        }
+        Problem::UnknownType(name) => {
+            let found = closest(name, names.keys().map(|k| &k[..]))?;
+            Some(format!("did you mean `{}`?", found))
+        }
+        Problem::UnknownPointer { type_name, pointer } => {
+            let pointers = names.get(type_name)?;
+            let found = closest(pointer, pointers.iter().map(|p| &p[..]))?;
+            Some(format!("did you mean `{}.{}`?", type_name, found))
+        }
+        Problem::Cardinality => Some(
+            "add a `FILTER` which selects a single object, `LIMIT 1`, \
+             or declare the pointer as `multi`".into()),
+    }
+}
+
+async fn fetch_names(cli: &mut Connection) -> anyhow::Result<Names> {
+    let value = query_json_value(cli, &format!(r###"
+        WITH MODULE schema
+        SELECT ObjectType {{
+            name,
+            pointers: {{ name }} FILTER .name != '__type__',
+        }}
+        FILTER NOT .is_from_alias
+            AND NOT re_test("{system}", .name)
+    "###, system=SYSTEM_MODULES)).await?;
+    let types: Vec<ObjectType> = serde_json::from_value(value)?;
+    Ok(types.into_iter()
+        .map(|t| {
+            (t.name, t.pointers.into_iter().map(|p| p.name).collect())
+        })
+        .collect())
+}
+
+/// Suggestion for the error, if there is a likely fix
+///
+/// Schema is only introspected if the connection isn't in a failed
+/// transaction, so it's safe to call after any error
+pub async fn hint(cli: &mut Connection, err: &anyhow::Error)
+    -> Option<String>
+{
+    let err = err.chain().find_map(|e| e.downcast_ref::<ErrorResponse>())?;
+    let problem = parse(&err.message)?;
+    if problem == Problem::Cardinality {
+        return suggest(&problem, &Names::new());
+    }
+    let failed = matches!(cli.transaction_state(),
+                          TransactionState::InFailedTransaction);
+    if !cli.is_consistent() || failed {
+        return None;
+    }
+    let names = fetch_names(cli).await
+        .map_err(|e| log::debug!("Cannot fetch names for a hint: {:#}", e))
+        .ok()?;
+    suggest(&problem, &names)
+}
+
+/// Prints the hint for the last error of the interactive shell
+pub async fn print_hint(state: &mut repl::State) {
+    let cli = match state.connection.as_mut() {
+        Some(cli) => cli,
+        None => return,
+    };
+    let err = match &state.last_error {
+        Some(err) => err,
+        None => return,
+    };
+    if let Some(hint) = hint(cli, err).await {
+        eprintln!("  Hint: {}", hint);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, suggest, Names, Problem};
+
+    fn names() -> Names {
+        let mut names = Names::new();
+        names.insert("default::User".into(),
+                     vec!["id".into(), "name".into(), "email".into()]);
+        names.insert("blog::Post".into(), vec!["id".into(), "title".into()]);
+        names
+    }
+
+    fn hint(message: &str) -> Option<String> {
+        suggest(&parse(message)?, &names())
+    }
+
+    #[test]
+    fn parse_messages() {
+        assert_eq!(parse("object type or alias 'Usr' does not exist"),
+                   Some(Problem::UnknownType("Usr")));
+        assert_eq!(parse("object type 'default::User' has no link \
+                          or property 'nmae'"),
+                   Some(Problem::UnknownPointer {
+                       type_name: "default::User",
+                       pointer: "nmae",
+                   }));
+        assert_eq!(parse("possibly more than one element returned by \
+                          an expression where only singletons are allowed"),
+                   Some(Problem::Cardinality));
+        assert_eq!(parse("unexpected 'SELEC'"), None);
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(hint("object type or alias 'Post' does not exist")
+                   .as_deref(),
+                   Some("type is defined in another module, \
+                         did you mean `blog::Post`?"));
+        assert_eq!(hint("object type or alias 'Usr' does not exist")
+                   .as_deref(),
+                   Some("did you mean `default::User`?"));
+        assert_eq!(hint("object type or alias 'default::Usre' \
+                         does not exist").as_deref(),
+                   Some("did you mean `default::User`?"));
+        assert_eq!(hint("object type 'default::User' has no link \
+                         or property 'nmae'").as_deref(),
+                   Some("did you mean `default::User.name`?"));
+        assert_eq!(hint("object type or alias 'Comment' does not exist"),
+                   None);
+    }
+}
@@ -0,0 +1,16 @@
+use async_std::task;
+
+use crate::generate::options::{GenerateCommand, Command};
+use crate::generate::queries;
+use crate::generate::types;
+use crate::options::Options;
+
+
+pub fn main(cmd: &GenerateCommand, options: &Options) -> anyhow::Result<()> {
+    use Command::*;
+
+    match &cmd.subcommand {
+        Types(c) => task::block_on(types::generate(c, options)),
+        Queries(c) => task::block_on(queries::generate(c, options)),
+    }
+}
@@ -0,0 +1,7 @@
+pub mod options;
+
+mod main;
+mod queries;
+mod types;
+
+pub use main::main;
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::{Clap, AppSettings, ValueHint};
+
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct GenerateCommand {
+    #[clap(subcommand)]
+    pub subcommand: Command,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum Command {
+    /// Generate type definitions for object types in the schema
+    Types(Types),
+    /// Generate typed functions for queries saved in `*.edgeql` files
+    Queries(Queries),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    TypeScript,
+    Python,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Types {
+    /// Language of generated definitions
+    #[clap(long, possible_values=&["typescript", "python"][..])]
+    pub language: Language,
+
+    /// Directory to write generated files to
+    #[clap(long, value_hint=ValueHint::DirPath,
+           default_value="./dbschema/gen")]
+    pub out: PathBuf,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Queries {
+    /// Files containing a single query each
+    #[clap(required=true, min_values=1, value_hint=ValueHint::FilePath)]
+    pub files: Vec<PathBuf>,
+
+    /// Language of generated functions
+    #[clap(long, possible_values=&["typescript", "python"][..])]
+    pub language: Language,
+
+    /// Directory to write generated files to. By default, each file is
+    /// written next to its query
+    #[clap(long, value_hint=ValueHint::DirPath)]
+    pub out: Option<PathBuf>,
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Language, anyhow::Error> {
+        match s {
+            "typescript" => Ok(Language::TypeScript),
+            "python" => Ok(Language::Python),
+            _ => Err(anyhow::anyhow!("unsupported language {:?}", s)),
+        }
+    }
+}
+
+impl Language {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Language::TypeScript => "ts",
+            Language::Python => "py",
+        }
+    }
+}
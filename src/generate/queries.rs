@@ -0,0 +1,505 @@
+//! Typed wrappers for queries saved in `*.edgeql` files
+//!
+//! Types of parameters and results are taken from the descriptors the
+//! server returns for a prepared statement, so the query is never executed.
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bytes::Bytes;
+use edgedb_client::client::Connection;
+use edgedb_protocol::client_message::ClientMessage;
+use edgedb_protocol::client_message::{Prepare, IoFormat, Cardinality};
+use edgedb_protocol::client_message::{DescribeStatement, DescribeAspect};
+use edgedb_protocol::codec;
+use edgedb_protocol::descriptors::{Descriptor, TypePos};
+use edgedb_protocol::server_message::{ServerMessage, CommandDataDescription};
+use uuid::Uuid;
+
+use crate::generate::options::{Language, Queries};
+use crate::generate::types::std_type;
+use crate::options::Options;
+use crate::protocol_trace;
+use crate::warnings;
+
+
+/// Language-independent type of a query parameter or result
+#[derive(Debug)]
+enum Ty {
+    Scalar(&'static str),
+    Enum(Vec<String>),
+    Array(Box<Ty>),
+    Tuple(Vec<Ty>),
+    NamedTuple(Vec<(String, Ty)>),
+    Object(Vec<(String, Ty)>),
+    Unknown,
+}
+
+enum Args {
+    None,
+    Positional(Vec<Ty>),
+    Named(Vec<(String, Ty)>),
+}
+
+struct Query {
+    name: String,
+    text: String,
+    args: Args,
+    /// `None` for statements that return no data
+    result: Option<Ty>,
+    many: bool,
+}
+
+fn scalar_name(id: &Uuid) -> Option<&'static str> {
+    let name = match *id {
+        codec::STD_UUID => "std::uuid",
+        codec::STD_STR => "std::str",
+        codec::STD_BYTES => "std::bytes",
+        codec::STD_INT16 => "std::int16",
+        codec::STD_INT32 => "std::int32",
+        codec::STD_INT64 => "std::int64",
+        codec::STD_FLOAT32 => "std::float32",
+        codec::STD_FLOAT64 => "std::float64",
+        codec::STD_DECIMAL => "std::decimal",
+        codec::STD_BOOL => "std::bool",
+        codec::STD_DATETIME => "std::datetime",
+        codec::CAL_LOCAL_DATETIME => "cal::local_datetime",
+        codec::CAL_LOCAL_DATE => "cal::local_date",
+        codec::CAL_LOCAL_TIME => "cal::local_time",
+        codec::STD_DURATION => "std::duration",
+        codec::STD_JSON => "std::json",
+        codec::STD_BIGINT => "std::bigint",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn convert(descriptors: &[Descriptor], pos: TypePos) -> anyhow::Result<Ty> {
+    let desc = descriptors.get(pos.0 as usize)
+        .context("invalid type descriptor position")?;
+    let ty = match desc {
+        Descriptor::BaseScalar(s) => match scalar_name(&s.id) {
+            Some(name) => Ty::Scalar(name),
+            None => Ty::Unknown,
+        },
+        Descriptor::Scalar(s) => convert(descriptors, s.base_type_pos)?,
+        Descriptor::Enumeration(e) => Ty::Enum(e.members.clone()),
+        Descriptor::Set(s) => {
+            Ty::Array(Box::new(convert(descriptors, s.type_pos)?))
+        }
+        Descriptor::Array(a) => {
+            Ty::Array(Box::new(convert(descriptors, a.type_pos)?))
+        }
+        Descriptor::Tuple(t) => Ty::Tuple(t.element_types.iter()
+            .map(|pos| convert(descriptors, *pos))
+            .collect::<anyhow::Result<_>>()?),
+        Descriptor::NamedTuple(t) => Ty::NamedTuple(t.elements.iter()
+            .map(|el| {
+                Ok((el.name.clone(), convert(descriptors, el.type_pos)?))
+            })
+            .collect::<anyhow::Result<_>>()?),
+        Descriptor::ObjectShape(shape) => Ty::Object(shape.elements.iter()
+            .filter(|el| !el.flag_implicit)
+            .map(|el| {
+                Ok((el.name.clone(), convert(descriptors, el.type_pos)?))
+            })
+            .collect::<anyhow::Result<_>>()?),
+        _ => Ty::Unknown,
+    };
+    Ok(ty)
+}
+
+async fn describe(cli: &mut Connection, text: &str)
+    -> anyhow::Result<CommandDataDescription>
+{
+    let statement_name = Bytes::from_static(b"");
+    let mut seq = cli.start_sequence().await?;
+    seq.send_messages(protocol_trace::sent(&[
+        ClientMessage::Prepare(Prepare {
+            headers: HashMap::new(),
+            io_format: IoFormat::Binary,
+            expected_cardinality: Cardinality::Many,
+            statement_name: statement_name.clone(),
+            command_text: String::from(text),
+        }),
+        ClientMessage::DescribeStatement(DescribeStatement {
+            headers: HashMap::new(),
+            aspect: DescribeAspect::DataDescription,
+            statement_name,
+        }),
+        ClientMessage::Flush,
+    ])).await?;
+    let description = loop {
+        let msg = seq.message().await?;
+        protocol_trace::received(&msg);
+        match msg {
+            ServerMessage::PrepareComplete(..) => {}
+            ServerMessage::CommandDataDescription(desc) => break desc,
+            ServerMessage::ErrorResponse(err) => {
+                seq.err_sync().await.ok();
+                return Err(anyhow::anyhow!(err));
+            }
+            ServerMessage::LogMessage(msg) => {
+                if let Err(e) = warnings::handle(&msg) {
+                    seq.err_sync().await.ok();
+                    return Err(e.into());
+                }
+            }
+            _ => {
+                log::warn!("Unsolicited message {:?}", msg);
+            }
+        }
+    };
+    // statement isn't executed, so just bring the connection back
+    // to the idle state
+    seq.err_sync().await?;
+    Ok(description)
+}
+
+async fn read_query(cli: &mut Connection, path: &Path)
+    -> anyhow::Result<Query>
+{
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("cannot read {}", path.display()))?;
+    let name = path.file_stem().and_then(|x| x.to_str())
+        .with_context(|| format!("invalid file name {}", path.display()))?
+        .to_string();
+    let description = describe(cli, text.trim()).await?;
+    let input = description.input()?;
+    let args = if input.is_empty_tuple() {
+        Args::None
+    } else {
+        let descriptors = input.descriptors();
+        match input.root() {
+            Descriptor::Tuple(t) => Args::Positional(t.element_types.iter()
+                .map(|pos| convert(descriptors, *pos))
+                .collect::<anyhow::Result<_>>()?),
+            Descriptor::NamedTuple(t) => Args::Named(t.elements.iter()
+                .map(|el| Ok((el.name.clone(),
+                              convert(descriptors, el.type_pos)?)))
+                .collect::<anyhow::Result<_>>()?),
+            root => anyhow::bail!("unsupported input descriptor {:?}", root),
+        }
+    };
+    let output = description.output()?;
+    let result = match output.root_pos() {
+        Some(pos) => Some(convert(output.descriptors(), pos)?),
+        None => None,
+    };
+    Ok(Query {
+        name,
+        text: text.trim().to_string(),
+        args,
+        result,
+        many: matches!(description.result_cardinality, Cardinality::Many),
+    })
+}
+
+/// Splits file name like `get-user` or `get_user` into words
+fn words(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+fn pascal_case(name: &str) -> String {
+    words(name).iter().map(|w| {
+        let mut chars = w.chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect::<String>(),
+            None => String::new(),
+        }
+    }).collect()
+}
+
+fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+    match chars.next() {
+        Some(c) => c.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    words(name).join("_")
+}
+
+fn literal(text: &str) -> String {
+    // JSON string literal is a valid literal in both TypeScript and Python
+    serde_json::to_string(text).expect("string is serializable")
+}
+
+fn ts_type(ty: &Ty) -> String {
+    match ty {
+        Ty::Scalar(name) => {
+            std_type(name, Language::TypeScript).unwrap_or("unknown").into()
+        }
+        Ty::Enum(values) => values.iter().map(|v| literal(v))
+            .collect::<Vec<_>>().join(" | "),
+        Ty::Array(item) => format!("Array<{}>", ts_type(item)),
+        Ty::Tuple(items) => format!("[{}]", items.iter()
+            .map(ts_type).collect::<Vec<_>>().join(", ")),
+        Ty::NamedTuple(fields) => format!("{{ {} }}", fields.iter()
+            .map(|(name, ty)| format!("{}: {};", name, ts_type(ty)))
+            .collect::<Vec<_>>().join(" ")),
+        // shape elements carry no cardinality, so any non-set element
+        // may be empty
+        Ty::Object(fields) => format!("{{ {} }}", fields.iter()
+            .map(|(name, ty)| match ty {
+                Ty::Array(_) => format!("{}: {};", name, ts_type(ty)),
+                _ => format!("{}: {} | null;", name, ts_type(ty)),
+            })
+            .collect::<Vec<_>>().join(" ")),
+        Ty::Unknown => "unknown".into(),
+    }
+}
+
+fn typescript(queries: &[Query]) -> anyhow::Result<String> {
+    let mut buf = String::new();
+    buf.push_str("// Generated by `edgedb generate queries`. Do not edit.\n\n\
+        interface Executor {\n  \
+            query(query: string, args?: unknown): Promise<unknown[]>;\n  \
+            querySingle(query: string, args?: unknown): Promise<unknown>;\n  \
+            execute(query: string): Promise<void>;\n\
+        }\n");
+    for q in queries {
+        let func = camel_case(&q.name);
+        let type_prefix = pascal_case(&q.name);
+        let (params, call_args) = match &q.args {
+            Args::None => (String::new(), String::new()),
+            Args::Positional(items) => {
+                writeln!(buf, "\nexport type {}Args = [{}];", type_prefix,
+                         items.iter().map(ts_type)
+                            .collect::<Vec<_>>().join(", "))?;
+                (format!(", args: {}Args", type_prefix), ", args".into())
+            }
+            Args::Named(fields) => {
+                writeln!(buf, "\nexport type {}Args = {{", type_prefix)?;
+                for (name, ty) in fields {
+                    writeln!(buf, "  {}: {};", name, ts_type(ty))?;
+                }
+                buf.push_str("};\n");
+                (format!(", args: {}Args", type_prefix), ", args".into())
+            }
+        };
+        let (ret, method) = match &q.result {
+            Some(ty) => {
+                writeln!(buf, "\nexport type {}Result = {};",
+                         type_prefix, ts_type(ty))?;
+                if q.many {
+                    (format!("{}Result[]", type_prefix), "query")
+                } else {
+                    (format!("{}Result | null", type_prefix), "querySingle")
+                }
+            }
+            None if call_args.is_empty() => ("void".into(), "execute"),
+            None => anyhow::bail!("query {:?} has parameters, \
+                                   but returns no data", q.name),
+        };
+        writeln!(buf, "\nexport async function {}(\n  \
+                           client: Executor{}\n\
+                       ): Promise<{}> {{\n  \
+                           return client.{}({}{}) as Promise<{}>;\n\
+                       }}",
+                 func, params, ret, method, literal(&q.text), call_args,
+                 ret)?;
+    }
+    Ok(buf)
+}
+
+struct PyWriter {
+    classes: String,
+}
+
+impl PyWriter {
+    fn ty(&mut self, ty: &Ty, hint: &str) -> anyhow::Result<String> {
+        let result = match ty {
+            Ty::Scalar(name) => {
+                std_type(name, Language::Python).unwrap_or("typing.Any")
+                    .into()
+            }
+            Ty::Enum(_) => "str".into(),
+            Ty::Array(item) => {
+                format!("typing.List[{}]", self.ty(item, hint)?)
+            }
+            Ty::Tuple(items) => {
+                let mut names = Vec::new();
+                for (idx, item) in items.iter().enumerate() {
+                    names.push(self.ty(item, &format!("{}{}", hint, idx))?);
+                }
+                format!("typing.Tuple[{}]", names.join(", "))
+            }
+            Ty::NamedTuple(fields) | Ty::Object(fields) => {
+                let mut attrs = String::new();
+                for (name, item) in fields {
+                    let item_ty = self.ty(item,
+                        &format!("{}{}", hint, pascal_case(name)))?;
+                    // shape elements carry no cardinality
+                    if matches!(ty, Ty::Object(_))
+                        && !matches!(item, Ty::Array(_))
+                    {
+                        writeln!(attrs, "    {}: typing.Optional[{}]",
+                                 name, item_ty)?;
+                    } else {
+                        writeln!(attrs, "    {}: {}", name, item_ty)?;
+                    }
+                }
+                if attrs.is_empty() {
+                    attrs.push_str("    pass\n");
+                }
+                write!(self.classes, "\n\nclass {}(typing.Protocol):\n{}",
+                       hint, attrs)?;
+                hint.into()
+            }
+            Ty::Unknown => "typing.Any".into(),
+        };
+        Ok(result)
+    }
+}
+
+fn python(queries: &[Query]) -> anyhow::Result<String> {
+    let mut functions = String::new();
+    let mut writer = PyWriter { classes: String::new() };
+    for q in queries {
+        let func = snake_case(&q.name);
+        let type_prefix = pascal_case(&q.name);
+        let mut params = String::new();
+        let mut call_args = String::new();
+        match &q.args {
+            Args::None => {}
+            Args::Positional(items) => {
+                for (idx, item) in items.iter().enumerate() {
+                    let ty = writer.ty(item,
+                        &format!("{}Arg{}", type_prefix, idx))?;
+                    write!(params, ",\n    arg{}: {}", idx, ty)?;
+                    write!(call_args, ", arg{}", idx)?;
+                }
+            }
+            Args::Named(fields) => {
+                params.push_str(",\n    *");
+                for (name, item) in fields {
+                    let ty = writer.ty(item,
+                        &format!("{}{}", type_prefix, pascal_case(name)))?;
+                    write!(params, ",\n    {}: {}", name, ty)?;
+                    write!(call_args, ", {}={}", name, name)?;
+                }
+            }
+        }
+        let (ret, method) = match &q.result {
+            Some(ty) => {
+                let ty = writer.ty(ty, &format!("{}Result", type_prefix))?;
+                if q.many {
+                    (format!("typing.List[{}]", ty), "query")
+                } else {
+                    (format!("typing.Optional[{}]", ty), "query_single")
+                }
+            }
+            None if call_args.is_empty() => ("None".into(), "execute"),
+            None => anyhow::bail!("query {:?} has parameters, \
+                                   but returns no data", q.name),
+        };
+        write!(functions, "\n\ndef {}(\n    client: typing.Any{},\n\
+                           ) -> {}:\n    \
+                               return client.{}(\n        {}{},\n    )\n",
+               func, params, ret, method, literal(&q.text), call_args)?;
+    }
+    Ok(format!("# Generated by `edgedb generate queries`. Do not edit.\n\
+                from __future__ import annotations\n\n\
+                import datetime\n\
+                import decimal\n\
+                import typing\n\
+                import uuid\n{}{}",
+               writer.classes, functions))
+}
+
+fn output_path(path: &Path, out: &Option<PathBuf>, lang: Language)
+    -> PathBuf
+{
+    let stem = path.file_stem().and_then(|x| x.to_str()).unwrap_or("query");
+    let name = match lang {
+        Language::TypeScript => format!("{}.edgeql.ts", stem),
+        Language::Python => format!("{}_edgeql.py", snake_case(stem)),
+    };
+    match out {
+        Some(dir) => dir.join(name),
+        None => path.with_file_name(name),
+    }
+}
+
+pub async fn generate(cmd: &Queries, options: &Options) -> anyhow::Result<()> {
+    let mut cli = options.conn_params.connect().await?;
+    if let Some(dir) = &cmd.out {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create {}", dir.display()))?;
+    }
+    for path in &cmd.files {
+        let query = read_query(&mut cli, path).await
+            .with_context(|| format!("error in {}", path.display()))?;
+        let queries = [query];
+        let text = match cmd.language {
+            Language::TypeScript => typescript(&queries)?,
+            Language::Python => python(&queries)?,
+        };
+        let out = output_path(path, &cmd.out, cmd.language);
+        fs::write(&out, text)
+            .with_context(|| format!("cannot write {}", out.display()))?;
+        eprintln!("{} -> {}", path.display(), out.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Query, Args, Ty, typescript, python, camel_case, snake_case};
+
+    fn query() -> Query {
+        Query {
+            name: "get-user".into(),
+            text: "SELECT User { name, friends: { name } } \
+                   FILTER .name = <str>$name".into(),
+            args: Args::Named(vec![("name".into(), Ty::Scalar("std::str"))]),
+            result: Some(Ty::Object(vec![
+                ("name".into(), Ty::Scalar("std::str")),
+                ("friends".into(), Ty::Array(Box::new(Ty::Object(vec![
+                    ("name".into(), Ty::Scalar("std::str")),
+                ])))),
+            ])),
+            many: true,
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(camel_case("get-user_by_id"), "getUserById");
+        assert_eq!(snake_case("get-user"), "get_user");
+    }
+
+    #[test]
+    fn typescript_wrapper() {
+        let text = typescript(&[query()]).unwrap();
+        assert!(text.contains(
+            "export type GetUserArgs = {\n  name: string;\n};"));
+        assert!(text.contains(
+            "export type GetUserResult = { name: string | null; \
+             friends: Array<{ name: string | null; }>; };"));
+        assert!(text.contains("export async function getUser(\n  \
+                               client: Executor, args: GetUserArgs\n\
+                               ): Promise<GetUserResult[]> {"));
+    }
+
+    #[test]
+    fn python_wrapper() {
+        let text = python(&[query()]).unwrap();
+        assert!(text.contains("class GetUserResult(typing.Protocol):\n    \
+                               name: typing.Optional[str]\n    \
+                               friends: typing.List[GetUserResultFriends]\n"));
+        assert!(text.contains("def get_user(\n    client: typing.Any,\n    \
+                               *,\n    name: str,\n\
+                               ) -> typing.List[GetUserResult]:"));
+        assert!(text.contains("name=name,\n    )"));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::generate::options::{Language, Types};
+use crate::non_interactive::query_json_value;
+use crate::options::Options;
+
+
+const SYSTEM_MODULES: &str =
+    "^(?:std|schema|math|sys|cfg|cal|stdgraphql)::";
+
+#[derive(Deserialize, Debug)]
+struct Pointer {
+    name: String,
+    target: String,
+    /// Element type if the target is an array
+    element: Option<String>,
+    is_link: bool,
+    required: bool,
+    multi: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct ObjectType {
+    name: String,
+    pointers: Vec<Pointer>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ScalarType {
+    name: String,
+    ancestors: Vec<String>,
+    enum_values: Option<Vec<String>>,
+}
+
+struct Schema {
+    objects: Vec<ObjectType>,
+    scalars: BTreeMap<String, ScalarType>,
+}
+
+/// Maps standard scalar types to the types of the target language
+pub fn std_type(name: &str, lang: Language) -> Option<&'static str> {
+    use Language::*;
+
+    let result = match (name, lang) {
+        ("std::str", TypeScript) => "string",
+        ("std::str", Python) => "str",
+        ("std::uuid", TypeScript) => "string",
+        ("std::uuid", Python) => "uuid.UUID",
+        ("std::bool", TypeScript) => "boolean",
+        ("std::bool", Python) => "bool",
+        ("std::int16", TypeScript) | ("std::int32", TypeScript) |
+        ("std::int64", TypeScript) |
+        ("std::float32", TypeScript) | ("std::float64", TypeScript)
+            => "number",
+        ("std::int16", Python) | ("std::int32", Python) |
+        ("std::int64", Python) | ("std::bigint", Python) => "int",
+        ("std::float32", Python) | ("std::float64", Python) => "float",
+        ("std::bigint", TypeScript) => "bigint",
+        ("std::decimal", TypeScript) => "string",
+        ("std::decimal", Python) => "decimal.Decimal",
+        ("std::datetime", TypeScript) => "Date",
+        ("std::datetime", Python) => "datetime.datetime",
+        ("cal::local_datetime", TypeScript) => "string",
+        ("cal::local_datetime", Python) => "datetime.datetime",
+        ("cal::local_date", TypeScript) => "string",
+        ("cal::local_date", Python) => "datetime.date",
+        ("cal::local_time", TypeScript) => "string",
+        ("cal::local_time", Python) => "datetime.time",
+        ("std::duration", TypeScript) => "string",
+        ("std::duration", Python) => "datetime.timedelta",
+        ("cal::relative_duration", TypeScript) => "string",
+        ("cal::relative_duration", Python) => "str",
+        ("std::json", TypeScript) => "unknown",
+        ("std::json", Python) => "typing.Any",
+        ("std::bytes", TypeScript) => "Uint8Array",
+        ("std::bytes", Python) => "bytes",
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Name of the generated type for a schema type name
+///
+/// Types in the `default` module lose their prefix, others are prefixed by
+/// the module name: `default::User` -> `User`, `auth::User` -> `auth_User`.
+fn type_name(name: &str) -> String {
+    match name.strip_prefix("default::") {
+        Some(name) => name.into(),
+        None => name.replace("::", "_"),
+    }
+}
+
+fn scalar(schema: &Schema, name: &str, lang: Language) -> String {
+    if let Some(result) = std_type(name, lang) {
+        return result.into();
+    }
+    if let Some(scalar) = schema.scalars.get(name) {
+        if scalar.enum_values.is_some() {
+            return type_name(name);
+        }
+        for ancestor in &scalar.ancestors {
+            if let Some(result) = std_type(ancestor, lang) {
+                return result.into();
+            }
+        }
+    }
+    match lang {
+        Language::TypeScript => "unknown".into(),
+        Language::Python => "typing.Any".into(),
+    }
+}
+
+fn pointer_type(schema: &Schema, ptr: &Pointer, lang: Language) -> String {
+    let base = if ptr.is_link {
+        type_name(&ptr.target)
+    } else if let Some(element) = &ptr.element {
+        match lang {
+            Language::TypeScript => {
+                format!("{}[]", scalar(schema, element, lang))
+            }
+            Language::Python => {
+                format!("typing.List[{}]", scalar(schema, element, lang))
+            }
+        }
+    } else {
+        scalar(schema, &ptr.target, lang)
+    };
+    match (lang, ptr.multi) {
+        (Language::TypeScript, true) => format!("{}[]", base),
+        (Language::Python, true) => format!("typing.List[{}]", base),
+        (Language::TypeScript, false) if !ptr.required => {
+            format!("{} | null", base)
+        }
+        (Language::Python, false) if !ptr.required => {
+            format!("typing.Optional[{}]", base)
+        }
+        (_, false) => base,
+    }
+}
+
+fn typescript(schema: &Schema) -> String {
+    let mut buf = String::new();
+    buf.push_str("// Generated by `edgedb generate types`. Do not edit.\n");
+    for scalar in schema.scalars.values() {
+        if let Some(values) = &scalar.enum_values {
+            let values = values.iter()
+                .map(|v| format!("{:?}", v))
+                .collect::<Vec<_>>().join(" | ");
+            writeln!(buf, "\nexport type {} = {};",
+                     type_name(&scalar.name), values).unwrap();
+        }
+    }
+    for object in &schema.objects {
+        writeln!(buf, "\nexport interface {} {{",
+                 type_name(&object.name)).unwrap();
+        for ptr in &object.pointers {
+            writeln!(buf, "  {}: {};", ptr.name,
+                     pointer_type(schema, ptr, Language::TypeScript)).unwrap();
+        }
+        buf.push_str("}\n");
+    }
+    buf
+}
+
+fn python(schema: &Schema) -> String {
+    let mut buf = String::new();
+    buf.push_str("# Generated by `edgedb generate types`. Do not edit.\n\
+                  from __future__ import annotations\n\n\
+                  import dataclasses\n\
+                  import datetime\n\
+                  import decimal\n\
+                  import typing\n\
+                  import uuid\n");
+    for scalar in schema.scalars.values() {
+        if let Some(values) = &scalar.enum_values {
+            let values = values.iter()
+                .map(|v| format!("{:?}", v))
+                .collect::<Vec<_>>().join(", ");
+            writeln!(buf, "\n\n{} = typing.Literal[{}]",
+                     type_name(&scalar.name), values).unwrap();
+        }
+    }
+    for object in &schema.objects {
+        writeln!(buf, "\n\n@dataclasses.dataclass\nclass {}:",
+                 type_name(&object.name)).unwrap();
+        if object.pointers.is_empty() {
+            buf.push_str("    pass\n");
+        }
+        for ptr in &object.pointers {
+            writeln!(buf, "    {}: {}", ptr.name,
+                     pointer_type(schema, ptr, Language::Python)).unwrap();
+        }
+    }
+    buf
+}
+
+async fn introspect(options: &Options) -> anyhow::Result<Schema> {
+    let mut conn = options.conn_params.connect().await?;
+    let objects = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema
+        SELECT ObjectType {{
+            name,
+            pointers: {{
+                name,
+                target := .target.name,
+                element := .target[IS Array].element_type.name,
+                is_link := .target IS ObjectType,
+                required,
+                multi := .cardinality = Cardinality.Many,
+            }}
+            FILTER .name != '__type__'
+            ORDER BY .name,
+        }}
+        FILTER NOT .is_from_alias
+            AND NOT re_test("{system}", .name)
+        ORDER BY .name;
+    "###, system=SYSTEM_MODULES)).await?;
+    let scalars = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema
+        SELECT ScalarType {{
+            name,
+            ancestors := array_agg(.ancestors.name),
+            enum_values,
+        }}
+        FILTER NOT .is_from_alias
+            AND NOT re_test("{system}", .name)
+        ORDER BY .name;
+    "###, system=SYSTEM_MODULES)).await?;
+    let scalars: Vec<ScalarType> = serde_json::from_value(scalars)
+        .context("cannot decode scalar types")?;
+    Ok(Schema {
+        objects: serde_json::from_value(objects)
+            .context("cannot decode object types")?,
+        scalars: scalars.into_iter().map(|s| (s.name.clone(), s)).collect(),
+    })
+}
+
+pub async fn generate(cmd: &Types, options: &Options) -> anyhow::Result<()> {
+    let schema = introspect(options).await?;
+    let text = match cmd.language {
+        Language::TypeScript => typescript(&schema),
+        Language::Python => python(&schema),
+    };
+    fs::create_dir_all(&cmd.out)
+        .with_context(|| format!("cannot create {}", cmd.out.display()))?;
+    let path = cmd.out.join(format!("types.{}", cmd.language.extension()));
+    fs::write(&path, text)
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    eprintln!("Definitions of {} object types are written to {}",
+              schema.objects.len(), path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{Schema, ObjectType, Pointer, ScalarType};
+    use super::{typescript, python};
+
+    fn schema() -> Schema {
+        let ptr = |name: &str, target: &str, is_link, required, multi| {
+            Pointer {
+                name: name.into(),
+                target: target.into(),
+                element: None,
+                is_link, required, multi,
+            }
+        };
+        let mut scalars = BTreeMap::new();
+        scalars.insert("default::Color".into(), ScalarType {
+            name: "default::Color".into(),
+            ancestors: vec!["std::anyenum".into()],
+            enum_values: Some(vec!["Red".into(), "Green".into()]),
+        });
+        Schema {
+            objects: vec![ObjectType {
+                name: "default::User".into(),
+                pointers: vec![
+                    ptr("color", "default::Color", false, false, false),
+                    ptr("friends", "default::User", true, false, true),
+                    ptr("name", "std::str", false, true, false),
+                ],
+            }],
+            scalars,
+        }
+    }
+
+    #[test]
+    fn typescript_interfaces() {
+        let text = typescript(&schema());
+        assert!(text.contains("export type Color = \"Red\" | \"Green\";"));
+        assert!(text.contains("  color: Color | null;\n"));
+        assert!(text.contains("  friends: User[];\n"));
+        assert!(text.contains("  name: string;\n"));
+    }
+
+    #[test]
+    fn python_dataclasses() {
+        let text = python(&schema());
+        assert!(text.contains("Color = typing.Literal[\"Red\", \"Green\"]"));
+        assert!(text.contains("    color: typing.Optional[Color]\n"));
+        assert!(text.contains("    friends: typing.List[User]\n"));
+        assert!(text.contains("    name: str\n"));
+    }
+}
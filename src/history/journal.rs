@@ -0,0 +1,75 @@
+//! Execution metadata of queries run in the REPL
+//!
+//! Every query is appended as a JSON line to `edgeql.journal` next to the
+//! history file. When the file grows over the size limit it's moved to
+//! `edgeql.journal.old`, so only the recent queries are kept.
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use anyhow::Context;
+use dirs::data_local_dir;
+use fn_error_context::context;
+
+
+const MAX_SIZE: u64 = 4 << 20;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    pub query: String,
+    /// Only recorded when `\set print-stats` is enabled
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub error: bool,
+}
+
+pub fn app_dir() -> anyhow::Result<PathBuf> {
+    let dir = data_local_dir().context("cannot find local data dir")?;
+    Ok(dir.join("edgedb"))
+}
+
+fn path() -> anyhow::Result<PathBuf> {
+    Ok(app_dir()?.join("edgeql.journal"))
+}
+
+#[context("error writing query journal")]
+fn _record(entry: &Entry) -> anyhow::Result<()> {
+    let path = path()?;
+    fs::create_dir_all(app_dir()?)?;
+    match fs::metadata(&path) {
+        Ok(meta) if meta.len() > MAX_SIZE => {
+            fs::rename(&path, path.with_extension("journal.old"))?;
+        }
+        _ => {}
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    fs::OpenOptions::new().create(true).append(true).open(&path)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+pub fn record(entry: &Entry) {
+    _record(entry).map_err(|e| log::warn!("{:#}", e)).ok();
+}
+
+#[context("error reading query journal")]
+pub fn read() -> anyhow::Result<Vec<Entry>> {
+    let path = path()?;
+    let mut result = Vec::new();
+    for path in &[path.with_extension("journal.old"), path] {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e)?,
+        };
+        for line in BufReader::new(file).lines() {
+            match serde_json::from_str(&line?) {
+                Ok(entry) => result.push(entry),
+                Err(e) => log::debug!("Skipping bad journal line: {}", e),
+            }
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,17 @@
+pub mod options;
+
+mod journal;
+mod stats;
+
+pub use journal::{record, Entry};
+
+use crate::history::options::{HistoryCommand, Command};
+
+
+pub fn main(cmd: &HistoryCommand) -> anyhow::Result<()> {
+    use Command::*;
+
+    match &cmd.subcommand {
+        Stats(c) => stats::show(c),
+    }
+}
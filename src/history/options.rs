@@ -0,0 +1,23 @@
+use clap::{Clap, AppSettings};
+
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct HistoryCommand {
+    #[clap(subcommand)]
+    pub subcommand: Command,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum Command {
+    /// Show most frequent, slowest and failing queries from REPL history
+    Stats(Stats),
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Stats {
+    /// Number of queries shown in each table
+    #[clap(long, default_value="10")]
+    pub limit: usize,
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+
+use anyhow::Context;
+use prettytable::{Table, Row, Cell};
+use rustyline::error::ReadlineError;
+use rustyline::history::History;
+
+use crate::history::journal::{self, Entry};
+use crate::history::options::Stats;
+use crate::table;
+
+
+const QUERY_WIDTH: usize = 60;
+
+fn short_query(query: &str) -> String {
+    let mut lines = query.trim().lines();
+    let first = lines.next().unwrap_or("");
+    let mut text = first.chars().take(QUERY_WIDTH).collect::<String>();
+    if text.len() < first.len() || lines.next().is_some() {
+        text.push_str(" ...");
+    }
+    text
+}
+
+fn read_history() -> anyhow::Result<Vec<String>> {
+    let mut history = History::new();
+    match history.load(&journal::app_dir()?.join("edgeql.history")) {
+        Err(ReadlineError::Io(e)) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e).context("error loading history")?,
+        Ok(()) => {}
+    }
+    Ok(history.iter()
+        .filter(|q| !q.trim_start().starts_with('\\'))
+        .cloned()
+        .collect())
+}
+
+fn print_table(title: &str, columns: &[&str], rows: Vec<Vec<String>>) {
+    println!("{}:", title);
+    if rows.is_empty() {
+        println!("  (no data)");
+        return;
+    }
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    table.set_titles(Row::new(
+        columns.iter().map(|x| table::header_cell(x)).collect()));
+    for row in rows {
+        table.add_row(Row::new(row.iter().map(|x| Cell::new(x)).collect()));
+    }
+    table.printstd();
+}
+
+fn most_frequent(history: &[String], limit: usize) -> Vec<Vec<String>> {
+    let mut counts = HashMap::new();
+    for query in history {
+        *counts.entry(query.trim()).or_insert(0) += 1;
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|(aq, ac), (bq, bc)| bc.cmp(ac).then(aq.cmp(bq)));
+    counts.into_iter().take(limit)
+        .map(|(query, count)| vec![count.to_string(), short_query(query)])
+        .collect()
+}
+
+fn slowest(journal: &[Entry], limit: usize) -> Vec<Vec<String>> {
+    let mut timed = journal.iter()
+        .filter_map(|e| e.duration_ms.map(|d| (d, &e.query)))
+        .collect::<Vec<_>>();
+    timed.sort_by(|(a, _), (b, _)| b.cmp(a));
+    timed.into_iter().take(limit)
+        .map(|(duration, query)| vec![
+            format!("{}ms", duration),
+            short_query(query),
+        ])
+        .collect()
+}
+
+fn failing(journal: &[Entry], limit: usize) -> Vec<Vec<String>> {
+    let mut counts = HashMap::new();
+    for entry in journal {
+        let (runs, errors) = counts.entry(entry.query.trim())
+            .or_insert((0, 0));
+        *runs += 1;
+        if entry.error {
+            *errors += 1;
+        }
+    }
+    let mut counts = counts.into_iter()
+        .filter(|(_, (_, errors))| *errors > 0)
+        .collect::<Vec<_>>();
+    counts.sort_by(|(_, (_, a)), (_, (_, b))| b.cmp(a));
+    counts.into_iter().take(limit)
+        .map(|(query, (runs, errors))| vec![
+            errors.to_string(),
+            runs.to_string(),
+            short_query(query),
+        ])
+        .collect()
+}
+
+pub fn show(options: &Stats) -> anyhow::Result<()> {
+    let history = read_history()?;
+    let journal = journal::read()?;
+    print_table("Most frequent queries", &["Count", "Query"],
+        most_frequent(&history, options.limit));
+    println!();
+    print_table("Slowest queries", &["Duration", "Query"],
+        slowest(&journal, options.limit));
+    if journal.iter().all(|e| e.duration_ms.is_none()) {
+        println!("  Use `\\set print-stats query` in the REPL \
+                  to record query timings.");
+    }
+    println!();
+    print_table("Failing queries", &["Errors", "Runs", "Query"],
+        failing(&journal, options.limit));
+    Ok(())
+}
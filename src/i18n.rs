@@ -0,0 +1,169 @@
+//! Message catalog for interactive prompts and messages
+//!
+//! Language is chosen by the `EDGEDB_LANG` environment variable, e.g.
+//! `EDGEDB_LANG=ru`. Messages missing in the selected catalog are shown
+//! in English.
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+
+use once_cell::sync::Lazy;
+
+
+const EN: &[(&str, &str)] = &[
+    ("project-init.new-project",
+     "Do you want to initialize a new project?"),
+    ("project-init.instance-type",
+     "What type of EdgeDB instance would you like to use with this project?"),
+    ("project-init.instance-name",
+     "Specify the name of EdgeDB instance to use with this project"),
+    ("project-init.invalid-name",
+     "instance name must be a valid identifier, \
+      (regex: ^[a-zA-Z_][a-zA-Z_0-9]*$)"),
+    ("project-init.use-existing",
+     "Do you want to use existing instance {name} for the project?"),
+    ("project-init.version",
+     "Specify the version of EdgeDB to use with this project"),
+    ("project-unlink.confirm-destroy",
+     "Do you really want to unlink and delete instance {name}?"),
+    ("project-unlink.ask-destroy",
+     "Do you also want to destroy instance {name} and its data? \
+      Otherwise the instance is kept"),
+    ("project-relink.confirm-mismatch",
+     "Schema of instance {name} doesn't match the project migrations. \
+      Link the project anyway?"),
+    ("canceled", "Canceled"),
+    ("server-destroy.used-by",
+     "Instance {name} is used by the following projects:"),
+    ("server-destroy.force-hint",
+     "If you really want to destroy the instance, run:"),
+    ("migration.ddl-will-be-applied",
+     "Following DDL statements will be applied:"),
+    ("migration.extra-ddl",
+     "Following extra DDL statements will be applied:"),
+    ("migration.already-approved",
+     "(approved as part as part of an earlier prompt)"),
+    ("migration.apply-ddl", "Apply the DDL statements?"),
+    ("migration.schema-diff", "Following schema changes will be made:"),
+    ("migration.remaining", "Prompts remaining after this one:"),
+    ("migration.no-remaining", "No prompts remaining after this one"),
+    ("migration.remaining-unknown",
+     "Further prompts depend on the input required by the last one"),
+    ("migration.no-confirmed", "No EdgeQL statements were confirmed yet"),
+    ("migration.confirmed", "Following EdgeQL statements were confirmed:"),
+    ("migration.unknown-command", "Error: unknown command {command}"),
+    ("migration.no-changes", "No schema changes detected."),
+];
+
+const RU: &[(&str, &str)] = &[
+    ("project-init.new-project",
+     "Инициализировать новый проект?"),
+    ("project-init.instance-type",
+     "Какой тип экземпляра EdgeDB использовать для этого проекта?"),
+    ("project-init.instance-name",
+     "Укажите имя экземпляра EdgeDB для этого проекта"),
+    ("project-init.invalid-name",
+     "имя экземпляра должно быть допустимым идентификатором \
+      (regex: ^[a-zA-Z_][a-zA-Z_0-9]*$)"),
+    ("project-init.use-existing",
+     "Использовать существующий экземпляр {name} для проекта?"),
+    ("project-init.version",
+     "Укажите версию EdgeDB для этого проекта"),
+    ("project-unlink.confirm-destroy",
+     "Действительно отвязать и удалить экземпляр {name}?"),
+    ("project-unlink.ask-destroy",
+     "Удалить также экземпляр {name} и его данные? \
+      Иначе экземпляр будет сохранён"),
+    ("project-relink.confirm-mismatch",
+     "Схема экземпляра {name} не соответствует миграциям проекта. \
+      Всё равно привязать проект?"),
+    ("canceled", "Отменено"),
+    ("server-destroy.used-by",
+     "Экземпляр {name} используется следующими проектами:"),
+    ("server-destroy.force-hint",
+     "Если вы действительно хотите удалить экземпляр, выполните:"),
+    ("migration.ddl-will-be-applied",
+     "Будут применены следующие DDL-выражения:"),
+    ("migration.extra-ddl",
+     "Будут применены следующие дополнительные DDL-выражения:"),
+    ("migration.already-approved",
+     "(подтверждено ранее)"),
+    ("migration.apply-ddl", "Применить DDL-выражения?"),
+    ("migration.schema-diff", "Схема будет изменена следующим образом:"),
+    ("migration.remaining", "Оставшиеся после этого вопросы:"),
+    ("migration.no-remaining", "После этого вопросов не осталось"),
+    ("migration.remaining-unknown",
+     "Дальнейшие вопросы зависят от ввода, требуемого последним"),
+    ("migration.no-confirmed", "Пока не подтверждено ни одного выражения"),
+    ("migration.confirmed", "Подтверждены следующие выражения EdgeQL:"),
+    ("migration.unknown-command", "Ошибка: неизвестная команда {command}"),
+    ("migration.no-changes", "Изменений схемы не обнаружено."),
+];
+
+static CATALOG: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
+    let mut catalog = EN.iter().cloned().collect::<HashMap<_, _>>();
+    if let Some(translation) = env::var("EDGEDB_LANG").ok()
+        .as_deref().and_then(find_catalog)
+    {
+        catalog.extend(translation.iter().cloned());
+    }
+    catalog
+});
+
+fn find_catalog(lang: &str) -> Option<&'static [(&'static str, &'static str)]>
+{
+    // accept `ru`, `ru_RU` and `ru_RU.UTF-8`
+    let lang = lang.split(|c| c == '_' || c == '.' || c == '-')
+        .next().unwrap_or("");
+    match lang {
+        "en" | "C" | "" => None,
+        "ru" => Some(RU),
+        _ => {
+            log::warn!("Unsupported EDGEDB_LANG={:?}", lang);
+            None
+        }
+    }
+}
+
+/// Returns the message in the current language
+pub fn text(key: &'static str) -> &'static str {
+    CATALOG.get(key).copied().unwrap_or_else(|| {
+        debug_assert!(false, "no message {:?}", key);
+        key
+    })
+}
+
+/// Returns the message with `{name}` placeholders substituted
+pub fn format(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let mut result = text(key).to_string();
+    for (name, value) in args {
+        result = result.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use regex::Regex;
+
+    use super::{EN, RU};
+
+    fn placeholders(text: &str) -> BTreeSet<String> {
+        Regex::new(r"\{\w+\}").unwrap()
+            .find_iter(text)
+            .map(|m| m.as_str().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn translations_match_english() {
+        for (key, text) in RU {
+            let english = EN.iter().find(|(k, _)| k == key)
+                .unwrap_or_else(|| panic!("no english message {:?}", key));
+            assert_eq!(placeholders(text), placeholders(english.1),
+                       "placeholders of {:?}", key);
+        }
+    }
+}
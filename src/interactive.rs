@@ -21,14 +21,25 @@ use edgedb_protocol::server_message::ServerMessage;
 use edgedb_protocol::value::Value;
 use edgeql_parser::preparser::{self, full_statement};
 
-use crate::commands::{backslash, ExitCode};
+use crate::bindings;
+use crate::classify;
+use crate::commands::{backslash, insert, ExitCode};
+use crate::config;
+use crate::history;
 use crate::options::Options;
+use crate::output_style;
 use crate::print::{self, PrintError};
 use crate::prompt;
+use crate::protocol_trace;
+use crate::question;
+use edgedb_client::client::Connection;
 use edgedb_client::reader::ReadError;
 use crate::repl;
+use crate::spinner::Spinner;
 use crate::variables::input_variables;
 use crate::error_display::print_query_error;
+use crate::error_hints;
+use crate::warnings;
 use crate::outputs::tab_separated;
 
 
@@ -89,23 +100,49 @@ impl<'a> Iterator for ToDo<'a> {
 }
 
 
+fn prompt_template(config: &config::Config) -> String {
+    if let Some(template) = config.shell.prompt.clone() {
+        match repl::validate_prompt(&template) {
+            Ok(()) => return template,
+            Err(e) => log::warn!("Ignoring `shell.prompt` setting: {:#}", e),
+        }
+    }
+    return repl::DEFAULT_PROMPT.into();
+}
+
 pub fn main(options: Options) -> Result<(), anyhow::Error> {
     let (control_wr, control_rd) = channel(1);
     let (repl_wr, repl_rd) = channel(1);
+    let config = options.config.clone();
+    let mut print_config = print::Config::new();
+    print_config.max_items(100);
+    print_config.colors(output_style::colors(atty::Stream::Stdout));
+    print_config.vector_display(print::VectorDisplay::Truncated);
+    print_config.display_rules = print::display::DisplayRules::new(
+        config.display.clone());
     let state = repl::State {
         prompt: repl::PromptRpc {
             control: control_wr,
             data: repl_rd,
         },
-        print: print::Config::new()
-            .max_items(100)
-            .colors(atty::is(atty::Stream::Stdout))
-            .clone(),
+        print: print_config,
         verbose_errors: false,
         last_error: None,
+        last_query: None,
+        last_result: None,
+        variables: Default::default(),
         implicit_limit: Some(100),
         output_mode: options.output_mode,
         input_mode: repl::InputMode::Emacs,
+        input_language: repl::InputLanguage::EdgeQL,
+        prompt_template: prompt_template(&config),
+        paste_transaction: false,
+        idle_keepalive: 60,
+        safe_mode: config.shell.safe_mode,
+        read_only: options.read_only,
+        statement_timeout: None,
+        transaction_isolation: repl::TransactionIsolation::Serializable,
+        deferrable: false,
         print_stats: repl::PrintStats::Off,
         history_limit: 10000,
         database: options.conn_params.get()?.get_database().into(),
@@ -177,7 +214,7 @@ fn _check_json_limit(json: &serde_json::Value, path: &mut String, limit: usize)
 fn print_json_limit_error(path: &str) {
     eprintln!("Error: Cannot render JSON result: {} is too long. \
         Consider putting an explicit LIMIT clause, \
-        or increase the implicit limit using `\\set limit`.",
+        or increase the implicit limit using `\\set implicit-limit`.",
         if path.is_empty() { "." } else { path });
 }
 
@@ -191,7 +228,8 @@ fn check_json_limit(json: &serde_json::Value, path: &str, limit: usize) -> bool
     return true;
 }
 
-async fn execute_backslash(mut state: &mut repl::State, text: &str)
+async fn execute_backslash(options: &Options, mut state: &mut repl::State,
+                           text: &str)
     -> anyhow::Result<()>
 {
     use backslash::ExecuteResult::*;
@@ -218,6 +256,9 @@ async fn execute_backslash(mut state: &mut repl::State, text: &str)
             return Err(CleanShutdown)?;
         }
         Ok(Input(text)) => state.initial_text = text,
+        Ok(Statements(texts)) => {
+            return execute_generated(options, state, &texts).await;
+        }
         Err(e) => {
             if e.is::<ExitCode>() {
                 // It's expected that command already printed all required
@@ -233,12 +274,75 @@ async fn execute_backslash(mut state: &mut repl::State, text: &str)
     Ok(())
 }
 
+/// Executes statements generated by the previous query (`\\gexec`)
+async fn execute_generated(options: &Options, state: &mut repl::State,
+                           texts: &[String])
+    -> anyhow::Result<()>
+{
+    let items = texts.iter()
+        .flat_map(|text| ToDo::new(text))
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        eprintln!("== there are no statements to execute ==");
+        return Ok(());
+    }
+    if items.iter().any(|item| matches!(item, ToDoItem::Backslash(_))) {
+        anyhow::bail!("backslash commands can't be executed by \\gexec");
+    }
+    print_statements(&items);
+    let q = question::Confirm::new(
+        "Execution stops at the first failed statement. Continue?");
+    if !q.ask()? {
+        eprintln!("Statements cancelled.");
+        return Ok(());
+    }
+    for (idx, item) in items.iter().enumerate() {
+        if let ToDoItem::Query(statement) = item {
+            if let Err(e) = execute_query(options, state, statement).await {
+                eprintln!("Executed {} of {} statements.", idx, items.len());
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn execute_query(options: &Options, mut state: &mut repl::State,
     statement: &str)
     -> anyhow::Result<()>
 {
     use crate::repl::OutputMode::*;
     use crate::repl::PrintStats::*;
+
+    state.last_result = None;
+
+    if classify::is_read_only() && classify::is_mutating(statement) {
+        eprintln!("Error: Statement modifies data, schema or configuration, \
+            but the session is read-only. \
+            Use `\\set read-only off` to allow it.");
+        return Err(QueryError)?;
+    }
+    if state.safe_mode {
+        if let Some(danger) = classify::danger(statement) {
+            let q = question::Confirm::new_dangerous(
+                format!("{} Do you really want to run it?", danger));
+            if !q.ask()? {
+                eprintln!("Statement cancelled.");
+                return Err(QueryError)?;
+            }
+        }
+    }
+
+    // transaction options set by `\set transaction-isolation` and
+    // `\set deferrable` apply to a plain `START TRANSACTION`
+    let start_tx;
+    let statement = if classify::is_plain_start_transaction(statement) {
+        start_tx = state.start_transaction_query();
+        &start_tx[..]
+    } else {
+        statement
+    };
+
     let start = Instant::now();
 
     let statement_name = Bytes::from_static(b"");
@@ -258,7 +362,7 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
 
     let start_prepare = Instant::now();
     let mut seq = cli.start_sequence().await?;
-    seq.send_messages(&[
+    seq.send_messages(protocol_trace::sent(&[
         ClientMessage::Prepare(Prepare {
             headers,
             io_format: match state.output_mode {
@@ -271,10 +375,12 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
             command_text: String::from(statement),
         }),
         ClientMessage::Flush,
-    ]).await?;
+    ])).await?;
 
+    let mut warning = None;
     loop {
         let msg = seq.message().await?;
+        protocol_trace::received(&msg);
         match msg {
             ServerMessage::PrepareComplete(..) => {
                 break;
@@ -285,28 +391,39 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
                 seq.err_sync().await?;
                 return Err(QueryError)?;
             }
+            ServerMessage::LogMessage(msg) => {
+                warning = warnings::handle(&msg).err().or(warning);
+            }
             _ => {
                 eprintln!("WARNING: unsolicited message {:?}", msg);
             }
         }
     }
+    if let Some(warning) = warning {
+        // statement isn't executed yet
+        eprintln!("Error: {}", warning);
+        state.last_error = Some(warning.into());
+        seq.end_clean();
+        return Err(QueryError)?;
+    }
     if state.print_stats == Detailed {
         eprintln!("{}",
             format!("Prepare: {:?}", start_prepare.elapsed()).dark_gray());
     }
 
     let start_describe = Instant::now();
-    seq.send_messages(&[
+    seq.send_messages(protocol_trace::sent(&[
         ClientMessage::DescribeStatement(DescribeStatement {
             headers: HashMap::new(),
             aspect: DescribeAspect::DataDescription,
             statement_name: statement_name.clone(),
         }),
         ClientMessage::Flush,
-    ]).await?;
+    ])).await?;
 
     let data_description = loop {
         let msg = seq.message().await?;
+        protocol_trace::received(&msg);
         match msg {
             ServerMessage::CommandDataDescription(data_desc) => {
                 break data_desc;
@@ -317,11 +434,20 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
                 seq.err_sync().await?;
                 return Err(QueryError)?;
             }
+            ServerMessage::LogMessage(msg) => {
+                warning = warnings::handle(&msg).err().or(warning);
+            }
             _ => {
                 eprintln!("WARNING: unsolicited message {:?}", msg);
             }
         }
     };
+    if let Some(warning) = warning {
+        eprintln!("Error: {}", warning);
+        state.last_error = Some(warning.into());
+        seq.end_clean();
+        return Err(QueryError)?;
+    }
     if state.print_stats == Detailed {
         eprintln!("{}",
             format!("Describe: {:?}", start_describe.elapsed()).dark_gray());
@@ -359,18 +485,21 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
     let mut arguments = BytesMut::with_capacity(8);
     incodec.encode(&mut arguments, &input)?;
 
-    seq.send_messages(&[
+    seq.send_messages(protocol_trace::sent(&[
         ClientMessage::Execute(Execute {
             headers: HashMap::new(),
             statement_name: statement_name.clone(),
             arguments: arguments.freeze(),
         }),
         ClientMessage::Sync,
-    ]).await?;
+    ])).await?;
 
+    let mut spinner = Spinner::start("Running query...");
     let mut items = seq.response(codec);
     if desc.root_pos().is_none() {
-        match items.get_completion().await {
+        let completion = items.get_completion().await;
+        spinner.stop();
+        match completion {
             Ok(ref val) => print::completion(val),
             Err(e) => {
                 eprintln!("Error: {}", e);
@@ -380,6 +509,7 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
         }
         return Ok(());
     }
+    state.last_query = Some(statement.into());
 
     let mut cfg = state.print.clone();
     if let Some((w, _h)) = term_size::dimensions_stdout() {
@@ -389,7 +519,9 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
     match state.output_mode {
         TabSeparated => {
             let mut index = 0;
+            let mut result = repl::LastResult::new(cfg.spill_threshold);
             while let Some(row) = items.next().await.transpose()? {
+                spinner.stop();
                 if index == 0 && state.print_stats == Detailed {
                     eprintln!("{}",
                         format!("First row: {:?}", start_execute.elapsed())
@@ -401,12 +533,16 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
                         eprintln!("Error: Too many rows. Consider \
                             putting an explicit LIMIT clause, \
                             or increase the implicit limit \
-                            using `\\set limit`.");
+                            using `\\set implicit-limit`.");
                         items.skip_remaining().await?;
                         return Err(QueryError)?;
                     }
                 }
-                let mut text = match tab_separated::format_row(&row) {
+                let null_display = cfg.null_display.as_deref()
+                    .unwrap_or("");
+                let mut text = match
+                    tab_separated::format_row(&row, null_display)
+                {
                     Ok(text) => text,
                     Err(e) => {
                         eprintln!("Error: {}", e);
@@ -419,10 +555,25 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
                 // trying to make writes atomic if possible
                 text += "\n";
                 stdout().write_all(text.as_bytes()).await?;
+                result.push(&row);
                 index += 1;
             }
+            spinner.stop();
+            state.last_result = Some(result);
         }
         Default => {
+            let mut rows = 0;
+            let mut result = repl::LastResult::new(cfg.spill_threshold);
+            let limit = state.implicit_limit;
+            let items = spinner.stop_on_data(items.inspect(|item| {
+                rows += 1;
+                match (item, limit) {
+                    // server returns one extra row to detect truncation
+                    (_, Some(limit)) if rows > limit => {}
+                    (Ok(value), _) => result.push(value),
+                    (Err(_), _) => {}
+                }
+            }));
             match print::native_to_stdout(items, &cfg).await {
                 Ok(()) => {}
                 Err(e) => {
@@ -441,10 +592,13 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
                 }
             }
             println!();
+            state.last_result = Some(result);
         }
         Json => {
             let mut index = 0;
+            let mut data = print::SpillBuffer::new(cfg.spill_threshold);
             while let Some(row) = items.next().await.transpose()? {
+                spinner.stop();
                 if index == 0 && state.print_stats == Detailed {
                     eprintln!("{}",
                         format!("First row: {:?}", start_execute.elapsed())
@@ -470,15 +624,18 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
                     .ok_or_else(|| anyhow::anyhow!(
                         "non-array returned from \
                          postgres in JSON mode"))?;
-                // trying to make writes atomic if possible
-                let mut data = print::json_to_string(jitems, &cfg)?;
-                data += "\n";
-                stdout().write_all(data.as_bytes()).await?;
+                print::json_to_spill(jitems, &cfg, &mut data)?;
+                data.push_str("\n")?;
             }
+            spinner.stop();
+            // output is written at once, so it's not interleaved
+            // with errors and notices
+            data.write_to_stdout().await?;
         }
         JsonElements => {
             let mut index = 0;
             while let Some(row) = items.next().await.transpose()? {
+                spinner.stop();
                 if index == 0 && state.print_stats == Detailed {
                     eprintln!("{}",
                         format!("First row: {:?}", start_execute.elapsed())
@@ -512,6 +669,7 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
                 stdout().write_all(data.as_bytes()).await?;
                 index += 1;
             }
+            spinner.stop();
         }
     }
     if state.print_stats != Off {
@@ -525,6 +683,71 @@ async fn execute_query(options: &Options, mut state: &mut repl::State,
     return Ok(());
 }
 
+async fn execute_json_insert(state: &mut repl::State, type_name: &str,
+    text: &str)
+    -> anyhow::Result<()>
+{
+    classify::guard_command("json mode insert")?;
+    let text = text.trim().trim_end_matches(';');
+    let docs = match serde_json::from_str(text) {
+        Ok(serde_json::Value::Array(items)) => items,
+        Ok(item) => vec![item],
+        Err(e) => {
+            eprintln!("Error: invalid JSON: {}", e);
+            return Err(QueryError)?;
+        }
+    };
+    let cli = state.connection.as_mut().expect("connection established");
+    let info = insert::introspect(cli, type_name).await?;
+    let mut cfg = state.print.clone();
+    if let Some((w, _h)) = term_size::dimensions_stdout() {
+        cfg.max_width(w);
+    }
+    for doc in docs {
+        let mut obj = match doc {
+            serde_json::Value::Object(obj) => obj,
+            _ => anyhow::bail!("JSON object expected"),
+        };
+        // links are set by the ids of the target objects
+        if let Err(e) = insert::validate(&info, &mut obj) {
+            eprintln!("Error: {}", e);
+            return Err(QueryError)?;
+        }
+        let fields = obj.keys().cloned().collect::<Vec<_>>();
+        let stmt = insert::insert_object_query(&info, &fields, "id");
+        let arg = Value::Tuple(vec![
+            Value::Json(serde_json::Value::Object(obj).to_string()),
+        ]);
+        let mut rows = cli.query_json(&stmt, &arg).await?;
+        while let Some(row) = rows.next().await.transpose()? {
+            let items: serde_json::Value = serde_json::from_str(&row)
+                .context("cannot decode json result")?;
+            let items = items.as_array()
+                .ok_or_else(|| anyhow::anyhow!(
+                    "non-array returned from postgres in JSON mode"))?;
+            let mut data = print::json_to_string(items, &cfg)?;
+            data += "\n";
+            stdout().write_all(data.as_bytes()).await?;
+        }
+    }
+    state.last_error = None;
+    Ok(())
+}
+
+fn print_statements(items: &[ToDoItem]) {
+    eprintln!("{}", format!("Running {} statements:", items.len()).dark_gray());
+    for (idx, item) in items.iter().enumerate() {
+        let text = match item {
+            ToDoItem::Query(text) | ToDoItem::Backslash(text) => text,
+        };
+        let mut lines = text.trim().lines();
+        let first = lines.next().unwrap_or("");
+        let more = if lines.next().is_some() { " ..." } else { "" };
+        eprintln!("{}",
+            format!("  {}. {}{}", idx + 1, first, more).dark_gray());
+    }
+}
+
 async fn _interactive_main(options: &Options, state: &mut repl::State)
     -> Result<(), anyhow::Error>
 {
@@ -546,10 +769,36 @@ async fn _interactive_main(options: &Options, state: &mut repl::State)
             }
             prompt::Input::Text(inp) => inp,
         };
-        for item in ToDo::new(&inp) {
+        let items = ToDo::new(&inp).collect::<Vec<_>>();
+        let queries = items.iter()
+            .filter(|item| matches!(item, ToDoItem::Query(_)))
+            .count();
+        if queries > 1 {
+            print_statements(&items);
+        }
+        // pasted text managing its own transaction is run as is
+        let wrap = queries > 1 && state.paste_transaction
+            && state.input_language == repl::InputLanguage::EdgeQL
+            && !state.in_transaction()
+            && !items.iter().any(|item| matches!(item,
+                ToDoItem::Query(q) if classify::is_transaction_control(q)));
+        if wrap {
+            state.soft_reconnect()
+                .race(async { ctrlc.next().await; Err(Interrupted)?})
+                .await?;
+            let start_tx = state.start_transaction_query();
+            let cli = state.connection.as_mut()
+                .expect("connection established");
+            if let Err(e) = cli.execute(&start_tx).await {
+                eprintln!("Error: {:#}", e);
+                continue;
+            }
+        }
+        let mut failed = false;
+        for item in items {
             let result = match item {
                 ToDoItem::Backslash(text) => {
-                    execute_backslash(state, text)
+                    execute_backslash(options, state, text)
                         .race(async { ctrlc.next().await; Err(Interrupted)?})
                         .await
                 }
@@ -557,26 +806,76 @@ async fn _interactive_main(options: &Options, state: &mut repl::State)
                     state.soft_reconnect()
                         .race(async { ctrlc.next().await; Err(Interrupted)?})
                         .await?;
-                    execute_query(options, state, statement)
-                        .race(async { ctrlc.next().await; Err(Interrupted)?})
-                        .await
+                    match state.input_language {
+                        repl::InputLanguage::EdgeQL => {
+                            let start = Instant::now();
+                            let text = bindings::interpolate(
+                                statement, &state.variables);
+                            let result = execute_query(options, state, &text)
+                                .race(async {
+                                    ctrlc.next().await; Err(Interrupted)?
+                                })
+                                .await;
+                            let timed = state.print_stats
+                                != repl::PrintStats::Off;
+                            history::record(&history::Entry {
+                                query: statement.trim().into(),
+                                duration_ms: if timed {
+                                    Some(start.elapsed().as_millis() as u64)
+                                } else {
+                                    None
+                                },
+                                error: result.is_err(),
+                            });
+                            result
+                        }
+                        repl::InputLanguage::JsonInsert(ref type_name) => {
+                            let type_name = type_name.clone();
+                            execute_json_insert(state, &type_name, statement)
+                                .race(async {
+                                    ctrlc.next().await; Err(Interrupted)?
+                                })
+                                .await
+                        }
+                    }
                 }
             };
             if let Err(err) = result {
                 if err.is::<Interrupted>() {
-                    eprintln!("Interrupted.");
+                    eprintln!("Interrupted. \
+                        Press Ctrl+C again to exit while cancelling.");
+                    // dropping the connection cancels query on the server
                     state.reconnect()
-                        .race(async { ctrlc.next().await; Err(Interrupted)? })
+                        .race(async {
+                            ctrlc.next().await;
+                            Err(CleanShutdown)?
+                        })
                         .await?;
                 } else if err.is::<CleanShutdown>() {
                     return Err(err)?;
-                } else if !err.is::<QueryError>() {
+                } else if err.is::<QueryError>() {
+                    error_hints::print_hint(state).await;
+                } else {
                     eprintln!("Error: {:#}", err);
                 }
+                failed = true;
                 // Don't continue next statements on error
                 break;
             }
         }
+        if wrap && state.in_transaction() {
+            let cli = state.connection.as_mut()
+                .expect("connection established");
+            let result = if failed {
+                eprintln!("Rolling back pasted statements.");
+                cli.execute("ROLLBACK").await
+            } else {
+                cli.execute("COMMIT").await
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {:#}", e);
+            }
+        }
     }
 }
 
@@ -1,9 +1,14 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::options::{Options, Command};
 use crate::commands::parser::Common;
 use crate::server::options::Command as Server;
 
 
 pub fn init(builder: &mut env_logger::Builder, opt: &Options) {
+    if opt.verbosity.show_details() {
+        builder.filter_module("edgedb", log::LevelFilter::Info);
+    }
     if opt.debug_print_frames {
         builder.filter_module("edgedb::incoming::frame",
                               log::LevelFilter::Debug);
@@ -40,3 +45,41 @@ pub fn init(builder: &mut env_logger::Builder, opt: &Options) {
         _ => {}
     }
 }
+
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts warnings and errors for `--fail-on=warnings`
+struct CountWarnings(env_logger::Logger);
+
+impl log::Log for CountWarnings {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+    fn log(&self, record: &log::Record) {
+        if record.level() <= log::Level::Warn && self.0.matches(record) {
+            WARNINGS.fetch_add(1, Ordering::Relaxed);
+        }
+        self.0.log(record)
+    }
+    fn flush(&self) {
+        self.0.flush()
+    }
+}
+
+/// Replaces `builder.init()`
+pub fn install(mut builder: env_logger::Builder) {
+    let logger = builder.build();
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(CountWarnings(logger)))
+        .expect("logger is installed once");
+}
+
+/// Counts a warning which is printed without the logger
+pub fn count_warning() {
+    WARNINGS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Number of warnings and errors reported so far
+pub fn warnings() -> usize {
+    WARNINGS.load(Ordering::Relaxed)
+}
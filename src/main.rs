@@ -11,46 +11,70 @@ use clap::Clap;
 
 use crate::options::Options;
 
+mod aliases;
 mod async_util;
+mod audit;
+mod bindings;
 mod bug;
+mod classify;
 mod commands;
 mod completion;
+mod config;
 mod connect;
 mod credentials;
+mod dry_run;
+mod env_file;
 mod error_display;
+mod error_hints;
 mod format;
+mod generate;
 mod highlight;
 mod hint;
+mod history;
+mod i18n;
 mod interactive;
 mod log_levels;
 mod migrations;
 mod non_interactive;
 mod options;
+mod output_style;
 mod outputs;
+mod pager;
 mod platform;
 mod print;
 mod process;
 mod project;
 mod prompt;
+mod protocol_trace;
+mod queries;
 mod question;
 mod repl;
+mod schema;
+mod script;
 mod self_install;
 mod self_upgrade;
 mod server;
+mod setup_wizard;
+mod spinner;
 mod statement;
 mod table;
+mod telemetry;
+mod testing;
+mod typos;
 mod variables;
+mod verbosity;
 mod version_check;
+mod warnings;
 
 fn main() {
     match _main() {
         Ok(()) => {}
         Err(ref e) => {
             let mut err = e;
-            let mut code = 1;
             if let Some(e) = err.downcast_ref::<commands::ExitCode>() {
                 e.exit();
             }
+            let code = commands::exit::error_code(err);
             if let Some(arc) = err.downcast_ref::<hint::ArcError>() {
                 // prevent duplicate error message
                 err = arc.inner();
@@ -68,10 +92,6 @@ fn main() {
                         issue ticket at \
                         https://github.com/edgedb/edgedb-cli/issues/new\
                         ?template=bug_report.md");
-                    code = 13;
-                } else if let Some(e) = e.downcast_ref::<commands::ExitCode>()
-                {
-                    code = e.code();
                 }
             }
             exit(code);
@@ -94,17 +114,32 @@ fn _main() -> anyhow::Result<()> {
         }
     }
 
+    platform::enable_ansi_escapes();
     let opt = Options::from_args_and_env()?;
 
     let mut builder = env_logger::Builder::from_env(
         env_logger::Env::default().default_filter_or("warn")
     );
     log_levels::init(&mut builder, &opt);
-    builder.init();
+    log_levels::install(builder);
 
-    version_check::check(opt.no_version_check);
+    // `edgedb config doctor` prints them itself
+    if !matches!(opt.subcommand, Some(options::Command::Config(..))) {
+        for warning in &opt.config.warnings {
+            log::warn!("{}", warning);
+        }
+    }
+
+    // `edgedb version --check` fetches the versions itself
+    if !matches!(opt.subcommand, Some(options::Command::Version(..))) {
+        version_check::check(
+            opt.no_version_check || !opt.verbosity.show_hints());
+    }
 
-    if opt.subcommand.is_some() {
+    let telemetry_mode = opt.config.telemetry;
+    let opt_fail_on = opt.fail_on;
+    let command = telemetry::command(&opt);
+    let result = if opt.subcommand.is_some() {
         commands::cli::main(opt)
     } else {
         if opt.interactive {
@@ -112,5 +147,18 @@ fn _main() -> anyhow::Result<()> {
         } else {
             task::block_on(non_interactive::main(opt))
         }
-    }
+    };
+    let result = match result {
+        Ok(()) if opt_fail_on == Some(options::FailOn::Warnings)
+            && log_levels::warnings() > 0
+        => {
+            eprintln!("edgedb error: {} warning(s) reported, failing \
+                       because of `--fail-on=warnings`",
+                       log_levels::warnings());
+            Err(commands::ExitCode::new(commands::exit::WARNINGS).into())
+        }
+        result => result,
+    };
+    telemetry::record(telemetry_mode, &command, &result);
+    result
 }
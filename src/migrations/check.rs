@@ -0,0 +1,225 @@
+//! Checks of migrations intended to be run in CI
+use async_std::fs;
+use edgedb_client::client::Connection;
+use edgedb_protocol::value::Value;
+use linked_hash_map::LinkedHashMap;
+use serde::Serialize;
+
+use crate::commands::{Options, ExitCode};
+use crate::commands::parser::MigrationCheck;
+use crate::migrations::context::Context;
+use crate::migrations::create::{execute_start_migration, CurrentMigration};
+use crate::migrations::migration::{self, MigrationFile};
+use crate::migrations::plan::destructive_statements;
+
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all="lowercase")]
+enum Status {
+    Ok,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize, Debug)]
+struct Outcome {
+    check: &'static str,
+    status: Status,
+    message: String,
+    #[serde(skip_serializing_if="Vec::is_empty")]
+    details: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Report {
+    ok: bool,
+    checks: Vec<Outcome>,
+}
+
+impl Outcome {
+    fn new(check: &'static str, status: Status, message: impl Into<String>)
+        -> Outcome
+    {
+        Outcome {
+            check,
+            status,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+}
+
+async fn db_revision(cli: &mut Connection)
+    -> anyhow::Result<Option<String>>
+{
+    cli.query_row_opt(r###"
+            WITH Last := (SELECT schema::Migration
+                          FILTER NOT EXISTS .<parents[IS schema::Migration])
+            SELECT name := Last.name
+        "###, &Value::empty_tuple()).await
+}
+
+async fn check_schema(ctx: &Context, cli: &mut Connection)
+    -> anyhow::Result<Outcome>
+{
+    execute_start_migration(ctx, cli).await?;
+    let data = cli.query_row::<CurrentMigration>(
+        "DESCRIBE CURRENT MIGRATION AS JSON",
+        &Value::empty_tuple(),
+    ).await;
+    cli.execute("ABORT MIGRATION").await?;
+    let data = data?;
+    if data.confirmed.is_empty() && data.complete {
+        return Ok(Outcome::new("schema", Status::Ok,
+            "schema matches the migrations"));
+    }
+    let mut outcome = Outcome::new("schema", Status::Failed,
+        "schema has changes without a migration, \
+         use `edgedb create-migration`");
+    outcome.details = data.confirmed.iter().cloned()
+        .chain(data.proposed.iter()
+            .flat_map(|p| p.statements.iter().map(|s| s.text.clone())))
+        .collect();
+    Ok(outcome)
+}
+
+/// Migrations following the database revision, which must be present in
+/// the files
+fn pending<'a>(migrations: &'a LinkedHashMap<String, MigrationFile>,
+    db_rev: &Option<String>)
+    -> Vec<(&'a String, &'a MigrationFile)>
+{
+    migrations.iter()
+        .skip_while(|(id, _)| db_rev.as_ref().map(|r| r != *id)
+                               .unwrap_or(false))
+        .skip(if db_rev.is_some() { 1 } else { 0 })
+        .collect()
+}
+
+/// Applies pending migrations in a transaction which is rolled back after
+/// comparing the schema, so that a database behind the files (e.g. a fresh
+/// one in CI) is checked too
+async fn check_pending_schema(ctx: &Context, cli: &mut Connection,
+    pending: &[(&String, &MigrationFile)])
+    -> anyhow::Result<Outcome>
+{
+    cli.execute("START TRANSACTION").await?;
+    let mut applied = Ok(());
+    for (id, file) in pending {
+        let result = match fs::read_to_string(&file.path).await {
+            Ok(data) => cli.execute(&data).await.map(|_| ()),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            applied = Err(format!("cannot apply {}: {:#}", id, e));
+            break;
+        }
+    }
+    let outcome = match applied {
+        Ok(()) => check_schema(ctx, cli).await,
+        Err(message) => Ok(Outcome::new("schema", Status::Failed, message)),
+    };
+    cli.execute("ROLLBACK").await?;
+    outcome
+}
+
+async fn check_additive(pending: &[(&String, &MigrationFile)])
+    -> anyhow::Result<Outcome>
+{
+    let mut outcome = Outcome::new("additive", Status::Ok, "");
+    let mut count = 0;
+    for (id, file) in pending {
+        count += 1;
+        let text = fs::read_to_string(&file.path).await?;
+        let (start, end) = file.data.text_range;
+        for stmt in destructive_statements(&text[start..end])? {
+            outcome.details.push(format!("{}: {}", id, stmt));
+        }
+    }
+    if outcome.details.is_empty() {
+        outcome.message = format!("{} pending migration(s) are additive",
+                                  count);
+    } else {
+        outcome.status = Status::Failed;
+        outcome.message = "pending migrations contain non-additive changes"
+            .into();
+    }
+    Ok(outcome)
+}
+
+async fn run_checks(cli: &mut Connection, check: &MigrationCheck)
+    -> anyhow::Result<Vec<Outcome>>
+{
+    let ctx = Context::from_config(&check.cfg);
+    let mut outcomes = Vec::new();
+    let migrations = match migration::read_all(&ctx, true).await {
+        Ok(migrations) => {
+            outcomes.push(Outcome::new("files", Status::Ok,
+                format!("{} migration file(s) are valid", migrations.len())));
+            migrations
+        }
+        Err(e) => {
+            outcomes.push(Outcome::new("files", Status::Failed,
+                                       format!("{:#}", e)));
+            outcomes.push(Outcome::new("schema", Status::Skipped,
+                                       "migration files are invalid"));
+            if check.require_additive {
+                outcomes.push(Outcome::new("additive", Status::Skipped,
+                                           "migration files are invalid"));
+            }
+            return Ok(outcomes);
+        }
+    };
+    let db_rev = db_revision(cli).await?;
+    if let Some(rev) = &db_rev {
+        if !migrations.contains_key(rev) {
+            // history diverged, or the files are behind the database
+            let message = format!("database revision {} is not in \
+                                   the migration files", rev);
+            outcomes.push(Outcome::new("schema", Status::Failed, &message));
+            if check.require_additive {
+                outcomes.push(Outcome::new("additive", Status::Failed,
+                                           message));
+            }
+            return Ok(outcomes);
+        }
+    }
+    let pending = pending(&migrations, &db_rev);
+    if pending.is_empty() {
+        outcomes.push(check_schema(&ctx, cli).await?);
+    } else {
+        outcomes.push(check_pending_schema(&ctx, cli, &pending).await?);
+    }
+    if check.require_additive {
+        outcomes.push(check_additive(&pending).await?);
+    }
+    Ok(outcomes)
+}
+
+pub async fn check(cli: &mut Connection, _options: &Options,
+    check: &MigrationCheck)
+    -> anyhow::Result<()>
+{
+    let checks = run_checks(cli, check).await?;
+    let ok = checks.iter().all(|c| c.status != Status::Failed);
+    if check.json {
+        let report = Report { ok, checks };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for outcome in &checks {
+            let status = match outcome.status {
+                Status::Ok => "OK",
+                Status::Failed => "FAILED",
+                Status::Skipped => "SKIPPED",
+            };
+            eprintln!("{}: {}: {}", outcome.check, status, outcome.message);
+            for line in outcome.details.iter().flat_map(|d| d.lines()) {
+                eprintln!("    {}", line);
+            }
+        }
+    }
+    if !ok {
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
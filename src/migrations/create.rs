@@ -13,6 +13,7 @@ use edgedb_protocol::server_message::ErrorResponse;
 use edgedb_protocol::value::Value;
 use edgeql_parser::hash::Hasher;
 use edgeql_parser::expr;
+use edgeql_parser::helpers::quote_string;
 use edgeql_parser::tokenizer::{TokenStream, Kind as TokenKind};
 use edgeql_parser::schema_file::validate;
 use fn_error_context::context;
@@ -23,15 +24,22 @@ use serde::Deserialize;
 use crate::bug;
 use crate::commands::parser::CreateMigration;
 use crate::commands::{Options, ExitCode};
+use crate::i18n;
+use crate::output_style;
 use crate::error_display::print_query_error;
 use crate::migrations::context::Context;
+use crate::migrations::errors::MigrationConflict;
 use crate::migrations::migration;
+use crate::migrations::partial;
 use crate::migrations::print_error::print_migration_error;
 use crate::migrations::prompt;
+use crate::migrations::sdl_diff;
 use crate::migrations::source_map::{Builder, SourceMap};
+use crate::migrations::template::{self, Expansion};
 use crate::platform::tmp_file_name;
 
 const SAFE_CONFIDENCE: f64 = 0.99999;
+const MAX_LISTED_PROPOSALS: usize = 100;
 
 pub enum SourceName {
     Prefix,
@@ -45,6 +53,8 @@ pub enum Choice {
     No,
     List,
     Confirmed,
+    All,
+    Skip,
     Back,
     Split,
     Quit,
@@ -104,10 +114,14 @@ async fn query_row<R>(cli: &mut Connection, text: &str)
 }
 
 #[context("could not read schema file {}", path.display())]
-async fn read_schema_file(path: &Path) -> anyhow::Result<String> {
+async fn read_schema_file(path: &Path,
+    variables: Option<&BTreeMap<String, String>>)
+    -> anyhow::Result<Expansion>
+{
     let data = fs::read_to_string(path).await?;
-    validate(&data)?;
-    Ok(data)
+    let expanded = template::expand_file(&data, variables)?;
+    validate(&expanded.text)?;
+    Ok(expanded)
 }
 
 async fn choice(prompt: &str) -> anyhow::Result<Choice> {
@@ -116,17 +130,20 @@ async fn choice(prompt: &str) -> anyhow::Result<Choice> {
     const HELP: &str = r###"
 y - confirm the prompt, use the DDL statements
 n - reject the prompt
-l - list the DDL statements associated with prompt
+l - list the DDL statements associated with prompt, and the prompts
+    remaining after it
 c - list already confirmed EdgeQL statements
+a - confirm this and all remaining prompts
 b - revert back to previous save point, perhaps previous question
-s - stop and save changes (splits migration into multiple)
+s - reject this and all remaining prompts, save changes confirmed so far
+d - stop and save changes (splits migration into multiple)
 q - quit without saving changes
 h or ? - print help
 "###;
 
     let mut input = String::with_capacity(10);
     loop {
-        println!("{} [y,n,l,c,b,s,q,?]", prompt);
+        println!("{} [y,n,l,c,a,b,s,d,q,?]", prompt);
         input.truncate(0);
         if io::stdin().read_line(&mut input).await? == 0 {
             return Ok(Quit);
@@ -136,15 +153,18 @@ h or ? - print help
             "n"|"no" => No,
             "l"|"list" => List,
             "c"|"confirmed" => Confirmed,
+            "a"|"all" => All,
+            "s"|"skip" => Skip,
             "b"|"back" => Back,
-            "s"|"stop"|"split" => Split,
+            "d"|"done"|"stop"|"split" => Split,
             "h"|"?"|"help" => {
                 print!("{}", HELP);
                 continue;
             }
             "q"|"quit" => Quit,
             val => {
-                eprintln!("Error: unknown command {}", val);
+                eprintln!("{}", i18n::format("migration.unknown-command",
+                                             &[("command", &val)]));
                 continue;
             }
         };
@@ -156,6 +176,7 @@ h or ? - print help
 async fn gen_start_migration(ctx: &Context)
     -> anyhow::Result<(String, SourceMap<SourceName>)>
 {
+    let variables = template::project_variables()?;
     let mut bld = Builder::new();
     bld.add_lines(SourceName::Prefix, "START MIGRATION TO {");
     let mut dir = fs::read_dir(&ctx.schema_dir).await?;
@@ -168,8 +189,9 @@ async fn gen_start_migration(ctx: &Context)
             continue;
         }
         let path = item.path();
-        let chunk = read_schema_file(&path).await?;
-        bld.add_lines(SourceName::File(path.clone()), &chunk);
+        let chunk = read_schema_file(&path, variables.as_ref()).await?;
+        bld.add_substituted(SourceName::File(path.clone()),
+                            &chunk.text, chunk.substitutions);
         bld.add_lines(SourceName::Semicolon(path), ";");
     }
     bld.add_lines(SourceName::Suffix, "};");
@@ -192,19 +214,19 @@ pub async fn execute_start_migration(ctx: &Context, cli: &mut Connection)
     }
 }
 
-async fn run_non_interactive(ctx: &Context, cli: &mut Connection, index: u64,
-    options: &CreateMigration)
-    -> anyhow::Result<()>
+/// Accepts proposals of the server until the migration is complete
+pub async fn accept_proposals(cli: &mut Connection, allow_unsafe: bool)
+    -> anyhow::Result<CurrentMigration>
 {
-    let descr = loop {
+    loop {
         let data = query_row::<CurrentMigration>(cli,
             "DESCRIBE CURRENT MIGRATION AS JSON"
         ).await?;
         if data.complete {
-            break data;
+            return Ok(data);
         }
         if let Some(proposal) = data.proposed {
-            if proposal.confidence >= SAFE_CONFIDENCE || options.allow_unsafe {
+            if proposal.confidence >= SAFE_CONFIDENCE || allow_unsafe {
                 if !proposal.required_user_input.is_empty() {
                     for input in proposal.required_user_input {
                         eprintln!("Input required: {}", input.prompt);
@@ -233,12 +255,103 @@ async fn run_non_interactive(ctx: &Context, cli: &mut Connection, index: u64,
                 interactive mode to confirm changes, \
                 or use `--allow-unsafe`");
         }
-    };
+    }
+}
+
+async fn collect_proposals(cli: &mut Connection)
+    -> anyhow::Result<Vec<Proposal>>
+{
+    let mut proposals = Vec::new();
+    while proposals.len() < MAX_LISTED_PROPOSALS {
+        let descr = query_row::<CurrentMigration>(cli,
+            "DESCRIBE CURRENT MIGRATION AS JSON"
+        ).await?;
+        let proposal = match descr.proposed {
+            Some(proposal) if !descr.complete => proposal,
+            _ => break,
+        };
+        let needs_input = !proposal.required_user_input.is_empty();
+        if !needs_input {
+            for statement in &proposal.statements {
+                execute(cli, &statement.text).await?;
+            }
+        }
+        proposals.push(proposal);
+        if needs_input {
+            break;
+        }
+    }
+    Ok(proposals)
+}
+
+/// Proposals the server makes if the current and all following ones are
+/// confirmed, starting with the current one
+///
+/// Proposals are applied to find out the next ones, and rolled back to the
+/// save point afterwards. Listing stops at a proposal which requires user
+/// input, because the rest depends on it.
+async fn remaining_proposals(cli: &mut Connection, save_point: usize)
+    -> anyhow::Result<Vec<Proposal>>
+{
+    let result = collect_proposals(cli).await;
+    execute(cli, format!("ROLLBACK TO SAVEPOINT migration_{}", save_point))
+        .await?;
+    result
+}
+
+/// Rejects the current and all following proposals
+///
+/// Every rejected proposal is excluded from the following ones, so the
+/// server eventually runs out of proposals.
+async fn reject_remaining(cli: &mut Connection)
+    -> anyhow::Result<CurrentMigration>
+{
+    loop {
+        execute(cli, "ALTER CURRENT MIGRATION REJECT PROPOSED").await?;
+        let descr = query_row::<CurrentMigration>(cli,
+            "DESCRIBE CURRENT MIGRATION AS JSON"
+        ).await?;
+        if descr.complete || descr.proposed.is_none() {
+            return Ok(descr);
+        }
+    }
+}
+
+fn print_remaining(proposals: &[Proposal]) {
+    if proposals.len() <= 1 {
+        println!("{}", i18n::text("migration.no-remaining"));
+        return;
+    }
+    println!("{}", i18n::text("migration.remaining"));
+    for (idx, proposal) in proposals.iter().enumerate().skip(1) {
+        match &proposal.prompt {
+            Some(prompt) => println!("{:3}. {}", idx, prompt),
+            None => println!("{:3}. {}", idx,
+                             i18n::text("migration.apply-ddl")),
+        }
+        for statement in &proposal.statements {
+            for line in statement.text.lines() {
+                println!("         {}", line);
+            }
+        }
+    }
+    let last = proposals.last().expect("not empty");
+    if !last.required_user_input.is_empty() {
+        println!("{}", i18n::text("migration.remaining-unknown"));
+    }
+}
+
+async fn run_non_interactive(ctx: &Context, cli: &mut Connection, index: u64,
+    options: &CreateMigration)
+    -> anyhow::Result<()>
+{
+    let descr = accept_proposals(cli, options.allow_unsafe).await?;
     if descr.confirmed.is_empty() && !options.allow_empty {
-        eprintln!("No schema changes detected.");
+        eprintln!("{}", i18n::text("migration.no-changes"));
         return Err(ExitCode::new(4))?;
     }
-    write_migration(ctx, &descr, index, false).await?;
+    write_migration(ctx, &descr, index, options.message.as_deref(), false)
+        .await?;
     Ok(())
 }
 
@@ -250,6 +363,7 @@ async fn run_interactive(ctx: &Context, cli: &mut Connection, index: u64,
 
     let mut operations = vec![Set::new()];
     let mut save_point = 0;
+    let mut accept_all = false;
     execute(cli, format!("DECLARE SAVEPOINT migration_{}", save_point)).await?;
     let descr = 'migration: loop {
         let descr = query_row::<CurrentMigration>(cli,
@@ -266,13 +380,27 @@ async fn run_interactive(ctx: &Context, cli: &mut Connection, index: u64,
             let input;
             if already_approved {
                 input = loop {
-                    println!("Following extra DDL statements will be applied:");
+                    println!("{}", i18n::text("migration.extra-ddl"));
                     for statement in &proposal.statements {
                         for line in statement.text.lines() {
                             println!("    {}", line);
                         }
                     }
-                    println!("(approved as part as part of an earlier prompt)");
+                    println!("{}", i18n::text("migration.already-approved"));
+                    match get_user_input(&proposal.required_user_input) {
+                        Ok(data) => break data,
+                        Err(e) if e.is::<Refused>() => continue,
+                        Err(e) => return Err(e.into()),
+                    };
+                };
+            } else if accept_all {
+                println!("{}", i18n::text("migration.ddl-will-be-applied"));
+                for statement in &proposal.statements {
+                    for line in statement.text.lines() {
+                        println!("    {}", line);
+                    }
+                }
+                input = loop {
                     match get_user_input(&proposal.required_user_input) {
                         Ok(data) => break data,
                         Err(e) if e.is::<Refused>() => continue,
@@ -280,16 +408,26 @@ async fn run_interactive(ctx: &Context, cli: &mut Connection, index: u64,
                     };
                 };
             } else {
+                if proposal.required_user_input.is_empty() {
+                    let diff = sdl_diff::proposal_diff(cli,
+                        &proposal.statements, save_point).await?;
+                    if !diff.is_empty() {
+                        println!("{}", i18n::text("migration.schema-diff"));
+                        sdl_diff::print(&diff,
+                            output_style::colors(atty::Stream::Stdout));
+                    }
+                }
                 let prompt = if let Some(prompt) = &proposal.prompt {
                     prompt
                 } else {
-                    println!("Following DDL statements will be applied:");
+                    println!("{}",
+                        i18n::text("migration.ddl-will-be-applied"));
                     for statement in &proposal.statements {
                         for line in statement.text.lines() {
                             println!("    {}", line);
                         }
                     }
-                    "Apply the DDL statements?"
+                    i18n::text("migration.apply-ddl")
                 };
                 loop {
                     match choice(prompt).await? {
@@ -313,21 +451,34 @@ async fn run_interactive(ctx: &Context, cli: &mut Connection, index: u64,
                             continue 'migration;
                         }
                         List => {
-                            println!("Following DDL statements will be applied:");
+                            println!("{}",
+                                i18n::text("migration.ddl-will-be-applied"));
                             for statement in &proposal.statements {
                                 for line in statement.text.lines() {
                                     println!("    {}", line);
                                 }
                             }
+                            let remaining = remaining_proposals(
+                                cli, save_point).await?;
+                            print_remaining(&remaining);
                             continue;
                         }
+                        All => {
+                            match get_user_input(&proposal.required_user_input) {
+                                Ok(data) => input = data,
+                                Err(e) if e.is::<Refused>() => continue,
+                                Err(e) => return Err(e.into()),
+                            };
+                            accept_all = true;
+                            break;
+                        }
                         Confirmed => {
                             if descr.confirmed.is_empty() {
-                                println!(
-                                    "No EdgeQL statements were confirmed yet");
+                                println!("{}",
+                                    i18n::text("migration.no-confirmed"));
                             } else {
-                                println!(
-                                    "Following EdgeQL statements were confirmed:");
+                                println!("{}",
+                                    i18n::text("migration.confirmed"));
                                 for statement in &descr.confirmed {
                                     for line in statement.lines() {
                                         println!("    {}", line);
@@ -348,6 +499,9 @@ async fn run_interactive(ctx: &Context, cli: &mut Connection, index: u64,
                             operations.truncate(save_point + 1);
                             continue 'migration;
                         }
+                        Skip => {
+                            break 'migration reject_remaining(cli).await?;
+                        }
                         Split => {
                             break 'migration descr;
                         }
@@ -373,6 +527,7 @@ async fn run_interactive(ctx: &Context, cli: &mut Connection, index: u64,
                             }
                         }
                         eprintln!("Rolling back last operation...");
+                        accept_all = false;
                         execute(cli, format!(
                             "ROLLBACK TO SAVEPOINT migration_{}", save_point)
                         ).await?;
@@ -398,29 +553,33 @@ async fn run_interactive(ctx: &Context, cli: &mut Connection, index: u64,
         }
     };
     if descr.confirmed.is_empty() && !options.allow_empty {
-        eprintln!("No schema changes detected.");
+        eprintln!("{}", i18n::text("migration.no-changes"));
         return Err(ExitCode::new(4))?;
     }
-    write_migration(ctx, &descr, index, true).await?;
+    write_migration(ctx, &descr, index, options.message.as_deref(), true)
+        .await?;
     Ok(())
 }
 
 pub async fn write_migration(ctx: &Context, descr: &CurrentMigration,
-    index: u64, verbose: bool)
+    index: u64, message: Option<&str>, verbose: bool)
     -> anyhow::Result<()>
 {
     let dir = ctx.schema_dir.join("migrations");
     let filename = dir.join(format!("{:05}.edgeql", index));
-    _write_migration(descr, filename.as_ref(), verbose).await
+    _write_migration(descr, filename.as_ref(), message, verbose).await
 }
 
 #[context("could not write migration file {}", filepath.display())]
 async fn _write_migration(descr: &CurrentMigration, filepath: &Path,
-    verbose: bool)
+    message: Option<&str>, verbose: bool)
     -> anyhow::Result<()>
 {
-    let statements = descr.confirmed.iter()
-        .map(|s| s.clone())
+    // message is a part of the migration text, so it's hashed too
+    let statements = message
+        .map(|m| format!("SET message := {};", quote_string(m)))
+        .into_iter()
+        .chain(descr.confirmed.iter().cloned())
         .collect::<Vec<_>>();
     let mut hasher = Hasher::start_migration(&descr.parent);
     for statement in &statements {
@@ -469,12 +628,15 @@ pub async fn create(cli: &mut Connection, _options: &Options,
         Some(&descr.parent)
     };
     if db_migration != migrations.keys().last() {
-        anyhow::bail!("Database must be updated to the last migration \
+        return Err(MigrationConflict(anyhow::anyhow!(
+            "Database must be updated to the last migration \
             on the filesystem for `create-migration`. Run:\n  \
-            edgedb migrate");
+            edgedb migrate")))?;
     }
 
-    let exec = if create.non_interactive {
+    let exec = if !create.only.is_empty() {
+        partial::create(&ctx, cli, migrations.len() as u64 + 1, &create).await
+    } else if create.non_interactive {
         run_non_interactive(&ctx, cli, migrations.len() as u64 +1,
             &create).await
     } else {
@@ -0,0 +1,4 @@
+/// Database and filesystem have diverged migration histories
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct MigrationConflict(pub anyhow::Error);
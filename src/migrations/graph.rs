@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub parents: Vec<String>,
+    pub text: String,
+}
+
+/// Orders nodes so that every node goes before its parents
+///
+/// Branches are kept contiguous and ties are broken by name so the output
+/// is stable.
+pub fn sort(nodes: Vec<Node>) -> Vec<Node> {
+    let names = nodes.iter()
+        .map(|n| n.name.clone())
+        .collect::<BTreeSet<_>>();
+    let mut children = BTreeMap::new();
+    for node in &nodes {
+        for parent in node.parents.iter().filter(|p| names.contains(*p)) {
+            *children.entry(parent.clone()).or_insert(0) += 1;
+        }
+    }
+    let mut by_name = nodes.into_iter()
+        .map(|n| (n.name.clone(), n))
+        .collect::<BTreeMap<_, _>>();
+    let mut queue = by_name.keys()
+        .filter(|name| !children.contains_key(*name))
+        .cloned()
+        .rev()
+        .collect::<Vec<_>>();
+    let mut output = Vec::new();
+    while let Some(name) = queue.pop() {
+        let node = by_name.remove(&name).expect("node is visited once");
+        for parent in node.parents.iter().rev() {
+            if let Some(num) = children.get_mut(parent) {
+                *num -= 1;
+                if *num == 0 {
+                    queue.push(parent.clone());
+                }
+            }
+        }
+        output.push(node);
+    }
+    // cycles can't happen in the real history, but don't lose nodes
+    output.extend(by_name.into_iter().map(|(_, n)| n));
+    output
+}
+
+fn lanes_prefix(lanes: &[Option<String>], mark: impl Fn(usize) -> char)
+    -> String
+{
+    let mut line = String::with_capacity(lanes.len()*2);
+    for idx in 0..lanes.len() {
+        line.push(mark(idx));
+        line.push(' ');
+    }
+    line
+}
+
+/// Line joining (`/`) or splitting (`\`) `moved` lanes with their left
+/// neighbours
+fn connector(lanes: &[Option<String>], moved: &[usize], mark: char)
+    -> String
+{
+    let mut line = vec![' '; lanes.len()*2];
+    for (idx, lane) in lanes.iter().enumerate() {
+        if moved.contains(&idx) {
+            line[(idx*2).saturating_sub(1)] = mark;
+        } else if lane.is_some() {
+            line[idx*2] = '|';
+        }
+    }
+    line.into_iter().collect::<String>().trim_end().into()
+}
+
+fn trim(lanes: &mut Vec<Option<String>>) {
+    while let Some(None) = lanes.last() {
+        lanes.pop();
+    }
+}
+
+/// Renders sorted nodes as lines of an ASCII graph, like
+/// `git log --graph --oneline`
+pub fn render(nodes: &[Node]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut lanes: Vec<Option<String>> = Vec::new();
+    let names = nodes.iter()
+        .map(|n| &n.name[..])
+        .collect::<BTreeSet<_>>();
+    for node in nodes {
+        let expecting = lanes.iter().enumerate()
+            .filter(|(_, lane)| lane.as_ref() == Some(&node.name))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        let col = match expecting.first() {
+            Some(&col) => col,
+            None => match lanes.iter().position(|l| l.is_none()) {
+                Some(col) => col,
+                None => {
+                    lanes.push(None);
+                    lanes.len() - 1
+                }
+            },
+        };
+        lanes[col] = Some(node.name.clone());
+
+        // branches forked from this node end here
+        let merged = &expecting[expecting.len().min(1)..];
+        if !merged.is_empty() {
+            lines.push(connector(&lanes, merged, '/'));
+            for &idx in merged {
+                lanes[idx] = None;
+            }
+            trim(&mut lanes);
+        }
+
+        let prefix = lanes_prefix(&lanes, |idx| {
+            if idx == col {
+                '*'
+            } else if lanes[idx].is_some() {
+                '|'
+            } else {
+                ' '
+            }
+        });
+        lines.push(format!("{}{}", prefix, node.text).trim_end().into());
+
+        // parents which are not shown would leave dangling lanes
+        let mut parents = node.parents.iter()
+            .filter(|p| names.contains(&p[..]));
+        lanes[col] = parents.next().cloned();
+        let mut forked = Vec::new();
+        for parent in parents {
+            if lanes.contains(&Some(parent.clone())) {
+                continue;
+            }
+            let idx = match lanes.iter().position(|l| l.is_none()) {
+                Some(idx) => idx,
+                None => {
+                    lanes.push(None);
+                    lanes.len() - 1
+                }
+            };
+            lanes[idx] = Some(parent.clone());
+            forked.push(idx);
+        }
+        if !forked.is_empty() {
+            lines.push(connector(&lanes, &forked, '\\'));
+        }
+        trim(&mut lanes);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Node, sort, render};
+
+    fn graph(input: &[(&str, &[&str])]) -> String {
+        let nodes = input.iter().map(|(name, parents)| Node {
+            name: name.to_string(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            text: name.to_string(),
+        }).collect();
+        render(&sort(nodes)).join("\n")
+    }
+
+    #[test]
+    fn linear() {
+        assert_eq!(graph(&[
+            ("m1", &[]),
+            ("m3", &["m2"]),
+            ("m2", &["m1"]),
+        ]), "* m3\n* m2\n* m1");
+    }
+
+    #[test]
+    fn diverged() {
+        assert_eq!(graph(&[
+            ("m1", &[]),
+            ("m2", &["m1"]),
+            ("a3", &["m2"]),
+            ("b3", &["m2"]),
+            ("b4", &["b3"]),
+        ]), "\
+            * a3\n\
+            | * b4\n\
+            | * b3\n\
+            |/\n\
+            * m2\n\
+            * m1");
+    }
+
+    #[test]
+    fn unknown_parent() {
+        assert_eq!(graph(&[
+            ("m2", &["m1"]),
+            ("m3", &["m2"]),
+        ]), "* m3\n* m2");
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::{BTreeSet, BTreeMap};
+use std::time::SystemTime;
 
+use async_std::fs;
 use async_std::prelude::StreamExt;
 use edgedb_client::client::Connection;
 use edgedb_derive::Queryable;
@@ -7,21 +9,42 @@ use edgedb_protocol::value::Value;
 
 use crate::commands::Options;
 use crate::commands::parser::MigrationLog;
+use crate::migrations::NULL_MIGRATION;
 use crate::migrations::context::Context;
+use crate::migrations::graph::{self, Node};
 use crate::migrations::migration;
 
 
 #[derive(Queryable, Clone)]
 struct Migration {
     name: String,
+    message: Option<String>,
     parent_names: Vec<String>,
 }
 
+struct Revision {
+    parents: Vec<String>,
+    message: Option<String>,
+    /// Modification time of the migration file
+    modified: Option<SystemTime>,
+    local: bool,
+    applied: bool,
+}
+
+fn print_revision(name: &str, message: Option<&str>) {
+    match message {
+        Some(message) => println!("{}  {}", name, message),
+        None => println!("{}", name),
+    }
+}
+
 
 pub async fn log(cli: &mut Connection, common: &Options, options: &MigrationLog)
     -> Result<(), anyhow::Error>
 {
-    if options.from_fs {
+    if options.graph && !options.from_fs {
+        return log_graph(Some(cli), options).await;
+    } else if options.from_fs {
         return log_fs(common, options).await;
     } else if options.from_db {
         return log_db(cli, common, options).await;
@@ -59,26 +82,37 @@ fn topology_sort(migrations: Vec<Migration>) -> Vec<Migration> {
     return output
 }
 
-pub async fn log_db(cli: &mut Connection, _common: &Options,
-    options: &MigrationLog)
-    -> Result<(), anyhow::Error>
+async fn db_migrations(cli: &mut Connection)
+    -> anyhow::Result<Vec<Migration>>
 {
     let mut items = cli.query::<Migration>(r###"
-            SELECT schema::Migration {name, parent_names := .parents.name }
+            SELECT schema::Migration {
+                name,
+                message,
+                parent_names := .parents.name,
+            }
         "###, &Value::empty_tuple()).await?;
     let mut migrations = Vec::new();
     while let Some(item) = items.next().await.transpose()? {
         migrations.push(item);
     }
+    Ok(migrations)
+}
+
+pub async fn log_db(cli: &mut Connection, _common: &Options,
+    options: &MigrationLog)
+    -> Result<(), anyhow::Error>
+{
+    let migrations = db_migrations(cli).await?;
     let output = topology_sort(migrations);
     let limit = options.limit.unwrap_or(output.len());
     if options.newest_first {
         for rev in output.iter().rev().take(limit) {
-            println!("{}", rev.name);
+            print_revision(&rev.name, rev.message.as_deref());
         }
     } else {
         for rev in output.iter().take(limit) {
-            println!("{}", rev.name);
+            print_revision(&rev.name, rev.message.as_deref());
         }
     }
     Ok(())
@@ -89,18 +123,109 @@ pub async fn log_fs(_common: &Options, options: &MigrationLog)
 {
     assert!(options.from_fs);
 
+    if options.graph {
+        return log_graph(None, options).await;
+    }
     let ctx = Context::from_config(&options.cfg);
     let migrations = migration::read_all(&ctx, true).await?;
     let limit = options.limit.unwrap_or(migrations.len());
     if options.newest_first {
-        for rev in migrations.keys().rev().take(limit) {
-            println!("{}", rev);
+        for (rev, file) in migrations.iter().rev().take(limit) {
+            print_revision(rev, file.data.message.as_deref());
         }
     } else {
-        for rev in migrations.keys().take(limit) {
-            println!("{}", rev);
+        for (rev, file) in migrations.iter().take(limit) {
+            print_revision(rev, file.data.message.as_deref());
         }
     }
     Ok(())
 }
 
+
+fn short_name(name: &str) -> &str {
+    &name[..name.len().min(12)]
+}
+
+async fn log_graph(cli: Option<&mut Connection>, options: &MigrationLog)
+    -> anyhow::Result<()>
+{
+    let ctx = Context::from_config(&options.cfg);
+    let local = migration::read_all(&ctx, true).await?;
+    let mut revisions = BTreeMap::new();
+    for (name, file) in &local {
+        let modified = fs::metadata(&file.path).await
+            .and_then(|m| m.modified()).ok();
+        let parents = if file.data.parent_id == NULL_MIGRATION {
+            Vec::new()
+        } else {
+            vec![file.data.parent_id.clone()]
+        };
+        revisions.insert(name.clone(), Revision {
+            parents,
+            message: file.data.message.clone(),
+            modified,
+            local: true,
+            applied: false,
+        });
+    }
+    let local_head = local.keys().last().cloned();
+    let mut db_head = None;
+    let connected = cli.is_some();
+    if let Some(cli) = cli {
+        let applied = db_migrations(cli).await?;
+        let parents = applied.iter()
+            .flat_map(|m| m.parent_names.iter())
+            .collect::<BTreeSet<_>>();
+        db_head = applied.iter()
+            .find(|m| !parents.contains(&m.name))
+            .map(|m| m.name.clone());
+        for item in applied {
+            let rev = revisions.entry(item.name).or_insert(Revision {
+                parents: item.parent_names,
+                message: None,
+                modified: None,
+                local: false,
+                applied: false,
+            });
+            rev.applied = true;
+            if rev.message.is_none() {
+                rev.message = item.message;
+            }
+        }
+    }
+
+    let nodes = revisions.into_iter().map(|(name, rev)| {
+        let mut heads = Vec::new();
+        if db_head.as_ref() == Some(&name) {
+            heads.push("database head");
+        }
+        if local_head.as_ref() == Some(&name) {
+            heads.push("local head");
+        }
+        let mut text = short_name(&name).to_string();
+        if !heads.is_empty() {
+            text.push_str(&format!(" ({})", heads.join(", ")));
+        }
+        if connected && !rev.local {
+            text.push_str(" [not in filesystem]");
+        } else if connected && !rev.applied {
+            text.push_str(" [not applied]");
+        }
+        if let Some(modified) = rev.modified {
+            text.push_str(&format!(" {}",
+                humantime::format_rfc3339_seconds(modified)));
+        }
+        if let Some(message) = &rev.message {
+            text.push_str(&format!(" {}", message));
+        }
+        Node { name, parents: rev.parents, text }
+    }).collect();
+    let mut nodes = graph::sort(nodes);
+    if let Some(limit) = options.limit {
+        nodes.truncate(limit);
+    }
+    for line in graph::render(&nodes) {
+        println!("{}", line);
+    }
+    Ok(())
+}
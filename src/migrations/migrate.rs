@@ -1,16 +1,26 @@
 use anyhow::Context as _;
+use async_ctrlc::CtrlC;
 use async_std::fs;
+use async_std::future::Future;
+use async_std::prelude::FutureExt;
 use async_std::path::Path;
 use async_std::stream::StreamExt;
 use edgedb_client::client::Connection;
 use edgedb_protocol::value::Value;
 use linked_hash_map::LinkedHashMap;
 
+use crate::audit;
 use crate::commands::Options;
 use crate::commands::ExitCode;
 use crate::commands::parser::Migrate;
+use crate::hint::HintExt;
+use crate::interactive::Interrupted;
 use crate::migrations::context::Context;
+use crate::migrations::errors::MigrationConflict;
 use crate::migrations::migration::{self, MigrationFile};
+use crate::migrations::precheck;
+use crate::spinner::Spinner;
+use crate::verbosity::Verbosity;
 
 
 fn skip_revisions(migrations: &mut LinkedHashMap<String, MigrationFile>,
@@ -22,9 +32,10 @@ fn skip_revisions(migrations: &mut LinkedHashMap<String, MigrationFile>,
             return Ok(())
         }
     }
-    anyhow::bail!("There is no database revision {} \
+    return Err(MigrationConflict(anyhow::anyhow!(
+        "There is no database revision {} \
         in the filesystem. Consider updating sources.",
-        db_migration);
+        db_migration)))?;
 }
 
 async fn check_revision_in_db(cli: &mut Connection, prefix: &str)
@@ -49,11 +60,34 @@ async fn check_revision_in_db(cli: &mut Connection, prefix: &str)
     return Ok(all_similar.pop())
 }
 
-pub async fn migrate(cli: &mut Connection, _options: &Options,
+/// Runs the future showing elapsed time, Ctrl+C interrupts it
+///
+/// All migrations are applied in a single transaction, so interrupting
+/// one drops the connection and the server rolls everything back.
+async fn with_progress<F, T>(ctrlc: &mut Option<CtrlC>,
+                             verbosity: Verbosity, fut: F)
+    -> anyhow::Result<T>
+    where F: Future<Output=anyhow::Result<T>>,
+{
+    let _spinner = if verbosity.show_progress() {
+        Some(Spinner::start("Applying migration (Ctrl+C to abort)"))
+    } else {
+        None
+    };
+    match ctrlc {
+        Some(ctrlc) => {
+            fut.race(async { ctrlc.next().await; Err(Interrupted)? }).await
+        }
+        None => fut.await,
+    }
+}
+
+pub async fn migrate(cli: &mut Connection, options: &Options,
     migrate: &Migrate)
     -> Result<(), anyhow::Error>
 {
     let ctx = Context::from_config(&migrate.cfg);
+    let verbosity = options.verbosity.with_flags(migrate.quiet, false);
 
     let mut migrations = migration::read_all(&ctx, true).await?;
     let db_migration: Option<String> = cli.query_row_opt(r###"
@@ -85,7 +119,7 @@ pub async fn migrate(cli: &mut Connection, _options: &Options,
             (Some(targ), None) => targ,
         };
         if let Some(db_rev) = db_rev {
-            if !migrate.quiet {
+            if verbosity.show_progress() {
                 if Some(&db_rev) == db_migration.as_ref() {
                     eprintln!("Database is up to date. Revision {}",
                         db_rev);
@@ -118,19 +152,48 @@ pub async fn migrate(cli: &mut Connection, _options: &Options,
         }
     }
     if migrations.is_empty() {
-        if !migrate.quiet {
+        if verbosity.show_progress() {
             eprintln!("Everything is up to date. Revision {}",
                 db_migration.as_ref().map(|x| &x[..]).unwrap_or("initial"));
         }
         return Ok(());
     }
+    // Interrupting the query leaves connection in an undefined state, so
+    // it's only done when the process exits right after
+    let mut ctrlc = if options.command_line {
+        CtrlC::new()
+            .map_err(|e| log::debug!("Cannot handle Ctrl+C: {}", e))
+            .ok()
+    } else {
+        None
+    };
     // TODO(tailhook) use special transaction facility
     cli.execute("START TRANSACTION").await?;
     for (_, migration) in migrations {
         let data = fs::read_to_string(&migration.path).await
             .context("error re-reading migration file")?;
-        cli.execute(data).await?;
-        if !migrate.quiet {
+        if migrate.check_data {
+            let checks = precheck::find_checks(&data)?;
+            if precheck::run(cli, &checks).await? > 0 {
+                cli.execute("ROLLBACK").await?;
+                return Err(anyhow::anyhow!(
+                    "existing data violates constraints added by {}",
+                    migration.data.id))
+                    .hint("fix the objects listed above and run \
+                           the migration again")?;
+            }
+        }
+        let result = with_progress(&mut ctrlc, verbosity,
+                                   cli.execute(data)).await;
+        if let Err(e) = result {
+            if e.is::<Interrupted>() {
+                return Err(e).hint("the transaction is rolled back, \
+                                    no migrations are applied")?;
+            }
+            return Err(e);
+        }
+        audit::revision_applied(&migration.data.id);
+        if verbosity.show_progress() {
             eprintln!("Applied {} ({})",
                 migration.data.id,
                 Path::new(migration.path.file_name().unwrap()).display());
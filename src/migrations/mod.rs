@@ -1,17 +1,28 @@
+mod check;
 mod context;
 mod create;
+mod errors;
 mod grammar;
+mod graph;
 mod log;
 mod migrate;
 mod migration;
+mod partial;
+mod plan;
+mod precheck;
 mod print_error;
+mod sdl_diff;
 mod source_map;
 mod status;
+pub mod template;
 mod prompt;
 
 const NULL_MIGRATION: &str = "initial";
 
+pub use check::check;
 pub use create::create;
+pub use errors::MigrationConflict;
 pub use migrate::migrate;
+pub use plan::plan;
 pub use status::status;
 pub use self::log::{log, log_fs};
@@ -0,0 +1,94 @@
+use edgedb_client::client::Connection;
+use edgeql_parser::tokenizer::{TokenStream, Kind};
+
+use crate::commands::ExitCode;
+use crate::commands::parser::{CreateMigration, DdlCategory};
+use crate::migrations::context::Context;
+use crate::migrations::create::{accept_proposals, write_migration};
+use crate::migrations::create::CurrentMigration;
+
+
+/// Returns the kind of the object a DDL statement changes, if it's one of
+/// the categories that can be applied separately
+pub fn category(statement: &str) -> Option<DdlCategory> {
+    use DdlCategory::*;
+
+    let mut words = TokenStream::new(statement)
+        .filter_map(|t| t.ok())
+        .take_while(|t| matches!(t.token.kind, Kind::Keyword | Kind::Ident))
+        .map(|t| t.token.value.to_uppercase());
+    match words.next().as_deref() {
+        Some("CREATE") | Some("ALTER") | Some("DROP") => {}
+        _ => return None,
+    }
+    let object = words.find(|w| {
+        !matches!(&w[..], "REQUIRED" | "OPTIONAL" | "SINGLE" | "MULTI")
+    })?;
+    match &object[..] {
+        "EXTENSION" => Some(Extensions),
+        "ALIAS" => Some(Aliases),
+        "FUNCTION" => Some(Functions),
+        "MODULE" => Some(Modules),
+        _ => None,
+    }
+}
+
+/// Writes a migration file with the changes of the categories in `--only`
+///
+/// Must be run within the started migration, which the caller aborts.
+pub async fn create(ctx: &Context, cli: &mut Connection, index: u64,
+    options: &CreateMigration)
+    -> anyhow::Result<()>
+{
+    let descr = accept_proposals(cli, options.allow_unsafe).await?;
+    let confirmed = descr.confirmed.iter()
+        .filter(|s| category(s).map(|c| options.only.contains(&c))
+                               .unwrap_or(false))
+        .cloned()
+        .collect::<Vec<_>>();
+    let skipped = descr.confirmed.len() - confirmed.len();
+    if confirmed.is_empty() {
+        eprintln!("No changes of the specified kinds");
+        return Err(ExitCode::new(4))?;
+    }
+    let partial = CurrentMigration {
+        complete: skipped == 0,
+        parent: descr.parent,
+        confirmed,
+        proposed: None,
+    };
+    let message = match &options.message {
+        Some(message) => message.clone(),
+        None => format!("only {}", options.only.iter().map(|c| c.as_str())
+                        .collect::<Vec<_>>().join(", ")),
+    };
+    write_migration(ctx, &partial, index, Some(&message), true).await?;
+    if skipped > 0 {
+        eprintln!("Left {} other change(s) for the next migration", skipped);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::category;
+    use crate::commands::parser::DdlCategory::*;
+
+    #[test]
+    fn categories() {
+        assert_eq!(category("CREATE EXTENSION graphql VERSION '1.0';"),
+                   Some(Extensions));
+        assert_eq!(category("create required global default::x -> str;"),
+                   None);
+        assert_eq!(category("ALTER ALIAS default::Users { USING (1) };"),
+                   Some(Aliases));
+        assert_eq!(category("DROP FUNCTION default::f(x: int64);"),
+                   Some(Functions));
+        assert_eq!(category("CREATE MODULE app IF NOT EXISTS;"),
+                   Some(Modules));
+        assert_eq!(category("CREATE TYPE default::User;"), None);
+        assert_eq!(category("ALTER TYPE default::User { \
+                             CREATE PROPERTY name -> str; };"), None);
+        assert_eq!(category("SELECT 1;"), None);
+    }
+}
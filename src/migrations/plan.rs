@@ -0,0 +1,487 @@
+//! Splitting a migration into phases safe for rolling deploys
+//!
+//! Statements of the migration are flattened (nested `ALTER` blocks are
+//! unwrapped down to individual commands) and each command is put into
+//! one of the phases:
+//!
+//! 1. *expand* -- additive changes the old application version tolerates,
+//! 2. *backfill* -- data updates for `SET REQUIRED USING (...)`,
+//! 3. *contract* -- drops, renames and new requirements which break the
+//!    old application version.
+//!
+//! Expand and contract phases are written as regular migration files, so
+//! they can replace the original migration in the history.
+use async_std::fs;
+use async_std::path::Path;
+use edgeql_parser::hash::Hasher;
+use edgeql_parser::tokenizer::{TokenStream, Kind, SpannedToken};
+
+use crate::commands::Options;
+use crate::commands::parser::MigrationPlan;
+use crate::hint::HintExt;
+use crate::migrations::context::Context;
+use crate::migrations::migration::{self, MigrationFile};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Expand,
+    Contract,
+}
+
+#[derive(Debug)]
+struct Leaf {
+    /// Headers of the enclosing `ALTER` blocks
+    path: Vec<String>,
+    /// Lowercase words of the statement (up to the first brace)
+    words: Vec<String>,
+    /// Full text of the statement without trailing semicolon
+    text: String,
+    /// Text of the `USING` expression in `SET REQUIRED USING (...)`
+    using: Option<String>,
+    /// Whether the statement sets a default, e.g. `SET default := ...`
+    has_default: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Plan {
+    expand: Vec<String>,
+    backfill: Vec<String>,
+    contract: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Item {
+    Statement(String),
+    Block(String, Vec<Item>),
+}
+
+fn is_name(token: &SpannedToken) -> bool {
+    matches!(token.token.kind, Kind::Ident | Kind::Keyword)
+}
+
+/// Removes indentation the statement had in the original file
+fn dedent(source: &str, start: usize, end: usize) -> String {
+    let line_start = source[..start].rfind('\n').map(|x| x + 1).unwrap_or(0);
+    let indent = start - line_start;
+    let mut result = String::new();
+    for (idx, line) in source[start..end].lines().enumerate() {
+        if idx > 0 {
+            result.push('\n');
+            let spaces = line.len() - line.trim_start().len();
+            result.push_str(&line[spaces.min(indent)..]);
+        } else {
+            result.push_str(line);
+        }
+    }
+    result
+}
+
+/// Flattens statements up to the closing brace or the end of input
+fn flatten(source: &str, tokens: &[SpannedToken], mut pos: usize,
+           path: &[String], leaves: &mut Vec<Leaf>)
+    -> anyhow::Result<usize>
+{
+    while pos < tokens.len() {
+        if tokens[pos].token.kind == Kind::CloseBrace {
+            return Ok(pos);
+        }
+        if tokens[pos].token.kind == Kind::Semicolon {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        let mut depth = 0;
+        let mut using = None;
+        while pos < tokens.len() {
+            let tok = &tokens[pos];
+            match tok.token.kind {
+                Kind::OpenParen | Kind::OpenBracket => depth += 1,
+                Kind::CloseParen | Kind::CloseBracket => depth -= 1,
+                Kind::OpenBrace | Kind::Semicolon if depth == 0 => break,
+                _ if depth == 0 && is_name(tok)
+                    && tok.token.value.eq_ignore_ascii_case("using")
+                => using = Some(pos + 1),
+                _ => {}
+            }
+            pos += 1;
+        }
+        let header = &tokens[start..pos];
+        let header_end = header.last()
+            .map(|t| t.end.offset as usize)
+            .ok_or_else(|| anyhow::anyhow!("empty statement"))?;
+        let stmt_start = tokens[start].start.offset as usize;
+        let words = header.iter().filter(|t| is_name(t))
+            .map(|t| t.token.value.to_lowercase())
+            .collect::<Vec<_>>();
+        let is_block = tokens.get(pos).map(|t| t.token.kind)
+            == Some(Kind::OpenBrace);
+        if is_block && words.first().map(|w| &w[..]) == Some("alter") {
+            let mut inner = path.to_vec();
+            inner.push(dedent(source, stmt_start, header_end));
+            pos = flatten(source, tokens, pos + 1, &inner, leaves)?;
+            if pos >= tokens.len() {
+                anyhow::bail!("unbalanced braces");
+            }
+            pos += 1;
+            continue;
+        }
+        let end = if is_block {
+            let mut braces = 0;
+            while pos < tokens.len() {
+                match tokens[pos].token.kind {
+                    Kind::OpenBrace => braces += 1,
+                    Kind::CloseBrace => {
+                        braces -= 1;
+                        if braces == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+            if pos >= tokens.len() {
+                anyhow::bail!("unbalanced braces");
+            }
+            pos += 1;
+            tokens[pos - 1].end.offset as usize
+        } else {
+            header_end
+        };
+        let has_default = tokens[start..pos].windows(2).any(|pair| {
+            is_name(&pair[0])
+                && pair[0].token.value.eq_ignore_ascii_case("default")
+                && pair[1].token.kind == Kind::Assign
+        });
+        let using = using.filter(|_| !is_block).and_then(|idx| {
+            tokens.get(idx).map(|t| {
+                source[t.start.offset as usize..header_end].to_string()
+            })
+        });
+        leaves.push(Leaf {
+            path: path.to_vec(),
+            words,
+            text: dedent(source, stmt_start, end),
+            using,
+            has_default,
+        });
+    }
+    Ok(pos)
+}
+
+fn leaves(source: &str) -> anyhow::Result<Vec<Leaf>> {
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("cannot parse migration: {}", e))?;
+    let mut leaves = Vec::new();
+    let pos = flatten(source, &tokens, 0, &[], &mut leaves)?;
+    if pos != tokens.len() {
+        anyhow::bail!("unbalanced braces");
+    }
+    Ok(leaves)
+}
+
+fn classify(leaf: &Leaf) -> Phase {
+    let words = leaf.words.iter().map(|w| &w[..]).collect::<Vec<_>>();
+    let has = |word| words.contains(&word);
+    match words.first().copied() {
+        Some("drop") | Some("rename") => Phase::Contract,
+        Some("set") => match words.get(1).copied() {
+            Some("required") | Some("single") | Some("type")
+            | Some("readonly") => Phase::Contract,
+            _ => Phase::Expand,
+        },
+        Some("reset") if has("default") => Phase::Contract,
+        Some("reset") => Phase::Expand,
+        // constraints on existing types may reject writes of old version
+        Some("create") if !leaf.path.is_empty()
+            && (words.get(1) == Some(&"constraint")
+                || words.get(2) == Some(&"constraint"))
+        => Phase::Contract,
+        // old version doesn't write new required pointers
+        Some("create") if !leaf.path.is_empty() && has("required")
+            && !leaf.has_default
+        => Phase::Contract,
+        Some("create") => Phase::Expand,
+        Some("alter") if has("drop") || has("rename") || has("required")
+            || has("single") || has("type") || has("readonly")
+        => Phase::Contract,
+        Some("alter") => Phase::Expand,
+        _ => Phase::Contract,
+    }
+}
+
+/// Returns name following `keyword` in the header, e.g. type name
+fn header_name<'a>(header: &'a str, keyword: &str) -> Option<&'a str> {
+    let mut words = header.split_whitespace();
+    while let Some(word) = words.next() {
+        if word.eq_ignore_ascii_case(keyword) {
+            return words.next();
+        }
+    }
+    None
+}
+
+/// Builds `UPDATE` filling objects which would fail `SET REQUIRED`
+fn backfill(leaf: &Leaf, expr: &str) -> Option<String> {
+    let type_name = leaf.path.first()
+        .and_then(|h| header_name(h, "type"))?;
+    let last = leaf.path.last()?;
+    let pointer = header_name(last, "property")
+        .or_else(|| header_name(last, "link"))?;
+    Some(format!("UPDATE {typ}\nFILTER NOT EXISTS .{ptr}\n\
+                  SET {{ {ptr} := {expr} }};",
+                 typ=type_name, ptr=pointer, expr=expr))
+}
+
+fn add_leaf(items: &mut Vec<Item>, path: &[String], text: String) {
+    match path.split_first() {
+        None => items.push(Item::Statement(text)),
+        Some((head, tail)) => {
+            if let Some(Item::Block(header, children)) = items.last_mut() {
+                if header == head {
+                    add_leaf(children, tail, text);
+                    return;
+                }
+            }
+            let mut children = Vec::new();
+            add_leaf(&mut children, tail, text);
+            items.push(Item::Block(head.clone(), children));
+        }
+    }
+}
+
+fn render(item: &Item) -> String {
+    match item {
+        Item::Statement(text) => format!("{};", text),
+        Item::Block(header, children) => {
+            let mut result = format!("{} {{\n", header);
+            for child in children {
+                for line in render(child).lines() {
+                    result.push_str("    ");
+                    result.push_str(line);
+                    result.push('\n');
+                }
+            }
+            result.push_str("};");
+            result
+        }
+    }
+}
+
+fn make_plan(body: &str) -> anyhow::Result<Plan> {
+    let mut expand = Vec::new();
+    let mut contract = Vec::new();
+    let mut plan = Plan::default();
+    for leaf in leaves(body)? {
+        match classify(&leaf) {
+            Phase::Expand => add_leaf(&mut expand, &leaf.path, leaf.text),
+            Phase::Contract => {
+                let is_required = leaf.words.get(0).map(|w| &w[..])
+                    == Some("set")
+                    && leaf.words.get(1).map(|w| &w[..]) == Some("required");
+                if let Some(expr) = leaf.using.as_ref().filter(|_| is_required)
+                {
+                    plan.backfill.extend(backfill(&leaf, expr));
+                }
+                add_leaf(&mut contract, &leaf.path, leaf.text);
+            }
+        }
+    }
+    plan.expand = expand.iter().map(render).collect();
+    plan.contract = contract.iter().map(render).collect();
+    Ok(plan)
+}
+
+/// Returns statements of the migration which break the old application
+pub fn destructive_statements(body: &str) -> anyhow::Result<Vec<String>> {
+    Ok(leaves(body)?.into_iter()
+        .filter(|leaf| classify(leaf) == Phase::Contract)
+        .map(|leaf| leaf.text)
+        .collect())
+}
+
+fn migration_text(parent: &str, statements: &[String])
+    -> anyhow::Result<(String, String)>
+{
+    let mut hasher = Hasher::start_migration(parent);
+    for statement in statements {
+        hasher.add_source(statement)
+            .map_err(|e| migration::hashing_error(statement, e))?;
+    }
+    let id = hasher.make_migration_id();
+    let mut text = format!("CREATE MIGRATION {}\n    ONTO {}\n{{\n",
+                           id, parent);
+    for statement in statements {
+        for line in statement.lines() {
+            text.push_str(&format!("  {}\n", line));
+        }
+    }
+    text.push_str("};\n");
+    Ok((id, text))
+}
+
+fn runbook(original: &MigrationFile, files: &[(String, String)],
+           backfill: bool, expand_id: &str)
+    -> String
+{
+    let name = original.path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let new_files = files.iter()
+        .map(|(name, _)| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(" and ");
+    let mut text = format!("# Rolling deploy of {}\n\n", original.data.id);
+    text.push_str(&format!(
+        "1. Replace `migrations/{}` with {} from this directory. \
+         Do this only if the original migration is not applied to \
+         any database yet.\n",
+        name, new_files));
+    text.push_str(&format!(
+        "2. Apply the expand phase: \
+         `edgedb migrate --to-revision {}`. It only adds to the schema, \
+         so the running version of the application keeps working.\n",
+        expand_id));
+    text.push_str("3. Deploy the new version of the application \
+                   to all instances.\n");
+    let mut step = 4;
+    if backfill {
+        text.push_str(&format!(
+            "{}. Fill in data for the new requirements: \
+             `edgedb < backfill.edgeql`.\n", step));
+        step += 1;
+    }
+    text.push_str(&format!(
+        "{}. Make sure no instance of the old version is running, \
+         then apply the contract phase: `edgedb migrate`.\n", step));
+    text
+}
+
+pub async fn plan(_options: &Options, cmd: &MigrationPlan)
+    -> anyhow::Result<()>
+{
+    if !cmd.online {
+        return Err(anyhow::anyhow!("no planning mode specified"))
+            .hint("only `--online` planning is supported")?;
+    }
+    let ctx = Context::from_config(&cmd.cfg);
+    let migrations = migration::read_all(&ctx, true).await?;
+    let (_, last) = match migrations.back() {
+        Some(last) => last,
+        None => anyhow::bail!("no migrations found in {}",
+                              ctx.schema_dir.join("migrations").display()),
+    };
+    let source = fs::read_to_string(&last.path).await?;
+    let (start, end) = last.data.text_range;
+    let plan = make_plan(&source[start..end])?;
+    if plan.contract.is_empty() {
+        eprintln!("Migration {} is additive, \
+                   it can be applied before the deploy as is.",
+                   last.data.id);
+        return Ok(());
+    }
+    if plan.expand.is_empty() {
+        eprintln!("Migration {} has no additive part, \
+                   apply it after all instances of the old version \
+                   are stopped.", last.data.id);
+        return Ok(());
+    }
+    let num = last.path.file_stem()
+        .and_then(|x| x.to_str())
+        .and_then(|x| x.parse::<u64>().ok())
+        .ok_or_else(|| anyhow::anyhow!("cannot determine number of {}",
+                                       last.path.display()))?;
+    let (expand_id, expand) = migration_text(&last.data.parent_id,
+                                             &plan.expand)?;
+    let (_, contract) = migration_text(&expand_id, &plan.contract)?;
+    let files = vec![
+        (format!("{:05}.edgeql", num), expand),
+        (format!("{:05}.edgeql", num + 1), contract),
+    ];
+    let out = Path::new(&cmd.out);
+    fs::create_dir_all(out).await?;
+    for (name, text) in &files {
+        fs::write(out.join(name), text).await?;
+    }
+    if !plan.backfill.is_empty() {
+        fs::write(out.join("backfill.edgeql"),
+                  plan.backfill.join("\n\n") + "\n").await?;
+    }
+    fs::write(out.join("RUNBOOK.md"),
+              runbook(last, &files, !plan.backfill.is_empty(), &expand_id))
+        .await?;
+    eprintln!("Plan for {} is written to {}: {} expand, {} backfill, \
+               {} contract statement(s). See RUNBOOK.md for the steps.",
+              last.data.id, out.display(), plan.expand.len(),
+              plan.backfill.len(), plan.contract.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::make_plan;
+
+    #[test]
+    fn split() {
+        let plan = make_plan(r###"
+            CREATE TYPE default::Post {
+                CREATE REQUIRED PROPERTY title -> std::str;
+            };
+            ALTER TYPE default::User {
+                CREATE PROPERTY nick -> std::str;
+                ALTER PROPERTY email {
+                    SET REQUIRED USING (.name ++ '@example.com');
+                    CREATE CONSTRAINT std::exclusive;
+                };
+                DROP PROPERTY legacy;
+            };
+        "###).unwrap();
+        assert_eq!(plan.expand, vec![
+            "CREATE TYPE default::Post {\n    \
+                CREATE REQUIRED PROPERTY title -> std::str;\n\
+             };",
+            "ALTER TYPE default::User {\n    \
+                CREATE PROPERTY nick -> std::str;\n\
+             };",
+        ]);
+        assert_eq!(plan.backfill, vec![
+            "UPDATE default::User\nFILTER NOT EXISTS .email\n\
+             SET { email := (.name ++ '@example.com') };",
+        ]);
+        assert_eq!(plan.contract, vec![
+            "ALTER TYPE default::User {\n    \
+                ALTER PROPERTY email {\n        \
+                    SET REQUIRED USING (.name ++ '@example.com');\n        \
+                    CREATE CONSTRAINT std::exclusive;\n    \
+                };\n    \
+                DROP PROPERTY legacy;\n\
+             };",
+        ]);
+    }
+
+    #[test]
+    fn required_with_default() {
+        let plan = make_plan(r###"
+            ALTER TYPE default::Post {
+                CREATE REQUIRED LINK owner -> default::User;
+                CREATE REQUIRED PROPERTY status -> std::str {
+                    SET default := 'draft';
+                };
+            };
+        "###).unwrap();
+        assert_eq!(plan.expand, vec![
+            "ALTER TYPE default::Post {\n    \
+                CREATE REQUIRED PROPERTY status -> std::str {\n        \
+                    SET default := 'draft';\n    \
+                };\n\
+             };",
+        ]);
+        assert_eq!(plan.contract, vec![
+            "ALTER TYPE default::Post {\n    \
+                CREATE REQUIRED LINK owner -> default::User;\n\
+             };",
+        ]);
+    }
+}
@@ -0,0 +1,403 @@
+//! Checking existing data against constraints added by a migration
+//!
+//! The migration DDL is scanned on the token level for constraints added
+//! to existing pointers and for `SET REQUIRED` without a `USING` clause.
+//! For each of them a query selecting violating objects is built, so the
+//! user gets a list of objects to fix instead of an error in the middle of
+//! the migration. Constraints which can't be translated into a query are
+//! skipped (the server still checks them when migration is applied).
+use anyhow::Context as _;
+use edgedb_client::client::Connection;
+use edgeql_parser::helpers::quote_name;
+use edgeql_parser::tokenizer::{TokenStream, Kind, SpannedToken};
+
+use crate::non_interactive::query_json_value;
+
+
+const EXAMPLES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub type_name: String,
+    pub pointer: String,
+    pub kind: CheckKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckKind {
+    Required,
+    /// Constraint name (without `std::`) and its arguments as EdgeQL text
+    Constraint(String, Vec<String>),
+}
+
+#[derive(Debug)]
+enum Frame {
+    /// `ALTER TYPE` of an existing type
+    Type(String),
+    /// `ALTER PROPERTY` or `ALTER LINK` of an existing pointer
+    Pointer(String, String),
+    Other,
+}
+
+fn is_name(token: &SpannedToken) -> bool {
+    matches!(token.token.kind, Kind::Ident | Kind::Keyword)
+}
+
+fn is_word(token: Option<&SpannedToken>, word: &str) -> bool {
+    token.map(|t| is_name(t) && t.token.value.eq_ignore_ascii_case(word))
+        .unwrap_or(false)
+}
+
+/// Reads possibly qualified name starting at `pos`
+fn read_name(tokens: &[SpannedToken], mut pos: usize)
+    -> Option<(String, usize)>
+{
+    let mut name = String::new();
+    loop {
+        let tok = tokens.get(pos).filter(|t| {
+            is_name(t) || t.token.kind == Kind::BacktickName
+        })?;
+        name.push_str(tok.token.value.trim_matches('`'));
+        pos += 1;
+        if tokens.get(pos).map(|t| t.token.kind) == Some(Kind::Namespace) {
+            name.push_str("::");
+            pos += 1;
+        } else {
+            return Some((name, pos));
+        }
+    }
+}
+
+/// Splits tokens of constraint arguments by top-level commas
+fn read_args(tokens: &[SpannedToken]) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    for tok in tokens {
+        match tok.token.kind {
+            Kind::OpenParen | Kind::OpenBracket | Kind::OpenBrace => {
+                depth += 1;
+            }
+            Kind::CloseParen | Kind::CloseBracket | Kind::CloseBrace => {
+                if depth == 0 {
+                    // closes the argument list, something else follows
+                    return None;
+                }
+                depth -= 1;
+            }
+            Kind::Comma if depth == 0 => {
+                args.push(current.join(" "));
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(tok.token.value);
+    }
+    if depth != 0 {
+        return None;
+    }
+    if !current.is_empty() {
+        args.push(current.join(" "));
+    }
+    Some(args)
+}
+
+/// Parses `CREATE CONSTRAINT name(args)` without `ON` and `EXCEPT`
+fn constraint(stmt: &[SpannedToken]) -> Option<CheckKind> {
+    if !is_word(stmt.get(0), "create") {
+        return None;
+    }
+    let mut pos = 1;
+    if is_word(stmt.get(pos), "delegated") {
+        pos += 1;
+    }
+    if !is_word(stmt.get(pos), "constraint") {
+        return None;
+    }
+    let (name, pos) = read_name(stmt, pos + 1)?;
+    let name = name.strip_prefix("std::").unwrap_or(&name).to_string();
+    let args = match stmt.get(pos).map(|t| t.token.kind) {
+        None => Vec::new(),
+        Some(Kind::OpenParen)
+            if stmt.last().map(|t| t.token.kind) == Some(Kind::CloseParen)
+        => read_args(&stmt[pos+1..stmt.len()-1])?,
+        Some(_) => return None,
+    };
+    Some(CheckKind::Constraint(name, args))
+}
+
+fn is_set_required(stmt: &[SpannedToken]) -> bool {
+    stmt.len() == 2
+        && is_word(stmt.get(0), "set")
+        && is_word(stmt.get(1), "required")
+}
+
+/// Returns pointer name if statement is `ALTER PROPERTY|LINK name ...`
+fn alter_pointer(stmt: &[SpannedToken]) -> Option<(String, usize)> {
+    if is_word(stmt.get(0), "alter")
+        && (is_word(stmt.get(1), "property") || is_word(stmt.get(1), "link"))
+    {
+        read_name(stmt, 2)
+    } else {
+        None
+    }
+}
+
+fn statement_check(stack: &[Frame], stmt: &[SpannedToken])
+    -> Option<Check>
+{
+    let (type_name, pointer, stmt) = match stack.last()? {
+        Frame::Pointer(type_name, pointer) => {
+            (type_name.clone(), pointer.clone(), stmt)
+        }
+        // short form: `ALTER PROPERTY name SET REQUIRED`
+        Frame::Type(type_name) => {
+            let (pointer, pos) = alter_pointer(stmt)?;
+            (type_name.clone(), pointer, &stmt[pos..])
+        }
+        Frame::Other => return None,
+    };
+    let kind = if is_set_required(stmt) {
+        CheckKind::Required
+    } else {
+        constraint(stmt)?
+    };
+    Some(Check { type_name, pointer, kind })
+}
+
+fn open_frame(stack: &[Frame], stmt: &[SpannedToken]) -> Frame {
+    match stack.last() {
+        // only top-level and migration block can alter types
+        None | Some(Frame::Other) if stack.len() <= 1 => {
+            if is_word(stmt.get(0), "alter") && is_word(stmt.get(1), "type") {
+                if let Some((name, pos)) = read_name(stmt, 2) {
+                    if pos == stmt.len() {
+                        return Frame::Type(name);
+                    }
+                }
+            }
+            Frame::Other
+        }
+        Some(Frame::Type(type_name)) => {
+            match alter_pointer(stmt) {
+                Some((name, pos)) if pos == stmt.len() => {
+                    Frame::Pointer(type_name.clone(), name)
+                }
+                _ => Frame::Other,
+            }
+        }
+        _ => Frame::Other,
+    }
+}
+
+/// Finds constraints which existing data must satisfy after migration
+pub fn find_checks(ddl: &str) -> anyhow::Result<Vec<Check>> {
+    let tokens = TokenStream::new(ddl)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("cannot parse migration: {}", e))?;
+    let mut checks = Vec::new();
+    let mut stack = Vec::new();
+    let mut start = 0;
+    let mut parens = 0;
+    for (idx, tok) in tokens.iter().enumerate() {
+        match tok.token.kind {
+            Kind::OpenParen | Kind::OpenBracket => parens += 1,
+            Kind::CloseParen | Kind::CloseBracket => parens -= 1,
+            // braces inside of expressions are shapes and sets
+            Kind::OpenBrace if parens == 0 => {
+                let frame = open_frame(&stack, &tokens[start..idx]);
+                stack.push(frame);
+                start = idx + 1;
+            }
+            Kind::CloseBrace if parens == 0 => {
+                stack.pop();
+                start = idx + 1;
+            }
+            Kind::Semicolon if parens == 0 => {
+                let stmt = &tokens[start..idx];
+                checks.extend(statement_check(&stack, stmt));
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(checks)
+}
+
+/// Returns filter condition selecting objects which violate the check
+fn violation(check: &Check) -> Option<String> {
+    let ptr = format!(".{}", quote_name(&check.pointer));
+    let (name, args) = match &check.kind {
+        CheckKind::Required => {
+            return Some(format!("NOT EXISTS {}", ptr));
+        }
+        CheckKind::Constraint(name, args) => (&name[..], args),
+    };
+    let arg = || args.get(0).filter(|_| args.len() == 1);
+    let cond = match name {
+        "exclusive" if args.is_empty() => {
+            let typ = quote_type(&check.type_name);
+            format!("EXISTS (SELECT DETACHED {typ} \
+                     FILTER {ptr} = {typ}{ptr} AND .id != {typ}.id)",
+                     typ=typ, ptr=ptr)
+        }
+        "max_len_value" => format!("len({}) > {}", ptr, arg()?),
+        "min_len_value" => format!("len({}) < {}", ptr, arg()?),
+        "max_value" => format!("{} > {}", ptr, arg()?),
+        "min_value" => format!("{} < {}", ptr, arg()?),
+        "max_ex_value" => format!("{} >= {}", ptr, arg()?),
+        "min_ex_value" => format!("{} <= {}", ptr, arg()?),
+        "regexp" => format!("NOT re_test({}, {})", arg()?, ptr),
+        "one_of" if !args.is_empty() => {
+            format!("{} NOT IN {{{}}}", ptr, args.join(", "))
+        }
+        _ => return None,
+    };
+    Some(cond)
+}
+
+fn quote_type(name: &str) -> String {
+    name.split("::").map(quote_name).collect::<Vec<_>>().join("::")
+}
+
+fn describe(check: &Check) -> String {
+    match &check.kind {
+        CheckKind::Required => {
+            format!("{}.{} is required", check.type_name, check.pointer)
+        }
+        CheckKind::Constraint(name, args) if args.is_empty() => {
+            format!("{}.{}: constraint {}",
+                    check.type_name, check.pointer, name)
+        }
+        CheckKind::Constraint(name, args) => {
+            format!("{}.{}: constraint {}({})",
+                    check.type_name, check.pointer, name, args.join(", "))
+        }
+    }
+}
+
+fn violation_query(check: &Check, cond: &str) -> String {
+    format!("WITH bad := (SELECT {typ} FILTER {cond}) \
+             SELECT {{ \
+                total := count(bad), \
+                examples := (SELECT bad {{ id, {ptr} }} LIMIT {limit}), \
+             }}",
+             typ=quote_type(&check.type_name), cond=cond,
+             ptr=quote_name(&check.pointer), limit=EXAMPLES)
+}
+
+/// Runs the checks and prints violating objects
+///
+/// Must be called in a transaction. Returns number of violated checks.
+pub async fn run(cli: &mut Connection, checks: &[Check])
+    -> anyhow::Result<usize>
+{
+    let mut violated = 0;
+    for check in checks {
+        let cond = match violation(check) {
+            Some(cond) => cond,
+            None => {
+                log::info!("Cannot pre-check {}", describe(check));
+                continue;
+            }
+        };
+        let query = violation_query(check, &cond);
+        log::debug!("Pre-check query: {}", query);
+        cli.execute("DECLARE SAVEPOINT precheck").await?;
+        let result = match query_json_value(cli, &query).await {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("Cannot pre-check {}: {:#}", describe(check), e);
+                cli.execute("ROLLBACK TO SAVEPOINT precheck").await?;
+                continue;
+            }
+        };
+        cli.execute("RELEASE SAVEPOINT precheck").await?;
+        let row = &result[0];
+        let total = row["total"].as_u64()
+            .context("invalid pre-check result")?;
+        if total == 0 {
+            continue;
+        }
+        violated += 1;
+        eprintln!("{}: violated by {} object(s), for example:",
+                  describe(check), total);
+        for item in row["examples"].as_array().into_iter().flatten() {
+            eprintln!("    {}", item);
+        }
+    }
+    Ok(violated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_checks, violation, Check, CheckKind};
+
+    fn check(ptr: &str, kind: CheckKind) -> Check {
+        Check {
+            type_name: "default::User".into(),
+            pointer: ptr.into(),
+            kind,
+        }
+    }
+
+    fn constraint(name: &str, args: &[&str]) -> CheckKind {
+        CheckKind::Constraint(name.into(),
+            args.iter().map(|a| a.to_string()).collect())
+    }
+
+    #[test]
+    fn migration() {
+        let checks = find_checks(r###"
+            CREATE MIGRATION m1abc ONTO m1def {
+                CREATE TYPE default::Post {
+                    CREATE REQUIRED PROPERTY title -> std::str {
+                        CREATE CONSTRAINT std::exclusive;
+                    };
+                };
+                ALTER TYPE default::User {
+                    ALTER PROPERTY email {
+                        SET REQUIRED;
+                        CREATE CONSTRAINT std::exclusive;
+                    };
+                    ALTER PROPERTY name {
+                        SET REQUIRED USING ('unknown');
+                        CREATE CONSTRAINT std::max_len_value(100);
+                    };
+                    ALTER PROPERTY role
+                        CREATE CONSTRAINT std::one_of('admin', 'user');
+                    CREATE PROPERTY nick -> std::str {
+                        CREATE CONSTRAINT std::exclusive;
+                    };
+                    ALTER LINK friends {
+                        CREATE CONSTRAINT exclusive ON (__subject__@since);
+                    };
+                };
+            };
+        "###).unwrap();
+        assert_eq!(checks, vec![
+            check("email", CheckKind::Required),
+            check("email", constraint("exclusive", &[])),
+            check("name", constraint("max_len_value", &["100"])),
+            check("role", constraint("one_of", &["'admin'", "'user'"])),
+        ]);
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(violation(&check("email", CheckKind::Required)).unwrap(),
+                   "NOT EXISTS .email");
+        assert_eq!(
+            violation(&check("email", constraint("exclusive", &[]))).unwrap(),
+            "EXISTS (SELECT DETACHED default::User \
+             FILTER .email = default::User.email \
+             AND .id != default::User.id)");
+        assert_eq!(
+            violation(&check("name", constraint("regexp", &["r'^\\w+$'"])))
+                .unwrap(),
+            "NOT re_test(r'^\\w+$', .name)");
+        assert!(violation(&check("x", constraint("expression", &[])))
+                .is_none());
+    }
+}
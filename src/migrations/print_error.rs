@@ -37,11 +37,11 @@ fn get_error_info<'x>(err: &ErrorResponse, source_map: &'x SourceMap<SourceName>
     let pend = err.attributes.get(&FIELD_POSITION_END)
        .and_then(|x| str::from_utf8(x).ok())
        .and_then(|x| x.parse::<u32>().ok())? as usize;
-    let (src, offset) = source_map.translate_range(pstart, pend).ok()?;
+    let (src, start, end) = source_map.source_range(pstart, pend).ok()?;
     let res = match src {
         SourceName::File(path) => {
             let data = fs::read_to_string(&path).ok()?;
-            (path.as_ref(), data, pstart - offset, pend - offset, false)
+            (path.as_ref(), data, start, end, false)
         }
         SourceName::Semicolon(path) => {
             let data = fs::read_to_string(&path).ok()?;
@@ -84,7 +84,7 @@ pub fn expression(prompt: &str, history_name: &str)
         eprintln!("Can't load history: {:#}", e);
     }).ok();
     editor.set_helper(Some(ExpressionHelper {
-        styler: Styler::dark_256(),
+        styler: Styler::configured(),
     }));
     let text = editor.readline(&prompt).context("readline error")?;
     editor.add_history_entry(&text);
@@ -0,0 +1,301 @@
+//! Schema diff of the types affected by a migration proposal
+//!
+//! The SDL of the types is described by the server before and after
+//! running the proposed statements, which are rolled back afterwards, so
+//! the diff reflects exactly what the proposal does.
+use colorful::Colorful;
+use edgedb_client::client::Connection;
+use edgedb_protocol::value::Value;
+use edgeql_parser::tokenizer::{TokenStream, Kind};
+
+use crate::migrations::create::StatementProposal;
+
+
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Same,
+    Removed,
+    Added,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Create,
+    Alter,
+    Drop,
+}
+
+/// The type a DDL statement changes, if any
+fn affected_type(statement: &str) -> Option<(Action, String)> {
+    let tokens = TokenStream::new(statement)
+        .take_while(|t| t.is_ok())
+        .filter_map(|t| t.ok())
+        .take(16)
+        .collect::<Vec<_>>();
+    let is_word = |idx: usize, word: &str| {
+        tokens.get(idx).map(|t| {
+            matches!(t.token.kind, Kind::Keyword | Kind::Ident)
+            && t.token.value.eq_ignore_ascii_case(word)
+        }).unwrap_or(false)
+    };
+    let action = if is_word(0, "create") {
+        Action::Create
+    } else if is_word(0, "alter") {
+        Action::Alter
+    } else if is_word(0, "drop") {
+        Action::Drop
+    } else {
+        return None;
+    };
+    let mut idx = 1;
+    while is_word(idx, "abstract") || is_word(idx, "scalar") {
+        idx += 1;
+    }
+    if !is_word(idx, "type") {
+        return None;
+    }
+    let name = tokens[idx+1..].iter()
+        .take_while(|t| matches!(t.token.kind,
+            Kind::Ident | Kind::BacktickName | Kind::Namespace))
+        .map(|t| t.token.value)
+        .collect::<String>();
+    if name.is_empty() {
+        return None;
+    }
+    Some((action, name))
+}
+
+fn diff_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let (n, m) = (old.len(), new.len());
+    // length of the longest common subsequence of the tails
+    let mut lcs = vec![vec![0_usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i+1][j+1] + 1
+            } else {
+                lcs[i+1][j].max(lcs[i][j+1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(n.max(m));
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push((Op::Same, old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i+1][j] >= lcs[i][j+1] {
+            ops.push((Op::Removed, old[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Added, new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|l| (Op::Removed, *l)));
+    ops.extend(new[j..].iter().map(|l| (Op::Added, *l)));
+    ops
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+/// Unified diff of two texts, empty if they are equal
+pub fn unified(old_title: &str, new_title: &str, old: &str, new: &str)
+    -> Vec<String>
+{
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    let ops = diff_ops(&old_lines, &new_lines);
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (idx, _) in ops.iter().enumerate().filter(|(_, o)| o.0 != Op::Same) {
+        let start = idx.saturating_sub(CONTEXT);
+        let end = (idx + CONTEXT + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return Vec::new();
+    }
+    let count = |ops: &[(Op, &str)], skip: Op| {
+        ops.iter().filter(|(op, _)| *op != skip).count()
+    };
+    let mut out = vec![
+        format!("--- {}", old_title),
+        format!("+++ {}", new_title),
+    ];
+    for (start, end) in hunks {
+        out.push(format!("@@ -{} +{} @@",
+            hunk_range(count(&ops[..start], Op::Added),
+                       count(&ops[start..end], Op::Added)),
+            hunk_range(count(&ops[..start], Op::Removed),
+                       count(&ops[start..end], Op::Removed))));
+        for (op, line) in &ops[start..end] {
+            let mark = match op {
+                Op::Same => ' ',
+                Op::Removed => '-',
+                Op::Added => '+',
+            };
+            out.push(format!("{}{}", mark, line));
+        }
+    }
+    out
+}
+
+pub fn print(lines: &[String], colors: bool) {
+    for line in lines {
+        if !colors {
+            println!("{}", line);
+        } else if line.starts_with("---") || line.starts_with("+++") {
+            println!("{}", line.clone().bold());
+        } else if line.starts_with("@@") {
+            println!("{}", line.clone().cyan());
+        } else if line.starts_with('-') {
+            println!("{}", line.clone().red());
+        } else if line.starts_with('+') {
+            println!("{}", line.clone().green());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+async fn describe(cli: &mut Connection, types: &[(Action, String)],
+    skip: Action)
+    -> anyhow::Result<Vec<String>>
+{
+    let mut result = Vec::with_capacity(types.len());
+    for (action, name) in types {
+        if *action == skip {
+            result.push(String::new());
+        } else {
+            result.push(cli.query_row::<String>(
+                &format!("DESCRIBE TYPE {} AS SDL", name),
+                &Value::empty_tuple()).await?);
+        }
+    }
+    Ok(result)
+}
+
+async fn try_diff(cli: &mut Connection, statements: &[StatementProposal],
+    types: &[(Action, String)])
+    -> anyhow::Result<Vec<String>>
+{
+    let old = describe(cli, types, Action::Create).await?;
+    for statement in statements {
+        cli.execute(&statement.text).await?;
+    }
+    let new = describe(cli, types, Action::Drop).await?;
+    let mut lines = Vec::new();
+    for (((_, name), old), new) in types.iter().zip(old).zip(new) {
+        lines.extend(unified(&format!("{} (current)", name),
+                             &format!("{} (proposed)", name),
+                             &old, &new));
+    }
+    Ok(lines)
+}
+
+/// Diff of the types changed by the statements
+///
+/// Statements are run inside the migration and rolled back to the save
+/// point. The diff is best effort: it's empty if the statements change no
+/// types or it could not be built.
+pub async fn proposal_diff(cli: &mut Connection,
+    statements: &[StatementProposal], save_point: usize)
+    -> anyhow::Result<Vec<String>>
+{
+    let mut types = Vec::new();
+    for statement in statements {
+        if let Some(item) = affected_type(&statement.text) {
+            if !types.contains(&item) {
+                types.push(item);
+            }
+        }
+    }
+    if types.is_empty() {
+        return Ok(Vec::new());
+    }
+    let result = try_diff(cli, statements, &types).await;
+    cli.execute(&format!("ROLLBACK TO SAVEPOINT migration_{}", save_point))
+        .await?;
+    match result {
+        Ok(lines) => Ok(lines),
+        Err(e) => {
+            log::debug!("Cannot build schema diff: {:#}", e);
+            Ok(Vec::new())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{affected_type, unified, Action};
+
+    #[test]
+    fn types() {
+        assert_eq!(affected_type("CREATE TYPE default::User {\n};"),
+                   Some((Action::Create, "default::User".into())));
+        assert_eq!(affected_type("alter type default::`Some Type` { };"),
+                   Some((Action::Alter, "default::`Some Type`".into())));
+        assert_eq!(affected_type("DROP SCALAR TYPE app::Status;"),
+                   Some((Action::Drop, "app::Status".into())));
+        assert_eq!(affected_type("CREATE ABSTRACT TYPE default::Named;"),
+                   Some((Action::Create, "default::Named".into())));
+        assert_eq!(affected_type("CREATE FUNCTION default::f() -> str \
+                                  USING ('');"), None);
+        assert_eq!(affected_type("SET message := 'x';"), None);
+    }
+
+    #[test]
+    fn diff() {
+        let old = "type User {\n    a;\n    b;\n    c;\n};";
+        let new = "type User {\n    a;\n    b2;\n    c;\n    d;\n};";
+        assert_eq!(unified("old", "new", old, new), vec![
+            "--- old",
+            "+++ new",
+            "@@ -1,5 +1,6 @@",
+            " type User {",
+            "     a;",
+            "-    b;",
+            "+    b2;",
+            "     c;",
+            "+    d;",
+            " };",
+        ]);
+        assert!(unified("old", "new", old, old).is_empty());
+    }
+
+    #[test]
+    fn created() {
+        assert_eq!(unified("old", "new", "", "type A;"), vec![
+            "--- old",
+            "+++ new",
+            "@@ -0,0 +1,1 @@",
+            "+type A;",
+        ]);
+    }
+
+    #[test]
+    fn hunks() {
+        let lines = |two: &str, nineteen: &str| (1..=20).map(|i| match i {
+            2 => two.to_string(),
+            19 => nineteen.to_string(),
+            _ => i.to_string(),
+        }).collect::<Vec<_>>().join("\n");
+        let old = lines("2", "19");
+        let new = lines("two", "nineteen");
+        let diff = unified("old", "new", &old, &new);
+        assert_eq!(diff[2], "@@ -1,5 +1,5 @@");
+        assert_eq!(diff[9], "@@ -16,5 +16,5 @@");
+    }
+}
@@ -1,10 +1,21 @@
 use std::mem;
+use std::ops::Range;
 
 
+/// Text inserted in place of the `source` range of the original file
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    /// Range of the inserted text, relative to the start of the slice
+    pub output: Range<usize>,
+    /// Range of the placeholder in the original file
+    pub source: Range<usize>,
+}
+
 struct Slice<N> {
     name: N,
     byte_offset: usize,
     size: usize,
+    substitutions: Vec<Substitution>,
 }
 
 pub struct SourceMap<N> {
@@ -30,10 +41,18 @@ impl<N> Builder<N> {
         (data.buffer, data.source_map)
     }
     pub fn add_lines(&mut self, name: N, data: &str) -> &mut Self {
+        self.add_substituted(name, data, Vec::new())
+    }
+    /// Adds text which differs from the original file by substitutions
+    pub fn add_substituted(&mut self, name: N, data: &str,
+                           substitutions: Vec<Substitution>)
+        -> &mut Self
+    {
         self.source_map.slices.push(Slice {
             name,
             byte_offset: self.buffer.len(),
             size: data.len(),
+            substitutions,
         });
         self.buffer.push_str(data);
         if !data.ends_with('\n') {
@@ -59,12 +78,36 @@ impl<N> SourceMap<N> {
         }
         return Err(())
     }
+    /// Translates the range to the offsets in the original file
+    pub fn source_range(&self, start: usize, end: usize)
+        -> Result<(&N, usize, usize), ()>
+    {
+        let (name, offset) = self.translate_range(start, end)?;
+        let slice = self.slices.iter().rev()
+            .find(|s| s.byte_offset == offset)
+            .ok_or(())?;
+        let map = |pos| source_offset(&slice.substitutions, pos);
+        Ok((name, map(start - offset), map(end - offset)))
+    }
+}
+
+/// Translates the offset in the substituted text to the original file
+pub fn source_offset(substitutions: &[Substitution], pos: usize) -> usize {
+    let subst = substitutions.iter()
+        .rev()
+        .find(|s| s.output.start <= pos);
+    match subst {
+        // errors inside of the inserted text point to placeholder
+        Some(s) if pos < s.output.end => s.source.start,
+        Some(s) => s.source.end + (pos - s.output.end),
+        None => pos,
+    }
 }
 
 
 #[cfg(test)]
 mod test {
-    use super::Builder;
+    use super::{Builder, Substitution};
 
     #[test]
     fn simple() {
@@ -89,4 +132,20 @@ mod test {
         assert_eq!(map.translate_range(13, 14).unwrap(), (&"file2", 13));
         assert_eq!(map.translate_range(27, 39).unwrap(), (&"file3", 27));
     }
+
+    #[test]
+    fn substituted() {
+        // `a ${X} b ${Y};` with X=long_value Y=1
+        let (_, map) = Builder::new()
+            .add_lines("prefix", "{")
+            .add_substituted("file1", "a long_value b 1;", vec![
+                Substitution { output: 2..12, source: 2..6 },
+                Substitution { output: 15..16, source: 9..13 },
+            ])
+            .done();
+        assert_eq!(map.source_range(2, 3).unwrap(), (&"file1", 0, 1));
+        assert_eq!(map.source_range(5, 15).unwrap(), (&"file1", 2, 7));
+        assert_eq!(map.source_range(17, 19).unwrap(), (&"file1", 9, 14));
+        assert_eq!(map.source_range(0, 1).unwrap(), (&"prefix", 0, 1));
+    }
 }
@@ -6,9 +6,10 @@ use crate::commands::parser::ShowStatus;
 use crate::migrations::context::Context;
 use crate::migrations::create::{execute_start_migration, CurrentMigration};
 use crate::migrations::migration;
+use crate::verbosity::Verbosity;
 
 
-async fn ensure_diff_is_empty(cli: &mut Connection, status: &ShowStatus)
+async fn ensure_diff_is_empty(cli: &mut Connection, verbosity: Verbosity)
     -> Result<(), anyhow::Error>
 {
     let data = cli.query_row::<CurrentMigration>(
@@ -16,7 +17,7 @@ async fn ensure_diff_is_empty(cli: &mut Connection, status: &ShowStatus)
         &Value::empty_tuple(),
     ).await?;
     if !data.confirmed.is_empty() || !data.complete {
-        if !status.quiet {
+        if verbosity.show_progress() {
             eprintln!("Detected differences between \
                 the database schema and the schema source, \
                 in particular:");
@@ -41,11 +42,12 @@ async fn ensure_diff_is_empty(cli: &mut Connection, status: &ShowStatus)
     Ok(())
 }
 
-pub async fn status(cli: &mut Connection, _options: &Options,
+pub async fn status(cli: &mut Connection, options: &Options,
     status: &ShowStatus)
     -> Result<(), anyhow::Error>
 {
     let ctx = Context::from_config(&status.cfg);
+    let verbosity = options.verbosity.with_flags(status.quiet, false);
     let migrations = migration::read_all(&ctx, true).await?;
     let db_migration: Option<String> = cli.query_row_opt(r###"
             WITH Last := (SELECT schema::Migration
@@ -53,7 +55,7 @@ pub async fn status(cli: &mut Connection, _options: &Options,
             SELECT name := Last.name
         "###, &Value::empty_tuple()).await?;
     if db_migration.as_ref() != migrations.keys().last() {
-        if !status.quiet {
+        if verbosity.show_progress() {
             if let Some(db_migration) = &db_migration {
                 if let Some(_) = migrations.get(db_migration) {
                     let mut iter = migrations.keys()
@@ -82,10 +84,10 @@ pub async fn status(cli: &mut Connection, _options: &Options,
         return Err(ExitCode::new(3).into());
     }
     execute_start_migration(&ctx, cli).await?;
-    let check = ensure_diff_is_empty(cli, status).await;
+    let check = ensure_diff_is_empty(cli, verbosity).await;
     let abort = cli.execute("ABORT MIGRATION").await;
     check.and(abort)?;
-    if !status.quiet {
+    if verbosity.show_progress() {
         eprintln!("Database is up to date. Last migration: {}.",
             db_migration.as_ref().map(|x| &x[..]).unwrap_or("initial"));
     }
@@ -0,0 +1,179 @@
+//! Expanding `${NAME}` placeholders in schema files
+//!
+//! Placeholders are only expanded in projects having the
+//! `[schema-variables]` table in `edgedb.toml`, so existing schemas
+//! containing `${` keep working. Values are taken from the environment
+//! variable of the same name, or from the table. `$${` is a literal `${`.
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::migrations::source_map::{self, Substitution};
+use crate::project;
+
+
+#[derive(Debug)]
+pub struct Expansion {
+    pub text: String,
+    pub substitutions: Vec<Substitution>,
+}
+
+impl Expansion {
+    /// Translates the offset in the expanded text to the original one
+    pub fn source_offset(&self, offset: usize) -> usize {
+        source_map::source_offset(&self.substitutions, offset)
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() &&
+        name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+pub fn expand(text: &str, lookup: impl Fn(&str) -> Option<String>)
+    -> anyhow::Result<Expansion>
+{
+    let mut result = String::with_capacity(text.len());
+    let mut substitutions = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = text[pos..].find('$') {
+        let start = pos + found;
+        if text[start..].starts_with("$${") {
+            result.push_str(&text[pos..start]);
+            let output_start = result.len();
+            result.push_str("${");
+            substitutions.push(Substitution {
+                output: output_start..result.len(),
+                source: start..start+3,
+            });
+            pos = start + 3;
+            continue;
+        }
+        if !text[start..].starts_with("${") {
+            result.push_str(&text[pos..=start]);
+            pos = start + 1;
+            continue;
+        }
+        let end = match text[start..].find('}') {
+            Some(end) => start + end + 1,
+            None => {
+                anyhow::bail!("line {}: unclosed placeholder `${{`",
+                              line_of(text, start));
+            }
+        };
+        let name = &text[start+2..end-1];
+        if !valid_name(name) {
+            anyhow::bail!("line {}: invalid placeholder `{}`",
+                          line_of(text, start), &text[start..end]);
+        }
+        let value = match lookup(name) {
+            Some(value) => value,
+            None => {
+                anyhow::bail!("line {}: variable {:?} is not set, \
+                    define it in the environment or in the \
+                    `[schema-variables]` table of `edgedb.toml`",
+                    line_of(text, start), name);
+            }
+        };
+        result.push_str(&text[pos..start]);
+        let output_start = result.len();
+        result.push_str(&value);
+        substitutions.push(Substitution {
+            output: output_start..result.len(),
+            source: start..end,
+        });
+        pos = end;
+    }
+    result.push_str(&text[pos..]);
+    Ok(Expansion { text: result, substitutions })
+}
+
+/// Variables defined in the project config, `None` if placeholders are
+/// not enabled for the project
+pub fn project_variables()
+    -> anyhow::Result<Option<BTreeMap<String, String>>>
+{
+    match project::project_dir_opt(None)? {
+        Some(dir) => {
+            let config = project::config::read(&dir.join("edgedb.toml"))?;
+            Ok(config.schema_variables)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Expands the file if placeholders are enabled, returns it as is
+/// otherwise
+pub fn expand_file(text: &str,
+    variables: Option<&BTreeMap<String, String>>)
+    -> anyhow::Result<Expansion>
+{
+    match variables {
+        Some(variables) => expand(text, lookup(variables)),
+        None => Ok(Expansion {
+            text: text.into(),
+            substitutions: Vec::new(),
+        }),
+    }
+}
+
+/// Environment variables take precedence over the project config
+pub fn lookup<'a>(variables: &'a BTreeMap<String, String>)
+    -> impl Fn(&str) -> Option<String> + 'a
+{
+    move |name| env::var(name).ok().or_else(|| variables.get(name).cloned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::expand;
+
+    fn vars(name: &str) -> Option<String> {
+        match name {
+            "VERSION" => Some("1.0".into()),
+            "EMPTY" => Some("".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn no_placeholders() {
+        let res = expand("type User;", vars).unwrap();
+        assert_eq!(res.text, "type User;");
+        assert!(res.substitutions.is_empty());
+    }
+
+    #[test]
+    fn substitute() {
+        let res = expand("version '${VERSION}'${EMPTY};", vars).unwrap();
+        assert_eq!(res.text, "version '1.0';");
+        assert_eq!(res.substitutions[0].output, 9..12);
+        assert_eq!(res.substitutions[0].source, 9..19);
+        assert_eq!(res.substitutions[1].output, 13..13);
+        assert_eq!(res.substitutions[1].source, 20..28);
+    }
+
+    #[test]
+    fn escape() {
+        let res = expand("regexp('^$${1,2}$') ${VERSION} $", vars).unwrap();
+        assert_eq!(res.text, "regexp('^${1,2}$') 1.0 $");
+        assert_eq!(res.source_offset(12), 13);
+        assert_eq!(res.source_offset(20), 20);
+        assert_eq!(res.source_offset(23), 31);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(expand("a;\n${UNKNOWN}", vars).unwrap_err().to_string(),
+            "line 2: variable \"UNKNOWN\" is not set, define it in the \
+             environment or in the `[schema-variables]` table of \
+             `edgedb.toml`");
+        assert_eq!(expand("${VERSION", vars).unwrap_err().to_string(),
+            "line 1: unclosed placeholder `${`");
+        assert_eq!(expand("${a b}", vars).unwrap_err().to_string(),
+            "line 1: invalid placeholder `${a b}`");
+    }
+}
@@ -1,16 +1,23 @@
+use std::fs;
+use std::path::Path;
 use std::str;
 
 use anyhow::{self, Context};
 use async_std::prelude::StreamExt;
-use async_std::io::{stdin, stdout};
+use async_std::io::{stdin, stdout, Read as AsyncRead};
 use async_std::io::prelude::WriteExt;
 
 use bytes::BytesMut;
 use edgeql_parser::preparser;
 use edgedb_protocol::value::Value;
 
+use crate::classify;
+use crate::error_hints;
+use crate::hint::HintExt;
 use crate::options::Options;
+use crate::output_style;
 use crate::print::{self, PrintError};
+use crate::script;
 use edgedb_client::reader::ReadError;
 use crate::statement::{ReadStatement, EndOfFile};
 use edgedb_client::client::Connection;
@@ -22,33 +29,67 @@ pub async fn main(options: Options)
     -> Result<(), anyhow::Error>
 {
     let mut conn = options.conn_params.connect().await?;
-    let mut stdin = stdin();
+    match &options.script_file {
+        Some(path) if path != Path::new("-") => {
+            let mut file = async_std::fs::File::open(path).await
+                .with_context(|| format!("cannot open {:?}", path))?;
+            run_script(&mut conn, &mut file, &options).await
+        }
+        _ => run_script(&mut conn, &mut stdin(), &options).await,
+    }
+}
+
+async fn run_script<T>(conn: &mut Connection, input: &mut T,
+                       options: &Options)
+    -> Result<(), anyhow::Error>
+    where T: AsyncRead + Unpin,
+{
     let mut inbuf = BytesMut::with_capacity(8192);
+    let mut conditions = script::Conditions::default();
     loop {
-        let stmt = match ReadStatement::new(&mut inbuf, &mut stdin).await {
+        if let Some(line) = script::read_directive(&mut inbuf, input).await? {
+            script::execute(&line, &mut conditions, conn).await?;
+            continue;
+        }
+        let stmt = match ReadStatement::new(&mut inbuf, input).await {
             Ok(chunk) => chunk,
             Err(e) if e.is::<EndOfFile>() => break,
             Err(e) => return Err(e),
         };
         let stmt = str::from_utf8(&stmt[..])
             .context("can't decode statement")?;
-        if preparser::is_empty(stmt) {
+        if preparser::is_empty(stmt) || !conditions.is_active() {
             continue;
         }
-        query(&mut conn, &stmt, &options).await?;
+        query(conn, &stmt, options).await?;
     }
-    Ok(())
+    conditions.finish()
 }
 
 pub async fn query(conn: &mut Connection, stmt: &str, options: &Options)
     -> Result<(), anyhow::Error>
+{
+    match _query(conn, stmt, options).await {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            match error_hints::hint(conn, &e).await {
+                Some(hint) => Err(e).with_hint(|| hint)?,
+                None => Err(e),
+            }
+        }
+    }
+}
+
+async fn _query(conn: &mut Connection, stmt: &str, options: &Options)
+    -> Result<(), anyhow::Error>
 {
     use crate::repl::OutputMode::*;
+    classify::guard(stmt)?;
     let mut cfg = print::Config::new();
     if let Some((w, _h)) = term_size::dimensions_stdout() {
         cfg.max_width(w);
     }
-    cfg.colors(atty::is(atty::Stream::Stdout));
+    cfg.colors(output_style::colors(atty::Stream::Stdout));
 
     match options.output_mode {
         TabSeparated => {
@@ -65,7 +106,7 @@ pub async fn query(conn: &mut Connection, stmt: &str, options: &Options)
                 },
             };
             while let Some(row) = items.next().await.transpose()? {
-                let mut text = tab_separated::format_row(&row)?;
+                let mut text = tab_separated::format_row(&row, "")?;
                 // trying to make writes atomic if possible
                 text += "\n";
                 stdout().write_all(text.as_bytes()).await?;
@@ -137,18 +178,78 @@ pub async fn query(conn: &mut Connection, stmt: &str, options: &Options)
                     Err(e) => Err(e)?,
                 },
             };
+            let mut data = print::SpillBuffer::new(cfg.spill_threshold);
             while let Some(row) = items.next().await.transpose()? {
                 let items: serde_json::Value = serde_json::from_str(&row)
                     .context("cannot decode json result")?;
                 let items = items.as_array()
                     .ok_or_else(|| anyhow::anyhow!(
                         "non-array returned from postgres in JSON mode"))?;
-                // trying to make writes atomic if possible
-                let mut data = print::json_to_string(items, &cfg)?;
-                data += "\n";
-                stdout().write_all(data.as_bytes()).await?;
+                print::json_to_spill(items, &cfg, &mut data)?;
+                data.push_str("\n")?;
             }
+            data.write_to_stdout().await?;
         }
     }
     Ok(())
 }
+
+pub async fn query_json_value(conn: &mut Connection, stmt: &str)
+    -> Result<serde_json::Value, anyhow::Error>
+{
+    let mut result = Vec::new();
+    let mut items = conn.query_json(stmt, &Value::empty_tuple()).await?;
+    while let Some(row) = items.next().await.transpose()? {
+        let items: serde_json::Value = serde_json::from_str(&row)
+            .context("cannot decode json result")?;
+        match items {
+            serde_json::Value::Array(items) => result.extend(items),
+            _ => anyhow::bail!("non-array returned from postgres in JSON mode"),
+        }
+    }
+    Ok(serde_json::Value::Array(result))
+}
+
+fn read_saved_result(path: &Path) -> Result<serde_json::Value, anyhow::Error>
+{
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("cannot read {:?}", path))?;
+    if let Ok(value) = serde_json::from_str(&data) {
+        return Ok(value);
+    }
+    // output of `--json-elements`: one element per line
+    let items = data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("cannot decode json in {:?}", path))?;
+    Ok(serde_json::Value::Array(items))
+}
+
+/// Runs a query and prints differences against the result saved in a file
+///
+/// Returns `true` if results are equal.
+pub async fn diff_with(conn: &mut Connection, stmt: &str, path: &Path)
+    -> Result<bool, anyhow::Error>
+{
+    classify::guard(stmt)?;
+    // result of a modification can't be reproduced by running it again
+    if classify::is_mutating(stmt) {
+        return Err(anyhow::anyhow!(
+            "only results of queries which don't modify data, \
+             schema or configuration can be compared"))
+            .hint("Save the result of a read-only query instead")?;
+    }
+    let old = read_saved_result(path)?;
+    let new = query_json_value(conn, stmt).await?;
+    let diffs = print::diff::diff(&old, &new);
+    if diffs.is_empty() {
+        eprintln!("No differences.");
+        return Ok(true);
+    }
+    let text = print::diff::format_diff(&diffs,
+        output_style::colors(atty::Stream::Stdout));
+    stdout().write_all(text.as_bytes()).await?;
+    eprintln!("{} difference(s) found.", diffs.len());
+    Ok(false)
+}
@@ -1,21 +1,41 @@
 use std::env;
+use std::path::PathBuf;
 use std::time::Duration;
 use std::fs;
+use std::str::FromStr;
 
 use anyhow::Context;
 use atty;
 use clap::{Clap, AppSettings, ValueHint};
 use edgedb_client::Builder;
 
-use crate::commands::parser::Common;
-use crate::connect::Connector;
-use crate::credentials::get_connector;
+use crate::classify;
+use crate::commands::parser::{Common, MigrationConfig};
+use crate::config::{self, Config};
+use crate::connect::{Connector, Source, ParamSource};
+use crate::credentials::{self, get_connector};
+use crate::dry_run;
+use crate::env_file;
+use crate::generate;
 use crate::hint::HintExt;
+use crate::history;
+use crate::output_style::{self, OutputStyle};
+use crate::print::style;
 use crate::project;
+use crate::protocol_trace;
+use crate::queries;
 use crate::repl::OutputMode;
+use crate::schema;
 use crate::self_install;
 use crate::self_upgrade;
 use crate::server;
+use crate::setup_wizard;
+use crate::telemetry;
+use crate::testing;
+use crate::typos;
+use crate::verbosity::Verbosity;
+use crate::version_check;
+use crate::warnings;
 
 
 static CONNECTION_ARG_HINT: &str = "\
@@ -84,6 +104,17 @@ pub struct RawOptions {
     #[clap(value_hint=ValueHint::Other)]  // TODO complete instance name
     pub instance: Option<String>,
 
+    /// Load `EDGEDB_*` variables from a file in `.env` format. Variables
+    /// already set in the environment take precedence
+    #[clap(long, help_heading=Some("CONNECTION OPTIONS"))]
+    #[clap(value_hint=ValueHint::FilePath)]
+    pub env_file: Option<PathBuf>,
+
+    /// Print `EDGEDB_*` variables, where they come from and the resolved
+    /// connection parameters to stderr
+    #[clap(long, help_heading=Some("DEBUG OPTIONS"))]
+    pub print_env: bool,
+
     #[clap(long, help_heading=Some("DEBUG OPTIONS"))]
     #[cfg_attr(not(feature="dev_mode"),
         clap(setting=clap::ArgSettings::Hidden))]
@@ -99,6 +130,17 @@ pub struct RawOptions {
         clap(setting=clap::ArgSettings::Hidden))]
     pub debug_print_codecs: bool,
 
+    /// Print protocol messages exchanged with the server to stderr
+    /// (query arguments and data are not printed, only their size)
+    #[clap(long, help_heading=Some("DEBUG OPTIONS"))]
+    pub trace_protocol: bool,
+
+    /// Write protocol trace into a file instead of stderr
+    /// (implies `--trace-protocol`)
+    #[clap(long, help_heading=Some("DEBUG OPTIONS"))]
+    #[clap(value_hint=ValueHint::FilePath)]
+    pub trace_protocol_file: Option<PathBuf>,
+
     /// Tab-separated output of the queries
     #[clap(short='t', long, overrides_with="json")]
     pub tab_separated: bool,
@@ -111,10 +153,51 @@ pub struct RawOptions {
     #[clap(short='c')]
     pub query: Option<String>,
 
+    /// Execute statements from a file instead of starting REPL (`-` reads
+    /// stdin). Lines starting with `\if QUERY`, `\else`, `\endif` and
+    /// `\echo TEXT` between the statements are executed by the tool
+    #[clap(short='f', long, value_hint=ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
     /// Disable version check
     #[clap(long)]
     pub no_version_check: bool,
 
+    /// Print only data and errors: no progress, status messages or hints
+    #[clap(short='q', long, conflicts_with="verbose")]
+    pub quiet: bool,
+
+    /// Print details of the operations, including info log messages
+    #[clap(short='v', long)]
+    pub verbose: bool,
+
+    /// Exit with a non-zero code if the command succeeded but printed
+    /// warnings
+    #[clap(long, possible_values=&["warnings"][..])]
+    pub fail_on: Option<FailOn>,
+
+    /// What to do with warnings sent by the server: print them to stderr
+    /// (default), hide, or fail the statement
+    #[clap(long, possible_values=&["show", "hide", "error"][..])]
+    pub warnings: Option<warnings::Policy>,
+
+    /// Reject statements which modify data, schema or configuration
+    /// before sending them to the server (can't be turned off
+    /// with `\set read-only` in the interactive shell)
+    #[clap(long)]
+    pub read_only: bool,
+
+    /// Print actions of destructive commands (`server destroy`,
+    /// `project unlink --destroy-server-instance`, `restore`) instead of
+    /// performing them
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Use `plain` to disable colors, box-drawing characters, spinners and
+    /// progress bars (useful for screen readers and logs)
+    #[clap(long, possible_values=&["default", "plain"][..])]
+    pub output_style: Option<OutputStyle>,
+
     #[clap(subcommand)]
     pub subcommand: Option<Command>,
 }
@@ -133,6 +216,46 @@ pub enum Command {
     Server(server::options::ServerCommand),
     /// Manage project installation
     Project(project::options::ProjectCommand),
+    /// Run tests against the database
+    Test(testing::options::TestCommand),
+    /// Analyze history of the interactive shell
+    History(history::options::HistoryCommand),
+    /// Generate code from the database schema
+    Generate(generate::options::GenerateCommand),
+    /// Inspect and export the database schema
+    Schema(schema::options::SchemaCommand),
+    /// Run the library of queries kept outside of migrations
+    Queries(queries::options::QueriesCommand),
+    /// Wait until the instance accepts connections (useful in CI)
+    Wait(Wait),
+    /// Insert objects from a file with one JSON object per line
+    Insert(Insert),
+    /// Update objects matching a filter in batches
+    Update(Update),
+    /// Delete objects matching a filter in batches
+    Delete(Delete),
+    /// Show a few objects of a type with their properties and links
+    Sample(Sample),
+    /// Print changes of a query result as JSON lines
+    Listen(Listen),
+    /// Check syntax of EdgeQL files without connecting to the server
+    Check(Check),
+    /// Print `file:line:column` of a definition in the schema files
+    Locate(Locate),
+    /// Show number of objects of each type
+    Stats(Stats),
+    /// Delete all data of the current database, or the database itself
+    WipeDatabase(WipeDatabase),
+    /// Inspect connection parameters
+    Connection(Connection),
+    /// Inspect settings of the command-line tool
+    Config(CliConfig),
+    /// Check the local installation for common problems
+    Doctor(server::options::Doctor),
+    /// Restore recently destroyed local resources
+    Undo(server::options::Undo),
+    /// Show log of operations which modified instances or databases
+    Log(Log),
     /// Install server
     #[clap(setting=AppSettings::Hidden, name="_self_install")]
     _SelfInstall(self_install::SelfInstall),
@@ -141,6 +264,10 @@ pub enum Command {
     _GenCompletions(self_install::GenCompletions),
     /// Upgrade this edgedb binary
     SelfUpgrade(self_upgrade::SelfUpgrade),
+    /// Show version of this edgedb binary
+    Version(version_check::ShowVersion),
+    /// Manage anonymous usage statistics (disabled by default)
+    Telemetry(telemetry::Telemetry),
     #[clap(flatten)]
     Common(Common),
 }
@@ -149,6 +276,275 @@ pub enum Command {
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub struct Query {
     pub queries: Vec<String>,
+    /// Compare query result with JSON saved earlier (for example, by
+    /// `edgedb --json query`) and print the differences
+    #[clap(long, value_hint=ValueHint::FilePath)]
+    pub diff_with: Option<PathBuf>,
+    /// Run queries against every database of the instance
+    #[clap(long, alias="all-branches", conflicts_with="diff-with")]
+    pub all_databases: bool,
+    /// Query databases concurrently (with `--all-databases`), output of
+    /// different databases may be interleaved
+    #[clap(long, requires="all-databases")]
+    pub parallel: bool,
+    /// Re-run the query on the interval (for example, `2s`), redrawing
+    /// the result and highlighting the changed lines, until interrupted
+    #[clap(long, name="INTERVAL",
+           parse(try_from_str=humantime::parse_duration),
+           conflicts_with_all=&["diff-with", "all-databases"])]
+    pub watch: Option<Duration>,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Insert {
+    /// Name of the object type, `default::` module is assumed if omitted
+    pub type_name: String,
+    /// File with one JSON object per line (`-` for stdin). Fields are
+    /// mapped to properties and links of the same name
+    #[clap(long, value_hint=ValueHint::FilePath)]
+    pub from_json: PathBuf,
+    /// Property of the link targets which link values refer to
+    #[clap(long, default_value="id")]
+    pub link_by: String,
+    /// Number of objects inserted by a single query
+    #[clap(long, default_value="100", parse(try_from_str=batch_size))]
+    pub batch_size: usize,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Sample {
+    /// Name of the object type, `default::` module is assumed if omitted
+    pub type_name: String,
+    /// Maximum number of objects to show
+    #[clap(long, default_value="10")]
+    pub limit: u64,
+    /// Pick random objects instead of the first ones
+    #[clap(long)]
+    pub random: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Listen {
+    /// Name of the channel, its query is taken from the `[listeners]`
+    /// section of the config unless `--query` is given
+    pub channel: String,
+    /// Query to poll for changes
+    #[clap(long)]
+    pub query: Option<String>,
+    /// How often to run the query
+    #[clap(long, default_value="1s",
+           parse(try_from_str=humantime::parse_duration))]
+    pub interval: Duration,
+    /// Print the current rows as inserted before the changes
+    #[clap(long)]
+    pub initial: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Check {
+    /// Files to check, `-` reads stdin
+    #[clap(required=true, value_hint=ValueHint::FilePath)]
+    pub files: Vec<PathBuf>,
+    /// Read the text of the file from stdin unless it's a terminal, the
+    /// file name is only used in diagnostics (for unsaved editor buffers)
+    #[clap(long)]
+    pub stdin_ok: bool,
+    /// Print diagnostics as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Locate {
+    /// Name of a type, alias, function, abstract constraint or annotation,
+    /// or `Type.pointer`. Any module matches if the module is omitted
+    pub name: String,
+    /// Directory with the schema files, `dbschema` of the project by
+    /// default
+    #[clap(long, value_hint=ValueHint::DirPath)]
+    pub schema_dir: Option<PathBuf>,
+    /// Also print the kind and the full name of the definition
+    #[clap(short='v', long)]
+    pub verbose: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Stats {
+    /// Also estimate size of the data by the length of its JSON
+    /// representation (reads every object, may be slow)
+    #[clap(long)]
+    pub sizes: bool,
+    /// Order of the rows, `count` and `size` put the largest types first
+    #[clap(long, default_value="count",
+           possible_values=&["name", "count", "size"][..])]
+    pub sort: StatsSort,
+    /// Output statistics as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct WipeDatabase {
+    /// Delete all objects but keep the schema. Without this option the
+    /// database is dropped and created again
+    #[clap(long)]
+    pub data_only: bool,
+    /// Delete only objects of the types matching the pattern, e.g.
+    /// `app::*` (may be repeated, implies `--data-only`)
+    #[clap(long="types", number_of_values=1)]
+    pub types: Vec<String>,
+    /// Do not ask for confirmation
+    #[clap(long)]
+    pub non_interactive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsSort {
+    Name,
+    Count,
+    Size,
+}
+
+impl FromStr for StatsSort {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<StatsSort, anyhow::Error> {
+        match s {
+            "name" => Ok(StatsSort::Name),
+            "count" => Ok(StatsSort::Count),
+            "size" => Ok(StatsSort::Size),
+            _ => Err(anyhow::anyhow!("unsupported sort order {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailOn {
+    Warnings,
+}
+
+impl FromStr for FailOn {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<FailOn, anyhow::Error> {
+        match s {
+            "warnings" => Ok(FailOn::Warnings),
+            _ => Err(anyhow::anyhow!("unsupported fail-on policy {:?}", s)),
+        }
+    }
+}
+
+fn batch_size(s: &str) -> anyhow::Result<usize> {
+    match s.parse()? {
+        0 => anyhow::bail!("batch size must be at least 1"),
+        size => Ok(size),
+    }
+}
+
+#[derive(Clap, Clone, Debug)]
+pub struct BulkOptions {
+    /// Show a sample of matching objects and ask for confirmation
+    #[clap(long)]
+    pub preview: bool,
+    /// Number of objects shown with `--preview`
+    #[clap(long, default_value="10")]
+    pub sample_size: usize,
+    /// Number of objects modified by a single query
+    #[clap(long, default_value="1000", parse(try_from_str=batch_size))]
+    pub batch_size: usize,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Update {
+    /// Name of the object type, `default::` module is assumed if omitted
+    pub type_name: String,
+    /// Expression selecting objects to update, e.g. `.active = false`
+    #[clap(long)]
+    pub filter: String,
+    /// Assignment to apply, e.g. `archived := true` (may be repeated)
+    #[clap(long="set", required=true, number_of_values=1)]
+    pub assignments: Vec<String>,
+    #[clap(flatten)]
+    pub bulk: BulkOptions,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Delete {
+    /// Name of the object type, `default::` module is assumed if omitted
+    pub type_name: String,
+    /// Expression selecting objects to delete, e.g. `.active = false`
+    #[clap(long)]
+    pub filter: String,
+    /// Delete without asking for confirmation
+    #[clap(long)]
+    pub non_interactive: bool,
+    #[clap(flatten)]
+    pub bulk: BulkOptions,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Log {
+    /// Show only the last N operations
+    #[clap(long)]
+    pub limit: Option<usize>,
+    /// Print records in JSON format
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Connection {
+    #[clap(subcommand)]
+    pub subcommand: ConnectionCommand,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum ConnectionCommand {
+    /// Show connection parameters and where each of them comes from:
+    /// options, environment, project or credentials file
+    Explain,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub struct CliConfig {
+    #[clap(subcommand)]
+    pub subcommand: CliConfigCommand,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum CliConfigCommand {
+    /// Show merged settings and where each of them comes from: system,
+    /// user or project config file, environment or options
+    Doctor,
+    /// Choose output format, color theme and other settings interactively
+    /// (this is also offered when the shell is started for the first time)
+    Setup,
+}
+
+#[derive(Clap, Clone, Debug)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Wait {
+    /// Fail if the instance is not ready after this time (e.g. '60s')
+    #[clap(long, default_value="60s",
+           parse(try_from_str=humantime::parse_duration))]
+    pub timeout: Duration,
+    /// Also wait until all migrations from the schema directory are applied
+    #[clap(long)]
+    pub migrations: bool,
+    #[clap(flatten)]
+    pub cfg: MigrationConfig,
+    /// Do not print any messages, only indicate success by exit status
+    #[clap(long)]
+    pub quiet: bool,
 }
 
 #[derive(Clap, Clone, Debug)]
@@ -173,23 +569,70 @@ pub struct RoleName {
 #[derive(Debug, Clone)]
 pub struct Options {
     pub conn_params: Connector,
+    /// Where each of the connection parameters comes from
+    pub conn_sources: Vec<ParamSource>,
     pub subcommand: Option<Command>,
+    /// Names of the subcommand and its parents, without arguments
+    pub command_name: Vec<String>,
     pub interactive: bool,
+    /// Script to execute non-interactively instead of stdin
+    pub script_file: Option<PathBuf>,
     pub debug_print_frames: bool,
     pub debug_print_descriptors: bool,
     pub debug_print_codecs: bool,
     pub output_mode: OutputMode,
     pub no_version_check: bool,
+    pub fail_on: Option<FailOn>,
+    pub read_only: bool,
+    /// How much to print besides data, commands may override it
+    pub verbosity: Verbosity,
+    /// Settings merged from config files, environment and options
+    pub config: Config,
 }
 
 impl Options {
     pub fn from_args_and_env() -> anyhow::Result<Options> {
-        let tmp = RawOptions::parse();
+        let args = aliases::expand(env::args_os().collect())?;
+        let mut command_name = telemetry::command_name(&args);
+        let tmp = RawOptions::parse_from(args);
         // TODO(pc) add option to force interactive mode not on a tty (tests)
         let interactive = tmp.query.is_none()
+            && tmp.file.is_none()
             && tmp.subcommand.is_none()
             && atty::is(atty::Stream::Stdin);
-        let mut conn_params = Connector::new(conn_params(&tmp));
+        dry_run::init(tmp.dry_run);
+        if tmp.trace_protocol || tmp.trace_protocol_file.is_some() {
+            protocol_trace::init(tmp.trace_protocol_file.as_deref())?;
+        }
+        if let Some(path) = &tmp.env_file {
+            env_file::load(path)?;
+        }
+        if interactive && setup_wizard::is_first_run() {
+            setup_wizard::first_run();
+        }
+        let config = cli_config(&tmp);
+        classify::init_read_only(config.read_only, tmp.read_only);
+        output_style::init(config.output_style);
+        warnings::init(tmp.warnings.unwrap_or(warnings::Policy::Show));
+        style::init_theme(config.color_theme);
+        let mut conn_sources = Vec::new();
+        let mut conn_params = Connector::new(
+            conn_params(&tmp, &mut conn_sources));
+        let password_source = if tmp.password_from_stdin {
+            Some(Source::Flag("--password-from-stdin"))
+        } else if tmp.no_password {
+            None
+        } else if tmp.password {
+            Some(Source::Flag("--password"))
+        } else if env::var_os("EDGEDB_PASSWORD").is_some() {
+            Some(Source::Env("EDGEDB_PASSWORD"))
+        } else {
+            None
+        };
+        if let Some(source) = password_source {
+            conn_sources.push(
+                ParamSource::new("password", "<hidden>", source));
+        }
         let password = if tmp.password_from_stdin {
             let password = rpassword::read_password()
                 .expect("password can be read");
@@ -213,14 +656,28 @@ impl Options {
             tmp.wait_until_available.map(|w| params.wait_until_available(w));
             tmp.connect_timeout.map(|t| params.connect_timeout(t));
         });
+        if tmp.print_env {
+            env_file::print_env(&conn_params);
+        }
 
+        if tmp.file.is_some()
+            && (tmp.query.is_some() || tmp.subcommand.is_some())
+        {
+            anyhow::bail!(
+                "Option `-f` conflicts with `-c` and specifying subcommand");
+        }
         let subcommand = if let Some(query) = tmp.query {
             if tmp.subcommand.is_some() {
                 anyhow::bail!(
                     "Option `-c` conflicts with specifying subcommand");
             } else {
+                command_name = vec!["query".into()];
                 Some(Command::Query(Query {
                     queries: vec![query],
+                    diff_with: None,
+                    all_databases: false,
+                    parallel: false,
+                    watch: None,
                 }))
             }
         } else {
@@ -229,34 +686,73 @@ impl Options {
 
         Ok(Options {
             conn_params,
+            conn_sources,
             interactive,
             subcommand,
+            command_name,
+            script_file: tmp.file,
             debug_print_frames: tmp.debug_print_frames,
             debug_print_descriptors: tmp.debug_print_descriptors,
             debug_print_codecs: tmp.debug_print_codecs,
-            output_mode: if tmp.tab_separated {
-                OutputMode::TabSeparated
-            } else if tmp.json {
-                OutputMode::Json
-            } else if interactive {
+            output_mode: config.output_format.unwrap_or(if interactive {
                 OutputMode::Default
             } else {
                 OutputMode::JsonElements
-            },
-            no_version_check: tmp.no_version_check,
+            }),
+            no_version_check: !config.version_check,
+            fail_on: tmp.fail_on,
+            read_only: config.read_only,
+            verbosity: Verbosity::new(tmp.quiet, tmp.verbose),
+            config,
         })
     }
 }
 
-fn conn_params(tmp: &RawOptions) -> anyhow::Result<Builder> {
+/// Applies command-line options on top of config files and environment
+fn cli_config(tmp: &RawOptions) -> Config {
+    use config::Source::Flag;
+
+    let mut config = config::get_config();
+    if let Some(style) = tmp.output_style {
+        config.output_style = style;
+        config.set_source("output-style", Flag("--output-style"));
+    }
+    if tmp.tab_separated {
+        config.output_format = Some(OutputMode::TabSeparated);
+        config.set_source("output-format", Flag("--tab-separated"));
+    } else if tmp.json {
+        config.output_format = Some(OutputMode::Json);
+        config.set_source("output-format", Flag("--json"));
+    }
+    if tmp.no_version_check {
+        config.version_check = false;
+        config.set_source("version-check", Flag("--no-version-check"));
+    }
+    if tmp.read_only {
+        config.read_only = true;
+        config.set_source("read-only", Flag("--read-only"));
+    }
+    config
+}
+
+fn from_env(name: &'static str) -> Option<(String, Source)> {
+    env::var(name).ok().map(|value| (value, Source::Env(name)))
+}
+
+fn conn_params(tmp: &RawOptions, sources: &mut Vec<ParamSource>)
+    -> anyhow::Result<Builder>
+{
+    let mut project_database = None;
     let instance = if let Some(dsn) = &tmp.dsn {
+        sources.push(ParamSource::new("dsn", "<hidden>",
+                                      Source::Flag("--dsn")));
         return Ok(Builder::from_dsn(dsn)?);
     } else if tmp.instance.is_some() ||
             tmp.host.is_some() || tmp.port.is_some() ||
             env::var("EDGEDB_HOST").is_ok() ||
             env::var("EDGEDB_PORT").is_ok()
     {
-        tmp.instance.clone()
+        tmp.instance.clone().map(|name| (name, Source::Flag("--instance")))
     } else {
         let dir = env::current_dir()
             .context("cannot determine current dir")
@@ -270,31 +766,84 @@ fn conn_params(tmp: &RawOptions) -> anyhow::Result<Builder> {
             })
             .hint(CONNECTION_ARG_HINT)?;
         let dir = project::stash_path(&config_dir)?;
-        Some(
+        project_database = project::read_database(&dir)?
+            .map(|db| (db, Source::Project(config_dir.clone())));
+        Some((
             fs::read_to_string(dir.join("instance-name"))
-            .context("error reading project settings")?
-        )
+                .context("error reading project settings")?,
+            Source::Project(config_dir),
+        ))
     };
 
     let admin = tmp.admin;
-    let user = tmp.user.clone().or_else(|| env::var("EDGEDB_USER").ok());
-    let host = tmp.host.clone().or_else(|| env::var("EDGEDB_HOST").ok());
-    let port = tmp.port.or_else(|| {
-        env::var("EDGEDB_PORT").ok().and_then(|x| x.parse().ok())
-    });
+    let user = tmp.user.clone().map(|user| (user, Source::Flag("--user")))
+        .or_else(|| from_env("EDGEDB_USER"));
+    let host = tmp.host.clone().map(|host| (host, Source::Flag("--host")))
+        .or_else(|| from_env("EDGEDB_HOST"));
+    let port = tmp.port.map(|port| (port, Source::Flag("--port")))
+        .or_else(|| {
+            env::var("EDGEDB_PORT").ok().and_then(|x| x.parse().ok())
+                .map(|port| (port, Source::Env("EDGEDB_PORT")))
+        });
     let database = tmp.database.clone()
-        .or_else(|| env::var("EDGEDB_DATABASE").ok());
+        .map(|db| (db, Source::Flag("--database")))
+        .or_else(|| from_env("EDGEDB_DATABASE"))
+        .or(project_database);
 
     let mut conn_params = Builder::new();
-    if let Some(name) = &instance {
-        conn_params = get_connector(name)?;
-        user.map(|user| conn_params.user(user));
-        database.map(|database| conn_params.database(database));
+    if let Some((name, source)) = &instance {
+        let name = typos::instance(name)?;
+        sources.push(ParamSource::new("instance", &name, source.clone()));
+        let creds = Source::Credentials(credentials::path(&name)?);
+        conn_params = get_connector(&name)?;
+        sources.push(ParamSource::new("address",
+            conn_params.get_addr(), creds.clone()));
+        match user {
+            Some((user, source)) => {
+                sources.push(ParamSource::new("user", &user, source));
+                conn_params.user(user);
+            }
+            None => {
+                sources.push(ParamSource::new("user",
+                    conn_params.get_user(), creds.clone()));
+            }
+        }
+        match database {
+            Some((database, source)) => {
+                sources.push(ParamSource::new("database", &database, source));
+                conn_params.database(database);
+            }
+            None => {
+                sources.push(ParamSource::new("database",
+                    "<from credentials or default>", creds));
+            }
+        }
     } else {
-        user.as_ref().map(|user| conn_params.user(user));
-        database.as_ref().map(|db| conn_params.database(db));
-        let host = host.unwrap_or_else(|| String::from("localhost"));
-        let port = port.unwrap_or(5656);
+        match user {
+            Some((user, source)) => {
+                sources.push(ParamSource::new("user", &user, source));
+                conn_params.user(user);
+            }
+            None => {
+                sources.push(ParamSource::new("user", "edgedb",
+                                              Source::Default));
+            }
+        }
+        match database {
+            Some((database, source)) => {
+                sources.push(ParamSource::new("database", &database, source));
+                conn_params.database(database);
+            }
+            None => {
+                sources.push(ParamSource::new("database", "<same as user>",
+                                              Source::Default));
+            }
+        }
+        let (host, host_source) = host
+            .unwrap_or_else(|| (String::from("localhost"), Source::Default));
+        let (port, port_source) = port.unwrap_or((5656, Source::Default));
+        sources.push(ParamSource::new("host", &host, host_source));
+        sources.push(ParamSource::new("port", port, port_source));
         let unix_host = host.contains("/");
         if admin || unix_host {
             let prefix = if unix_host {
@@ -312,6 +861,12 @@ fn conn_params(tmp: &RawOptions) -> anyhow::Result<Builder> {
                     format!("{}/.s.EDGEDB.{}", prefix, port)
                 }
             };
+            let source = if admin {
+                Source::Flag("--admin")
+            } else {
+                Source::Default
+            };
+            sources.push(ParamSource::new("unix socket", &path, source));
             conn_params.unix_addr(path);
         } else {
             conn_params.tcp_addr(host, port);
@@ -0,0 +1,47 @@
+//! Output policy shared by the formatter, tables, spinners and prompts
+//!
+//! In `plain` style no colors, box-drawing characters, spinners or
+//! progress bars are used. Status is reported as separate lines of text
+//! instead, which works better for screen readers and dumb logs.
+use std::str::FromStr;
+
+use once_cell::sync::OnceCell;
+
+
+static STYLE: OnceCell<OutputStyle> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStyle {
+    Default,
+    Plain,
+}
+
+impl FromStr for OutputStyle {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<OutputStyle> {
+        match s {
+            "default" => Ok(OutputStyle::Default),
+            "plain" => Ok(OutputStyle::Plain),
+            _ => anyhow::bail!("Unsupported output style, \
+                options: `default`, `plain`"),
+        }
+    }
+}
+
+pub fn init(style: OutputStyle) {
+    STYLE.set(style).ok();
+}
+
+pub fn is_plain() -> bool {
+    STYLE.get() == Some(&OutputStyle::Plain)
+}
+
+/// Whether colors should be used when writing to the stream
+pub fn colors(stream: atty::Stream) -> bool {
+    !is_plain() && atty::is(stream)
+}
+
+/// Whether spinners and progress bars can be drawn on the stream
+pub fn animations(stream: atty::Stream) -> bool {
+    !is_plain() && atty::is(stream)
+}
@@ -1,14 +1,18 @@
 use edgedb_protocol::value::Value::{self, *};
 
 
-pub fn format_row(v: &Value) -> Result<String, anyhow::Error> {
+/// Formats row as tab-separated fields, missing fields are printed as
+/// `null_display`
+pub fn format_row(v: &Value, null_display: &str)
+    -> Result<String, anyhow::Error>
+{
     match v {
         Object { shape, fields } => {
             Ok(shape.elements.iter().zip(fields)
                 .filter(|(s, _)| !s.flag_implicit)
                 .map(|(_, v)| match v {
                     Some(v) => value_to_string(v),
-                    None => Ok(String::new()),
+                    None => Ok(null_display.into()),
                 })
                 .collect::<Result<Vec<_>,_>>()?.join("\t"))
         }
@@ -0,0 +1,51 @@
+//! Showing long text through the pager
+//!
+//! The pager is taken from `EDGEDB_PAGER` or `PAGER` and defaults to
+//! `less -R`.
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+
+fn command() -> Command {
+    let pager = env::var("EDGEDB_PAGER")
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| String::from("less -R"));
+    let mut items = pager.split_whitespace();
+    let mut cmd = Command::new(items.next().unwrap_or("less"));
+    cmd.args(items);
+    cmd
+}
+
+/// Pipes the text into the pager and waits until it exits
+pub fn run(text: &str) -> anyhow::Result<()> {
+    let mut cmd = command();
+    cmd.stdin(Stdio::piped());
+    let mut child = cmd.spawn()?;
+    let mut childin = child.stdin.take().expect("stdin is piped");
+    // the user may quit the pager before reading everything
+    childin.write_all(text.as_bytes()).ok();
+    drop(childin);
+    let res = child.wait()?;
+    if res.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("pager exited with: {}", res))
+    }
+}
+
+/// Prints the text, using the pager if it doesn't fit on the terminal
+pub fn page(text: &str) -> anyhow::Result<()> {
+    let fits = match term_size::dimensions_stdout() {
+        Some((_w, h)) if atty::is(atty::Stream::Stdin) => {
+            text.lines().count() < h
+        }
+        // not a terminal
+        _ => true,
+    };
+    if fits {
+        print!("{}", text);
+        return Ok(());
+    }
+    run(text)
+}
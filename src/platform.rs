@@ -18,6 +18,30 @@ pub fn get_current_uid() -> Uid {
     unsafe { libc::geteuid() }
 }
 
+/// Makes Windows console interpret ANSI escape sequences (colors)
+#[cfg(windows)]
+pub fn enable_ansi_escapes() {
+    use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::winbase::{STD_OUTPUT_HANDLE, STD_ERROR_HANDLE};
+    use winapi::um::wincon::ENABLE_VIRTUAL_TERMINAL_PROCESSING;
+
+    for &stream in &[STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+        unsafe {
+            let handle = GetStdHandle(stream);
+            let mut mode = 0;
+            if GetConsoleMode(handle, &mut mode) != 0 {
+                SetConsoleMode(handle,
+                    mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn enable_ansi_escapes() {
+}
+
 pub fn home_dir() -> anyhow::Result<PathBuf> {
     dirs::home_dir()
     .ok_or_else(|| anyhow::anyhow!("Cannot determine home directory"))
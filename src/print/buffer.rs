@@ -9,6 +9,7 @@ use snafu::{IntoError, Error, ErrorCompat};
 use crate::print::Printer;
 use crate::print::stream::Output;
 use crate::print::formatter::ColorfulExt;
+use crate::print::width::grapheme_width;
 
 use Delim::*;
 
@@ -96,7 +97,7 @@ impl<T: Output> Printer<T> {
                 }
                 self.column = 0;
             } else {
-                self.column += 1;
+                self.column += grapheme_width(g);
             }
         }
         if self.flow && self.column > self.max_width {
@@ -186,7 +187,7 @@ impl<T: Output> Printer<T> {
         } else {
             self.write("...".clear())?;
             self.write(format!(" (further results hidden \
-                `\\set limit {limit}`)\n",
+                `\\set implicit-limit {limit}`)\n",
                 limit=self.max_items.unwrap_or(0))
                 .dark_gray())?;
         }
@@ -0,0 +1,169 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use colorful::Colorful;
+use serde_json::{Value, Map};
+
+
+#[derive(Debug, PartialEq)]
+pub enum Change<'a> {
+    Added(&'a Value),
+    Removed(&'a Value),
+    Modified(&'a Value, &'a Value),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Difference<'a> {
+    pub path: String,
+    pub change: Change<'a>,
+}
+
+
+fn object_id(value: &Value) -> Option<&str> {
+    value.as_object()
+        .and_then(|obj| obj.get("id"))
+        .and_then(|id| id.as_str())
+}
+
+fn all_have_ids(items: &[Value]) -> bool {
+    items.iter().all(|item| object_id(item).is_some())
+}
+
+fn diff_objects<'a>(old: &'a Map<String, Value>, new: &'a Map<String, Value>,
+    path: &mut String, result: &mut Vec<Difference<'a>>)
+{
+    let level = path.len();
+    let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+    for key in keys {
+        write!(path, ".{}", key).expect("formatting succeeds");
+        match (old.get(key), new.get(key)) {
+            (Some(a), Some(b)) => _diff(a, b, path, result),
+            (Some(a), None) => result.push(Difference {
+                path: path.clone(),
+                change: Change::Removed(a),
+            }),
+            (None, Some(b)) => result.push(Difference {
+                path: path.clone(),
+                change: Change::Added(b),
+            }),
+            (None, None) => unreachable!(),
+        }
+        path.truncate(level);
+    }
+}
+
+fn diff_by_id<'a>(old: &'a [Value], new: &'a [Value],
+    path: &mut String, result: &mut Vec<Difference<'a>>)
+{
+    let level = path.len();
+    for a in old {
+        let id = object_id(a).expect("checked by all_have_ids");
+        write!(path, "[id={}]", id).expect("formatting succeeds");
+        match new.iter().find(|b| object_id(b) == Some(id)) {
+            Some(b) => _diff(a, b, path, result),
+            None => result.push(Difference {
+                path: path.clone(),
+                change: Change::Removed(a),
+            }),
+        }
+        path.truncate(level);
+    }
+    for b in new {
+        let id = object_id(b).expect("checked by all_have_ids");
+        if !old.iter().any(|a| object_id(a) == Some(id)) {
+            write!(path, "[id={}]", id).expect("formatting succeeds");
+            result.push(Difference {
+                path: path.clone(),
+                change: Change::Added(b),
+            });
+            path.truncate(level);
+        }
+    }
+}
+
+fn diff_by_index<'a>(old: &'a [Value], new: &'a [Value],
+    path: &mut String, result: &mut Vec<Difference<'a>>)
+{
+    let level = path.len();
+    for idx in 0..old.len().max(new.len()) {
+        write!(path, "[{}]", idx).expect("formatting succeeds");
+        match (old.get(idx), new.get(idx)) {
+            (Some(a), Some(b)) => _diff(a, b, path, result),
+            (Some(a), None) => result.push(Difference {
+                path: path.clone(),
+                change: Change::Removed(a),
+            }),
+            (None, Some(b)) => result.push(Difference {
+                path: path.clone(),
+                change: Change::Added(b),
+            }),
+            (None, None) => unreachable!(),
+        }
+        path.truncate(level);
+    }
+}
+
+fn _diff<'a>(old: &'a Value, new: &'a Value,
+    path: &mut String, result: &mut Vec<Difference<'a>>)
+{
+    use serde_json::Value::*;
+
+    match (old, new) {
+        (Object(a), Object(b)) => diff_objects(a, b, path, result),
+        (Array(a), Array(b)) if all_have_ids(a) && all_have_ids(b) => {
+            diff_by_id(a, b, path, result)
+        }
+        (Array(a), Array(b)) => diff_by_index(a, b, path, result),
+        (a, b) if a == b => {}
+        (a, b) => result.push(Difference {
+            path: path.clone(),
+            change: Change::Modified(a, b),
+        }),
+    }
+}
+
+/// Structural difference between two JSON values
+///
+/// Arrays of objects which all have an `id` are matched by id, so
+/// reordered results are not reported as changes. Other arrays are
+/// compared element by element.
+pub fn diff<'a>(old: &'a Value, new: &'a Value) -> Vec<Difference<'a>> {
+    let mut result = Vec::new();
+    _diff(old, new, &mut String::new(), &mut result);
+    return result;
+}
+
+fn json(value: &Value) -> String {
+    serde_json::to_string(value).expect("can serialize json")
+}
+
+pub fn format_diff(diffs: &[Difference], colors: bool) -> String {
+    let mut buf = String::with_capacity(diffs.len() * 32);
+    for item in diffs {
+        let path = if item.path.is_empty() { "." } else { &item.path[..] };
+        let mut lines = Vec::with_capacity(2);
+        match item.change {
+            Change::Removed(a) => {
+                lines.push((format!("- {}: {}", path, json(a)), false));
+            }
+            Change::Added(b) => {
+                lines.push((format!("+ {}: {}", path, json(b)), true));
+            }
+            Change::Modified(a, b) => {
+                lines.push((format!("- {}: {}", path, json(a)), false));
+                lines.push((format!("+ {}: {}", path, json(b)), true));
+            }
+        }
+        for (line, added) in lines {
+            if !colors {
+                buf.push_str(&line);
+            } else if added {
+                write!(&mut buf, "{}", line.green()).unwrap();
+            } else {
+                write!(&mut buf, "{}", line.red()).unwrap();
+            }
+            buf.push('\n');
+        }
+    }
+    return buf;
+}
@@ -0,0 +1,95 @@
+//! User-defined display rules for scalar values
+//!
+//! Rules are configured in the `[display]` section of `cli.toml` and are
+//! looked up by the name of the scalar type. Values of custom scalars are
+//! sent by the server as their base type, so they use the rule of the base
+//! type (e.g. `std::decimal` for a `money` scalar extending it).
+use std::collections::BTreeMap;
+
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+
+use edgedb_protocol::value::Value;
+
+
+/// Types which can have a display rule
+pub const SCALAR_TYPES: &[&str] = &[
+    "std::uuid",
+    "std::str",
+    "std::int16",
+    "std::int32",
+    "std::int64",
+    "std::float32",
+    "std::float64",
+    "std::bigint",
+    "std::decimal",
+    "std::bool",
+    "std::datetime",
+    "std::duration",
+    "cal::local_datetime",
+    "cal::local_date",
+    "cal::local_time",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct DisplayRule {
+    /// Text to display with `{value}` replaced by the value
+    pub template: Option<String>,
+    /// Substrings replaced in the value before it's put into template
+    pub replace: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DisplayRules {
+    rules: BTreeMap<String, DisplayRule>,
+}
+
+impl DisplayRule {
+    fn apply(&self, mut text: String) -> String {
+        for (old, new) in &self.replace {
+            text = text.replace(old, new);
+        }
+        match &self.template {
+            Some(template) => template.replace("{value}", &text),
+            None => text,
+        }
+    }
+}
+
+impl DisplayRules {
+    pub fn new(rules: BTreeMap<String, DisplayRule>) -> DisplayRules {
+        DisplayRules { rules }
+    }
+    /// Returns value formatted by the user-defined rule (if there is one)
+    pub(in crate::print) fn apply(&self, value: &Value) -> Option<String> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let (type_name, text) = scalar_text(value)?;
+        let rule = self.rules.get(type_name)?;
+        Some(rule.apply(text))
+    }
+}
+
+fn scalar_text(value: &Value) -> Option<(&'static str, String)> {
+    use Value as V;
+    let result = match value {
+        V::Uuid(u) => ("std::uuid", u.to_string()),
+        V::Str(s) => ("std::str", s.clone()),
+        V::Int16(v) => ("std::int16", v.to_string()),
+        V::Int32(v) => ("std::int32", v.to_string()),
+        V::Int64(v) => ("std::int64", v.to_string()),
+        V::Float32(v) => ("std::float32", v.to_string()),
+        V::Float64(v) => ("std::float64", v.to_string()),
+        V::BigInt(v) => ("std::bigint", BigInt::from(v).to_string()),
+        V::Decimal(v) => ("std::decimal", BigDecimal::from(v).to_string()),
+        V::Bool(v) => ("std::bool", v.to_string()),
+        V::Datetime(t) => ("std::datetime", format!("{:?}", t)),
+        V::Duration(d) => ("std::duration", d.to_string()),
+        V::LocalDatetime(t) => ("cal::local_datetime", format!("{:?}", t)),
+        V::LocalDate(d) => ("cal::local_date", format!("{:?}", d)),
+        V::LocalTime(t) => ("cal::local_time", format!("{:?}", t)),
+        _ => return None,
+    };
+    Some(result)
+}
@@ -1,10 +1,10 @@
 use crate::print::stream::Output;
-use crate::print::Printer;
+use crate::print::{Printer, VectorDisplay, NumberFormat};
 
 use colorful::{Colorful, core::color_string::CString};
 
 use crate::print::buffer::Result;
-
+use crate::print::display::DisplayRules;
 
 pub(in crate::print) trait ColorfulExt {
     fn clear(&self) -> CString;
@@ -23,6 +23,8 @@ pub trait Formatter {
     fn nil(&mut self) -> Result<Self::Error>;
     fn typed<S: ToString>(&mut self, typ: &str, s: S) -> Result<Self::Error>;
     fn error<S: ToString>(&mut self, typ: &str, s: S) -> Result<Self::Error>;
+    fn vector(&mut self, dim: usize, norm: f64, preview: &[String])
+        -> Result<Self::Error>;
     fn set<F>(&mut self, f: F) -> Result<Self::Error>
         where F: FnMut(&mut Self) -> Result<Self::Error>;
     fn tuple<F>(&mut self, f: F) -> Result<Self::Error>
@@ -43,6 +45,9 @@ pub trait Formatter {
     fn tuple_field(&mut self, f: &str) -> Result<Self::Error>;
 
     fn implicit_properties(&self) -> bool;
+    fn truncate_vectors(&self) -> bool;
+    fn display_rules(&self) -> &DisplayRules;
+    fn number_format(&self) -> NumberFormat;
     fn expand_strings(&self) -> bool;
     fn max_items(&self) -> Option<usize>;
 }
@@ -55,7 +60,8 @@ impl<T: Output> Formatter for Printer<T> {
     }
     fn nil(&mut self) -> Result<Self::Error> {
         self.delimit()?;
-        self.write("{}".dark_gray())
+        let text = self.null_display.as_deref().unwrap_or("{}").to_string();
+        self.write(text.dark_gray())
     }
     fn typed<S: ToString>(&mut self, typ: &str, s: S) -> Result<Self::Error> {
         self.delimit()?;
@@ -69,6 +75,15 @@ impl<T: Output> Formatter for Printer<T> {
         self.write(format!("'{}'", s.to_string().escape_default()).red())?;
         Ok(())
     }
+    fn vector(&mut self, dim: usize, norm: f64, preview: &[String])
+        -> Result<Self::Error>
+    {
+        self.delimit()?;
+        self.write(format!("<vector dim={} norm={:.4}", dim, norm).red())?;
+        self.write(format!(" [{}, …]", preview.join(", ")).green())?;
+        self.write(">".red())?;
+        Ok(())
+    }
     fn set<F>(&mut self, f: F) -> Result<Self::Error>
         where F: FnMut(&mut Self) -> Result<Self::Error>
     {
@@ -144,6 +159,18 @@ impl<T: Output> Formatter for Printer<T> {
         self.implicit_properties
     }
 
+    fn truncate_vectors(&self) -> bool {
+        self.vector_display == VectorDisplay::Truncated
+    }
+
+    fn display_rules(&self) -> &DisplayRules {
+        &self.display_rules
+    }
+
+    fn number_format(&self) -> NumberFormat {
+        self.number_format
+    }
+
     fn expand_strings(&self) -> bool {
         self.expand_strings
     }
@@ -14,6 +14,11 @@ mod buffer;
 mod stream;
 mod formatter;
 pub mod style;
+pub mod diff;
+pub mod display;
+mod number;
+mod spill;
+mod width;
 #[cfg(test)] mod tests;
 
 pub(in crate::print) use native::FormatExt;
@@ -21,6 +26,12 @@ pub(in crate::print) use formatter::Formatter;
 use formatter::ColorfulExt;
 use buffer::{Exception, WrapErr, UnwrapExc, Delim};
 use stream::Output;
+pub use spill::SpillBuffer;
+pub use width::str_width;
+use display::DisplayRules;
+pub use number::NumberFormat;
+
+use crate::output_style;
 
 
 #[derive(Snafu, Debug)]
@@ -39,6 +50,24 @@ pub struct Config {
     pub max_width: Option<usize>,
     pub implicit_properties: bool,
     pub max_items: Option<usize>,
+    /// Marker printed for missing values instead of `{}`
+    pub null_display: Option<String>,
+    pub vector_display: VectorDisplay,
+    pub display_rules: DisplayRules,
+    pub number_format: NumberFormat,
+    /// Size of output, or of rows kept for `\gexec`, held in memory
+    /// before it's moved to a temporary file
+    pub spill_threshold: Option<usize>,
+}
+
+pub const DEFAULT_SPILL_THRESHOLD: usize = 64 << 20;
+
+/// How long arrays of floats (embeddings) are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorDisplay {
+    Full,
+    /// Only first few components, dimensionality and norm are shown
+    Truncated,
 }
 
 
@@ -50,6 +79,10 @@ pub(in crate::print) struct Printer<T> {
     max_width: usize,
     implicit_properties: bool,
     max_items: Option<usize>,
+    null_display: Option<String>,
+    vector_display: VectorDisplay,
+    display_rules: DisplayRules,
+    number_format: NumberFormat,
     trailing_comma: bool,
 
     // state
@@ -75,6 +108,11 @@ impl Config {
             max_width: None,
             implicit_properties: false,
             max_items: None,
+            null_display: None,
+            vector_display: VectorDisplay::Full,
+            display_rules: DisplayRules::default(),
+            number_format: NumberFormat::Default,
+            spill_threshold: Some(DEFAULT_SPILL_THRESHOLD),
         }
     }
     #[allow(dead_code)]
@@ -90,6 +128,10 @@ impl Config {
         self.colors = Some(value);
         self
     }
+    pub fn vector_display(&mut self, value: VectorDisplay) -> &mut Config {
+        self.vector_display = value;
+        self
+    }
     #[allow(dead_code)]
     pub fn expand_strings(&mut self, value: bool) -> &mut Config {
         self.expand_strings = value;
@@ -97,8 +139,28 @@ impl Config {
     }
 }
 
+impl std::str::FromStr for VectorDisplay {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<VectorDisplay, anyhow::Error> {
+        match s {
+            "full" => Ok(VectorDisplay::Full),
+            "truncated" => Ok(VectorDisplay::Truncated),
+            _ => Err(anyhow::anyhow!("unsupported vector display {:?}", s)),
+        }
+    }
+}
+
+impl VectorDisplay {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorDisplay::Full => "full",
+            VectorDisplay::Truncated => "truncated",
+        }
+    }
+}
+
 pub fn completion(res: &Bytes) {
-    if atty::is(atty::Stream::Stderr) {
+    if output_style::colors(atty::Stream::Stderr) {
         eprintln!("{}",
             format!("OK: {}", String::from_utf8_lossy(&res[..]))
                 .dark_gray().bold());
@@ -190,10 +252,25 @@ pub async fn native_to_stdout<S, I, E>(rows: S, config: &Config)
         term_size::dimensions_stdout().map(|(w, _h)| w).unwrap_or(80)
     });
     let colors = config.colors
-            .unwrap_or_else(|| atty::is(atty::Stream::Stdout));
+            .unwrap_or_else(|| output_style::colors(atty::Stream::Stdout));
     _native_format(rows, config, w, colors, Stdout {}).await
 }
 
+pub async fn native_to_string<S, I, E>(rows: S, config: &Config)
+    -> Result<String, PrintError<E, Infallible>>
+    where S: Stream<Item=Result<I, E>> + Send + Unpin,
+          I: FormatExt,
+          E: fmt::Debug + Error + 'static,
+{
+    let mut out = String::new();
+    _native_format(rows, config,
+        config.max_width.unwrap_or(80),
+        config.colors.unwrap_or(false),
+        &mut out,
+    ).await?;
+    Ok(out)
+}
+
 async fn _native_format<S, I, E, O>(mut rows: S, config: &Config,
     max_width: usize, colors: bool, output: O)
     -> Result<(), PrintError<E, O::Error>>
@@ -210,6 +287,10 @@ async fn _native_format<S, I, E, O>(mut rows: S, config: &Config,
         max_width,
         implicit_properties: config.implicit_properties,
         max_items: config.max_items,
+        null_display: config.null_display.clone(),
+        vector_display: config.vector_display,
+        display_rules: config.display_rules.clone(),
+        number_format: config.number_format,
         trailing_comma: true,
 
         buffer: String::with_capacity(8192),
@@ -237,9 +318,9 @@ async fn _native_format<S, I, E, O>(mut rows: S, config: &Config,
     Ok(())
 }
 
-fn format_rows_str<I: FormatExt>(prn: &mut Printer<&mut String>, items: &[I],
-    open: &str, close: &str, reopen: bool)
-    -> buffer::Result<Infallible>
+fn format_rows_str<I: FormatExt, O: Output>(prn: &mut Printer<O>,
+    items: &[I], open: &str, close: &str, reopen: bool)
+    -> buffer::Result<O::Error>
 {
     if reopen {
         prn.reopen_block()?;
@@ -255,10 +336,10 @@ fn format_rows_str<I: FormatExt>(prn: &mut Printer<&mut String>, items: &[I],
     Ok(())
 }
 
-pub fn json_to_string<I: FormatExt>(items: &[I], config: &Config)
-    -> Result<String, Infallible>
+fn _json_format<I: FormatExt, O: Output>(items: &[I], config: &Config,
+    output: O)
+    -> Result<(), O::Error>
 {
-    let mut out = String::new();
     let mut prn = Printer {
         colors: config.colors.unwrap_or(false),
         indent: config.indent,
@@ -266,10 +347,14 @@ pub fn json_to_string<I: FormatExt>(items: &[I], config: &Config)
         max_width: config.max_width.unwrap_or(80),
         implicit_properties: config.implicit_properties,
         max_items: config.max_items,
+        null_display: config.null_display.clone(),
+        vector_display: config.vector_display,
+        display_rules: config.display_rules.clone(),
+        number_format: config.number_format,
         trailing_comma: false,
 
         buffer: String::with_capacity(8192),
-        stream: &mut out,
+        stream: output,
         delim: Delim::None,
         flow: false,
         committed: 0,
@@ -286,9 +371,26 @@ pub fn json_to_string<I: FormatExt>(items: &[I], config: &Config)
         Err(Exception::Error(e)) => return Err(e),
     };
     prn.end().unwrap_exc()?;
+    Ok(())
+}
+
+pub fn json_to_string<I: FormatExt>(items: &[I], config: &Config)
+    -> Result<String, Infallible>
+{
+    let mut out = String::new();
+    _json_format(items, config, &mut out)?;
     Ok(out)
 }
 
+/// Same as `json_to_string` but appends to the buffer kept on disk when
+/// it's large
+pub fn json_to_spill<I: FormatExt>(items: &[I], config: &Config,
+    out: &mut SpillBuffer)
+    -> Result<(), io::Error>
+{
+    _json_format(items, config, out)
+}
+
 pub fn json_item_to_string<I: FormatExt>(item: &I, config: &Config)
     -> Result<String, Infallible>
 {
@@ -300,6 +402,10 @@ pub fn json_item_to_string<I: FormatExt>(item: &I, config: &Config)
         max_width: config.max_width.unwrap_or(80),
         implicit_properties: config.implicit_properties,
         max_items: config.max_items,
+        null_display: config.null_display.clone(),
+        vector_display: config.vector_display,
+        display_rules: config.display_rules.clone(),
+        number_format: config.number_format,
         trailing_comma: false,
 
         buffer: String::with_capacity(8192),
@@ -8,6 +8,11 @@ use edgedb_protocol::value::Value;
 use crate::print::formatter::Formatter;
 use crate::print::buffer::Result;
 
+/// Float arrays shorter than this are never displayed as vectors
+const MIN_VECTOR_DIM: usize = 16;
+/// Number of components shown for truncated vectors
+const VECTOR_PREVIEW: usize = 6;
+
 
 pub trait FormatExt {
     fn format<F: Formatter>(&self, prn: &mut F) -> Result<F::Error>;
@@ -91,6 +96,9 @@ fn format_decimal(value: BigDecimal) -> String {
 impl FormatExt for Value {
     fn format<F: Formatter>(&self, prn: &mut F) -> Result<F::Error> {
         use Value as V;
+        if let Some(text) = prn.display_rules().apply(self) {
+            return prn.const_scalar(text);
+        }
         match self {
             V::Nothing => prn.const_scalar("Nothing"),
             V::Uuid(u) => prn.const_scalar(u),
@@ -98,13 +106,13 @@ impl FormatExt for Value {
                 prn.const_scalar(format_string(s, prn.expand_strings()))
             }
             V::Bytes(b) => prn.const_scalar(format_bytes(b)),
-            V::Int16(v) => prn.const_scalar(v),
-            V::Int32(v) => prn.const_scalar(v),
-            V::Int64(v) => prn.const_scalar(v),
-            V::Float32(v) => prn.const_scalar(v),
-            V::Float64(v) => prn.const_scalar(v),
-            V::BigInt(v) => prn.const_scalar(format_bigint(v.into())),
-            V::Decimal(v) => prn.const_scalar(format_decimal(v.into())),
+            V::Int16(v) => format_number(prn, v.to_string()),
+            V::Int32(v) => format_number(prn, v.to_string()),
+            V::Int64(v) => format_number(prn, v.to_string()),
+            V::Float32(v) => format_number(prn, v.to_string()),
+            V::Float64(v) => format_number(prn, v.to_string()),
+            V::BigInt(v) => format_number(prn, format_bigint(v.into())),
+            V::Decimal(v) => format_number(prn, format_decimal(v.into())),
             V::Bool(v) => prn.const_scalar(v),
             V::Datetime(t) => prn.typed("datetime", format!("{:?}", t)),
             V::LocalDatetime(t)
@@ -196,30 +204,73 @@ impl FormatExt for Value {
                     Ok(())
                 })
             }
-            V::Array(items) => {
-                prn.array(|prn| {
-                    if let Some(limit) = prn.max_items() {
-                        for item in &items[..min(limit, items.len())] {
-                            item.format(prn)?;
-                            prn.comma()?;
-                        }
-                        if items.len() > limit {
-                            prn.ellipsis()?;
-                        }
-                    } else {
-                        for item in items {
-                            item.format(prn)?;
-                            prn.comma()?;
-                        }
+            V::Array(items) if prn.truncate_vectors() => {
+                match vector_norm(items) {
+                    Some(norm) => {
+                        let preview = items.iter().take(VECTOR_PREVIEW)
+                            .map(|item| match item {
+                                Value::Float32(v) => v.to_string(),
+                                Value::Float64(v) => v.to_string(),
+                                _ => unreachable!(),
+                            })
+                            .collect::<Vec<_>>();
+                        prn.vector(items.len(), norm, &preview)
                     }
-                    Ok(())
-                })
+                    None => format_array(items, prn),
+                }
             }
+            V::Array(items) => format_array(items, prn),
             V::Enum(v) => prn.const_scalar(&**v),
         }
     }
 }
 
+fn format_number<F: Formatter>(prn: &mut F, text: String)
+    -> Result<F::Error>
+{
+    let text = prn.number_format().apply(&text);
+    prn.const_scalar(text)
+}
+
+/// Returns euclidean norm of an array which looks like an embedding vector
+fn vector_norm(items: &[Value]) -> Option<f64> {
+    if items.len() < MIN_VECTOR_DIM {
+        return None;
+    }
+    let mut sum = 0.0;
+    for item in items {
+        let value = match item {
+            Value::Float32(v) => *v as f64,
+            Value::Float64(v) => *v,
+            _ => return None,
+        };
+        sum += value * value;
+    }
+    Some(sum.sqrt())
+}
+
+fn format_array<F: Formatter>(items: &[Value], prn: &mut F)
+    -> Result<F::Error>
+{
+    prn.array(|prn| {
+        if let Some(limit) = prn.max_items() {
+            for item in &items[..min(limit, items.len())] {
+                item.format(prn)?;
+                prn.comma()?;
+            }
+            if items.len() > limit {
+                prn.ellipsis()?;
+            }
+        } else {
+            for item in items {
+                item.format(prn)?;
+                prn.comma()?;
+            }
+        }
+        Ok(())
+    })
+}
+
 impl FormatExt for Option<Value> {
     fn format<F: Formatter>(&self, prn: &mut F) -> Result<F::Error> {
         match self {
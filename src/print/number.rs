@@ -0,0 +1,100 @@
+use std::env;
+
+use once_cell::sync::Lazy;
+
+
+static LOCALE_SEPARATORS: Lazy<(&str, &str)> = Lazy::new(locale_separators);
+
+/// Digit grouping for numbers in human-readable output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat {
+    Default,
+    /// `1_234_567.5`
+    Underscore,
+    /// `1,234,567.5`
+    Comma,
+    /// Separators are chosen by `LC_ALL`, `LC_NUMERIC` or `LANG`
+    Locale,
+}
+
+impl std::str::FromStr for NumberFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<NumberFormat, anyhow::Error> {
+        match s {
+            "default" => Ok(NumberFormat::Default),
+            "underscore" => Ok(NumberFormat::Underscore),
+            "comma" => Ok(NumberFormat::Comma),
+            "locale" => Ok(NumberFormat::Locale),
+            _ => Err(anyhow::anyhow!("unsupported number format {:?}", s)),
+        }
+    }
+}
+
+fn locale_separators() -> (&'static str, &'static str) {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let lang = locale.split(|c| c == '_' || c == '.' || c == '-')
+        .next().unwrap_or("");
+    match lang {
+        "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr"
+        => (".", ","),
+        "fr" | "ru" | "uk" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb"
+        => ("\u{00A0}", ","),
+        _ => (",", "."),
+    }
+}
+
+impl NumberFormat {
+    pub fn as_str(&self) -> &'static str {
+        use NumberFormat::*;
+        match self {
+            Default => "default",
+            Underscore => "underscore",
+            Comma => "comma",
+            Locale => "locale",
+        }
+    }
+    fn separators(&self) -> Option<(&'static str, &'static str)> {
+        use NumberFormat::*;
+        match self {
+            Default => None,
+            Underscore => Some(("_", ".")),
+            Comma => Some((",", ".")),
+            Locale => Some(*LOCALE_SEPARATORS),
+        }
+    }
+    /// Inserts thousands separators into a formatted number
+    ///
+    /// Sign, fractional part, exponent and suffix (like `n` of bigint) are
+    /// kept as is, only the decimal point may be replaced.
+    pub fn apply(&self, number: &str) -> String {
+        let (thousands, point) = match self.separators() {
+            Some(seps) => seps,
+            None => return number.into(),
+        };
+        let (sign, rest) = if number.starts_with('-') {
+            number.split_at(1)
+        } else {
+            ("", number)
+        };
+        let int_len = rest.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (int_part, mut tail) = rest.split_at(int_len);
+        let mut result = String::with_capacity(number.len() + int_len / 3);
+        result.push_str(sign);
+        for (idx, c) in int_part.chars().enumerate() {
+            if idx > 0 && (int_len - idx) % 3 == 0 {
+                result.push_str(thousands);
+            }
+            result.push(c);
+        }
+        if tail.starts_with('.') {
+            result.push_str(point);
+            tail = &tail[1..];
+        }
+        result.push_str(tail);
+        result
+    }
+}
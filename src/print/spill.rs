@@ -0,0 +1,103 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use async_std::io::prelude::WriteExt;
+
+use crate::print::stream::Output;
+
+
+/// Output buffer that moves its contents to a temporary file when it
+/// grows over the threshold
+///
+/// Data is written as it's produced, so only the part under the threshold
+/// is kept in memory.
+#[derive(Debug)]
+pub struct SpillBuffer {
+    memory: String,
+    file: Option<File>,
+    threshold: Option<usize>,
+}
+
+impl SpillBuffer {
+    pub fn new(threshold: Option<usize>) -> SpillBuffer {
+        SpillBuffer {
+            memory: String::new(),
+            file: None,
+            threshold,
+        }
+    }
+    pub fn push_str(&mut self, data: &str) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            return file.write_all(data.as_bytes());
+        }
+        self.memory.push_str(data);
+        match self.threshold {
+            Some(threshold) if self.memory.len() > threshold => {
+                log::debug!("Spilling {} bytes of output to disk",
+                            self.memory.len());
+                let mut file = tempfile::tempfile()?;
+                file.write_all(self.memory.as_bytes())?;
+                self.memory = String::new();
+                self.file = Some(file);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    /// Appends a string which is read back by `into_lines`
+    pub fn push_line(&mut self, line: &str) -> io::Result<()> {
+        let encoded = serde_json::to_string(line)
+            .expect("string can be serialized");
+        self.push_str(&encoded)?;
+        self.push_str("\n")
+    }
+    pub fn into_lines(self) -> io::Result<Vec<String>> {
+        let text = match self.file {
+            Some(mut file) => {
+                file.seek(SeekFrom::Start(0))?;
+                let mut text = String::new();
+                file.read_to_string(&mut text)?;
+                text
+            }
+            None => self.memory,
+        };
+        text.lines()
+            .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+            .collect()
+    }
+    pub async fn write_to_stdout(self) -> io::Result<()> {
+        let mut out = async_std::io::stdout();
+        match self.file {
+            Some(mut file) => {
+                file.seek(SeekFrom::Start(0))?;
+                let mut file = async_std::fs::File::from(file);
+                async_std::io::copy(&mut file, &mut out).await?;
+            }
+            None => out.write_all(self.memory.as_bytes()).await?,
+        }
+        out.flush().await
+    }
+}
+
+impl<'a> Output for &'a mut SpillBuffer {
+    type Error = io::Error;
+    fn write(&mut self, data: &str) -> Result<(), io::Error> {
+        self.push_str(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SpillBuffer;
+
+    #[test]
+    fn lines() {
+        let mut buf = SpillBuffer::new(Some(16));
+        buf.push_line("SELECT 1").unwrap();
+        assert!(buf.file.is_none());
+        buf.push_line("SELECT 'multi\nline'").unwrap();
+        assert!(buf.file.is_some());
+        assert_eq!(buf.into_lines().unwrap(),
+                   vec!["SELECT 1", "SELECT 'multi\nline'"]);
+    }
+}
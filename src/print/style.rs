@@ -1,8 +1,20 @@
 use std::fmt::Write;
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use colorful::{Colorful, RGB, Style as TermStyle};
+use once_cell::sync::OnceCell;
+
+
+static COLOR_THEME: OnceCell<ColorTheme> = OnceCell::new();
+
+/// Colors of highlighted output, chosen by terminal background
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorTheme {
+    Dark,
+    Light,
+}
 
 
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
@@ -82,7 +94,62 @@ impl<T: AsRef<str>> AddStyle for T {
     }
 }
 
+impl FromStr for ColorTheme {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<ColorTheme> {
+        match s {
+            "dark" => Ok(ColorTheme::Dark),
+            "light" => Ok(ColorTheme::Light),
+            _ => anyhow::bail!("Unsupported color theme, \
+                options: `dark`, `light`"),
+        }
+    }
+}
+
+impl ColorTheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorTheme::Dark => "dark",
+            ColorTheme::Light => "light",
+        }
+    }
+}
+
+pub fn init_theme(theme: ColorTheme) {
+    COLOR_THEME.set(theme).ok();
+}
+
 impl Styler {
+    /// Styler for the `color-theme` setting
+    pub fn configured() -> Styler {
+        match COLOR_THEME.get() {
+            Some(ColorTheme::Light) => Styler::light_256(),
+            Some(ColorTheme::Dark) | None => Styler::dark_256(),
+        }
+    }
+    pub fn light_256() -> Styler {
+        use self::Style::*;
+        use colorful::Style::*;
+
+        let mut t = HashMap::new();
+        t.insert(Decorator, Item(RGB::new(0x87, 0x5f, 0x00), None));
+        t.insert(String, Item(RGB::new(0x00, 0x87, 0x00), None));
+        t.insert(Number, Item(RGB::new(0xaf, 0x00, 0x00), None));
+        t.insert(Constant, Item(RGB::new(0x00, 0x5f, 0xaf), None));
+        t.insert(Punctuation, Item(RGB::new(0x5f, 0x5f, 0x00), None));
+        t.insert(Keyword, Item(RGB::new(0x00, 0x5f, 0xaf), None));
+        t.insert(DeclName, Item(RGB::new(0x87, 0x00, 0xaf), Some(Bold)));
+        t.insert(Tag, Item(RGB::new(0x00, 0x5f, 0xaf), None));
+        t.insert(Comment, Item(RGB::new(0x8a, 0x8a, 0x8a), None));
+        t.insert(BackslashCommand,
+            Item(RGB::new(0x87, 0x00, 0xaf), Some(Bold)));
+        t.insert(Error,
+            Item(RGB::new(0xd7, 0x00, 0x00), Some(Bold)));
+
+        return Styler(Arc::new(Theme {
+            items: t,
+        }));
+    }
     pub fn dark_256() -> Styler {
         use self::Style::*;
         use colorful::Style::*;
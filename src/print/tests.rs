@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::convert::Infallible;
 use std::convert::TryFrom;
 use std::str::FromStr;
@@ -12,6 +13,7 @@ use edgedb_protocol::model::Datetime;
 use edgedb_protocol::codec::{ObjectShape, ShapeElement};
 use crate::print::{self, _native_format, Config};
 use crate::print::native::FormatExt;
+use crate::print::display::{DisplayRule, DisplayRules};
 
 struct UnfusedStream<'a, I>(Option<&'a [I]>);
 
@@ -58,6 +60,11 @@ fn test_format<I: FormatExt + Clone + Send + Sync>(items: &[I])
         max_width: Some(80),
         implicit_properties: false,
         max_items: None,
+        null_display: None,
+        vector_display: print::VectorDisplay::Full,
+        display_rules: Default::default(),
+        number_format: print::NumberFormat::Default,
+        spill_threshold: None,
     })
 }
 
@@ -96,6 +103,26 @@ fn bigdecimal() {
     ]).unwrap(), "{10.1n}");
 }
 
+#[test]
+fn number_format() {
+    use print::NumberFormat::*;
+
+    assert_eq!(Underscore.apply("1234567"), "1_234_567");
+    assert_eq!(Underscore.apply("-1234.5"), "-1_234.5");
+    assert_eq!(Underscore.apply("123"), "123");
+    assert_eq!(Underscore.apply("12345.678n"), "12_345.678n");
+    assert_eq!(Underscore.apply("1e11n"), "1e11n");
+    assert_eq!(Comma.apply("1234567.5"), "1,234,567.5");
+    assert_eq!(Default.apply("1234567"), "1234567");
+
+    let mut config = Config::new();
+    config.number_format = Comma;
+    assert_eq!(test_format_cfg(&[
+        Value::Int64(1234567),
+        Value::BigInt(123456.into()),
+    ], &config).unwrap(), "{1,234,567, 123,456n}");
+}
+
 #[test]
 fn bigint() {
     assert_eq!(test_format(&[
@@ -159,7 +186,7 @@ fn array_ellipsis() {
   [
     10,
     20,
-    ... (further results hidden `\set limit 2`)
+    ... (further results hidden `\set implicit-limit 2`)
   ],
 }"###);
     assert_eq!(test_format_cfg(&[
@@ -205,6 +232,29 @@ fn wrap() {
     ], Config::new().max_width(10)).unwrap(), "{\n  10,\n  20,\n  30,\n}");
 }
 
+#[test]
+fn wrap_wide_chars() {
+    assert_eq!(test_format_cfg(&[
+        Value::Str("日本語".into()),
+        Value::Str("日本語".into()),
+    ], Config::new().max_width(20)).unwrap(), "{'日本語', '日本語'}");
+    assert_eq!(test_format_cfg(&[
+        Value::Str("日本語".into()),
+        Value::Str("日本語".into()),
+    ], Config::new().max_width(18)).unwrap(),
+        "{\n  '日本語',\n  '日本語',\n}");
+}
+
+#[test]
+fn wide_chars_width() {
+    assert_eq!(print::str_width("abc"), 3);
+    assert_eq!(print::str_width("日本語"), 6);
+    assert_eq!(print::str_width("e\u{301}"), 1);
+    assert_eq!(print::str_width("👍"), 2);
+    assert_eq!(print::str_width("🇺🇦"), 2);
+    assert_eq!(print::str_width("👨\u{200D}👩\u{200D}👧"), 2);
+}
+
 #[test]
 fn object() {
     let shape = ObjectShape::new(vec![
@@ -255,6 +305,69 @@ fn object() {
 }"###);
 }
 
+#[test]
+fn null_display() {
+    let shape = ObjectShape::new(vec![
+        ShapeElement {
+            flag_implicit: false,
+            flag_link_property: false,
+            flag_link: false,
+            name: "field1".into(),
+        },
+        ShapeElement {
+            flag_implicit: false,
+            flag_link_property: false,
+            flag_link: false,
+            name: "field2".into(),
+        }
+    ]);
+    let mut config = Config::new();
+    config.max_width(60);
+    config.null_display = Some("<empty>".into());
+    assert_eq!(test_format_cfg(&[
+        Value::Object { shape: shape.clone(), fields: vec![
+            Some(Value::Str("".into())),
+            None,
+        ]},
+    ], &config).unwrap(), "{Object {field1: '', field2: <empty>}}");
+}
+
+#[test]
+fn vector_display() {
+    let vector = Value::Array(vec![Value::Float64(1.0); 16]);
+    let short = Value::Array(vec![Value::Float64(1.0); 2]);
+    let mut config = Config::new();
+    config.vector_display(print::VectorDisplay::Truncated);
+    assert_eq!(test_format_cfg(&[vector.clone()], &config).unwrap(),
+        "{<vector dim=16 norm=4.0000 [1, 1, 1, 1, 1, 1, …]>}");
+    assert_eq!(test_format_cfg(&[short], &config).unwrap(), "{[1, 1]}");
+    config.vector_display(print::VectorDisplay::Full);
+    assert_eq!(test_format_cfg(&[vector], &config).unwrap(),
+        format!("{{[{}]}}", vec!["1"; 16].join(", ")));
+}
+
+#[test]
+fn display_rules() {
+    let mut rules = BTreeMap::new();
+    rules.insert("std::uuid".into(), DisplayRule {
+        template: None,
+        replace: vec![("-".into(), "".into())],
+    });
+    rules.insert("std::decimal".into(), DisplayRule {
+        template: Some("${value}".into()),
+        replace: Vec::new(),
+    });
+    let mut config = Config::new();
+    config.display_rules = DisplayRules::new(rules);
+    assert_eq!(test_format_cfg(&[
+        Value::Uuid(uuid::Uuid::from_str(
+            "6f8a4a3e-1a8b-11eb-8e6e-f3a8b5e1a2c4").unwrap()),
+        Value::Decimal(TryFrom::try_from(
+            BigDecimal::from_str("10.1").unwrap()
+        ).unwrap()),
+        Value::Int64(10),
+    ], &config).unwrap(), "{6f8a4a3e1a8b11eb8e6ef3a8b5e1a2c4, $10.1, 10}");
+}
 
 #[test]
 fn link_property() {
@@ -383,3 +496,45 @@ fn json() {
   }
 ]"###);
 }
+
+#[test]
+fn json_wide_chars() {
+    assert_eq!(json_fmt_width(20, r###"[["日本", "日本"]]"###),
+        r###"[["日本", "日本"]]"###);
+    assert_eq!(json_fmt_width(15, r###"[["日本", "日本"]]"###), r###"[
+  [
+    "日本",
+    "日本"
+  ]
+]"###);
+}
+
+fn json_diff(old: &str, new: &str) -> String {
+    let old = serde_json::from_str(old).unwrap();
+    let new = serde_json::from_str(new).unwrap();
+    print::diff::format_diff(&print::diff::diff(&old, &new), false)
+}
+
+#[test]
+fn diff() {
+    assert_eq!(json_diff("[1, 2]", "[1, 2]"), "");
+    assert_eq!(json_diff("[1, 2]", "[1, 3, 4]"),
+        "- [1]: 2\n+ [1]: 3\n+ [2]: 4\n");
+    assert_eq!(json_diff(r#"{"a": 1, "b": "x"}"#, r#"{"b": "y", "c": null}"#),
+        "- .a: 1\n- .b: \"x\"\n+ .b: \"y\"\n+ .c: null\n");
+    assert_eq!(json_diff("1", "{}"), "- .: 1\n+ .: {}\n");
+}
+
+#[test]
+fn diff_by_id() {
+    assert_eq!(json_diff(
+        r#"[{"id": "1", "n": 1}, {"id": "2", "n": 2}]"#,
+        r#"[{"id": "2", "n": 2}, {"id": "1", "n": 1}]"#),
+        "");
+    assert_eq!(json_diff(
+        r#"[{"id": "1", "n": 1}, {"id": "2", "n": 2}]"#,
+        r#"[{"id": "3", "n": 3}, {"id": "1", "n": 0}]"#),
+        "- [id=1].n: 1\n+ [id=1].n: 0\n\
+         - [id=2]: {\"id\":\"2\",\"n\":2}\n\
+         + [id=3]: {\"id\":\"3\",\"n\":3}\n");
+}
@@ -0,0 +1,32 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+
+/// Number of terminal columns taken by a single grapheme cluster
+///
+/// Only the base character is measured, so combining marks take no space.
+/// Emoji sequences (flags, ZWJ sequences, emoji presentation) take two
+/// columns as most terminals render them.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    let mut chars = grapheme.chars();
+    let base = match chars.next() {
+        Some(c) => c,
+        None => return 0,
+    };
+    let width = base.width().unwrap_or(0);
+    if is_regional_indicator(base) ||
+        chars.any(|c| c == '\u{FE0F}' || c == '\u{200D}')
+    {
+        return 2;
+    }
+    width
+}
+
+/// Number of terminal columns taken by a single line of text
+pub fn str_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
@@ -1,5 +1,5 @@
 use std::io;
-use std::process::{Command, Child, exit};
+use std::process::{Command, Child, ExitStatus, exit};
 use std::time::Duration;
 
 use anyhow::Context;
@@ -131,6 +131,9 @@ impl ProcessGuard {
             child: cmd.spawn()?,
         })
     }
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
 }
 
 impl Drop for ProcessGuard {
@@ -11,6 +11,11 @@ use crate::server::distribution::MajorVersion;
 #[serde(rename_all="kebab-case")]
 struct SrcConfig {
     edgedb: SrcEdgedb,
+    #[serde(default)]
+    schema_variables: Option<BTreeMap<String, String>>,
+    /// Settings of the command-line tool, read by `crate::config`
+    #[serde(default, rename="cli")]
+    _cli: Option<toml::Value>,
     #[serde(flatten)]
     extra: BTreeMap<String, toml::Value>,
 }
@@ -27,6 +32,9 @@ struct SrcEdgedb {
 #[derive(Debug)]
 pub struct Config {
     pub edgedb: Edgedb,
+    /// Values of `${NAME}` placeholders in schema files, placeholders are
+    /// only expanded if the table is present
+    pub schema_variables: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug)]
@@ -51,6 +59,7 @@ pub fn read(path: &Path) -> anyhow::Result<Config> {
     return Ok(Config {
         edgedb: Edgedb {
             server_version: val.edgedb.server_version,
-        }
+        },
+        schema_variables: val.schema_variables,
     })
 }
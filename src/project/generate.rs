@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::hint::HintExt;
+use crate::project::config;
+use crate::project::options::{GenerateCi, GenerateCompose, CiProvider};
+use crate::project::project_dir;
+use crate::question;
+
+
+const GITHUB: &str = include_str!("templates/github.yml");
+const GITLAB: &str = include_str!("templates/gitlab.yml");
+const COMPOSE: &str = include_str!("templates/compose.yml");
+const COMPOSE_MIGRATIONS: &str =
+    include_str!("templates/compose-migrations.yml");
+const MIGRATIONS_DOCKERFILE: &str =
+    include_str!("templates/migrations.Dockerfile");
+
+/// Replaces `{{name}}` placeholders of the template
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut result = template.to_string();
+    for (name, value) in vars {
+        result = result.replace(&format!("{{{{{}}}}}", name), value);
+    }
+    result
+}
+
+/// Docker image of the server version the project is pinned to
+fn image(project_dir: &Path) -> anyhow::Result<String> {
+    let config = config::read(&project_dir.join("edgedb.toml"))?;
+    match &config.edgedb.server_version {
+        Some(ver) => Ok(format!("edgedb/edgedb:{}", ver.title())),
+        None => {
+            log::warn!("No `server-version` in `edgedb.toml`, \
+                        the latest server will be used in CI");
+            Ok("edgedb/edgedb".into())
+        }
+    }
+}
+
+fn ask(prompt: &str, value: &Option<String>, default: &str,
+       non_interactive: bool)
+    -> anyhow::Result<String>
+{
+    if let Some(value) = value {
+        return Ok(value.clone());
+    }
+    if non_interactive {
+        return Ok(default.into());
+    }
+    question::String::new(prompt).default(default).ask()
+}
+
+fn write(path: &Path, text: &str, overwrite: bool) -> anyhow::Result<()> {
+    if path.exists() && !overwrite {
+        return Err(anyhow::anyhow!("{} already exists", path.display()))
+            .hint("use `--overwrite` to replace it")?;
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create {}", dir.display()))?;
+    }
+    fs::write(path, text)
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    eprintln!("Written {}", path.display());
+    Ok(())
+}
+
+pub fn generate_ci(cmd: &GenerateCi) -> anyhow::Result<()> {
+    let dir = project_dir(cmd.project_dir.as_ref().map(|x| x.as_path()))?;
+    let image = image(&dir)?;
+    let dsn_secret = ask("Name of the secret with DSN of the production \
+                          database", &cmd.dsn_secret, "EDGEDB_DSN",
+                         cmd.non_interactive)?;
+    let branch = ask("Branch to apply migrations from", &cmd.branch, "main",
+                     cmd.non_interactive)?;
+    let (template, path) = match cmd.provider {
+        CiProvider::Github => {
+            (GITHUB, PathBuf::from(".github/workflows/edgedb.yml"))
+        }
+        CiProvider::Gitlab => (GITLAB, PathBuf::from(".gitlab-ci.yml")),
+    };
+    let text = render(template, &[
+        ("image", &image),
+        ("dsn_secret", &dsn_secret),
+        ("branch", &branch),
+    ]);
+    if cmd.stdout {
+        print!("{}", text);
+        return Ok(());
+    }
+    write(&dir.join(path), &text, cmd.overwrite)?;
+    eprintln!("Add the DSN of the production database as the `{}` secret \
+               of the repository", dsn_secret);
+    Ok(())
+}
+
+pub fn generate_compose(cmd: &GenerateCompose) -> anyhow::Result<()> {
+    let dir = project_dir(cmd.project_dir.as_ref().map(|x| x.as_path()))?;
+    let image = image(&dir)?;
+    let port = cmd.port.to_string();
+    let vars: [(&str, &str); 3] = [
+        ("service", &cmd.service),
+        ("image", &image),
+        ("port", &port),
+    ];
+    let migrations = if cmd.migrations_job {
+        render(COMPOSE_MIGRATIONS, &vars)
+    } else {
+        String::new()
+    };
+    let mut vars = vars.to_vec();
+    vars.push(("migrations", &migrations));
+    print!("{}", render(COMPOSE, &vars));
+    if cmd.migrations_job {
+        write(&dir.join("Dockerfile.edgedb-migrations"),
+              &render(MIGRATIONS_DOCKERFILE, &vars), cmd.overwrite)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, GITHUB, GITLAB};
+    use super::{COMPOSE, COMPOSE_MIGRATIONS, MIGRATIONS_DOCKERFILE};
+
+    #[test]
+    fn templates() {
+        let vars = [
+            ("image", "edgedb/edgedb:1-beta2"),
+            ("dsn_secret", "PROD_DSN"),
+            ("branch", "master"),
+        ];
+        let github = render(GITHUB, &vars);
+        assert!(github.contains("image: edgedb/edgedb:1-beta2\n"));
+        assert!(github.contains("${{ secrets.PROD_DSN }}"));
+        assert!(github.contains("branches: [master]"));
+        let gitlab = render(GITLAB, &vars);
+        assert!(gitlab.contains("name: edgedb/edgedb:1-beta2\n"));
+        assert!(gitlab.contains("--dsn \"$PROD_DSN\""));
+        for text in &[github, gitlab] {
+            assert!(!text.replace("${{", "").contains("{{"));
+        }
+    }
+
+    #[test]
+    fn compose() {
+        let vars = [
+            ("service", "db"),
+            ("image", "edgedb/edgedb:1-beta2"),
+            ("port", "10700"),
+        ];
+        let migrations = render(COMPOSE_MIGRATIONS, &vars);
+        let mut vars = vars.to_vec();
+        vars.push(("migrations", &migrations));
+        let compose = render(COMPOSE, &vars);
+        assert!(compose.contains("  db:\n    image: edgedb/edgedb:1-beta2\n"));
+        assert!(compose.contains("\"10700:5656\""));
+        assert!(compose.contains("  db-migrations:\n"));
+        assert!(compose.contains("\nvolumes:\n  db-data:\n"));
+        let dockerfile = render(MIGRATIONS_DOCKERFILE, &vars);
+        assert!(dockerfile.contains("\"-H\", \"db\""));
+        for text in &[compose, dockerfile] {
+            assert!(!text.contains("{{"));
+        }
+    }
+}
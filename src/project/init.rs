@@ -13,6 +13,7 @@ use rand::{thread_rng, seq::SliceRandom};
 
 use crate::commands::ExitCode;
 use crate::connect::Connector;
+use crate::i18n;
 use crate::migrations;
 use crate::platform::{tmp_file_path, home_dir, path_bytes, symlink_dir};
 use crate::process::ProcessGuard;
@@ -27,8 +28,10 @@ use crate::server::is_valid_name;
 use crate::server::methods::{InstallMethod, InstallationMethods, Methods};
 use crate::server::options::StartConf;
 use crate::server::os_trait::{Method, InstanceRef};
+use crate::server::service;
 use crate::server::version::Version;
 use crate::table;
+use crate::verbosity::Verbosity;
 
 const CHARS: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
 const DEFAULT_ESDL: &str = "\
@@ -77,7 +80,7 @@ fn ask_method(available: &InstallationMethods, options: &Init)
         }
     }
     let mut q = question::Numeric::new(
-        "What type of EdgeDB instance would you like to use with this project?"
+        i18n::text("project-init.instance-type")
     );
     if available.package.supported {
         q.option("Local (native package)", InstallMethod::Package);
@@ -153,21 +156,19 @@ fn ask_name(methods: &Methods, dir: &Path, options: &Init)
         return Ok(default_name)
     }
     let mut q = question::String::new(
-        "Specify the name of EdgeDB instance to use with this project"
+        i18n::text("project-init.instance-name")
     );
     q.default(&default_name);
     loop {
         let target_name = q.ask()?;
         if !is_valid_name(&target_name) {
-            eprintln!("instance name must be a valid identifier, \
-                       (regex: ^[a-zA-Z_][a-zA-Z_0-9]*$)");
+            eprintln!("{}", i18n::text("project-init.invalid-name"));
             continue;
         }
         if instances.contains(&target_name) {
             let confirm = question::Confirm::new(
-                format!("Do you want to use existing instance {:?} \
-                         for the project?",
-                         target_name)
+                i18n::format("project-init.use-existing",
+                             &[("name", &format!("{:?}", target_name))])
             );
             if confirm.ask()? {
                 return Ok(target_name);
@@ -212,7 +213,7 @@ fn ask_version(meth: &dyn Method, options: &Init)
             .context("cannot find stable EdgeDB version")
         })?;
     let mut q = question::String::new(
-        "Specify the version of EdgeDB to use with this project"
+        i18n::text("project-init.version")
     );
     q.default(distribution.major_version().as_str());
     loop {
@@ -363,7 +364,7 @@ fn init_existing(options: &Init, project_dir: &Path) -> anyhow::Result<()> {
         version: distr.version().clone(),
         nightly: distr.major_version().is_nightly(),
         distribution: distr,
-        method: method,
+        method: method.clone(),
         storage: meth.get_storage(false, &name)?,
         credentials: home_dir()?.join(".edgedb").join("credentials")
             .join(format!("{}.json", &name)),
@@ -371,13 +372,19 @@ fn init_existing(options: &Init, project_dir: &Path) -> anyhow::Result<()> {
         database: "edgedb".into(),
         port: allocate_port(&name)?,
         start_conf: StartConf::Auto,
+        portable: false,
+        service_manager: if method == InstallMethod::Docker {
+            None
+        } else {
+            service::choose(None)?
+        },
         suppress_messages: true,
     };
 
     println!("Initializing EdgeDB instance...");
     let err_manual = !try_bootstrap(meth.as_ref(), &settings)?;
 
-    write_stash_dir(&stash_dir, project_dir, &name)?;
+    write_stash_dir(&stash_dir, project_dir, &name, None)?;
 
     let inst = meth.get_instance(&name)?;
     if err_manual {
@@ -445,13 +452,17 @@ fn stash_name(path: &Path) -> anyhow::Result<OsString> {
 }
 
 #[context("error writing project dir {:?}", dir)]
-fn write_stash_dir(dir: &Path, project_dir: &Path, instance_name: &str)
+pub fn write_stash_dir(dir: &Path, project_dir: &Path, instance_name: &str,
+    database: Option<&str>)
     -> anyhow::Result<()>
 {
     let tmp = tmp_file_path(&dir);
     fs::create_dir_all(&tmp)?;
     fs::write(&tmp.join("project-path"), path_bytes(project_dir)?)?;
     fs::write(&tmp.join("instance-name"), instance_name.as_bytes())?;
+    if let Some(database) = database {
+        fs::write(&tmp.join("database"), database.as_bytes())?;
+    }
 
     let lnk = tmp.join("project-link");
     symlink_dir(project_dir, &lnk)
@@ -485,7 +496,7 @@ fn init_new(options: &Init, project_dir: &Path) -> anyhow::Result<()> {
         eprintln!("Initializing new project...");
     } else {
         let q = question::Confirm::new(
-            "Do you want to initialize a new project?"
+            i18n::text("project-init.new-project")
         );
         if !q.ask()? {
             return Ok(());
@@ -542,7 +553,7 @@ fn init_new(options: &Init, project_dir: &Path) -> anyhow::Result<()> {
         version: distr.version().clone(),
         nightly: distr.major_version().is_nightly(),
         distribution: distr,
-        method: method,
+        method: method.clone(),
         storage: meth.get_storage(false, &name)?,
         credentials: home_dir()?.join(".edgedb").join("credentials")
             .join(format!("{}.json", &name)),
@@ -550,6 +561,12 @@ fn init_new(options: &Init, project_dir: &Path) -> anyhow::Result<()> {
         database: "edgedb".into(),
         port: allocate_port(&name)?,
         start_conf: StartConf::Auto,
+        portable: false,
+        service_manager: if method == InstallMethod::Docker {
+            None
+        } else {
+            service::choose(None)?
+        },
         suppress_messages: true,
     };
 
@@ -557,7 +574,7 @@ fn init_new(options: &Init, project_dir: &Path) -> anyhow::Result<()> {
     let err_manual = !try_bootstrap(meth.as_ref(), &settings)?;
     // TODO(tailhook) execute migrations
 
-    write_stash_dir(&stash_dir, project_dir, &name)?;
+    write_stash_dir(&stash_dir, project_dir, &name, None)?;
 
     let inst = meth.get_instance(&name)?;
     if err_manual {
@@ -601,6 +618,7 @@ async fn migrate(inst: &InstanceRef<'_>) -> anyhow::Result<()> {
             command_line: true,
             styler: None,
             conn_params: Connector::new(Ok(conn_params)),
+            verbosity: Verbosity::Normal,
         },
         &Migrate {
             cfg: MigrationConfig {
@@ -608,6 +626,9 @@ async fn migrate(inst: &InstanceRef<'_>) -> anyhow::Result<()> {
             },
             quiet: false,
             to_revision: None,
+            check_data: false,
+            all_databases: false,
+            parallel: false,
         }).await?;
     Ok(())
 }
@@ -1,6 +1,8 @@
 use crate::project::options::{ProjectCommand, Command};
 
+use crate::project::generate;
 use crate::project::init;
+use crate::project::relink;
 use crate::project::unlink;
 
 pub fn main(cmd: &ProjectCommand) -> anyhow::Result<()> {
@@ -9,5 +11,8 @@ pub fn main(cmd: &ProjectCommand) -> anyhow::Result<()> {
     match &cmd.subcommand {
         Init(c) => init::init(c),
         Unlink(c) => unlink::unlink(c),
+        Relink(c) => relink::relink(c),
+        GenerateCi(c) => generate::generate_ci(c),
+        GenerateCompose(c) => generate::generate_compose(c),
     }
 }
@@ -1,18 +1,23 @@
 use std::env;
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 
 pub mod options;
 
+pub mod generate;
 mod main;
 pub mod init;
 mod unlink;
-mod config;
+mod relink;
+pub mod config;
 
 pub use main::main;
 pub use init::{stash_path};
 pub use unlink::unlink;
+pub use relink::relink;
 
 pub fn project_dir(cli_option: Option<&Path>) -> anyhow::Result<PathBuf> {
     project_dir_opt(cli_option)?
@@ -39,3 +44,12 @@ pub fn project_dir_opt(cli_options: Option<&Path>)
         }
     }
 }
+
+/// Returns the database chosen by `edgedb project relink --database`
+pub fn read_database(stash_dir: &Path) -> anyhow::Result<Option<String>> {
+    match fs::read_to_string(stash_dir.join("database")) {
+        Ok(name) => Ok(Some(name.trim().into())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context("error reading project settings"),
+    }
+}
@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use clap::{Clap, AppSettings, ValueHint};
 use crate::server::methods::InstallMethod;
@@ -19,6 +20,14 @@ pub enum Command {
     /// Remove association with and optionally destroy the
     /// linked EdgeDB intstance.
     Unlink(Unlink),
+    /// Link the project to a different EdgeDB instance or database
+    Relink(Relink),
+    /// Generate a CI configuration which checks migrations of pull
+    /// requests and applies them on the main branch
+    GenerateCi(GenerateCi),
+    /// Print a docker-compose service for the server version of the
+    /// project
+    GenerateCompose(GenerateCompose),
 }
 
 #[derive(Clap, Debug, Clone)]
@@ -53,9 +62,112 @@ pub struct Unlink {
     pub project_dir: Option<PathBuf>,
 
     /// If specified, the associated EdgeDB instance is destroyed by running edgedb server destroy.
-    #[clap(long, short='D')]
+    #[clap(long, short='D', alias="destroy-data",
+           conflicts_with="keep-instance")]
     pub destroy_server_instance: bool,
 
+    /// Keep the instance and its data without asking. Unless specified,
+    /// interactive unlink offers to destroy the instance if no other
+    /// project uses it
+    #[clap(long)]
+    pub keep_instance: bool,
+
     #[clap(long)]
     pub non_interactive: bool,
 }
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Relink {
+    /// Name of the instance to link the project to
+    pub instance: String,
+
+    /// Specifies a project root directory explicitly.
+    #[clap(long, value_hint=ValueHint::DirPath)]
+    pub project_dir: Option<PathBuf>,
+
+    /// Database to connect to instead of the default one of the instance
+    #[clap(long)]
+    pub database: Option<String>,
+
+    /// Relink without asking, even if the schema of the instance doesn't
+    /// match the migrations of the project
+    #[clap(long)]
+    pub non_interactive: bool,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct GenerateCi {
+    /// CI service to generate configuration for
+    #[clap(long, default_value="github",
+           possible_values=&["github", "gitlab"][..])]
+    pub provider: CiProvider,
+
+    /// Specifies a project root directory explicitly.
+    #[clap(long, value_hint=ValueHint::DirPath)]
+    pub project_dir: Option<PathBuf>,
+
+    /// Name of the CI secret containing DSN of the production database
+    #[clap(long)]
+    pub dsn_secret: Option<String>,
+
+    /// Branch which is migrated to the production database
+    #[clap(long)]
+    pub branch: Option<String>,
+
+    /// Print configuration to stdout instead of writing the file
+    #[clap(long)]
+    pub stdout: bool,
+
+    /// Replace the configuration file if it exists
+    #[clap(long)]
+    pub overwrite: bool,
+
+    /// Run in non-interactive mode (accepting all defaults)
+    #[clap(long)]
+    pub non_interactive: bool,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct GenerateCompose {
+    /// Specifies a project root directory explicitly.
+    #[clap(long, value_hint=ValueHint::DirPath)]
+    pub project_dir: Option<PathBuf>,
+
+    /// Name of the service
+    #[clap(long, default_value="edgedb")]
+    pub service: String,
+
+    /// Port on the host the server is exposed at
+    #[clap(long, default_value="5656")]
+    pub port: u16,
+
+    /// Also add a service which applies migrations of the project, and
+    /// write `Dockerfile.edgedb-migrations` for it
+    #[clap(long)]
+    pub migrations_job: bool,
+
+    /// Replace the Dockerfile if it exists
+    #[clap(long)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    Github,
+    Gitlab,
+}
+
+impl FromStr for CiProvider {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<CiProvider> {
+        match s {
+            "github" => Ok(CiProvider::Github),
+            "gitlab" => Ok(CiProvider::Gitlab),
+            _ => anyhow::bail!("Unknown CI provider {:?}. \
+                Options: github, gitlab", s),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::Path;
+
+use async_std::task;
+
+use crate::commands::{ExitCode, Options};
+use crate::commands::parser::{MigrationConfig, ShowStatus};
+use crate::connect::Connector;
+use crate::credentials;
+use crate::hint::HintExt;
+use crate::i18n;
+use crate::migrations;
+use crate::project::init::write_stash_dir;
+use crate::project::options::Relink;
+use crate::project::{project_dir, stash_path};
+use crate::question;
+use crate::verbosity::Verbosity;
+
+
+/// Checks that the database has exactly the migrations of the project
+fn migrations_match(project_dir: &Path, options: &Relink)
+    -> anyhow::Result<bool>
+{
+    let mut conn_params = credentials::get_connector(&options.instance)?;
+    if let Some(database) = &options.database {
+        conn_params.database(database);
+    }
+    let cmd_options = Options {
+        command_line: true,
+        styler: None,
+        conn_params: Connector::new(Ok(conn_params.clone())),
+        verbosity: Verbosity::Normal,
+    };
+    let status = ShowStatus {
+        cfg: MigrationConfig {
+            schema_dir: project_dir.join("dbschema"),
+        },
+        quiet: false,
+    };
+    task::block_on(async {
+        let mut conn = conn_params.connect().await?;
+        match migrations::status(&mut conn, &cmd_options, &status).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.is::<ExitCode>() => Ok(false),
+            Err(e) => Err(e),
+        }
+    })
+}
+
+pub fn relink(options: &Relink) -> anyhow::Result<()> {
+    let dir = project_dir(options.project_dir.as_ref().map(|x| x.as_path()))?;
+    let stash_path = stash_path(&dir)?;
+    let name = &options.instance;
+    if !credentials::path(name)?.exists() {
+        return Err(anyhow::anyhow!("no credentials for instance {:?}", name))
+            .hint("Run `edgedb server status` to list local instances")?;
+    }
+
+    if !migrations_match(&dir, options)? {
+        if options.non_interactive {
+            eprintln!("Run `edgedb migrate` to apply the project migrations.");
+        } else {
+            let q = question::Confirm::new(
+                i18n::format("project-relink.confirm-mismatch",
+                             &[("name", &format!("{:?}", name))])
+            );
+            if !q.ask()? {
+                eprintln!("{}", i18n::text("canceled"));
+                return Ok(())
+            }
+        }
+    }
+
+    let old_name = fs::read_to_string(stash_path.join("instance-name")).ok();
+    if stash_path.exists() {
+        fs::remove_dir_all(&stash_path)?;
+    }
+    write_stash_dir(&stash_path, &dir, name,
+                    options.database.as_ref().map(|x| &x[..]))?;
+    match old_name {
+        Some(old_name) if old_name.trim() != name => {
+            eprintln!("Project relinked from instance {:?} to {:?}",
+                      old_name.trim(), name);
+        }
+        _ => eprintln!("Project linked to instance {:?}", name),
+    }
+    Ok(())
+}
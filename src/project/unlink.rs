@@ -1,8 +1,11 @@
 use std::fs;
+use std::path::Path;
 
 use anyhow::Context;
 
 use crate::commands::ExitCode;
+use crate::dry_run;
+use crate::i18n;
 use crate::project::options::Unlink;
 use crate::project::{project_dir, stash_path};
 use crate::server::destroy;
@@ -10,6 +13,27 @@ use crate::server::options::Destroy;
 use crate::question;
 
 
+/// Asks whether the instance should be destroyed too, unless it's used by
+/// other projects
+fn offer_destroy(options: &Unlink, inst: &str, stash_path: &Path)
+    -> anyhow::Result<bool>
+{
+    if options.keep_instance || options.non_interactive
+        || dry_run::is_enabled()
+    {
+        return Ok(false);
+    }
+    let used_elsewhere = destroy::find_project_dirs(inst)?.iter()
+        .any(|d| d != stash_path);
+    if used_elsewhere {
+        return Ok(false);
+    }
+    let q = question::Confirm::new_dangerous(
+        i18n::format("project-unlink.ask-destroy",
+                     &[("name", &format!("{:?}", inst))]));
+    q.ask()
+}
+
 pub fn unlink(options: &Unlink) -> anyhow::Result<()> {
     let dir = project_dir(options.project_dir.as_ref().map(|x| x.as_path()))?;
     let stash_path = stash_path(&dir)?;
@@ -18,13 +42,13 @@ pub fn unlink(options: &Unlink) -> anyhow::Result<()> {
             let inst = fs::read_to_string(&stash_path.join("instance-name"))
                 .context("failed to read instance name")?;
             let inst = inst.trim();
-            if !options.non_interactive {
+            if !options.non_interactive && !dry_run::is_enabled() {
                 let q = question::Confirm::new_dangerous(
-                    format!("Do you really want to unlink \
-                             and delete instance {:?}?", inst.trim())
+                    i18n::format("project-unlink.confirm-destroy",
+                                 &[("name", &format!("{:?}", inst.trim()))])
                 );
                 if !q.ask()? {
-                    eprintln!("Canceled");
+                    eprintln!("{}", i18n::text("canceled"));
                     return Ok(())
                 }
             }
@@ -40,11 +64,25 @@ pub fn unlink(options: &Unlink) -> anyhow::Result<()> {
                     name: inst.to_string(),
                     verbose: false,
                     force: true,
+                    purge: false,
                 })?;
             }
-            fs::remove_dir_all(&stash_path)?;
+            if dry_run::perform(format_args!(
+                "remove project directory {}", stash_path.display()))
+            {
+                fs::remove_dir_all(&stash_path)?;
+            }
         } else {
             match fs::read_to_string(&stash_path.join("instance-name")) {
+                Ok(name) if offer_destroy(options, name.trim(),
+                                          &stash_path)? => {
+                    destroy::do_destroy(&Destroy {
+                        name: name.trim().to_string(),
+                        verbose: false,
+                        force: true,
+                        purge: false,
+                    })?;
+                }
                 Ok(name) => {
                     eprintln!("Unlinking instance {:?}", name);
                 }
@@ -54,7 +92,11 @@ pub fn unlink(options: &Unlink) -> anyhow::Result<()> {
                     eprintln!("Removing project configuration directory...");
                 }
             };
-            fs::remove_dir_all(&stash_path)?;
+            if dry_run::perform(format_args!(
+                "remove project directory {}", stash_path.display()))
+            {
+                fs::remove_dir_all(&stash_path)?;
+            }
         }
     }
     Ok(())
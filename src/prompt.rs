@@ -2,7 +2,7 @@ use std::borrow::Cow;
 use std::fs;
 use std::io::{ErrorKind, Write};
 use std::env;
-use std::process::{Command, Stdio};
+use std::process::Command;
 
 use anyhow::{self, Context as _Context};
 use async_std::channel::{Sender, Receiver, RecvError};
@@ -10,7 +10,8 @@ use async_std::task;
 use dirs::data_local_dir;
 use rustyline::{self, error::ReadlineError, KeyPress, Cmd};
 use rustyline::{Editor, Config, Helper, Context};
-use rustyline::config::{EditMode, CompletionType, Builder as ConfigBuilder};
+use rustyline::config::{EditMode, CompletionType, ColorMode};
+use rustyline::config::{Builder as ConfigBuilder};
 use rustyline::hint::Hinter;
 use rustyline::highlight::{Highlighter, PromptInfo};
 use rustyline::history::History;
@@ -20,15 +21,16 @@ use rustyline::completion::Completer;
 use edgeql_parser::preparser::full_statement;
 use crate::commands::backslash;
 use crate::completion;
-use crate::print::style::Styler;
+use crate::print::{self, style::Styler};
 use crate::highlight;
-use crate::repl::{TX_MARKER, FAILURE_MARKER};
+use crate::output_style;
+use crate::pager;
 
 use colorful::Colorful;
 
 
 pub enum Control {
-    EdgeqlInput { prompt: String, initial: String },
+    EdgeqlInput { prompt: Prompt, initial: String },
     ParameterInput { name: String, type_name: String, initial: String },
     ShowHistory,
     SpawnEditor { entry: Option<isize> },
@@ -43,8 +45,52 @@ pub enum Input {
     Interrupt,
 }
 
+const RESET: &str = "\x1b[0m";
+
+/// Prompt text with the escape sequences kept aside
+///
+/// The line editor computes the width of the prompt from the text, so the
+/// escape sequences are only inserted by the highlighter.
+#[derive(Debug, Clone, Default)]
+pub struct Prompt {
+    pub text: String,
+    /// Escape sequences and byte offsets in the text to insert them at
+    codes: Vec<(usize, &'static str)>,
+}
+
+impl Prompt {
+    pub fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+    pub fn push_code(&mut self, code: &'static str) {
+        self.codes.push((self.text.len(), code));
+    }
+    pub fn push_colored(&mut self, text: &str, code: &'static str) {
+        self.push_code(code);
+        self.push_str(text);
+        self.push_code(RESET);
+    }
+    fn highlighted(&self) -> String {
+        if self.codes.is_empty() {
+            return self.text.clone();
+        }
+        let mut buf = String::with_capacity(self.text.len() + 32);
+        let mut pos = 0;
+        for &(offset, code) in &self.codes {
+            buf.push_str(&self.text[pos..offset]);
+            buf.push_str(code);
+            pos = offset;
+        }
+        buf.push_str(&self.text[pos..]);
+        // colors of the prompt must not leak into the input
+        buf.push_str(RESET);
+        return buf;
+    }
+}
+
 pub struct EdgeqlHelper {
     styler: Styler,
+    prompt: Prompt,
 }
 
 impl Helper for EdgeqlHelper {}
@@ -63,20 +109,9 @@ impl Highlighter for EdgeqlHelper {
         -> Cow<'b, str>
     {
         if info.line_no() > 0 {
-            return format!("{0:.>1$}", " ", prompt.len()).into();
-        } else if prompt.ends_with("> ") {
-            let content = &prompt[..prompt.len()-2];
-            if content.ends_with(TX_MARKER) {
-                return format!("{}{}> ",
-                    &content[..content.len()-TX_MARKER.len()],
-                    TX_MARKER.green()).into();
-            } else if content.ends_with(FAILURE_MARKER) {
-                return format!("{}{}> ",
-                    &content[..content.len()-FAILURE_MARKER.len()],
-                    FAILURE_MARKER.red()).into();
-            } else {
-                return prompt.into();
-            }
+            return format!("{0:.>1$}", " ", print::str_width(prompt)).into();
+        } else if prompt == self.prompt.text {
+            return self.prompt.highlighted().into();
         } else {
             return prompt.into();
         }
@@ -208,7 +243,8 @@ pub fn create_editor(config: &ConfigBuilder) -> Editor<EdgeqlHelper> {
         eprintln!("Can't load history: {:#}", e);
     }).ok();
     editor.set_helper(Some(EdgeqlHelper {
-        styler: Styler::dark_256(),
+        styler: Styler::configured(),
+        prompt: Prompt::default(),
     }));
     return editor;
 }
@@ -221,12 +257,16 @@ pub fn var_editor(config: &ConfigBuilder, type_name: &str) -> Editor<()> {
     return editor;
 }
 
-pub fn edgeql_input(prompt: &str, editor: &mut Editor<EdgeqlHelper>,
+pub fn edgeql_input(prompt: Prompt, editor: &mut Editor<EdgeqlHelper>,
     data: &Sender<Input>, initial: &str)
     -> anyhow::Result<()>
 {
+    let prompt_text = prompt.text.clone();
+    if let Some(helper) = editor.helper_mut() {
+        helper.prompt = prompt;
+    }
     let text = match
-        editor.readline_with_initial(&prompt, (&initial, ""))
+        editor.readline_with_initial(&prompt_text, (&initial, ""))
     {
         Ok(text) => text,
         Err(ReadlineError::Eof) => {
@@ -253,7 +293,13 @@ pub fn main(data: Sender<Input>, control: Receiver<Control>)
 {
     let config = Config::builder();
     let config = config.edit_mode(EditMode::Emacs);
-    let mut config = config.completion_type(CompletionType::List);
+    let config = config.completion_type(CompletionType::List);
+    // pasted text is inserted as a whole, so multiple statements are not
+    // submitted one line at a time
+    let mut config = config.bracketed_paste(true);
+    if output_style::is_plain() {
+        config = config.color_mode(ColorMode::Disabled);
+    }
     let mut editor = create_editor(&config);
     'outer: loop {
         match task::block_on(control.recv()) {
@@ -271,7 +317,7 @@ pub fn main(data: Sender<Input>, control: Receiver<Control>)
                 editor = create_editor(&config);
             }
             Ok(Control::EdgeqlInput { prompt, initial }) => {
-                edgeql_input(&prompt, &mut editor, &data, &initial)?;
+                edgeql_input(prompt, &mut editor, &data, &initial)?;
             }
             Ok(Control::ParameterInput { name, type_name, initial })
             => {
@@ -345,34 +391,22 @@ pub fn main(data: Sender<Input>, control: Receiver<Control>)
 }
 
 fn show_history(history: &History) -> Result<(), anyhow::Error> {
-    let pager = env::var("EDGEDB_PAGER")
-        .or_else(|_| env::var("PAGER"))
-        .unwrap_or_else(|_| String::from("less -R"));
-    let mut items = pager.split_whitespace();
-    let mut cmd = Command::new(items.next().unwrap());
-    cmd.stdin(Stdio::piped());
-    cmd.args(items);
-    let mut child = cmd.spawn()?;
-    let childin = child.stdin.as_mut().expect("stdin is piped");
+    use std::fmt::Write;
+
+    let mut text = String::new();
     for index in (0..history.len()).rev() {
         if let Some(s) = history.get(index) {
             let prefix = format!("[-{}] ", history.len() - index);
             let mut lines = s.lines();
             if let Some(first) = lines.next() {
-                writeln!(childin, "{}{}", prefix, first)?;
+                writeln!(text, "{}{}", prefix, first)?;
             }
             for next in lines {
-                writeln!(childin, "{:1$}{2}", "", prefix.len(), next)?;
+                writeln!(text, "{:1$}{2}", "", prefix.len(), next)?;
             }
         }
     }
-    drop(childin);
-    let res = child.wait()?;
-    if res.success() {
-        Ok(())
-    } else {
-        Err(anyhow::anyhow!("pager exited with: {}", res))
-    }
+    pager::run(&text)
 }
 
 fn spawn_editor(data: &str) -> Result<String, anyhow::Error> {
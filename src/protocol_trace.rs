@@ -0,0 +1,132 @@
+//! Logging of protocol messages for `--trace-protocol`
+//!
+//! Only messages sent and received by the tool itself (REPL queries, dump
+//! and restore) go through the tap points. The handshake and the data rows
+//! of query results are exchanged inside the client library and can't be
+//! traced. Query text, arguments and data blocks are never written, only
+//! their sizes.
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use edgedb_protocol::client_message::ClientMessage;
+use edgedb_protocol::server_message::ServerMessage;
+use once_cell::sync::OnceCell;
+
+
+static OUTPUT: OnceCell<Mutex<Box<dyn Write + Send>>> = OnceCell::new();
+
+pub fn init(file: Option<&Path>) -> anyhow::Result<()> {
+    let output: Box<dyn Write + Send> = match file {
+        Some(path) => Box::new(fs::File::create(path)
+            .with_context(|| format!("cannot create trace file {:?}", path))?),
+        None => Box::new(io::stderr()),
+    };
+    OUTPUT.set(Mutex::new(output)).ok();
+    Ok(())
+}
+
+fn write(direction: &str, description: String) {
+    if let Some(output) = OUTPUT.get() {
+        let mut output = output.lock().expect("not poisoned");
+        writeln!(output, "{} {} {}",
+            humantime::format_rfc3339_millis(SystemTime::now()),
+            direction, description)
+            .map_err(|e| log::warn!("Cannot write protocol trace: {}", e))
+            .ok();
+    }
+}
+
+fn headers<K: std::fmt::Debug, V: AsRef<[u8]>>(
+    headers: impl IntoIterator<Item=(K, V)>)
+    -> String
+{
+    headers.into_iter()
+        .map(|(k, v)| format!("{:?}={:?}", k,
+                              String::from_utf8_lossy(v.as_ref())))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn describe_client(msg: &ClientMessage) -> String {
+    use ClientMessage::*;
+
+    match msg {
+        Prepare(p) => format!("Prepare headers=[{}] io_format={:?} \
+                               cardinality={:?} query=<{} bytes>",
+            headers(&p.headers), p.io_format, p.expected_cardinality,
+            p.command_text.len()),
+        ExecuteScript(s) => format!("ExecuteScript headers=[{}] \
+                                     script=<{} bytes>",
+            headers(&s.headers), s.script_text.len()),
+        OptimisticExecute(e) => format!("OptimisticExecute headers=[{}] \
+                                         io_format={:?} query=<{} bytes> \
+                                         arguments=<{} bytes>",
+            headers(&e.headers), e.io_format, e.command_text.len(),
+            e.arguments.len()),
+        DescribeStatement(d) => format!("DescribeStatement aspect={:?}",
+            d.aspect),
+        Execute(e) => format!("Execute headers=[{}] arguments=<{} bytes>",
+            headers(&e.headers), e.arguments.len()),
+        RestoreBlock(b) => format!("RestoreBlock data=<{} bytes>",
+            b.data.len()),
+        Restore(r) => format!("Restore jobs={} data=<{} bytes>",
+            r.jobs, r.data.len()),
+        ClientHandshake(_) => "ClientHandshake".into(),
+        Dump(_) => "Dump".into(),
+        RestoreEof => "RestoreEof".into(),
+        Sync => "Sync".into(),
+        Flush => "Flush".into(),
+        Terminate => "Terminate".into(),
+        // never printed in full, as it may contain credentials
+        _ => "<other message>".into(),
+    }
+}
+
+fn describe_server(msg: &ServerMessage) -> String {
+    use ServerMessage::*;
+
+    match msg {
+        ErrorResponse(e) => format!("ErrorResponse code=0x{:08x} {:?}",
+            e.code, e.message),
+        CommandComplete(c) => format!("CommandComplete status={:?}",
+            String::from_utf8_lossy(&c.status_data)),
+        Data(d) => format!("Data <{} bytes>",
+            d.data.iter().map(|x| x.len()).sum::<usize>()),
+        DumpBlock(b) => format!("DumpBlock <{} bytes>", b.data.len()),
+        DumpHeader(b) => format!("DumpHeader <{} bytes>", b.data.len()),
+        LogMessage(m) => format!("LogMessage severity={:?} {:?}",
+            m.severity, m.text),
+        ReadyForCommand(r) => format!("ReadyForCommand state={:?}",
+            r.transaction_state),
+        PrepareComplete(p) => format!("PrepareComplete cardinality={:?}",
+            p.cardinality),
+        CommandDataDescription(d) => format!("CommandDataDescription \
+                                              cardinality={:?}",
+            d.result_cardinality),
+        RestoreReady(r) => format!("RestoreReady jobs={}", r.jobs),
+        ServerHandshake(_) => "ServerHandshake".into(),
+        Authentication(_) => "Authentication".into(),
+        ServerKeyData(_) => "ServerKeyData".into(),
+        ParameterStatus(_) => "ParameterStatus".into(),
+        _ => "<other message>".into(),
+    }
+}
+
+pub fn sent(messages: &[ClientMessage]) -> &[ClientMessage] {
+    if OUTPUT.get().is_some() {
+        for msg in messages {
+            write("->", describe_client(msg));
+        }
+    }
+    messages
+}
+
+pub fn received(msg: &ServerMessage) {
+    if OUTPUT.get().is_some() {
+        write("<-", describe_server(msg));
+    }
+}
@@ -0,0 +1,231 @@
+//! Library of queries kept outside of migrations (views, functions, etc.)
+//!
+//! Each `*.edgeql` file may start with header comments declaring files
+//! which must be executed before it:
+//!
+//! ```edgeql
+//! -- requires: users.edgeql, permissions.edgeql
+//! CREATE FUNCTION ...
+//! ```
+//!
+//! Both `#` and `--` comment styles are accepted in the header. Since `--`
+//! is not an EdgeQL comment, such header lines are blanked out before the
+//! file is sent to the server.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::classify;
+use crate::dry_run;
+use crate::hint::HintExt;
+use crate::options::Options;
+use crate::queries::options::Run;
+
+
+#[derive(Debug)]
+struct QueryFile {
+    path: PathBuf,
+    requires: Vec<String>,
+    text: String,
+}
+
+/// Splits header off the file, returning dependencies and executable text
+fn parse_header(data: &str) -> anyhow::Result<(Vec<String>, String)> {
+    let mut requires = Vec::new();
+    let mut text = String::with_capacity(data.len());
+    let mut in_header = true;
+    for line in data.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if in_header {
+            let comment = if let Some(c) = trimmed.strip_prefix("--") {
+                c
+            } else if let Some(c) = trimmed.strip_prefix('#') {
+                c
+            } else if trimmed.is_empty() {
+                text.push_str(line);
+                continue;
+            } else {
+                in_header = false;
+                text.push_str(line);
+                continue;
+            };
+            if let Some(list) = comment.trim().strip_prefix("requires:") {
+                for name in list.split(',') {
+                    let name = name.trim();
+                    if name.is_empty() {
+                        anyhow::bail!("empty file name in `requires` header");
+                    }
+                    requires.push(name.to_string());
+                }
+            }
+            if trimmed.starts_with('#') {
+                text.push_str(line);
+            } else if line.ends_with('\n') {
+                text.push('\n');
+            }
+        } else {
+            text.push_str(line);
+        }
+    }
+    Ok((requires, text))
+}
+
+fn read_library(dir: &Path) -> anyhow::Result<BTreeMap<String, QueryFile>> {
+    let mut result = BTreeMap::new();
+    for item in fs::read_dir(dir)
+        .with_context(|| format!("cannot read directory {:?}", dir))?
+    {
+        let path = item?.path();
+        if !path.extension().map(|x| x == "edgeql").unwrap_or(false) {
+            continue;
+        }
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => anyhow::bail!("invalid file name {:?}", path),
+        };
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {:?}", path))?;
+        let (requires, text) = parse_header(&data)
+            .with_context(|| format!("invalid header in {:?}", path))?;
+        result.insert(name, QueryFile { path, requires, text });
+    }
+    Ok(result)
+}
+
+/// Orders files so that each one comes after all of its dependencies
+///
+/// Files which don't depend on each other are sorted by name, so the order
+/// is stable.
+fn execution_order(files: &BTreeMap<String, QueryFile>)
+    -> anyhow::Result<Vec<&str>>
+{
+    let mut pending = BTreeMap::new();
+    let mut dependents = BTreeMap::<&str, Vec<&str>>::new();
+    for (name, file) in files {
+        let mut deps = BTreeSet::new();
+        for dep in &file.requires {
+            let dep = match files.get_key_value(dep) {
+                Some((dep, _)) => dep.as_str(),
+                None => anyhow::bail!("{:?} requires {:?} \
+                                       which does not exist", name, dep),
+            };
+            if deps.insert(dep) {
+                dependents.entry(dep).or_default().push(name);
+            }
+        }
+        pending.insert(name.as_str(), deps.len());
+    }
+    let mut ready = pending.iter()
+        .filter(|(_, deps)| **deps == 0)
+        .map(|(name, _)| *name)
+        .collect::<BTreeSet<_>>();
+    let mut order = Vec::with_capacity(files.len());
+    while let Some(name) = ready.iter().next().copied() {
+        ready.remove(name);
+        pending.remove(name);
+        order.push(name);
+        for &dependent in dependents.get(name).into_iter().flatten() {
+            let deps = pending.get_mut(dependent)
+                .expect("dependent is pending");
+            *deps -= 1;
+            if *deps == 0 {
+                ready.insert(dependent);
+            }
+        }
+    }
+    if !pending.is_empty() {
+        let cycle = pending.keys().copied().collect::<Vec<_>>();
+        return Err(anyhow::anyhow!("dependency cycle between {}",
+                                   cycle.join(", ")))
+            .hint("check `requires` headers of these files")?;
+    }
+    Ok(order)
+}
+
+pub async fn run(cmd: &Run, options: &Options) -> anyhow::Result<()> {
+    let files = read_library(&cmd.dir)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("no `*.edgeql` files found in {:?}",
+                                   cmd.dir))
+            .hint("put views and functions into `*.edgeql` files")?;
+    }
+    let order = execution_order(&files)?;
+    if cmd.list {
+        for name in order {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    classify::guard_command("queries run")?;
+    let mut cli = options.conn_params.connect().await?;
+    for name in order {
+        let file = &files[name];
+        if !dry_run::perform(format_args!("execute {:?}", file.path)) {
+            continue;
+        }
+        eprintln!("Executing {}", name);
+        cli.execute(&file.text).await
+            .with_context(|| format!("error executing {:?}", file.path))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{parse_header, execution_order, QueryFile};
+
+    fn library(items: Vec<(&str, Vec<&str>)>)
+        -> BTreeMap<String, QueryFile>
+    {
+        items.into_iter().map(|(name, requires)| {
+            (name.to_string(), QueryFile {
+                path: name.into(),
+                requires: requires.into_iter().map(String::from).collect(),
+                text: String::new(),
+            })
+        }).collect()
+    }
+
+    #[test]
+    fn header() {
+        let (requires, text) = parse_header(
+            "-- requires: a.edgeql, b.edgeql\n\
+             # requires: c.edgeql\n\
+             SELECT 1;\n\
+             -- requires: d.edgeql\n").unwrap();
+        assert_eq!(requires, ["a.edgeql", "b.edgeql", "c.edgeql"]);
+        assert_eq!(text,
+            "\n# requires: c.edgeql\nSELECT 1;\n-- requires: d.edgeql\n");
+        assert!(parse_header("-- requires: a.edgeql,\n").is_err());
+    }
+
+    #[test]
+    fn order() {
+        let files = library(vec![
+            ("views.edgeql", vec!["funcs.edgeql", "base.edgeql"]),
+            ("funcs.edgeql", vec!["base.edgeql"]),
+            ("base.edgeql", vec![]),
+            ("aaa.edgeql", vec![]),
+        ]);
+        assert_eq!(execution_order(&files).unwrap(), [
+            "aaa.edgeql", "base.edgeql", "funcs.edgeql", "views.edgeql",
+        ]);
+    }
+
+    #[test]
+    fn cycle() {
+        let files = library(vec![
+            ("a.edgeql", vec!["b.edgeql"]),
+            ("b.edgeql", vec!["a.edgeql"]),
+            ("c.edgeql", vec![]),
+        ]);
+        assert_eq!(execution_order(&files).unwrap_err().to_string(),
+                   "dependency cycle between a.edgeql, b.edgeql");
+        let files = library(vec![("a.edgeql", vec!["missing.edgeql"])]);
+        assert!(execution_order(&files).is_err());
+    }
+}
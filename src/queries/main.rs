@@ -0,0 +1,14 @@
+use async_std::task;
+
+use crate::options::Options;
+use crate::queries::library;
+use crate::queries::options::{QueriesCommand, Command};
+
+
+pub fn main(cmd: &QueriesCommand, options: &Options) -> anyhow::Result<()> {
+    use Command::*;
+
+    match &cmd.subcommand {
+        Run(c) => task::block_on(library::run(c, options)),
+    }
+}
@@ -0,0 +1,6 @@
+pub mod options;
+
+mod library;
+mod main;
+
+pub use main::main;
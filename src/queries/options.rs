@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use clap::{Clap, AppSettings, ValueHint};
+
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct QueriesCommand {
+    #[clap(subcommand)]
+    pub subcommand: Command,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum Command {
+    /// Execute all queries of the library in dependency order
+    Run(Run),
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Run {
+    /// Directory containing `*.edgeql` files. A file may list files it
+    /// depends on in a `-- requires: a.edgeql, b.edgeql` header line.
+    #[clap(value_hint=ValueHint::DirPath,
+           default_value="./dbschema/queries")]
+    pub dir: PathBuf,
+
+    /// Only print the order in which files would be executed
+    #[clap(long)]
+    pub list: bool,
+}
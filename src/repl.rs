@@ -2,12 +2,15 @@ use std::time::Duration;
 
 use anyhow::Context;
 use async_std::prelude::FutureExt;
+use async_std::task;
 use async_std::channel::{Sender, Receiver, RecvError};
 use colorful::Colorful;
 use edgedb_client::client::Connection;
 use edgedb_protocol::server_message::TransactionState;
+use edgedb_protocol::value::Value;
 
 use crate::async_util::timeout;
+use crate::bindings;
 use crate::connect::Connector;
 use crate::prompt;
 use crate::print;
@@ -15,6 +18,12 @@ use crate::print;
 
 pub const TX_MARKER: &str = "[tx]";
 pub const FAILURE_MARKER: &str = "[tx:failed]";
+pub const DEFAULT_PROMPT: &str = "{database}{mode}{tx}> ";
+pub const PROMPT_VARIABLES: &[&str] = &[
+    "database", "user", "addr", "tx", "mode", "error",
+    "red", "green", "yellow", "blue", "magenta", "cyan", "gray",
+    "bold", "reset",
+];
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,6 +40,21 @@ pub enum InputMode {
     Emacs,
 }
 
+/// Language of the text entered at the REPL prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputLanguage {
+    EdgeQL,
+    /// Each entered JSON document is inserted as an object of the type
+    JsonInsert(String),
+}
+
+/// Isolation level of transactions started from the REPL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionIsolation {
+    Serializable,
+    RepeatableRead,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PrintStats {
     Off,
@@ -39,6 +63,21 @@ pub enum PrintStats {
 }
 
 
+/// `\gexec` of more statements is hardly intended, they aren't kept
+pub const MAX_GEXEC_STATEMENTS: usize = 10000;
+
+/// What `\gexec` and `\gset` need of the last result, instead of a copy
+/// of every row
+#[derive(Debug)]
+pub struct LastResult {
+    pub rows: usize,
+    /// `\gset` requires a single row
+    pub first: Option<Value>,
+    /// Rows for `\gexec`, moved to disk if large, `None` if some of them
+    /// are not strings or there are too many of them
+    pub statements: Option<print::SpillBuffer>,
+}
+
 pub struct PromptRpc {
     pub control: Sender<prompt::Control>,
     pub data: Receiver<prompt::Input>,
@@ -49,8 +88,27 @@ pub struct State {
     pub print: print::Config,
     pub verbose_errors: bool,
     pub last_error: Option<anyhow::Error>,
+    pub last_query: Option<String>,
+    /// Part of the last result needed by `\gexec` and `\gset`, not kept
+    /// in the JSON output modes
+    pub last_result: Option<LastResult>,
+    /// Variables set by `\gset`, referenced in queries as `:name`
+    pub variables: bindings::Variables,
     pub implicit_limit: Option<usize>,
     pub input_mode: InputMode,
+    pub input_language: InputLanguage,
+    pub prompt_template: String,
+    pub paste_transaction: bool,
+    /// Seconds of idle time between keep-alive queries, 0 disables them
+    pub idle_keepalive: usize,
+    /// Ask for confirmation before running destructive statements
+    pub safe_mode: bool,
+    /// Reject mutating statements before sending them
+    pub read_only: bool,
+    /// Server-side limit of the statement execution time
+    pub statement_timeout: Option<Duration>,
+    pub transaction_isolation: TransactionIsolation,
+    pub deferrable: bool,
     pub output_mode: OutputMode,
     pub print_stats: PrintStats,
     pub history_limit: usize,
@@ -61,6 +119,46 @@ pub struct State {
     pub initial_text: String,
 }
 
+/// Runs a no-op statement so that idle connection isn't dropped by
+/// load balancers and firewalls
+async fn keep_alive(conn: &mut Connection) {
+    if !conn.is_consistent() || matches!(conn.transaction_state(),
+                                         TransactionState::InFailedTransaction)
+    {
+        return;
+    }
+    conn.execute("SELECT 1").await
+        .map_err(|e| log::debug!("Keep-alive error: {:#}", e))
+        .ok();
+}
+
+impl LastResult {
+    pub fn new(spill_threshold: Option<usize>) -> LastResult {
+        LastResult {
+            rows: 0,
+            first: None,
+            statements: Some(print::SpillBuffer::new(spill_threshold)),
+        }
+    }
+    pub fn push(&mut self, row: &Value) {
+        if self.rows == 0 {
+            self.first = Some(row.clone());
+        }
+        self.rows += 1;
+        let kept = match (row, &mut self.statements) {
+            (Value::Str(text), Some(statements))
+                if self.rows <= MAX_GEXEC_STATEMENTS
+            => statements.push_line(text)
+                .map_err(|e| log::warn!("Cannot keep the result: {}", e))
+                .is_ok(),
+            _ => false,
+        };
+        if !kept {
+            self.statements = None;
+        }
+    }
+}
+
 impl PromptRpc {
     pub async fn variable_input(&mut self,
         name: &str, type_name: &str, initial: &str)
@@ -92,6 +190,7 @@ impl State {
                 env!("CARGO_PKG_VERSION"));
             self.last_version = Some(fetched_version);
         }
+        self.configure_session(&mut conn).await?;
         self.database = self.conn_params.get()?.get_database().into();
         self.connection = Some(conn);
         Ok(())
@@ -108,11 +207,36 @@ impl State {
                 env!("CARGO_PKG_VERSION"));
             self.last_version = Some(fetched_version);
         }
+        self.configure_session(&mut conn).await?;
         self.conn_params = params;
         self.database = database.into();
         self.connection = Some(conn);
         Ok(())
     }
+    /// Applies session settings to a fresh connection
+    async fn configure_session(&self, conn: &mut Connection)
+        -> anyhow::Result<()>
+    {
+        if self.statement_timeout.is_some() {
+            conn.execute(&self.statement_timeout_query()).await?;
+        }
+        Ok(())
+    }
+    pub fn statement_timeout_query(&self) -> String {
+        match self.statement_timeout {
+            Some(timeout) => format!(
+                "CONFIGURE SESSION SET query_execution_timeout \
+                 := <std::duration>'{}ms'", timeout.as_millis()),
+            None => "CONFIGURE SESSION RESET query_execution_timeout".into(),
+        }
+    }
+    /// Statement which starts a transaction with the configured options
+    pub fn start_transaction_query(&self) -> String {
+        format!("START TRANSACTION ISOLATION {}, {}, {}",
+            self.transaction_isolation.as_edgeql(),
+            if self.read_only { "READ ONLY" } else { "READ WRITE" },
+            if self.deferrable { "DEFERRABLE" } else { "NOT DEFERRABLE" })
+    }
     pub async fn soft_reconnect(&mut self) -> anyhow::Result<()> {
         if !self.in_transaction() {
             self.ensure_connection().await?;
@@ -122,7 +246,13 @@ impl State {
     pub async fn ensure_connection(&mut self) -> anyhow::Result<()> {
         match &self.connection {
             Some(c) if c.is_consistent() => {}
-            Some(_) => {
+            Some(c) => {
+                if !matches!(c.transaction_state(),
+                             TransactionState::NotInTransaction)
+                {
+                    eprintln!("WARNING: Connection was lost, \
+                               transaction cancelled");
+                }
                 eprintln!("Reconnecting...");
                 self.reconnect().await?;
             }
@@ -146,15 +276,48 @@ impl State {
     {
         use TransactionState::*;
 
-        let prompt = format!("{}{}> ",
-            self.database,
-            match self.connection.as_ref().map(|c| c.transaction_state()) {
-                Some(NotInTransaction) => "",
-                Some(InTransaction) => TX_MARKER,
-                Some(InFailedTransaction) => FAILURE_MARKER,
-                None => "",
+        let colors = self.print.colors.unwrap_or(false);
+        let prompt = render_prompt(&self.prompt_template, |name, prompt| {
+            match name {
+                "database" => prompt.push_str(&self.database),
+                "user" => prompt.push_str(&self.conn_params.get()
+                    .map(|p| p.get_user().to_owned())
+                    .unwrap_or_default()),
+                "addr" => prompt.push_str(&self.conn_params.get()
+                    .map(|p| p.get_addr().to_string())
+                    .unwrap_or_default()),
+                "tx" => match self.connection.as_ref()
+                    .map(|c| c.transaction_state())
+                {
+                    Some(NotInTransaction) | None => {}
+                    Some(InTransaction) if colors => {
+                        prompt.push_colored(TX_MARKER, GREEN);
+                    }
+                    Some(InTransaction) => prompt.push_str(TX_MARKER),
+                    Some(InFailedTransaction) if colors => {
+                        prompt.push_colored(FAILURE_MARKER, RED);
+                    }
+                    Some(InFailedTransaction) => {
+                        prompt.push_str(FAILURE_MARKER);
+                    }
+                },
+                "mode" => match self.input_language {
+                    InputLanguage::EdgeQL => {}
+                    InputLanguage::JsonInsert(ref name) => {
+                        prompt.push_str(&format!("[json:{}]", name));
+                    }
+                },
+                "error" => if self.last_error.is_some() {
+                    prompt.push_str("[error]");
+                },
+                _ => match color_code(name) {
+                    Some(code) if colors => prompt.push_code(code),
+                    Some(_) => {}
+                    None => return false,
+                },
             }
-        );
+            true
+        })?;
         self.prompt.control.send(
                 prompt::Control::EdgeqlInput {
                     prompt,
@@ -162,10 +325,22 @@ impl State {
                 }
             ).await
             .context("cannot send to input thread")?;
-        let result = if let Some(conn) = &mut self.connection {
-            self.prompt.data.recv().race(conn.passive_wait()).await
-        } else {
-            self.prompt.data.recv().await
+        let result = loop {
+            let conn = match &mut self.connection {
+                Some(conn) => conn,
+                None => break self.prompt.data.recv().await,
+            };
+            if self.idle_keepalive == 0 {
+                break self.prompt.data.recv().race(conn.passive_wait()).await;
+            }
+            let interval = Duration::from_secs(self.idle_keepalive as u64);
+            let data = &self.prompt.data;
+            let input = async { Some(data.recv().await) };
+            let tick = async { task::sleep(interval).await; None };
+            match input.race(conn.passive_wait()).race(tick).await {
+                Some(result) => break result,
+                None => keep_alive(conn).await,
+            }
         };
         match result {
             Err(RecvError) | Ok(prompt::Input::Eof) => Ok(prompt::Input::Eof),
@@ -214,6 +389,68 @@ impl State {
     }
 }
 
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+
+fn color_code(name: &str) -> Option<&'static str> {
+    match name {
+        "red" => Some(RED),
+        "green" => Some(GREEN),
+        "yellow" => Some("\x1b[33m"),
+        "blue" => Some("\x1b[34m"),
+        "magenta" => Some("\x1b[35m"),
+        "cyan" => Some("\x1b[36m"),
+        "gray" => Some("\x1b[90m"),
+        "bold" => Some("\x1b[1m"),
+        "reset" => Some("\x1b[0m"),
+        _ => None,
+    }
+}
+
+/// Substitutes `{name}` variables in a prompt template
+///
+/// Use `{{` and `}}` for literal braces. The `var` callback appends the
+/// value of the variable and returns `false` if the name is unknown.
+pub fn render_prompt<F>(template: &str, mut var: F)
+    -> anyhow::Result<prompt::Prompt>
+    where F: FnMut(&str, &mut prompt::Prompt) -> bool
+{
+    let mut result = prompt::Prompt::default();
+    let mut tail = template;
+    while let Some(pos) = tail.find(|c| c == '{' || c == '}') {
+        result.push_str(&tail[..pos]);
+        tail = &tail[pos..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            result.push_str(&tail[..1]);
+            tail = &tail[2..];
+        } else if tail.starts_with('}') {
+            anyhow::bail!("unmatched `}}` in prompt template");
+        } else {
+            let end = tail.find('}').ok_or_else(|| {
+                anyhow::anyhow!("unmatched `{{` in prompt template")
+            })?;
+            let name = &tail[1..end];
+            if !var(name, &mut result) {
+                anyhow::bail!("unknown prompt variable {{{}}}, \
+                    supported: {}", name,
+                    PROMPT_VARIABLES.iter()
+                        .map(|v| format!("{{{}}}", v))
+                        .collect::<Vec<_>>()
+                        .join(", "));
+            }
+            tail = &tail[end+1..];
+        }
+    }
+    result.push_str(tail);
+    Ok(result)
+}
+
+/// Checks that template only refers to known variables
+pub fn validate_prompt(template: &str) -> anyhow::Result<()> {
+    render_prompt(template, |name, _| PROMPT_VARIABLES.contains(&name))?;
+    Ok(())
+}
+
 impl std::str::FromStr for InputMode {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<InputMode, anyhow::Error> {
@@ -238,6 +475,17 @@ impl std::str::FromStr for OutputMode {
     }
 }
 
+impl std::str::FromStr for TransactionIsolation {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<TransactionIsolation, anyhow::Error> {
+        match s {
+            "serializable" => Ok(TransactionIsolation::Serializable),
+            "repeatable-read" => Ok(TransactionIsolation::RepeatableRead),
+            _ => Err(anyhow::anyhow!("unsupported isolation level {:?}", s)),
+        }
+    }
+}
+
 impl std::str::FromStr for PrintStats {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<PrintStats, anyhow::Error> {
@@ -273,6 +521,23 @@ impl OutputMode {
     }
 }
 
+impl TransactionIsolation {
+    pub fn as_str(&self) -> &'static str {
+        use TransactionIsolation::*;
+        match self {
+            Serializable => "serializable",
+            RepeatableRead => "repeatable-read",
+        }
+    }
+    pub fn as_edgeql(&self) -> &'static str {
+        use TransactionIsolation::*;
+        match self {
+            Serializable => "SERIALIZABLE",
+            RepeatableRead => "REPEATABLE READ",
+        }
+    }
+}
+
 impl PrintStats {
     pub fn as_str(&self) -> &'static str {
         use PrintStats::*;
@@ -0,0 +1,336 @@
+//! Conversion of object types into JSON Schema documents
+//!
+//! Links are represented as references to the definition of the target
+//! type. Constraints which have a JSON Schema counterpart (lengths, ranges,
+//! regular expressions) are converted, others are skipped.
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::non_interactive::query_json_value;
+use crate::options::Options;
+use crate::schema::options::{Export, ExportFormat};
+
+
+const SYSTEM_MODULES: &str =
+    "^(?:std|schema|math|sys|cfg|cal|stdgraphql)::";
+
+#[derive(Deserialize, Debug)]
+struct Constraint {
+    name: String,
+    params: Vec<Param>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Param {
+    value: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pointer {
+    name: String,
+    target: String,
+    element: Option<String>,
+    is_link: bool,
+    required: bool,
+    multi: bool,
+    constraints: Vec<Constraint>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ObjectType {
+    name: String,
+    pointers: Vec<Pointer>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ScalarType {
+    name: String,
+    ancestors: Vec<String>,
+    enum_values: Option<Vec<String>>,
+    constraints: Vec<Constraint>,
+}
+
+struct Schema {
+    objects: Vec<ObjectType>,
+    scalars: BTreeMap<String, ScalarType>,
+}
+
+fn std_scalar(name: &str, format: ExportFormat) -> Option<Value> {
+    let value = match name {
+        "std::str" | "cal::local_datetime" | "std::duration"
+        | "cal::relative_duration" => json!({"type": "string"}),
+        "std::uuid" => json!({"type": "string", "format": "uuid"}),
+        "std::bool" => json!({"type": "boolean"}),
+        "std::int16" | "std::int32" | "std::int64" | "std::bigint" => {
+            json!({"type": "integer"})
+        }
+        "std::float32" | "std::float64" | "std::decimal" => {
+            json!({"type": "number"})
+        }
+        "std::datetime" => json!({"type": "string", "format": "date-time"}),
+        "cal::local_date" => json!({"type": "string", "format": "date"}),
+        "cal::local_time" => json!({"type": "string", "format": "time"}),
+        "std::json" => json!({}),
+        "std::bytes" => match format {
+            ExportFormat::JsonSchema => {
+                json!({"type": "string", "contentEncoding": "base64"})
+            }
+            ExportFormat::OpenApi => {
+                json!({"type": "string", "format": "byte"})
+            }
+        },
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Parses a constraint argument which is stored as an EdgeQL expression
+fn argument(constraint: &Constraint) -> Option<Value> {
+    let value = constraint.params.iter().find_map(|p| p.value.as_ref())?;
+    let value = value.trim();
+    if let Ok(num) = value.parse::<i64>() {
+        return Some(num.into());
+    }
+    if let Ok(num) = value.parse::<f64>() {
+        return Some(num.into());
+    }
+    let unquoted = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\''))
+        .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')));
+    unquoted.map(|v| v.replace("\\\\", "\\").into())
+}
+
+fn apply_constraints(schema: &mut Map<String, Value>,
+                     constraints: &[Constraint])
+{
+    for constraint in constraints {
+        let keyword = match &constraint.name[..] {
+            "std::max_len_value" => "maxLength",
+            "std::min_len_value" => "minLength",
+            "std::max_value" => "maximum",
+            "std::min_value" => "minimum",
+            "std::max_ex_value" => "exclusiveMaximum",
+            "std::min_ex_value" => "exclusiveMinimum",
+            "std::regexp" => "pattern",
+            _ => continue,
+        };
+        if let Some(value) = argument(constraint) {
+            schema.insert(keyword.into(), value);
+        }
+    }
+}
+
+fn reference(name: &str, format: ExportFormat) -> Value {
+    match format {
+        ExportFormat::JsonSchema => {
+            json!({"$ref": format!("#/$defs/{}", name)})
+        }
+        ExportFormat::OpenApi => {
+            json!({"$ref": format!("#/components/schemas/{}", name)})
+        }
+    }
+}
+
+fn scalar(schema: &Schema, name: &str, format: ExportFormat) -> Value {
+    if let Some(value) = std_scalar(name, format) {
+        return value;
+    }
+    let scalar = match schema.scalars.get(name) {
+        Some(scalar) => scalar,
+        None => return json!({}),
+    };
+    let mut value = if let Some(values) = &scalar.enum_values {
+        json!({"type": "string", "enum": values})
+    } else {
+        scalar.ancestors.iter()
+            .find_map(|a| std_scalar(a, format))
+            .unwrap_or_else(|| json!({}))
+    };
+    if let Value::Object(map) = &mut value {
+        apply_constraints(map, &scalar.constraints);
+    }
+    value
+}
+
+fn pointer(schema: &Schema, ptr: &Pointer, format: ExportFormat) -> Value {
+    let mut value = if ptr.is_link {
+        reference(&ptr.target, format)
+    } else if let Some(element) = &ptr.element {
+        json!({"type": "array", "items": scalar(schema, element, format)})
+    } else {
+        scalar(schema, &ptr.target, format)
+    };
+    if let Value::Object(map) = &mut value {
+        if !ptr.is_link {
+            apply_constraints(map, &ptr.constraints);
+        }
+    }
+    if ptr.multi {
+        json!({"type": "array", "items": value})
+    } else {
+        value
+    }
+}
+
+fn convert(schema: &Schema, format: ExportFormat) -> Value {
+    let mut definitions = Map::new();
+    for object in &schema.objects {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for ptr in &object.pointers {
+            properties.insert(ptr.name.clone(), pointer(schema, ptr, format));
+            if ptr.required {
+                required.push(ptr.name.clone());
+            }
+        }
+        let mut definition = json!({
+            "type": "object",
+            "properties": properties,
+        });
+        if !required.is_empty() {
+            definition["required"] = required.into();
+        }
+        definitions.insert(object.name.clone(), definition);
+    }
+    match format {
+        ExportFormat::JsonSchema => json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": definitions,
+        }),
+        ExportFormat::OpenApi => json!({
+            "openapi": "3.1.0",
+            "info": {
+                "title": "EdgeDB schema",
+                "version": "1",
+            },
+            "paths": {},
+            "components": {
+                "schemas": definitions,
+            },
+        }),
+    }
+}
+
+async fn introspect(options: &Options) -> anyhow::Result<Schema> {
+    let mut conn = options.conn_params.connect().await?;
+    let objects = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema
+        SELECT ObjectType {{
+            name,
+            pointers: {{
+                name,
+                target := .target.name,
+                element := .target[IS Array].element_type.name,
+                is_link := .target IS ObjectType,
+                required,
+                multi := .cardinality = Cardinality.Many,
+                constraints: {{
+                    name,
+                    params: {{ value := @value }}
+                        FILTER .name != '__subject__',
+                }},
+            }}
+            FILTER .name != '__type__'
+            ORDER BY .name,
+        }}
+        FILTER NOT .is_from_alias
+            AND NOT re_test("{system}", .name)
+        ORDER BY .name;
+    "###, system=SYSTEM_MODULES)).await?;
+    let scalars = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema
+        SELECT ScalarType {{
+            name,
+            ancestors := array_agg(.ancestors.name),
+            enum_values,
+            constraints: {{
+                name,
+                params: {{ value := @value }}
+                    FILTER .name != '__subject__',
+            }},
+        }}
+        FILTER NOT .is_from_alias
+            AND NOT re_test("{system}", .name)
+        ORDER BY .name;
+    "###, system=SYSTEM_MODULES)).await?;
+    let scalars: Vec<ScalarType> = serde_json::from_value(scalars)
+        .context("cannot decode scalar types")?;
+    Ok(Schema {
+        objects: serde_json::from_value(objects)
+            .context("cannot decode object types")?,
+        scalars: scalars.into_iter().map(|s| (s.name.clone(), s)).collect(),
+    })
+}
+
+pub async fn export(cmd: &Export, options: &Options) -> anyhow::Result<()> {
+    let schema = introspect(options).await?;
+    let text = serde_json::to_string_pretty(&convert(&schema, cmd.format))?;
+    match &cmd.output {
+        Some(path) => {
+            fs::write(path, text + "\n")
+                .with_context(|| format!("cannot write {}", path.display()))?;
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::{Schema, ObjectType, Pointer, Constraint, Param, convert};
+    use crate::schema::options::ExportFormat;
+
+    #[test]
+    fn json_schema() {
+        let schema = Schema {
+            objects: vec![ObjectType {
+                name: "default::User".into(),
+                pointers: vec![
+                    Pointer {
+                        name: "friends".into(),
+                        target: "default::User".into(),
+                        element: None,
+                        is_link: true,
+                        required: false,
+                        multi: true,
+                        constraints: Vec::new(),
+                    },
+                    Pointer {
+                        name: "name".into(),
+                        target: "std::str".into(),
+                        element: None,
+                        is_link: false,
+                        required: true,
+                        multi: false,
+                        constraints: vec![Constraint {
+                            name: "std::max_len_value".into(),
+                            params: vec![Param { value: Some("100".into()) }],
+                        }],
+                    },
+                ],
+            }],
+            scalars: BTreeMap::new(),
+        };
+        assert_eq!(convert(&schema, ExportFormat::JsonSchema)["$defs"],
+            json!({
+                "default::User": {
+                    "type": "object",
+                    "properties": {
+                        "friends": {
+                            "type": "array",
+                            "items": {"$ref": "#/$defs/default::User"},
+                        },
+                        "name": {"type": "string", "maxLength": 100},
+                    },
+                    "required": ["name"],
+                },
+            }));
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::files::SimpleFile;
+use codespan_reporting::term::emit;
+use colorful::Colorful;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use termcolor::{ColorChoice, StandardStream};
+
+use crate::commands::ExitCode;
+use crate::non_interactive::query_json_value;
+use crate::options::Options;
+use crate::output_style;
+use crate::schema::options::Grep;
+
+
+const SYSTEM_MODULES: &str =
+    "^(?:std|schema|math|sys|cfg|cal|stdgraphql)::";
+
+#[derive(Deserialize, Debug)]
+struct Annotation {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pointer {
+    name: String,
+    is_link: bool,
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Type {
+    name: String,
+    #[serde(default)]
+    pointers: Vec<Pointer>,
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Function {
+    name: String,
+    body: Option<String>,
+    annotations: Vec<Annotation>,
+}
+
+struct Printer {
+    pattern: Regex,
+    colors: bool,
+    matches: usize,
+}
+
+impl Printer {
+    fn highlight(&self, text: &str) -> String {
+        if !self.colors {
+            return text.into();
+        }
+        self.pattern.replace_all(text, |c: &regex::Captures| {
+            c[0].to_string().yellow().bold().to_string()
+        }).into_owned()
+    }
+    fn name(&mut self, kind: &str, name: &str) {
+        if self.pattern.is_match(name) {
+            self.matches += 1;
+            println!("{} {}", kind, self.highlight(name));
+        }
+    }
+    fn annotations(&mut self, subject: &str, annotations: &[Annotation]) {
+        for ann in annotations {
+            if self.pattern.is_match(&ann.name)
+                || self.pattern.is_match(&ann.value)
+            {
+                self.matches += 1;
+                println!("annotation {} {} := {:?}", subject,
+                         self.highlight(&ann.name),
+                         self.highlight(&ann.value));
+            }
+        }
+    }
+    /// Prints lines containing matches with their location
+    fn text(&mut self, title: &str, name: &str, text: &str)
+        -> anyhow::Result<()>
+    {
+        let labels = self.pattern.find_iter(text)
+            .filter(|m| !m.range().is_empty())
+            .map(|m| Label::primary((), m.range()))
+            .collect::<Vec<_>>();
+        if labels.is_empty() {
+            return Ok(());
+        }
+        self.matches += labels.len();
+        let file = SimpleFile::new(name, text);
+        let diag = Diagnostic::note()
+            .with_message(title)
+            .with_labels(labels);
+        let choice = if self.colors {
+            ColorChoice::Always
+        } else {
+            ColorChoice::Never
+        };
+        emit(&mut StandardStream::stdout(choice), &Default::default(),
+             &file, &diag)?;
+        Ok(())
+    }
+}
+
+fn files(dir: &Path, extension: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(files);
+        }
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("cannot read {}", dir.display()));
+        }
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map(|e| e == extension).unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+async fn grep_database(printer: &mut Printer, options: &Options)
+    -> anyhow::Result<()>
+{
+    let mut conn = options.conn_params.connect().await?;
+    let objects = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema
+        SELECT ObjectType {{
+            name,
+            pointers: {{
+                name,
+                is_link := .target IS ObjectType,
+                annotations: {{ name, value := @value }},
+            }}
+            FILTER .name != '__type__'
+            ORDER BY .name,
+            annotations: {{ name, value := @value }},
+        }}
+        FILTER NOT .is_from_alias
+            AND NOT re_test("{system}", .name)
+        ORDER BY .name;
+    "###, system=SYSTEM_MODULES)).await?;
+    let mut types: Vec<Type> = serde_json::from_value(objects)
+        .context("cannot decode object types")?;
+    let scalars = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema
+        SELECT ScalarType {{
+            name,
+            annotations: {{ name, value := @value }},
+        }}
+        FILTER NOT .is_from_alias
+            AND NOT re_test("{system}", .name)
+        ORDER BY .name;
+    "###, system=SYSTEM_MODULES)).await?;
+    let scalars: Vec<Type> = serde_json::from_value(scalars)
+        .context("cannot decode scalar types")?;
+    types.extend(scalars);
+    let functions = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema
+        SELECT Function {{
+            name,
+            body,
+            annotations: {{ name, value := @value }},
+        }}
+        FILTER NOT re_test("{system}", .name)
+        ORDER BY .name;
+    "###, system=SYSTEM_MODULES)).await?;
+    let functions: Vec<Function> = serde_json::from_value(functions)
+        .context("cannot decode functions")?;
+
+    for typ in &types {
+        printer.name("type", &typ.name);
+        printer.annotations(&typ.name, &typ.annotations);
+        for ptr in &typ.pointers {
+            let kind = if ptr.is_link { "link" } else { "property" };
+            let name = format!("{}.{}", typ.name, ptr.name);
+            printer.name(kind, &name);
+            printer.annotations(&name, &ptr.annotations);
+        }
+    }
+    for func in &functions {
+        printer.name("function", &func.name);
+        printer.annotations(&func.name, &func.annotations);
+        if let Some(body) = &func.body {
+            printer.text(&format!("body of function {}", func.name),
+                         &func.name, body)?;
+        }
+    }
+    Ok(())
+}
+
+fn grep_files(printer: &mut Printer, schema_dir: &Path)
+    -> anyhow::Result<()>
+{
+    let schema = files(schema_dir, "esdl")?;
+    let migrations = files(&schema_dir.join("migrations"), "edgeql")?;
+    for (title, path) in schema.iter().map(|p| ("schema file", p))
+        .chain(migrations.iter().map(|p| ("migration", p)))
+    {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+        printer.text(title, &path.display().to_string(), &text)?;
+    }
+    Ok(())
+}
+
+pub async fn grep(cmd: &Grep, options: &Options) -> anyhow::Result<()> {
+    let pattern = RegexBuilder::new(&cmd.pattern)
+        .case_insensitive(cmd.ignore_case)
+        .build()
+        .context("invalid pattern")?;
+    let mut printer = Printer {
+        pattern,
+        colors: output_style::colors(atty::Stream::Stdout),
+        matches: 0,
+    };
+    if !cmd.files_only {
+        grep_database(&mut printer, options).await?;
+    }
+    grep_files(&mut printer, &cmd.cfg.schema_dir)?;
+    if printer.matches == 0 {
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
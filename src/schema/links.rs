@@ -0,0 +1,202 @@
+use std::fmt::Write;
+
+use anyhow::Context;
+use edgeql_parser::helpers::quote_string;
+use serde::Deserialize;
+
+use crate::non_interactive::query_json_value;
+use crate::options::Options;
+use crate::schema::options::Links;
+
+
+#[derive(Deserialize, Debug)]
+struct Property {
+    name: String,
+    target: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    name: String,
+    /// Target type for outgoing links and source type for incoming ones
+    other: String,
+    required: bool,
+    multi: bool,
+    on_target_delete: Option<String>,
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TypeLinks {
+    name: String,
+    outgoing: Vec<Link>,
+    incoming: Vec<Link>,
+}
+
+fn deletion_policy(value: &str) -> &str {
+    match value {
+        "Restrict" => "restrict",
+        "DeleteSource" => "delete source",
+        "Allow" => "allow",
+        "DeferredRestrict" => "deferred restrict",
+        _ => value,
+    }
+}
+
+fn describe(link: &Link) -> String {
+    let mut flags = vec![
+        if link.required { "required" } else { "optional" },
+        if link.multi { "multi" } else { "single" },
+    ];
+    let policy;
+    if let Some(value) = &link.on_target_delete {
+        policy = format!("on target delete {}", deletion_policy(value));
+        flags.push(&policy);
+    }
+    flags.join(", ")
+}
+
+fn render_links(buf: &mut String, links: &[Link], outgoing: bool, last: bool) {
+    let (branch, indent) = if last {
+        ("└── ", "    ")
+    } else {
+        ("├── ", "│   ")
+    };
+    if outgoing {
+        writeln!(buf, "{}outgoing", branch).unwrap();
+    } else {
+        writeln!(buf, "{}incoming", branch).unwrap();
+    }
+    if links.is_empty() {
+        writeln!(buf, "{}└── (none)", indent).unwrap();
+    }
+    for (idx, link) in links.iter().enumerate() {
+        let (branch, sub_indent) = if idx + 1 == links.len() {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        if outgoing {
+            writeln!(buf, "{}{}{} -> {} [{}]", indent, branch,
+                     link.name, link.other, describe(link)).unwrap();
+        } else {
+            writeln!(buf, "{}{}{}.{} [{}]", indent, branch,
+                     link.other, link.name, describe(link)).unwrap();
+        }
+        for (pidx, prop) in link.properties.iter().enumerate() {
+            let branch = if pidx + 1 == link.properties.len() {
+                "└── "
+            } else {
+                "├── "
+            };
+            writeln!(buf, "{}{}{}@{}: {}", indent, sub_indent, branch,
+                     prop.name, prop.target).unwrap();
+        }
+    }
+}
+
+fn render(links: &TypeLinks) -> String {
+    let mut buf = String::new();
+    writeln!(buf, "{}", links.name).unwrap();
+    render_links(&mut buf, &links.outgoing, true, false);
+    render_links(&mut buf, &links.incoming, false, true);
+    buf
+}
+
+pub async fn links(cmd: &Links, options: &Options) -> anyhow::Result<()> {
+    let name = if cmd.type_name.contains("::") {
+        cmd.type_name.clone()
+    } else {
+        format!("default::{}", cmd.type_name)
+    };
+    let mut conn = options.conn_params.connect().await?;
+    let value = query_json_value(&mut conn, &format!(r###"
+        WITH MODULE schema, name := {name}
+        SELECT ObjectType {{
+            name,
+            outgoing := (
+                SELECT .links {{
+                    name,
+                    other := .target.name,
+                    required,
+                    multi := .cardinality = Cardinality.Many,
+                    on_target_delete,
+                    properties: {{
+                        name,
+                        target := .target.name,
+                    }}
+                    FILTER .name NOT IN {{'source', 'target'}}
+                    ORDER BY .name,
+                }}
+                FILTER .name != '__type__'
+                ORDER BY .name
+            ),
+            incoming := (
+                SELECT Link {{
+                    name,
+                    other := .source.name,
+                    required,
+                    multi := .cardinality = Cardinality.Many,
+                    on_target_delete,
+                    properties: {{
+                        name,
+                        target := .target.name,
+                    }}
+                    FILTER .name NOT IN {{'source', 'target'}}
+                    ORDER BY .name,
+                }}
+                FILTER .target.name = name
+                    AND .name != '__type__'
+                    AND NOT .source[IS ObjectType].is_from_alias
+                ORDER BY .source.name THEN .name
+            ),
+        }}
+        FILTER .name = name
+    "###, name=quote_string(&name))).await?;
+    let mut found: Vec<TypeLinks> = serde_json::from_value(value)
+        .context("cannot decode links")?;
+    if found.is_empty() {
+        anyhow::bail!("object type {:?} is not found", name);
+    }
+    print!("{}", render(&found.remove(0)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TypeLinks, Link, Property, render};
+
+    #[test]
+    fn tree() {
+        let links = TypeLinks {
+            name: "default::User".into(),
+            outgoing: vec![Link {
+                name: "friends".into(),
+                other: "default::User".into(),
+                required: false,
+                multi: true,
+                on_target_delete: Some("Allow".into()),
+                properties: vec![Property {
+                    name: "since".into(),
+                    target: "std::datetime".into(),
+                }],
+            }],
+            incoming: vec![Link {
+                name: "author".into(),
+                other: "default::Post".into(),
+                required: true,
+                multi: false,
+                on_target_delete: Some("DeleteSource".into()),
+                properties: Vec::new(),
+            }],
+        };
+        assert_eq!(render(&links), "\
+default::User
+├── outgoing
+│   └── friends -> default::User [optional, multi, on target delete allow]
+│       └── @since: std::datetime
+└── incoming
+    └── default::Post.author [required, single, on target delete delete source]
+");
+    }
+}
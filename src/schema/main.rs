@@ -0,0 +1,18 @@
+use async_std::task;
+
+use crate::options::Options;
+use crate::schema::export;
+use crate::schema::grep;
+use crate::schema::links;
+use crate::schema::options::{SchemaCommand, Command};
+
+
+pub fn main(cmd: &SchemaCommand, options: &Options) -> anyhow::Result<()> {
+    use Command::*;
+
+    match &cmd.subcommand {
+        Export(c) => task::block_on(export::export(c, options)),
+        Links(c) => task::block_on(links::links(c, options)),
+        Grep(c) => task::block_on(grep::grep(c, options)),
+    }
+}
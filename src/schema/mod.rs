@@ -0,0 +1,8 @@
+pub mod options;
+
+mod export;
+mod grep;
+mod links;
+mod main;
+
+pub use main::main;
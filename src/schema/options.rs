@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::{Clap, AppSettings, ValueHint};
+
+use crate::commands::parser::MigrationConfig;
+
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct SchemaCommand {
+    #[clap(subcommand)]
+    pub subcommand: Command,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum Command {
+    /// Export object types as JSON Schema or OpenAPI components
+    Export(Export),
+    /// Show incoming and outgoing links of an object type
+    Links(Links),
+    /// Search names and annotations of types, properties and functions,
+    /// function bodies, schema and migration files
+    Grep(Grep),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    JsonSchema,
+    OpenApi,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Export {
+    /// Output format
+    #[clap(long, default_value="json-schema",
+           possible_values=&["json-schema", "openapi"][..])]
+    pub format: ExportFormat,
+
+    /// Write to a file instead of stdout
+    #[clap(short='o', long, value_hint=ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Links {
+    /// Name of the object type, `default::` module is assumed if omitted
+    pub type_name: String,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Grep {
+    /// Regular expression to search for
+    pub pattern: String,
+
+    /// Ignore case of the pattern
+    #[clap(short='i', long)]
+    pub ignore_case: bool,
+
+    /// Only search schema and migration files, don't connect to
+    /// the database
+    #[clap(long)]
+    pub files_only: bool,
+
+    #[clap(flatten)]
+    pub cfg: MigrationConfig,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<ExportFormat, anyhow::Error> {
+        match s {
+            "json-schema" => Ok(ExportFormat::JsonSchema),
+            "openapi" => Ok(ExportFormat::OpenApi),
+            _ => Err(anyhow::anyhow!("unsupported export format {:?}", s)),
+        }
+    }
+}
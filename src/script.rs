@@ -0,0 +1,229 @@
+//! Client-side directives of the scripts executed non-interactively
+//!
+//! A line starting with a backslash between the statements is a directive:
+//!
+//! * `\if QUERY` executes the following statements up to the matching
+//!   `\else` or `\endif` only if the query returns `true`
+//! * `\else` executes the statements up to `\endif` otherwise
+//! * `\echo TEXT` prints the text to stdout
+//!
+//! Conditions can be nested, queries of the skipped `\if` directives are
+//! not evaluated.
+use std::str;
+
+use anyhow::Context;
+use async_std::io::Read as AsyncRead;
+use async_std::io::prelude::ReadExt;
+use bytes::{Buf, BytesMut};
+use edgedb_client::client::Connection;
+use edgedb_protocol::value::Value;
+
+use crate::classify;
+
+
+#[derive(Debug, PartialEq)]
+pub enum Directive<'a> {
+    If(&'a str),
+    Else,
+    Endif,
+    Echo(&'a str),
+}
+
+#[derive(Debug)]
+struct Branch {
+    parent_active: bool,
+    condition: bool,
+    in_else: bool,
+}
+
+/// Nested `\if` blocks of the script
+#[derive(Debug, Default)]
+pub struct Conditions {
+    stack: Vec<Branch>,
+}
+
+impl Branch {
+    fn is_active(&self) -> bool {
+        self.parent_active && self.condition != self.in_else
+    }
+}
+
+impl Conditions {
+    /// Whether statements at the current position are executed
+    pub fn is_active(&self) -> bool {
+        self.stack.last().map(|b| b.is_active()).unwrap_or(true)
+    }
+    fn start(&mut self, condition: bool) {
+        let parent_active = self.is_active();
+        self.stack.push(Branch {
+            parent_active,
+            condition,
+            in_else: false,
+        });
+    }
+    fn switch(&mut self) -> anyhow::Result<()> {
+        match self.stack.last_mut() {
+            Some(branch) if branch.in_else => {
+                anyhow::bail!("duplicate `\\else` for the same `\\if`");
+            }
+            Some(branch) => {
+                branch.in_else = true;
+                Ok(())
+            }
+            None => anyhow::bail!("`\\else` without `\\if`"),
+        }
+    }
+    fn end(&mut self) -> anyhow::Result<()> {
+        match self.stack.pop() {
+            Some(_) => Ok(()),
+            None => anyhow::bail!("`\\endif` without `\\if`"),
+        }
+    }
+    /// Checks that all the blocks are closed at the end of the script
+    pub fn finish(&self) -> anyhow::Result<()> {
+        if !self.stack.is_empty() {
+            anyhow::bail!("{} `\\if` without `\\endif` at the end of script",
+                          self.stack.len());
+        }
+        Ok(())
+    }
+}
+
+pub fn parse(line: &str) -> anyhow::Result<Directive> {
+    let line = line.trim();
+    let (name, arg) = match line.find(char::is_whitespace) {
+        Some(pos) => (&line[..pos], line[pos..].trim()),
+        None => (line, ""),
+    };
+    match name {
+        "\\if" => {
+            let query = arg.trim_end_matches(';').trim_end();
+            if query.is_empty() {
+                anyhow::bail!("`\\if` requires a query returning bool");
+            }
+            Ok(Directive::If(query))
+        }
+        "\\else" | "\\endif" if !arg.is_empty() => {
+            anyhow::bail!("unexpected argument of `{}`: {:?}", name, arg);
+        }
+        "\\else" => Ok(Directive::Else),
+        "\\endif" => Ok(Directive::Endif),
+        "\\echo" => Ok(Directive::Echo(arg)),
+        _ => anyhow::bail!("unknown directive {:?}, supported: \
+                            `\\if`, `\\else`, `\\endif`, `\\echo`", name),
+    }
+}
+
+pub async fn execute(line: &str, conditions: &mut Conditions,
+                     conn: &mut Connection)
+    -> anyhow::Result<()>
+{
+    match parse(line)? {
+        Directive::If(query) => {
+            let condition = if conditions.is_active() {
+                classify::guard(query)?;
+                conn.query_row::<bool>(query, &Value::empty_tuple()).await
+                    .with_context(|| format!("cannot evaluate `\\if {}`",
+                                             query))?
+            } else {
+                false
+            };
+            conditions.start(condition);
+        }
+        Directive::Else => conditions.switch()?,
+        Directive::Endif => conditions.end()?,
+        Directive::Echo(text) => {
+            if conditions.is_active() {
+                println!("{}", text);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn read_more<T>(buf: &mut BytesMut, input: &mut T)
+    -> anyhow::Result<usize>
+    where T: AsyncRead + Unpin,
+{
+    let mut chunk = [0u8; 8192];
+    let bytes = input.read(&mut chunk).await?;
+    buf.extend_from_slice(&chunk[..bytes]);
+    Ok(bytes)
+}
+
+/// Reads the directive if the input continues with one
+///
+/// Whitespace and comments before it are skipped. Returns `None` if the
+/// input continues with a statement or is finished.
+pub async fn read_directive<T>(buf: &mut BytesMut, input: &mut T)
+    -> anyhow::Result<Option<String>>
+    where T: AsyncRead + Unpin,
+{
+    loop {
+        let space = buf.iter().take_while(|b| b.is_ascii_whitespace())
+            .count();
+        buf.advance(space);
+        let first = match buf.first() {
+            Some(&first) => first,
+            None => {
+                if read_more(buf, input).await? == 0 {
+                    return Ok(None);
+                }
+                continue;
+            }
+        };
+        if first != b'#' && first != b'\\' {
+            return Ok(None);
+        }
+        let line = match buf.iter().position(|b| *b == b'\n') {
+            Some(end) => buf.split_to(end + 1),
+            None => {
+                if read_more(buf, input).await? > 0 {
+                    continue;
+                }
+                buf.split_to(buf.len())
+            }
+        };
+        if first == b'\\' {
+            let line = str::from_utf8(&line)
+                .context("can't decode directive")?;
+            return Ok(Some(line.trim_end().into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Conditions, Directive};
+
+    #[test]
+    fn directives() {
+        assert_eq!(parse("\\if SELECT EXISTS User;").unwrap(),
+                   Directive::If("SELECT EXISTS User"));
+        assert_eq!(parse("\\echo  Creating users ").unwrap(),
+                   Directive::Echo("Creating users"));
+        assert_eq!(parse("\\endif\n").unwrap(), Directive::Endif);
+        assert!(parse("\\if").is_err());
+        assert!(parse("\\else now").is_err());
+        assert!(parse("\\elif x").is_err());
+    }
+
+    #[test]
+    fn nested() {
+        let mut cond = Conditions::default();
+        cond.start(false);
+        assert!(!cond.is_active());
+        cond.start(true);
+        assert!(!cond.is_active());
+        cond.switch().unwrap();
+        assert!(!cond.is_active());
+        cond.end().unwrap();
+        cond.switch().unwrap();
+        assert!(cond.is_active());
+        assert!(cond.switch().is_err());
+        assert!(cond.finish().is_err());
+        cond.end().unwrap();
+        assert!(cond.finish().is_ok());
+        assert!(cond.end().is_err());
+    }
+}
@@ -12,11 +12,14 @@ use indicatif::{ProgressBar, ProgressStyle};
 use url::Url;
 
 use crate::async_util::timeout;
+use crate::output_style;
 use crate::platform::home_dir;
 use crate::process;
+use crate::server::detect::ARCH;
 use crate::server::package::RepositoryInfo;
 use crate::server::remote;
 use crate::server::version::Version;
+use crate::verbosity::Verbosity;
 
 
 #[derive(Clap, Clone, Debug)]
@@ -50,8 +53,8 @@ pub fn get_repo(max_wait: Duration) -> anyhow::Result<RepositoryInfo> {
         ""
     };
     let url = format!(
-        "https://packages.edgedb.com/archive/.jsonindexes/{}-x86_64{}.json",
-        platform, suffix
+        "https://packages.edgedb.com/archive/.jsonindexes/{}-{}{}.json",
+        platform, ARCH, suffix
     );
 
     task::block_on(timeout(
@@ -101,7 +104,11 @@ async fn download(url: &str, path: &Path, quiet: bool) -> anyhow::Result<()> {
         .await
         .map_err(|e| anyhow::anyhow!("{}", e))?
         .take_body();
-    let bar = if quiet {
+    let plain = output_style::is_plain();
+    if plain && !quiet {
+        eprintln!("Downloading {}...", url);
+    }
+    let bar = if quiet || plain {
         ProgressBar::hidden()
     } else if let Some(len) = body.len() {
         ProgressBar::new(len as u64)
@@ -122,10 +129,16 @@ async fn download(url: &str, path: &Path, quiet: bool) -> anyhow::Result<()> {
         out.write_all(&buf[..bytes]).await?;
         bar.inc(bytes as u64);
     }
+    if plain && !quiet {
+        eprintln!("Downloaded {} bytes", bar.position());
+    }
     Ok(())
 }
 
-pub fn main(options: &SelfUpgrade) -> anyhow::Result<()> {
+pub fn main(options: &SelfUpgrade, verbosity: Verbosity)
+    -> anyhow::Result<()>
+{
+    let verbosity = verbosity.with_flags(options.quiet, options.verbose);
     let path = binary_path()?;
     if !_can_upgrade(&path)? {
         anyhow::bail!("Only binary installed at {:?} can be upgraded", path);
@@ -148,7 +161,8 @@ pub fn main(options: &SelfUpgrade) -> anyhow::Result<()> {
         .join(&pkg.installref)
         .context("package installref is invalid")?;
     let tmp_path = path.with_extension("download");
-    task::block_on(download(&url.to_string(), &tmp_path, options.quiet))?;
+    task::block_on(download(&url.to_string(), &tmp_path,
+                             !verbosity.show_progress()))?;
     let backup_path = path.with_extension("backup");
     if cfg!(unix) {
         fs::remove_file(&backup_path).ok();
@@ -164,7 +178,7 @@ pub fn main(options: &SelfUpgrade) -> anyhow::Result<()> {
         anyhow::bail!("unknown OS");
     }
     process::run(Command::new(&path).arg("_gen_completions").arg("--home"))?;
-    if !options.quiet {
+    if verbosity.show_progress() {
         println!("Upgraded to version {} (revision {})",
             pkg.version, pkg.revision);
     }
@@ -0,0 +1,371 @@
+//! Generic server builds distributed as tarballs
+//!
+//! Used on platforms without native packages: FreeBSD, musl-based
+//! distributions (Alpine) and as a fallback for other Linux distributions.
+//! Archives are unpacked into the user data directory, so no privileges
+//! are needed to install them.
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+use async_std::task;
+use serde::Serialize;
+
+use crate::dry_run;
+use crate::hint::HintExt;
+use crate::process;
+use crate::server::detect::{ARCH, Lazy, VersionQuery};
+use crate::server::distribution::{DistributionRef, MajorVersion};
+use crate::server::docker::DockerCandidate;
+use crate::server::init::{self, Storage};
+use crate::server::install;
+use crate::server::linux;
+use crate::server::methods::{InstallationMethods, InstallMethod};
+use crate::server::options::{Upgrade, Destroy};
+use crate::server::os_trait::{CurrentOs, Method, InstanceRef};
+use crate::server::package::{self, PackageMethod, Package};
+use crate::server::package::{PackageCandidate, RepositoryInfo};
+use crate::server::remote;
+use crate::server::unix;
+use crate::server::upgrade;
+use crate::server::version::Version;
+
+
+#[derive(Debug, Serialize)]
+pub struct Archive {
+    #[serde(flatten)]
+    unix: unix::Unix,
+    distro_name: String,
+    distro_version: String,
+    platform: String,
+    #[serde(skip)]
+    stable_repo: Lazy<Option<RepositoryInfo>>,
+    #[serde(skip)]
+    nightly_repo: Lazy<Option<RepositoryInfo>>,
+}
+
+/// Directory where archives are unpacked, one subdirectory per slot
+pub fn base_dir() -> anyhow::Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Can't determine data directory"))?
+        .join("edgedb").join("portable"))
+}
+
+pub fn server_path(slot: &str) -> anyhow::Result<PathBuf> {
+    Ok(base_dir()?.join(slot).join("bin").join("edgedb-server"))
+}
+
+fn archive_name(pkg: &Package) -> String {
+    format!("edgedb-server-{}_{}.tar.gz",
+        pkg.slot, pkg.version.as_ref().replace("-", "_"))
+}
+
+/// Returns architecture from the ELF header, `None` for other
+/// executable formats and architectures
+fn elf_arch(header: &[u8]) -> Option<&'static str> {
+    if header.len() < 20 || &header[..4] != b"\x7fELF" {
+        return None;
+    }
+    let machine = [header[18], header[19]];
+    let machine = match header[5] {
+        1 => u16::from_le_bytes(machine),
+        2 => u16::from_be_bytes(machine),
+        _ => return None,
+    };
+    match machine {
+        0x3E => Some("x86_64"),
+        0xB7 => Some("aarch64"),
+        _ => None,
+    }
+}
+
+fn check_arch(server: &Path) -> anyhow::Result<()> {
+    let mut header = [0u8; 20];
+    fs::File::open(server)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .with_context(|| format!("cannot read {:?}", server))?;
+    match elf_arch(&header) {
+        Some(arch) if arch == ARCH => Ok(()),
+        arch => {
+            return Err(anyhow::anyhow!(
+                "server archive is built for {}, but this system is {}",
+                arch.unwrap_or("unknown architecture"), ARCH))
+                .hint("The package index points to a build for \
+                       another architecture. \
+                       Try `--method=docker` instead.")?;
+        }
+    }
+}
+
+impl Archive {
+    fn new(platform: String, distro_name: String, distro_version: String)
+        -> Archive
+    {
+        Archive {
+            unix: unix::Unix::new(),
+            distro_name,
+            distro_version,
+            platform,
+            stable_repo: Lazy::lazy(),
+            nightly_repo: Lazy::lazy(),
+        }
+    }
+    /// Alpine and other distributions based on musl libc
+    pub fn musl(rel: &os_release::OsRelease) -> Archive {
+        Archive::new(format!("{}-unknown-linux-musl", ARCH),
+                     rel.name.clone(), rel.version_id.clone())
+    }
+    /// Fallback for glibc-based distributions without native packages
+    pub fn linux(rel: &os_release::OsRelease) -> Archive {
+        Archive::new(format!("{}-unknown-linux-gnu", ARCH),
+                     rel.name.clone(), rel.version_id.clone())
+    }
+    pub fn freebsd() -> Archive {
+        let version = process::get_text(Command::new("uname").arg("-r"))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|e| {
+                log::info!("Cannot detect FreeBSD version: {:#}", e);
+                String::new()
+            });
+        Archive::new(format!("{}-unknown-freebsd", ARCH),
+                     "FreeBSD".into(), version)
+    }
+    fn get_repo(&self, nightly: bool)
+        -> anyhow::Result<Option<&RepositoryInfo>>
+    {
+        let (repo, suffix) = if nightly {
+            (&self.nightly_repo, ".nightly")
+        } else {
+            (&self.stable_repo, "")
+        };
+        repo.get_or_try_init(|| {
+            Ok(task::block_on(remote::get_json_opt(
+                &format!("https://packages.edgedb.com/archive/\
+                    .jsonindexes/{}{}.json", self.platform, suffix),
+                "failed to fetch repository index"))?
+            .map(|mut repo: RepositoryInfo| {
+                repo.packages.retain(|p| p.basename == "edgedb-server");
+                repo
+            }))
+        }).map(|opt| opt.as_ref())
+    }
+}
+
+impl CurrentOs for Archive {
+    fn get_available_methods(&self)
+        -> Result<InstallationMethods, anyhow::Error>
+    {
+        let version_supported = self.get_repo(false)?
+            .map(|repo| repo.packages.iter()
+                 .any(|p| p.slot.is_some() && p.matches_arch()))
+            .unwrap_or(false);
+        Ok(InstallationMethods {
+            package: PackageCandidate {
+                supported: version_supported,
+                distro_name: format!("{} (portable build)", self.distro_name),
+                distro_version: self.distro_version.clone(),
+                distro_supported: true,
+                version_supported,
+            },
+            docker: DockerCandidate::detect()?,
+        })
+    }
+    fn detect_all(&self) -> serde_json::Value {
+        self.unix.detect_all();
+        serde_json::to_value(self).expect("can serialize")
+    }
+    fn make_method<'x>(&'x self, method: &InstallMethod,
+        methods: &InstallationMethods)
+        -> anyhow::Result<Box<dyn Method + 'x>>
+    {
+        use InstallMethod::*;
+
+        match method {
+            Package => Ok(Box::new(methods.package.make_method(self)?)),
+            Docker => Ok(Box::new(methods.docker.make_method(self)?)),
+        }
+    }
+}
+
+impl<'os> Method for PackageMethod<'os, Archive> {
+    fn name(&self) -> InstallMethod {
+        InstallMethod::Package
+    }
+    fn install(&self, settings: &install::Settings)
+        -> Result<(), anyhow::Error>
+    {
+        let pkg = settings.distribution.downcast_ref::<Package>()
+            .context("invalid archive package")?;
+        let tmpdir = tempfile::tempdir()?;
+        let name = archive_name(pkg);
+        let archive_path = tmpdir.path().join(&name);
+        let url = if settings.distribution.major_version().is_nightly() {
+            format!("https://packages.edgedb.com/archive/{}.nightly/{}",
+                    self.os.platform, name)
+        } else {
+            format!("https://packages.edgedb.com/archive/{}/{}",
+                    self.os.platform, name)
+        };
+        task::block_on(remote::get_verified_file(&archive_path, &url,
+                                                 pkg.sha256.as_deref()))
+            .context("failed to download server archive")?;
+
+        // unpack next to the target, so that previous installation of
+        // the same slot is kept if the archive doesn't fit this system
+        let target = base_dir()?.join(&pkg.slot);
+        let unpacked = base_dir()?.join(format!(".{}.tmp", pkg.slot));
+        let mut cmd = Command::new("tar");
+        cmd.arg("-xzf").arg(&archive_path)
+            .arg("--strip-components=1")
+            .arg("-C").arg(&unpacked);
+        if !dry_run::run(&cmd) {
+            return Ok(());
+        }
+        if unpacked.exists() {
+            fs::remove_dir_all(&unpacked)
+                .with_context(|| format!("cannot remove {:?}", unpacked))?;
+        }
+        fs::create_dir_all(&unpacked)
+            .with_context(|| format!("cannot create {:?}", unpacked))?;
+        process::run(&mut cmd)?;
+        if let Err(e) = check_arch(&unpacked.join("bin")
+                                   .join("edgedb-server"))
+        {
+            fs::remove_dir_all(&unpacked).ok();
+            return Err(e);
+        }
+        fs::write(unpacked.join("version"), pkg.version.as_ref())
+            .with_context(|| format!("cannot write version to {:?}",
+                                     unpacked))?;
+        if target.exists() {
+            fs::remove_dir_all(&target)
+                .with_context(|| format!("cannot remove {:?}", target))?;
+        }
+        fs::rename(&unpacked, &target)
+            .with_context(|| format!("cannot rename {:?} to {:?}",
+                                     unpacked, target))?;
+        Ok(())
+    }
+    fn uninstall(&self, distr: &DistributionRef)
+        -> Result<(), anyhow::Error>
+    {
+        let pkg = distr.downcast_ref::<Package>()
+            .context("invalid archive package")?;
+        let target = base_dir()?.join(&pkg.slot);
+        if dry_run::perform(format_args!("remove {}", target.display())) {
+            fs::remove_dir_all(&target)
+                .with_context(|| format!("cannot remove {:?}", target))?;
+        }
+        Ok(())
+    }
+    fn all_versions(&self, nightly: bool)
+        -> anyhow::Result<Vec<DistributionRef>>
+    {
+        Ok(self.os.get_repo(nightly)?
+            .map(|x| {
+                x.packages.iter()
+                .filter(|p| p.slot.is_some() && p.matches_arch())
+                .map(|p| p.into())
+                .collect()
+            }).unwrap_or_else(Vec::new))
+    }
+    fn get_version(&self, query: &VersionQuery)
+        -> anyhow::Result<DistributionRef>
+    {
+        let packages = self.os.get_repo(query.is_nightly())?
+            .ok_or_else(|| anyhow::anyhow!("No repository found"))?;
+        package::find_version(packages, query)
+    }
+    fn installed_versions(&self) -> anyhow::Result<Vec<DistributionRef>> {
+        Ok(self.installed.get_or_try_init(|| {
+            let dir = base_dir()?;
+            let mut result = Vec::new();
+            if !dir.exists() {
+                return Ok(result);
+            }
+            for item in fs::read_dir(&dir)? {
+                let item = item?;
+                let slot = match item.file_name().to_str() {
+                    Some(slot) => slot.to_owned(),
+                    None => continue,
+                };
+                let version = match
+                    fs::read_to_string(item.path().join("version"))
+                {
+                    Ok(version) => version.trim().to_owned(),
+                    Err(e) => {
+                        log::info!("Cannot get version of {:?}: {}",
+                                   item.path(), e);
+                        continue;
+                    }
+                };
+                result.push(Package {
+                    major_version: if version.contains(".dev") {
+                        MajorVersion::Nightly
+                    } else {
+                        MajorVersion::Stable(Version(slot.clone()))
+                    },
+                    version: Version(version),
+                    slot,
+                    sha256: None,
+                }.into_ref());
+            }
+            Ok(result)
+        })?.clone())
+    }
+    fn detect_all(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("can serialize")
+    }
+    fn bootstrap(&self, init: &init::Settings) -> anyhow::Result<()> {
+        unix::bootstrap(self, init)
+    }
+    fn get_storage(&self, system: bool, name: &str)-> anyhow::Result<Storage> {
+        unix::storage(system, name)
+    }
+    fn storage_exists(&self, storage: &Storage) -> anyhow::Result<bool> {
+        unix::storage_exists(storage)
+    }
+    fn clean_storage(&self, storage: &Storage) -> anyhow::Result<()> {
+        unix::clean_storage(storage)
+    }
+    fn all_instances<'x>(&'x self) -> anyhow::Result<Vec<InstanceRef<'x>>> {
+        linux::all_instances(self)
+    }
+    fn get_instance<'x>(&'x self, name: &str)
+        -> anyhow::Result<InstanceRef<'x>>
+    {
+        linux::get_instance(self, name)
+    }
+    fn upgrade(&self, todo: &upgrade::ToDo, options: &Upgrade)
+        -> anyhow::Result<()>
+    {
+        unix::upgrade(todo, options, self)
+    }
+    fn destroy(&self, options: &Destroy) -> anyhow::Result<()> {
+        linux::destroy(options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::elf_arch;
+
+    fn header(class: u8, data: u8, machine: [u8; 2]) -> Vec<u8> {
+        let mut header = b"\x7fELF".to_vec();
+        header.extend(&[class, data]);
+        header.resize(18, 0);
+        header.extend(&machine);
+        header
+    }
+
+    #[test]
+    fn elf_header() {
+        assert_eq!(elf_arch(&header(2, 1, [0x3E, 0])), Some("x86_64"));
+        assert_eq!(elf_arch(&header(2, 1, [0xB7, 0])), Some("aarch64"));
+        assert_eq!(elf_arch(&header(2, 2, [0, 0xB7])), Some("aarch64"));
+        assert_eq!(elf_arch(&header(2, 1, [0x28, 0])), None);
+        assert_eq!(elf_arch(b"\xcf\xfa\xed\xfe"), None);
+    }
+}
@@ -154,7 +154,7 @@ impl CurrentOs for Centos {
         let version_supported = self.get_repo(false)?
             .map(|repo| repo.packages.iter().any(|p| {
                 (p.basename == "edgedb" || p.basename == "edgedb-server")
-                && p.architecture == ARCH
+                && p.matches_arch()
             }))
             .unwrap_or(false);
         Ok(InstallationMethods {
@@ -219,7 +219,10 @@ impl<'os> Method for PackageMethod<'os, Centos> {
         Ok(self.os.get_repo(nightly)?
             .map(|x| {
                 x.packages.iter()
-                .filter(|p| p.basename == "edgedb-server" && p.slot.is_some())
+                .filter(|p| {
+                    p.basename == "edgedb-server" && p.slot.is_some()
+                    && p.matches_arch()
+                })
                 .map(|p| p.into())
                 .collect()
             }).unwrap_or_else(Vec::new))
@@ -293,6 +296,7 @@ impl<'os> Method for PackageMethod<'os, Centos> {
                     },
                     version: Version(ver.to_owned()),
                     slot: major_version.to_owned(),
+                    sha256: None,
                 }.into_ref());
             }
             Ok(result)
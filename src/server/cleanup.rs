@@ -0,0 +1,203 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use indicatif::HumanBytes;
+use prettytable::{Table, Row, Cell};
+
+use crate::platform::{config_dir, home_dir};
+use crate::project::init::stash_base;
+use crate::question;
+use crate::server::detect;
+use crate::server::distribution::DistributionRef;
+use crate::server::methods::InstallMethod;
+use crate::server::options::Cleanup;
+use crate::server::remote;
+use crate::server::trash;
+use crate::server::uninstall::is_used_by;
+use crate::server::unix;
+use crate::table;
+
+
+struct StaleFile {
+    path: PathBuf,
+    reason: &'static str,
+    size: u64,
+}
+
+fn size(path: &Path) -> io::Result<u64> {
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += size(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+fn add_file(files: &mut Vec<StaleFile>, path: PathBuf, reason: &'static str) {
+    match size(&path) {
+        Ok(size) => files.push(StaleFile { path, reason, size }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Cannot read {}: {}", path.display(), e),
+    }
+}
+
+fn list_dir(dir: &Path) -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                log::warn!("Cannot read {}: {}", dir.display(), e);
+            }
+            return Vec::new();
+        }
+    };
+    entries.filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_str()?.to_string();
+            Some((name, e.path()))
+        })
+        .collect()
+}
+
+/// Dumps are kept in the data dir after upgrade, they are only needed
+/// while the upgrade is in progress
+fn stale_dumps(files: &mut Vec<StaleFile>) -> anyhow::Result<()> {
+    let base = unix::base_data_dir()?;
+    for (name, path) in list_dir(&base) {
+        let instance = match name.strip_suffix(".dump") {
+            Some(instance) => instance,
+            None => continue,
+        };
+        let upgrading = base
+            .join(format!("{}.UPGRADE_IN_PROGRESS", instance))
+            .exists();
+        let initialized = base.join(instance).join("metadata.json").exists();
+        if initialized && !upgrading {
+            add_file(files, path, "upgrade dump");
+        }
+    }
+    Ok(())
+}
+
+/// Temporary files are left when writing of a file is interrupted
+fn temporary_files(files: &mut Vec<StaleFile>) -> anyhow::Result<()> {
+    let dirs = [
+        unix::base_data_dir()?,
+        home_dir()?.join(".edgedb").join("credentials"),
+        stash_base()?,
+        config_dir()?,
+    ];
+    for dir in &dirs {
+        for (name, path) in list_dir(dir) {
+            if name.starts_with(".~") && name.ends_with(".tmp") ||
+                name == ".instance_ports.json.tmp"
+            {
+                add_file(files, path, "temporary file");
+            }
+        }
+    }
+    if let Ok(exe) = env::current_exe() {
+        add_file(files, exe.with_extension("download"),
+                 "incomplete self-upgrade download");
+    }
+    Ok(())
+}
+
+/// Downloaded packages are only reused when the same version is installed
+/// again
+fn cached_downloads(files: &mut Vec<StaleFile>) -> anyhow::Result<()> {
+    for (_, path) in list_dir(&remote::downloads_dir()?) {
+        add_file(files, path, "cached download");
+    }
+    Ok(())
+}
+
+fn trash_entries(files: &mut Vec<StaleFile>) -> anyhow::Result<()> {
+    for path in trash::entry_dirs()? {
+        add_file(files, path, "destroyed instance");
+    }
+    Ok(())
+}
+
+pub fn cleanup(options: &Cleanup) -> anyhow::Result<()> {
+    let os = detect::current_os()?;
+    let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
+    let mut versions = Vec::<(InstallMethod, DistributionRef)>::new();
+    for (meth_name, meth) in &methods {
+        let mut candidates = meth.installed_versions()?;
+        for inst in meth.all_instances()? {
+            let major = inst.get_version()?;
+            let exact = inst.get_current_version()?;
+            candidates.retain(|cand| !is_used_by(cand, major, exact));
+        }
+        versions.extend(candidates.into_iter()
+            .map(|distr| (meth_name.clone(), distr)));
+    }
+    let mut files = Vec::new();
+    stale_dumps(&mut files)?;
+    temporary_files(&mut files)?;
+    cached_downloads(&mut files)?;
+    trash_entries(&mut files)?;
+
+    if versions.is_empty() && files.is_empty() {
+        eprintln!("Nothing to clean up.");
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    table.set_titles(Row::new(["Kind", "Item", "Size"]
+        .iter().map(|x| table::header_cell(x)).collect()));
+    for (meth_name, distr) in &versions {
+        table.add_row(Row::new(vec![
+            Cell::new("unused server version"),
+            Cell::new(&format!("{} ({})",
+                distr.version(), meth_name.short_name())),
+            Cell::new("?"),
+        ]));
+    }
+    for file in &files {
+        table.add_row(Row::new(vec![
+            Cell::new(file.reason),
+            Cell::new(&file.path.display().to_string()),
+            Cell::new(&HumanBytes(file.size).to_string()),
+        ]));
+    }
+    table.printstd();
+    let total = files.iter().map(|f| f.size).sum::<u64>();
+    if versions.is_empty() {
+        eprintln!("Reclaimable space: {}", HumanBytes(total));
+    } else {
+        eprintln!("Reclaimable space: {} plus the size of unused versions",
+                  HumanBytes(total));
+    }
+
+    if options.dry_run {
+        return Ok(());
+    }
+    if !options.non_interactive {
+        let q = question::Confirm::new("Remove all of the above?");
+        if !q.ask()? {
+            return Ok(());
+        }
+    }
+    for (meth_name, distr) in &versions {
+        log::info!("{}: Uninstalling {}",
+            meth_name.title(), distr.version());
+        methods[meth_name].uninstall(distr)?;
+    }
+    for file in &files {
+        log::info!("Removing {}", file.path.display());
+        if file.path.is_dir() {
+            fs::remove_dir_all(&file.path)?;
+        } else {
+            fs::remove_file(&file.path)?;
+        }
+    }
+    eprintln!("Cleanup complete.");
+    Ok(())
+}
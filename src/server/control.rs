@@ -3,11 +3,16 @@ use std::path::{Path};
 
 use fn_error_context::context;
 
+use crate::hint::HintExt;
+use crate::typos;
+
 use crate::server::detect;
 use crate::server::options::InstanceCommand;
 use crate::server::metadata::Metadata;
 use crate::server::methods::Methods;
+use crate::server::portable;
 use crate::server::revert;
+use crate::server::start_repair;
 use crate::server::status;
 use crate::server::os_trait::{InstanceRef};
 
@@ -30,8 +35,14 @@ pub fn get_instance<'x>(methods: &'x Methods, name: &str)
             }
         }
     }
-    anyhow::bail!("Cannot find instance {:?}:\n{}", name,
-        errors.join("\n"))
+    let err = anyhow::anyhow!("Cannot find instance {:?}:\n{}", name,
+        errors.join("\n"));
+    match typos::similar_instance(name) {
+        Some(found) => Err(err)
+            .with_hint(|| format!("did you mean `{}`?", found))
+            .map_err(Into::into),
+        None => Err(err),
+    }
 }
 
 pub fn instance_command(cmd: &InstanceCommand) -> anyhow::Result<()> {
@@ -41,6 +52,7 @@ pub fn instance_command(cmd: &InstanceCommand) -> anyhow::Result<()> {
         Start(c) => &c.name,
         Stop(c) => &c.name,
         Restart(c) => &c.name,
+        Run(c) => &c.name,
         Logs(c) => &c.name,
         Revert(c) => &c.name,
         Status(c) => {
@@ -55,9 +67,10 @@ pub fn instance_command(cmd: &InstanceCommand) -> anyhow::Result<()> {
     let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
     let inst = get_instance(&methods, name)?;
     match cmd {
-        Start(c) => inst.start(c),
+        Start(c) => start_repair::start(&inst, c),
         Stop(c) => inst.stop(c),
         Restart(c) => inst.restart(c),
+        Run(c) => portable::run(&inst, c),
         Logs(c) => inst.logs(c),
         Revert(c) => revert::revert(inst, c),
         Status(options) => {
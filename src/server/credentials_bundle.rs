@@ -0,0 +1,191 @@
+//! Passphrase-encrypted credentials for moving instance access between
+//! machines
+//!
+//! The key is derived from the passphrase with PBKDF2-HMAC-SHA256 and the
+//! credentials are sealed with ChaCha20-Poly1305. The bundle is a JSON
+//! document with a `format` number, so that the layout can be changed later.
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use edgedb_client::credentials::Credentials;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::aead::{CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Serialize, Deserialize};
+
+use crate::credentials;
+use crate::hint::HintExt;
+use crate::server::is_valid_name;
+use crate::server::options::{ExportCredentials, ImportCredentials};
+use crate::server::reset_password::{read_credentials, write_credentials};
+
+
+const FORMAT: u16 = 1;
+const ITERATIONS: u32 = 100_000;
+const SALT_LENGTH: usize = 16;
+const KEY_LENGTH: usize = 32;
+const AAD: &[u8] = b"edgedb-credentials-bundle";
+
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    format: u16,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Payload {
+    instance_name: String,
+    credentials: Credentials,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32)
+    -> anyhow::Result<LessSafeKey>
+{
+    let iterations = NonZeroU32::new(iterations)
+        .context("invalid number of iterations")?;
+    let mut key = [0u8; KEY_LENGTH];
+    ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations, salt, passphrase.as_bytes(), &mut key);
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key)
+        .map_err(|_| anyhow::anyhow!("invalid key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn seal(data: &[u8], passphrase: &str, iterations: u32)
+    -> anyhow::Result<Bundle>
+{
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt).expect("random bytes");
+    rng.fill(&mut nonce).expect("random bytes");
+    let key = derive_key(passphrase, &salt, iterations)?;
+    let mut buf = data.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce),
+        Aad::from(AAD), &mut buf)
+        .map_err(|_| anyhow::anyhow!("cannot encrypt credentials"))?;
+    Ok(Bundle {
+        format: FORMAT,
+        iterations,
+        salt: base64::encode(&salt),
+        nonce: base64::encode(&nonce),
+        ciphertext: base64::encode(&buf),
+    })
+}
+
+fn open(bundle: &Bundle, passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    if bundle.format != FORMAT {
+        return Err(anyhow::anyhow!(
+            "unsupported credentials bundle format {}", bundle.format))
+            .hint("The bundle was probably created by a newer version \
+                   of edgedb, upgrade this one first")?;
+    }
+    let salt = base64::decode(&bundle.salt).context("invalid salt")?;
+    let nonce = Nonce::try_assume_unique_for_key(
+        &base64::decode(&bundle.nonce).context("invalid nonce")?)
+        .map_err(|_| anyhow::anyhow!("invalid nonce"))?;
+    let mut buf = base64::decode(&bundle.ciphertext)
+        .context("invalid ciphertext")?;
+    let key = derive_key(passphrase, &salt, bundle.iterations)?;
+    let data = key.open_in_place(nonce, Aad::from(AAD), &mut buf)
+        .map_err(|_| {
+            anyhow::anyhow!("cannot decrypt credentials: \
+                             wrong passphrase or damaged file")
+        })?;
+    Ok(data.to_vec())
+}
+
+fn read_passphrase(from_stdin: bool, confirm: bool) -> anyhow::Result<String>
+{
+    if from_stdin {
+        let passphrase = rpassword::read_password()?;
+        if passphrase.is_empty() {
+            anyhow::bail!("passphrase must not be empty");
+        }
+        return Ok(passphrase);
+    }
+    loop {
+        let passphrase = rpassword::read_password_from_tty(
+            Some("Passphrase: "))?;
+        if passphrase.is_empty() {
+            eprintln!("Passphrase must not be empty");
+            continue;
+        }
+        if !confirm {
+            return Ok(passphrase);
+        }
+        let again = rpassword::read_password_from_tty(
+            Some("Confirm passphrase: "))?;
+        if passphrase != again {
+            eprintln!("Passphrases don't match");
+        } else {
+            return Ok(passphrase);
+        }
+    }
+}
+
+pub fn export_credentials(options: &ExportCredentials) -> anyhow::Result<()> {
+    let payload = Payload {
+        instance_name: options.name.clone(),
+        credentials: read_credentials(&credentials::path(&options.name)?)?,
+    };
+    let output = options.output.clone().unwrap_or_else(|| {
+        PathBuf::from(format!("{}.edgedb-credentials", options.name))
+    });
+    let passphrase = read_passphrase(options.passphrase_from_stdin, true)?;
+    let bundle = seal(&serde_json::to_vec(&payload)?,
+                      &passphrase, ITERATIONS)?;
+    fs::write(&output, serde_json::to_vec_pretty(&bundle)?)
+        .with_context(|| format!("cannot write {}", output.display()))?;
+    eprintln!("Credentials of {:?} are exported to {}. Import them with:\n  \
+               edgedb server import-credentials {}",
+              options.name, output.display(), output.display());
+    Ok(())
+}
+
+pub fn import_credentials(options: &ImportCredentials) -> anyhow::Result<()> {
+    let data = fs::read(&options.file)
+        .with_context(|| format!("cannot read {}", options.file.display()))?;
+    let bundle: Bundle = serde_json::from_slice(&data)
+        .with_context(|| {
+            format!("{} is not a credentials bundle", options.file.display())
+        })?;
+    let passphrase = read_passphrase(options.passphrase_from_stdin, false)?;
+    let payload: Payload = serde_json::from_slice(&open(&bundle, &passphrase)?)
+        .context("invalid credentials in the bundle")?;
+    // the bundle is untrusted, its name must not escape
+    // the credentials directory
+    if !is_valid_name(&payload.instance_name) {
+        anyhow::bail!("invalid instance name {:?} in the bundle",
+                      payload.instance_name);
+    }
+    let name = options.name.as_ref().unwrap_or(&payload.instance_name);
+    let path = credentials::path(name)?;
+    if path.exists() && !options.overwrite {
+        return Err(anyhow::anyhow!(
+            "credentials for instance {:?} already exist", name))
+            .hint("Use `--overwrite` to replace them or `--name` \
+                   to import under a different name")?;
+    }
+    write_credentials(&path, &payload.credentials)?;
+    eprintln!("Credentials are imported. To connect run:\n  edgedb -I {}",
+              name.escape_default());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{seal, open};
+
+    #[test]
+    fn roundtrip() {
+        let bundle = seal(b"{\"port\": 10700}", "secret", 10).unwrap();
+        assert_eq!(open(&bundle, "secret").unwrap(), b"{\"port\": 10700}");
+        assert!(open(&bundle, "wrong").is_err());
+    }
+}
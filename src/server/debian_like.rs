@@ -8,7 +8,7 @@ use anyhow::Context;
 use async_std::task;
 use serde::Serialize;
 
-use crate::server::detect::{Lazy, ARCH};
+use crate::server::detect::Lazy;
 use crate::server::docker::DockerCandidate;
 use crate::server::install::{self, Operation, Command};
 use crate::server::package::{RepositoryInfo, PackageCandidate, Package};
@@ -78,7 +78,10 @@ impl Debian {
         Ok(self.get_repo(nightly)?
             .map(|x| {
                 x.packages.iter()
-                .filter(|p| p.basename == "edgedb-server" && p.slot.is_some())
+                .filter(|p| {
+                    p.basename == "edgedb-server" && p.slot.is_some()
+                    && p.matches_arch()
+                })
                 .map(|p| p.into())
                 .collect()
             }).unwrap_or_else(Vec::new))
@@ -89,7 +92,7 @@ impl Debian {
         let version_supported = self.get_repo(false)?
             .map(|repo| repo.packages.iter().any(|p| {
                 (p.basename == "edgedb" || p.basename == "edgedb-server")
-                && p.architecture == ARCH
+                && p.matches_arch()
             }))
             .unwrap_or(false);
         Ok(InstallationMethods {
@@ -244,6 +247,7 @@ pub fn get_installed() -> anyhow::Result<Vec<DistributionRef>> {
                     } else {
                         MajorVersion::Stable(Version(major_version.into()))
                     },
+                    sha256: None,
                 }.into_ref());
                 break;
             }
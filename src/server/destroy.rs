@@ -5,6 +5,8 @@ use std::path::{Path, PathBuf};
 use fn_error_context::context;
 
 use crate::commands::{self, ExitCode};
+use crate::dry_run;
+use crate::i18n;
 use crate::project::init::stash_base;
 use crate::server::detect;
 use crate::server::errors::InstanceNotFound;
@@ -46,8 +48,8 @@ fn read_path(path: &Path) -> anyhow::Result<PathBuf> {
 }
 
 pub fn print_warning(name: &str, project_dirs: &[PathBuf]) {
-    eprintln!("Instance {:?} is used by the following projects:",
-              name);
+    eprintln!("{}", i18n::format("server-destroy.used-by",
+                                 &[("name", &format!("{:?}", name))]));
     for dir in project_dirs {
         let path_path = dir.join("project-path");
         let dest = match read_path(&path_path) {
@@ -59,7 +61,7 @@ pub fn print_warning(name: &str, project_dirs: &[PathBuf]) {
         };
         eprintln!("  {}", dest.display());
     }
-    eprintln!("If you really want to destroy the instance, run:");
+    eprintln!("{}", i18n::text("server-destroy.force-hint"));
     eprintln!("  edgedb server destroy {:?} --force", name);
 }
 
@@ -71,6 +73,10 @@ pub fn destroy(options: &Destroy) -> anyhow::Result<()> {
     }
     do_destroy(options)?;
     for dir in project_dirs {
+        if !dry_run::perform(format_args!("remove project directory {}",
+                                          dir.display())) {
+            continue;
+        }
         let path_path = dir.join("project-path");
         match read_path(&path_path) {
             Ok(path) => eprintln!("Unlinking {}", path.display()),
@@ -1,9 +1,11 @@
 use std::fmt;
 use std::collections::BTreeMap;
+use std::process::Command;
 
 use once_cell::sync::OnceCell;
 use serde::Serialize;
 
+use crate::process;
 use crate::server::version::Version;
 use crate::server::os_trait::CurrentOs;
 use crate::server::methods::{self, InstallMethod};
@@ -14,10 +16,15 @@ use anyhow::Context;
 
 #[cfg(target_arch="x86_64")]
 pub const ARCH: &str = "x86_64";
+#[cfg(target_arch="aarch64")]
+pub const ARCH: &str = "aarch64";
 #[cfg(not(any(
     target_arch="x86_64",
+    target_arch="aarch64",
 )))]
-compile_error!("Unsupported architecture, supported: x86_64");
+compile_error!("Unsupported architecture, supported: x86_64, aarch64");
+
+static SERVER_ARCH: OnceCell<&'static str> = OnceCell::new();
 
 #[derive(Clone, Debug, Default)]
 pub struct Lazy<T>(once_cell::sync::OnceCell<T>);
@@ -73,8 +80,33 @@ impl<T> Lazy<T> {
     }
 }
 
+/// Architecture of server packages to install
+///
+/// This is the architecture the CLI is built for, except for x86_64 CLI
+/// running under Rosetta on Apple Silicon: native aarch64 server is
+/// installed there.
+pub fn server_arch() -> &'static str {
+    SERVER_ARCH.get_or_init(|| {
+        if cfg!(target_os="macos") && ARCH == "x86_64" && is_rosetta() {
+            log::info!("Running under Rosetta, using aarch64 packages");
+            "aarch64"
+        } else {
+            ARCH
+        }
+    })
+}
+
+fn is_rosetta() -> bool {
+    // `sysctl.proc_translated` is 1 for translated processes and is
+    // missing on Intel macs
+    process::get_text(Command::new("sysctl")
+                      .arg("-in").arg("sysctl.proc_translated"))
+        .map(|value| value.trim() == "1")
+        .unwrap_or(false)
+}
+
 pub fn current_os() -> anyhow::Result<Box<dyn CurrentOs>> {
-    use crate::server::{windows, macos, linux, unknown_os};
+    use crate::server::{windows, macos, linux, archive, unknown_os};
 
     if cfg!(windows) {
         Ok(Box::new(windows::Windows::new()))
@@ -83,6 +115,8 @@ pub fn current_os() -> anyhow::Result<Box<dyn CurrentOs>> {
     } else if cfg!(target_os="linux") {
         linux::detect_distro()
             .context("error detecting linux distribution")
+    } else if cfg!(target_os="freebsd") {
+        Ok(Box::new(archive::Archive::freebsd()))
     } else {
         Ok(Box::new(unknown_os::Unknown::new()))
     }
@@ -94,6 +128,7 @@ pub fn main(_arg: &crate::server::options::Detect)
     #[derive(Serialize)]
     struct Info {
         os_type: &'static str,
+        arch: &'static str,
         os_info: serde_json::Value,
         detected: methods::InstallationMethods,
         methods: BTreeMap<InstallMethod, serde_json::Value>,
@@ -104,6 +139,7 @@ pub fn main(_arg: &crate::server::options::Detect)
     let methods = detected.instantiate_all(&*os, true)?;
     serde_json::to_writer_pretty(std::io::stdout(), &Info {
         os_type: os.get_type_name(),
+        arch: server_arch(),
         os_info: os.detect_all(),
         detected,
         methods: methods.iter()
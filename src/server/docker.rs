@@ -14,6 +14,7 @@ use linked_hash_map::LinkedHashMap;
 use serde::{Serialize, Deserialize};
 
 use crate::credentials::{self, get_connector};
+use crate::dry_run;
 use crate::process;
 use crate::platform::home_dir;
 
@@ -682,6 +683,12 @@ impl<'os, O: CurrentOs + ?Sized> DockerMethod<'os, O> {
         })?;
         Ok(())
     }
+    fn object_exists(&self, kind: &str, name: &str) -> anyhow::Result<bool> {
+        Ok(process::run_or_stderr(Command::new(&self.cli)
+            .arg(kind)
+            .arg("inspect")
+            .arg(name))?.is_ok())
+    }
     fn delete_container(&self, name: &str) -> anyhow::Result<bool> {
         match process::run_or_stderr(Command::new(&self.cli)
             .arg("container")
@@ -1082,36 +1089,50 @@ impl<'os, O: CurrentOs + ?Sized> Method for DockerMethod<'os, O> {
     fn destroy(&self, options: &Destroy) -> anyhow::Result<()> {
         let mut found = false;
         let container_name = format!("edgedb_{}", options.name);
-        if self.delete_container(&container_name)? {
-            log::info!(target: "edgedb::server::destroy",
-                "Removed container {:?}", container_name);
-            found = true;
-        }
         let up_container = format!("edgedb_upgrade_{}", options.name);
-        if self.delete_container(&up_container)? {
-            log::info!(target: "edgedb::server::destroy",
-                "Removed container {:?}", up_container);
-            found = true;
-        }
-        match process::run_or_stderr(Command::new(&self.cli)
-            .arg("volume")
-            .arg("remove")
-            .arg(&container_name))?
-        {
-            Ok(_) => {
+        for container in &[&container_name, &up_container] {
+            if dry_run::is_enabled() {
+                if self.object_exists("container", container)? {
+                    dry_run::perform(format_args!(
+                        "stop and remove container {:?}", container));
+                    found = true;
+                }
+            } else if self.delete_container(container)? {
                 log::info!(target: "edgedb::server::destroy",
-                    "Removed volume {:?}", container_name);
+                    "Removed container {:?}", container);
                 found = true;
             }
-            Err(text) if text.contains("No such volume") => {},
-            Err(text) => anyhow::bail!("docker error: {}", text),
+        }
+        if dry_run::is_enabled() {
+            if self.object_exists("volume", &container_name)? {
+                dry_run::perform(format_args!(
+                    "remove volume {:?}", container_name));
+                found = true;
+            }
+        } else {
+            match process::run_or_stderr(Command::new(&self.cli)
+                .arg("volume")
+                .arg("remove")
+                .arg(&container_name))?
+            {
+                Ok(_) => {
+                    log::info!(target: "edgedb::server::destroy",
+                        "Removed volume {:?}", container_name);
+                    found = true;
+                }
+                Err(text) if text.contains("No such volume") => {},
+                Err(text) => anyhow::bail!("docker error: {}", text),
+            }
         }
         let credentials = credentials::path(&options.name)?;
         if credentials.exists() {
             found = true;
-            log::info!(target: "edgedb::server::destroy",
-                "Removing credentials file {}", credentials.display());
-            fs::remove_file(&credentials)?;
+            if dry_run::perform(format_args!("remove credentials file {}",
+                                             credentials.display())) {
+                log::info!(target: "edgedb::server::destroy",
+                    "Removing credentials file {}", credentials.display());
+                fs::remove_file(&credentials)?;
+            }
         }
         if found {
             Ok(())
@@ -1131,6 +1152,9 @@ impl<O: CurrentOs + ?Sized> DockerInstance<'_, O> {
             method: InstallMethod::Docker,
             port: self.get_port()?,
             start_conf: self.get_start_conf()?,
+            bind_addresses: Vec::new(),
+            portable: false,
+            service_manager: None,
         })
     }
     fn get_backup(&self) -> anyhow::Result<BackupStatus> {
@@ -0,0 +1,233 @@
+//! Diagnostics of the local installation
+//!
+//! Every check produces a list of problems. A problem may have a fix which
+//! is applied after the user's confirmation.
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::ExitCode;
+use crate::question;
+use crate::server::detect;
+use crate::server::init::Storage;
+use crate::server::methods::InstallMethod;
+use crate::server::options::Doctor;
+use crate::server::status::{Status, Service, Port, DataDirectory};
+use crate::server::unix;
+
+
+pub struct Problem {
+    message: String,
+    hint: Option<String>,
+    fix: Option<Fix>,
+}
+
+pub struct Fix {
+    title: String,
+    dangerous: bool,
+    action: Box<dyn FnOnce() -> anyhow::Result<()>>,
+}
+
+impl Problem {
+    pub fn new(message: String) -> Problem {
+        Problem { message, hint: None, fix: None }
+    }
+    pub fn hint(mut self, hint: impl Into<String>) -> Problem {
+        self.hint = Some(hint.into());
+        self
+    }
+    pub fn fix(mut self, title: String, dangerous: bool,
+           action: impl FnOnce() -> anyhow::Result<()> + 'static)
+        -> Problem
+    {
+        self.fix = Some(Fix { title, dangerous, action: Box::new(action) });
+        self
+    }
+}
+
+fn check_path(problems: &mut Vec<Problem>) {
+    let found = match which::which_all("edgedb") {
+        Ok(found) => found,
+        Err(e) => {
+            log::debug!("Error searching PATH: {}", e);
+            return;
+        }
+    };
+    let mut binaries = Vec::<PathBuf>::new();
+    for path in found {
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        if !binaries.contains(&path) {
+            binaries.push(path);
+        }
+    }
+    if binaries.len() > 1 {
+        let list = binaries.iter()
+            .map(|p| format!("\n    {}", p.display()))
+            .collect::<String>();
+        problems.push(Problem::new(format!(
+            "multiple `edgedb` binaries are found in PATH, \
+             the first one shadows the others:{}", list))
+            .hint("Remove outdated binaries or reorder PATH"));
+    } else if let (Some(first), Ok(current)) =
+        (binaries.first(), env::current_exe())
+    {
+        let current = fs::canonicalize(&current).unwrap_or(current);
+        if first != &current {
+            problems.push(Problem::new(format!(
+                "`edgedb` in PATH is {}, not the running binary {}",
+                first.display(), current.display()))
+                .hint("Remove the outdated binary or reorder PATH"));
+        }
+    }
+}
+
+pub fn check_instance(status: &Status, problems: &mut Vec<Problem>) {
+    let name = &status.name;
+    match &status.data_status {
+        DataDirectory::NoMetadata => {
+            let mut problem = Problem::new(format!(
+                "data directory of instance {:?} has no metadata, \
+                 it's probably left from an interrupted initialization",
+                name));
+            if let Storage::UserDir(path) = &status.storage {
+                let path = path.clone();
+                problem = problem.fix(
+                    format!("Remove {}", path.display()), true,
+                    move || Ok(fs::remove_dir_all(&path)?));
+            }
+            problems.push(problem);
+        }
+        DataDirectory::Upgrading(_) => {
+            problems.push(Problem::new(format!(
+                "instance {:?} is in the middle of an upgrade", name))
+                .hint(format!("Finish the upgrade with \
+                    `edgedb server upgrade` or undo it with \
+                    `edgedb server revert {}`", name.escape_default())));
+        }
+        DataDirectory::Absent | DataDirectory::Normal => {}
+    }
+    if !status.credentials_file_exists {
+        problems.push(Problem::new(format!(
+            "credentials file of instance {:?} is missing", name))
+            .hint(format!("Run `edgedb server reset-password {}` \
+                to create it", name.escape_default())));
+    }
+    let has_data = matches!(status.data_status, DataDirectory::Normal);
+    let portable = status.metadata.as_ref()
+        .map(|m| m.portable).unwrap_or(false);
+    if status.method == InstallMethod::Package && has_data
+        && !status.service_exists && !portable
+    {
+        let mut problem = Problem::new(format!(
+            "service of instance {:?} is not installed", name));
+        if let Ok(metadata) = &status.metadata {
+            let name = name.clone();
+            let metadata = metadata.clone();
+            problem = problem.fix("Recreate the service".into(), false,
+                move || unix::create_user_service(&name, &metadata));
+        }
+        problems.push(problem);
+    }
+    let running = matches!(status.service, Service::Running {..});
+    if !running && matches!(status.port_status, Port::Occupied) {
+        let port = status.metadata.as_ref().ok().map(|m| m.port)
+            .or(status.reserved_port);
+        if let Some(port) = port {
+            problems.push(Problem::new(format!(
+                "instance {:?} is not running, but its port {} \
+                 is used by another process", name, port))
+                .hint(format!("Stop that process or move the instance \
+                    to another port with `edgedb server set-port {} <port>`",
+                    name.escape_default())));
+        }
+    }
+}
+
+fn check_ports(statuses: &[Status], problems: &mut Vec<Problem>) {
+    let mut by_port = BTreeMap::<u16, Vec<&str>>::new();
+    for status in statuses {
+        if let Ok(metadata) = &status.metadata {
+            by_port.entry(metadata.port).or_default().push(&status.name);
+        }
+    }
+    for (port, names) in by_port {
+        if names.len() > 1 {
+            problems.push(Problem::new(format!(
+                "instances {} are configured to use the same port {}",
+                names.iter().map(|n| format!("{:?}", n))
+                    .collect::<Vec<_>>().join(", "),
+                port))
+                .hint("Only one of them can be running at a time"));
+        }
+    }
+}
+
+fn find_problems() -> anyhow::Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+    check_path(&mut problems);
+
+    let os = detect::current_os()?;
+    let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
+    let mut statuses = Vec::new();
+    for meth in methods.values() {
+        statuses.extend(meth.all_instances()?.into_iter()
+            .map(|i| i.get_status()));
+    }
+    for status in &statuses {
+        check_instance(status, &mut problems);
+    }
+    check_ports(&statuses, &mut problems);
+    Ok(problems)
+}
+
+/// Prints problems and applies fixes confirmed by the user
+///
+/// Returns the number of problems fixed.
+pub fn offer_fixes(problems: Vec<Problem>, non_interactive: bool)
+    -> anyhow::Result<usize>
+{
+    let mut fixed = 0;
+    for problem in problems {
+        eprintln!("Problem: {}", problem.message);
+        if let Some(hint) = &problem.hint {
+            eprintln!("  Hint: {}", hint);
+        }
+        let fix = match problem.fix {
+            Some(fix) if !non_interactive => fix,
+            Some(fix) => {
+                eprintln!("  Fix available: {}", fix.title);
+                continue;
+            }
+            None => continue,
+        };
+        let question = format!("{}?", fix.title);
+        let confirmed = if fix.dangerous {
+            question::Confirm::new_dangerous(question).ask()?
+        } else {
+            question::Confirm::new(question).ask()?
+        };
+        if confirmed {
+            match (fix.action)() {
+                Ok(()) => fixed += 1,
+                Err(e) => eprintln!("edgedb error: {:#}", e),
+            }
+        }
+    }
+    Ok(fixed)
+}
+
+pub fn doctor(options: &Doctor) -> anyhow::Result<()> {
+    let problems = find_problems()?;
+    if problems.is_empty() {
+        eprintln!("No problems found.");
+        return Ok(());
+    }
+    let total = problems.len();
+    let fixed = offer_fixes(problems, options.non_interactive)?;
+    eprintln!("Found {} problem(s), fixed {}.", total, fixed);
+    if fixed < total {
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! Machine-readable description of the local instances
+//!
+//! The JSON export can be passed back with `--diff` to detect drift. The
+//! terraform export is a `locals` block which can be dropped into a
+//! configuration as is.
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::Context;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::commands::ExitCode;
+use crate::server::detect;
+use crate::server::options::{ExportState, StateFormat};
+
+
+const FORMAT: u16 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct InstanceState {
+    method: String,
+    version: String,
+    current_version: Option<String>,
+    port: u16,
+    start_conf: String,
+    #[serde(default)]
+    bind_addresses: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct State {
+    format: u16,
+    instances: BTreeMap<String, InstanceState>,
+}
+
+fn current_state() -> anyhow::Result<State> {
+    let os = detect::current_os()?;
+    let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
+    let mut instances = BTreeMap::new();
+    for meth in methods.values() {
+        for inst in meth.all_instances()? {
+            let status = inst.get_status();
+            let meta = match status.metadata {
+                Ok(meta) => meta,
+                Err(e) => {
+                    log::warn!("Skipping instance {:?}: {:#}",
+                               status.name, e);
+                    continue;
+                }
+            };
+            instances.insert(status.name, InstanceState {
+                method: status.method.short_name().into(),
+                version: meta.version.title().into(),
+                current_version: meta.current_version
+                    .map(|v| v.num().into()),
+                port: meta.port,
+                start_conf: meta.start_conf.to_string(),
+                bind_addresses: meta.bind_addresses,
+            });
+        }
+    }
+    Ok(State { format: FORMAT, instances })
+}
+
+fn fields(inst: &InstanceState) -> serde_json::Map<String, Value> {
+    match serde_json::to_value(inst).expect("state is serializable") {
+        Value::Object(map) => map,
+        _ => unreachable!("state is an object"),
+    }
+}
+
+/// Renders a `locals` block, JSON values are valid HCL expressions
+fn terraform(state: &State) -> String {
+    let mut out = String::from(
+        "# Generated by `edgedb server export-state --format=terraform`\n\
+         locals {\n  edgedb_instances = {\n");
+    for (name, inst) in &state.instances {
+        out.push_str(&format!("    {} = {{\n", name));
+        for (key, value) in fields(inst) {
+            out.push_str(&format!("      {} = {}\n", key, value));
+        }
+        out.push_str("    }\n");
+    }
+    out.push_str("  }\n}\n");
+    out
+}
+
+/// Lines describing changes from the `old` state to the `new` one
+fn diff(old: &State, new: &State) -> Vec<String> {
+    let mut lines = Vec::new();
+    for name in old.instances.keys() {
+        if !new.instances.contains_key(name) {
+            lines.push(format!("- {}", name));
+        }
+    }
+    for (name, inst) in &new.instances {
+        let old_inst = match old.instances.get(name) {
+            Some(old_inst) => old_inst,
+            None => {
+                lines.push(format!("+ {}", name));
+                continue;
+            }
+        };
+        let old_fields = fields(old_inst);
+        for (key, value) in fields(inst) {
+            let old_value = old_fields.get(&key).unwrap_or(&Value::Null);
+            if *old_value != value {
+                lines.push(format!("~ {}: {} {} -> {}",
+                                   name, key, old_value, value));
+            }
+        }
+    }
+    lines
+}
+
+pub fn export_state(options: &ExportState) -> anyhow::Result<()> {
+    let state = current_state()?;
+    if let Some(path) = &options.diff {
+        let data = fs::read(path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+        let old: State = serde_json::from_slice(&data)
+            .with_context(|| {
+                format!("{} is not a JSON state export", path.display())
+            })?;
+        if old.format != FORMAT {
+            anyhow::bail!("unsupported state format {}", old.format);
+        }
+        let lines = diff(&old, &state);
+        if lines.is_empty() {
+            eprintln!("No changes");
+            return Ok(());
+        }
+        for line in lines {
+            println!("{}", line);
+        }
+        return Err(ExitCode::new(1))?;
+    }
+    match options.format {
+        StateFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&state)?);
+        }
+        StateFormat::Terraform => print!("{}", terraform(&state)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{State, InstanceState, FORMAT, diff, terraform};
+
+    fn inst(version: &str, port: u16) -> InstanceState {
+        InstanceState {
+            method: "package".into(),
+            version: version.into(),
+            current_version: None,
+            port,
+            start_conf: "auto".into(),
+            bind_addresses: Vec::new(),
+        }
+    }
+
+    fn state(instances: &[(&str, InstanceState)]) -> State {
+        State {
+            format: FORMAT,
+            instances: instances.iter()
+                .map(|(n, i)| (n.to_string(), i.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn render_terraform() {
+        let text = terraform(&state(&[("inst1", inst("1-beta2", 10700))]));
+        assert!(text.contains(
+            "locals {\n  edgedb_instances = {\n    inst1 = {\n"));
+        for line in &[
+            "      bind_addresses = []\n",
+            "      current_version = null\n",
+            "      method = \"package\"\n",
+            "      port = 10700\n",
+        ] {
+            assert!(text.contains(line), "{:?} not in {:?}", line, text);
+        }
+        assert!(text.ends_with("    }\n  }\n}\n"));
+    }
+
+    #[test]
+    fn drift() {
+        let old = state(&[
+            ("inst1", inst("1-beta2", 10700)),
+            ("inst2", inst("1-beta2", 10701)),
+        ]);
+        let new = state(&[
+            ("inst1", inst("1-beta3", 10700)),
+            ("inst3", inst("1-beta3", 10702)),
+        ]);
+        assert_eq!(diff(&old, &new), vec![
+            "- inst2",
+            "~ inst1: version \"1-beta2\" -> \"1-beta3\"",
+            "+ inst3",
+        ]);
+        assert!(diff(&old, &old).is_empty());
+    }
+}
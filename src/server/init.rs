@@ -18,13 +18,15 @@ use crate::server::reset_password::{generate_password, write_credentials};
 use crate::server::reset_password::{password_hash};
 use crate::server::detect::{self, VersionQuery};
 use crate::server::errors::CannotCreateService;
+use crate::server::init_wizard;
 use crate::server::metadata::Metadata;
 use crate::server::methods::{InstallationMethods, InstallMethod, Methods};
-use crate::server::options::{Init, StartConf};
+use crate::server::options::{Init, StartConf, ServiceManager};
 use crate::server::os_trait::{Method, CurrentOs, InstanceRef};
 use crate::server::version::Version;
 use crate::server::distribution::DistributionRef;
 use crate::server::package::Package;
+use crate::server::service;
 use crate::table;
 
 use edgedb_client::credentials::Credentials;
@@ -54,6 +56,8 @@ pub struct Settings {
     pub database: String,
     pub port: u16,
     pub start_conf: StartConf,
+    pub portable: bool,
+    pub service_manager: Option<ServiceManager>,
     pub suppress_messages: bool,
 }
 
@@ -107,6 +111,16 @@ fn _write_ports(port_map: &BTreeMap<String, u16>, port_file: &Path)
     Ok(())
 }
 
+pub fn set_port(name: &str, port: u16) -> anyhow::Result<()> {
+    let port_file = port_file()?;
+    let mut port_map = _read_ports(&port_file)?;
+    port_map.insert(name.to_string(), port);
+    _write_ports(&port_map, &port_file).with_context(|| {
+        format!("failed writing port mapping {}", port_file.display())
+    })?;
+    Ok(())
+}
+
 pub fn allocate_port(name: &str) -> anyhow::Result<u16> {
     let port_file = port_file()?;
     let mut port_map = _read_ports(&port_file)?;
@@ -121,6 +135,13 @@ pub fn allocate_port(name: &str) -> anyhow::Result<u16> {
     Ok(port)
 }
 
+/// Returns the port `allocate_port` would pick, without saving it
+pub fn suggest_port(name: &str) -> anyhow::Result<u16> {
+    let port_map = read_ports()?;
+    Ok(port_map.get(name).copied()
+       .unwrap_or_else(|| next_min_port(&port_map)))
+}
+
 fn find_version<F>(methods: &Methods, mut cond: F)
     -> anyhow::Result<Option<(DistributionRef, InstallMethod)>>
     where F: FnMut(&DistributionRef) -> bool
@@ -208,6 +229,13 @@ pub fn find_distribution<'x>(current_os: &'x dyn CurrentOs,
 }
 
 pub fn init(options: &Init) -> anyhow::Result<()> {
+    let asked;
+    let options = if options.interactive {
+        asked = init_wizard::ask_options(options)?;
+        &asked
+    } else {
+        options
+    };
     let version_query = VersionQuery::new(
         options.nightly, options.version.as_ref());
     let current_os = detect::current_os()?;
@@ -215,7 +243,16 @@ pub fn init(options: &Init) -> anyhow::Result<()> {
     let (distr, meth_name, method) = find_distribution(
         &*current_os, &avail_methods,
         &version_query, &options.method)?;
-    let port = allocate_port(&options.name)?;
+    if options.no_service && meth_name == InstallMethod::Docker {
+        anyhow::bail!("`--no-service` is not supported for docker instances");
+    }
+    let port = match options.port {
+        Some(port) => {
+            set_port(&options.name, port)?;
+            port
+        }
+        None => allocate_port(&options.name)?,
+    };
     let settings = Settings {
         name: options.name.clone(),
         system: options.system,
@@ -229,7 +266,19 @@ pub fn init(options: &Init) -> anyhow::Result<()> {
         user: options.default_user.clone(),
         database: options.default_database.clone(),
         port,
-        start_conf: options.start_conf,
+        start_conf: if options.no_service {
+            StartConf::Manual
+        } else {
+            options.start_conf
+        },
+        portable: options.no_service,
+        service_manager: if options.no_service ||
+                            meth_name == InstallMethod::Docker
+        {
+            None
+        } else {
+            service::choose(options.service_manager)?
+        },
         suppress_messages: false,
     };
     settings.print();
@@ -364,6 +413,9 @@ impl Settings {
             method: self.method.clone(),
             port: self.port,
             start_conf: self.start_conf,
+            bind_addresses: Vec::new(),
+            portable: self.portable,
+            service_manager: self.service_manager,
         }
     }
     pub fn print(&self) {
@@ -374,12 +426,19 @@ impl Settings {
         ]));
         table.add_row(Row::new(vec![
             Cell::new("Mode"),
-            Cell::new(if self.method == InstallMethod::Docker {
-                "Docker"
+            Cell::new(&if self.method == InstallMethod::Docker {
+                "Docker".into()
+            } else if self.portable {
+                "Portable (no service)".into()
             } else if self.system {
-                "System Service"
+                "System Service".into()
             } else {
-                "User Service"
+                match self.service_manager {
+                    None | Some(ServiceManager::Systemd) => {
+                        "User Service".into()
+                    }
+                    Some(manager) => format!("Service ({})", manager),
+                }
             }),
         ]));
         table.add_row(Row::new(vec![
@@ -0,0 +1,190 @@
+use crate::question;
+use crate::server::init::suggest_port;
+use crate::server::is_valid_name;
+use crate::server::options::{Init, StartConf};
+use crate::server::version::Version;
+
+
+#[derive(Clone, Copy)]
+enum Channel {
+    Stable,
+    Nightly,
+    Specific,
+}
+
+fn ask_name(options: &Init) -> anyhow::Result<String> {
+    let mut q = question::String::new("Instance name");
+    q.default(&options.name);
+    loop {
+        let name = q.ask()?;
+        if is_valid_name(&name) {
+            return Ok(name);
+        }
+        eprintln!("Instance name must be a valid identifier \
+                   (regex: ^[a-zA-Z_][a-zA-Z_0-9]*$)");
+    }
+}
+
+fn ask_version(options: &mut Init) -> anyhow::Result<()> {
+    let channel = question::Numeric::new("Which version of EdgeDB to use?")
+        .option("Latest stable", Channel::Stable)
+        .option("Nightly", Channel::Nightly)
+        .option("Specific version", Channel::Specific)
+        .ask()?;
+    let default = options.version.take()
+        .map(|v| v.num().to_string())
+        .unwrap_or_default();
+    options.nightly = false;
+    match channel {
+        Channel::Stable => {}
+        Channel::Nightly => options.nightly = true,
+        Channel::Specific => {
+            let mut q = question::String::new("Version");
+            q.default(&default);
+            loop {
+                let value = q.ask()?;
+                let value = value.trim();
+                if !value.is_empty() {
+                    options.version = Some(Version(value.into()));
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn ask_port(options: &Init) -> anyhow::Result<u16> {
+    let default = match options.port {
+        Some(port) => port,
+        None => suggest_port(&options.name)?,
+    }.to_string();
+    let mut q = question::String::new("Port");
+    q.default(&default);
+    loop {
+        match q.ask()?.trim().parse() {
+            Ok(port) if port > 0 => return Ok(port),
+            _ => eprintln!("Port must be a number from 1 to 65535"),
+        }
+    }
+}
+
+fn ask_string(title: &str, default: &str) -> anyhow::Result<String> {
+    let mut q = question::String::new(title);
+    q.default(default);
+    loop {
+        let value = q.ask()?;
+        if !value.trim().is_empty() {
+            return Ok(value.trim().into());
+        }
+    }
+}
+
+/// Returns command-line which initializes the same instance
+/// without questions
+fn command_line(options: &Init) -> String {
+    let mut args = vec![
+        "edgedb".to_string(),
+        "server".into(),
+        "init".into(),
+        options.name.clone(),
+    ];
+    if options.nightly {
+        args.push("--nightly".into());
+    }
+    if let Some(version) = &options.version {
+        args.push(format!("--version={}", version.num()));
+    }
+    if let Some(method) = &options.method {
+        args.push(method.option().into());
+    }
+    if let Some(port) = options.port {
+        args.push(format!("--port={}", port));
+    }
+    if options.start_conf != StartConf::Auto {
+        args.push(format!("--start-conf={}", options.start_conf));
+    }
+    if options.default_user != "edgedb" {
+        args.push(format!("--default-user={}", options.default_user));
+    }
+    if options.default_database != "edgedb" {
+        args.push(format!("--default-database={}",
+                          options.default_database));
+    }
+    shell_words::join(&args)
+}
+
+fn print_summary(options: &Init) {
+    println!("Summary:");
+    println!("  Instance name: {}", options.name);
+    println!("  Version: {}", if options.nightly {
+        "nightly".to_string()
+    } else if let Some(version) = &options.version {
+        version.num().to_string()
+    } else {
+        "latest stable".to_string()
+    });
+    if let Some(port) = options.port {
+        println!("  Port: {}", port);
+    }
+    println!("  Start on boot: {}", match options.start_conf {
+        StartConf::Auto => "yes",
+        StartConf::Manual => "no",
+    });
+    println!("  Default user: {}", options.default_user);
+    println!("  Default database: {}", options.default_database);
+    println!("  Password: generated and saved in the credentials file");
+    println!("Equivalent command:");
+    println!("  {}", command_line(options));
+}
+
+/// Asks for instance settings, values passed on the command-line
+/// are used as defaults
+pub fn ask_options(options: &Init) -> anyhow::Result<Init> {
+    let mut options = options.clone();
+    options.interactive = false;
+    loop {
+        options.name = ask_name(&options)?;
+        ask_version(&mut options)?;
+        options.port = Some(ask_port(&options)?);
+        let start = question::Confirm::new(
+            "Start the instance automatically on login?");
+        options.start_conf = if start.ask()? {
+            StartConf::Auto
+        } else {
+            StartConf::Manual
+        };
+        options.default_user = ask_string("Default user",
+                                          &options.default_user)?;
+        options.default_database = ask_string("Default database",
+                                              &options.default_database)?;
+        print_summary(&options);
+        if question::Confirm::new("Initialize the instance?").ask()? {
+            return Ok(options);
+        }
+        let retry = question::Confirm::new("Change the settings?");
+        if !retry.ask()? {
+            anyhow::bail!("Canceled by user");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Clap;
+
+    use super::command_line;
+    use crate::server::options::Init;
+
+    #[test]
+    fn equivalent_command() {
+        let mut options = Init::parse_from(&["init", "inst1"]);
+        assert_eq!(command_line(&options), "edgedb server init inst1");
+        options.nightly = true;
+        options.port = Some(10701);
+        options.default_user = "my user".into();
+        assert_eq!(command_line(&options),
+            "edgedb server init inst1 --nightly --port=10701 \
+             '--default-user=my user'");
+    }
+}
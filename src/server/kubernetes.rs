@@ -0,0 +1,95 @@
+//! Kubernetes manifests for running a copy of a local instance in a cluster
+//!
+//! Only the server version, the port, and the default user and database are
+//! carried over. A new password is generated for the cluster, the one of
+//! the local instance is never exported.
+use std::fs;
+
+use anyhow::Context;
+
+use crate::credentials;
+use crate::project::generate::render;
+use crate::server::control::get_instance;
+use crate::server::detect;
+use crate::server::options::{GenerateK8s, K8sFormat};
+use crate::server::reset_password::{generate_password, read_credentials};
+
+
+const MANIFESTS: &str = include_str!("templates/k8s.yml");
+const HELM_VALUES: &str = include_str!("templates/helm-values.yml");
+
+/// Converts an instance name to a valid name of a Kubernetes resource
+fn resource_name(instance: &str) -> String {
+    let name = instance.to_lowercase().replace('_', "-");
+    format!("edgedb-{}", name.trim_matches('-'))
+        .trim_end_matches('-').into()
+}
+
+/// Quotes a value for YAML (JSON strings are valid YAML)
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).expect("string can be serialized")
+}
+
+pub fn generate(options: &GenerateK8s) -> anyhow::Result<()> {
+    let os = detect::current_os()?;
+    let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
+    let inst = get_instance(&methods, &options.name)?;
+    let version = inst.get_version()?.title().to_string();
+    let port = inst.get_port()?.to_string();
+
+    let cred_path = credentials::path(&options.name)?;
+    let (user, database) = if cred_path.exists() {
+        let creds = read_credentials(&cred_path)?;
+        let database = creds.database.clone()
+            .unwrap_or_else(|| "edgedb".into());
+        (creds.user, database)
+    } else {
+        ("edgedb".into(), "edgedb".into())
+    };
+
+    let template = match options.format {
+        K8sFormat::Manifests => MANIFESTS,
+        K8sFormat::Helm => HELM_VALUES,
+    };
+    let text = render(template, &[
+        ("instance", &options.name),
+        ("name", &resource_name(&options.name)),
+        ("namespace", &options.namespace),
+        ("image", &format!("edgedb/edgedb:{}", version)),
+        ("version", &version),
+        ("port", &port),
+        ("user", &quote(&user)),
+        ("database", &quote(&database)),
+        ("password", &quote(&generate_password())),
+        ("storage", &options.storage),
+    ]);
+    match &options.output {
+        Some(path) => {
+            fs::write(path, &text)
+                .with_context(|| format!("cannot write {}", path.display()))?;
+            eprintln!("Written {}", path.display());
+        }
+        None => print!("{}", text),
+    }
+    eprintln!("A new password is generated for the cluster, \
+               keep the output private");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{resource_name, quote};
+
+    #[test]
+    fn names() {
+        assert_eq!(resource_name("inst1"), "edgedb-inst1");
+        assert_eq!(resource_name("My_Instance"), "edgedb-my-instance");
+        assert_eq!(resource_name("_tmp_"), "edgedb-tmp");
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("edgedb"), r#""edgedb""#);
+        assert_eq!(quote(r#"a"b: c"#), r#""a\"b: c""#);
+    }
+}
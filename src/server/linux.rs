@@ -8,23 +8,27 @@ use dirs::home_dir;
 use edgedb_client as client;
 use serde::Serialize;
 
-use crate::credentials::{self, get_connector};
-use crate::platform::{get_current_uid};
+use crate::credentials::get_connector;
+use crate::dry_run;
 use crate::process;
 use crate::server::control::read_metadata;
 use crate::server::detect::Lazy;
 use crate::server::distribution::{MajorVersion};
+use crate::server::doctor::Problem;
 use crate::server::docker::DockerCandidate;
 use crate::server::errors::InstanceNotFound;
 use crate::server::metadata::Metadata;
 use crate::server::methods::{InstallationMethods, InstallMethod};
 use crate::server::options::{StartConf, Start, Stop, Restart, Logs, Destroy};
+use crate::server::options::ServiceManager;
 use crate::server::os_trait::{CurrentOs, Method, Instance, InstanceRef};
 use crate::server::package::PackageCandidate;
+use crate::server::portable;
+use crate::server::service::{self, ServiceBackend};
 use crate::server::status::{Service, Status};
 use crate::server::version::Version;
 use crate::server::unix;
-use crate::server::{debian, ubuntu, centos};
+use crate::server::{archive, debian, ubuntu, centos};
 
 
 #[derive(Debug)]
@@ -57,12 +61,15 @@ impl LocalInstance<'_> {
             }
         })
     }
+    fn service(&self) -> Box<dyn ServiceBackend> {
+        service::backend(self.get_meta().ok()
+                         .and_then(|m| m.service_manager))
+    }
     fn socket_dir(&self) -> anyhow::Result<PathBuf> {
-        Ok(dirs::runtime_dir()
-            .unwrap_or_else(|| {
-                Path::new("/run/user").join(get_current_uid().to_string())
-            })
-            .join(format!("edgedb-{}", self.name)))
+        self.service().runstate_dir(&self.name)
+    }
+    fn is_portable(&self) -> bool {
+        self.get_meta().map(|m| m.portable).unwrap_or(false)
     }
 }
 
@@ -90,27 +97,24 @@ impl Instance for LocalInstance<'_> {
     fn start(&self, options: &Start) -> anyhow::Result<()> {
         if options.foreground {
             process::run(&mut self.get_command()?)?;
+        } else if self.is_portable() {
+            portable::no_service(&self.name)?;
         } else {
-            process::run(Command::new("systemctl")
-                .arg("--user")
-                .arg("start")
-                .arg(format!("edgedb-server@{}", self.name)))?;
+            self.service().start(&self.name)?;
         }
         Ok(())
     }
     fn stop(&self, _options: &Stop) -> anyhow::Result<()> {
-        process::run(Command::new("systemctl")
-            .arg("--user")
-            .arg("stop")
-            .arg(format!("edgedb-server@{}", self.name)))?;
-        Ok(())
+        if self.is_portable() {
+            return portable::stop(&self.name);
+        }
+        self.service().stop(&self.name)
     }
     fn restart(&self, _options: &Restart) -> anyhow::Result<()> {
-        process::run(Command::new("systemctl")
-            .arg("--user")
-            .arg("restart")
-            .arg(format!("edgedb-server@{}", self.name)))?;
-        Ok(())
+        if self.is_portable() {
+            return portable::no_service(&self.name);
+        }
+        self.service().restart(&self.name)
     }
     fn get_connector(&self, admin: bool) -> anyhow::Result<client::Builder> {
         if admin {
@@ -128,11 +132,10 @@ impl Instance for LocalInstance<'_> {
         }
     }
     fn service_status(&self) -> anyhow::Result<()> {
-        process::exit_from(Command::new("systemctl")
-            .arg("--user")
-            .arg("status")
-            .arg(format!("edgedb-server@{}", self.name)))?;
-        Ok(())
+        if self.is_portable() {
+            return portable::no_service(&self.name);
+        }
+        self.service().print_status(&self.name)
     }
     fn name(&self) -> &str {
         &self.name
@@ -141,17 +144,22 @@ impl Instance for LocalInstance<'_> {
         self.method
     }
     fn get_status(&self) -> Status {
-        let system = false;
-        let service = systemd_status(&self.name, system);
-        let service_exists = systemd_service_path(&self.name, system)
-            .map(|p| p.exists())
-            .unwrap_or(false);
+        if self.is_portable() {
+            let service = portable::service_status(&self.name);
+            return unix::status(&self.name, &self.path, false, service);
+        }
+        let backend = self.service();
+        let service = backend.status(&self.name);
+        let service_exists = backend.exists(&self.name);
         unix::status(&self.name, &self.path, service_exists, service)
     }
     fn get_command(&self) -> anyhow::Result<Command> {
         let socket_dir = self.socket_dir()?;
         let mut cmd = Command::new(get_server_path(Some(self.get_slot()?)));
         cmd.arg("--port").arg(self.get_meta()?.port.to_string());
+        for addr in &self.get_meta()?.bind_addresses {
+            cmd.arg("--bind-address").arg(addr);
+        }
         cmd.arg("--data-dir").arg(&self.path);
         cmd.arg("--runstate-dir").arg(&socket_dir);
         Ok(cmd)
@@ -176,15 +184,10 @@ impl Instance for LocalInstance<'_> {
         unix::revert(self, metadata)
     }
     fn logs(&self, logs: &Logs) -> anyhow::Result<()> {
-        let mut cmd = Command::new("journalctl");
-        cmd.arg("--user-unit").arg(unit_name(&self.name));
-        if let Some(n) = logs.tail  {
-            cmd.arg(format!("--lines={}", n));
-        }
-        if logs.follow {
-            cmd.arg("--follow");
+        if self.is_portable() {
+            return portable::no_service(&self.name);
         }
-        process::run(&mut cmd)
+        self.service().logs(&self.name, logs)
     }
 }
 
@@ -239,11 +242,12 @@ pub fn detect_distro() -> Result<Box<dyn CurrentOs>, anyhow::Error> {
             "debian" => Ok(Box::new(debian::Debian::new(&rel)?)),
             "ubuntu" => Ok(Box::new(ubuntu::Ubuntu::new(&rel)?)),
             "centos" => Ok(Box::new(centos::Centos::new(&rel)?)),
-            _ => Ok(Box::new(Unknown {
-                distro_name: rel.id.clone(),
-                distro_version: rel.version_codename.clone(),
-                error: anyhow::anyhow!("Unsupported distribution {:?}", rel.id)
-            })),
+            "alpine" => Ok(Box::new(archive::Archive::musl(&rel))),
+            _ => {
+                log::info!("No native packages for {:?}, \
+                            using portable builds", rel.id);
+                Ok(Box::new(archive::Archive::linux(&rel)))
+            }
         }
     } else if Path::new("/etc/centos-release").exists() {
         let data = fs::read_to_string("/etc/centos-release")
@@ -271,7 +275,16 @@ pub fn detect_distro() -> Result<Box<dyn CurrentOs>, anyhow::Error> {
 
 pub fn get_server_path(slot: Option<&String>) -> PathBuf {
     if let Some(slot) = slot {
-        Path::new("/usr/bin").join(format!("edgedb-server-{}", slot))
+        let path = Path::new("/usr/bin")
+            .join(format!("edgedb-server-{}", slot));
+        if path.exists() {
+            return path;
+        }
+        // installed from a portable build archive
+        match archive::server_path(slot) {
+            Ok(portable) if portable.exists() => portable,
+            _ => path,
+        }
     } else {
         PathBuf::from("/usr/bin/edgedb-server")
     }
@@ -294,7 +307,7 @@ Type=notify
 Environment=EDGEDATA={directory}
 RuntimeDirectory=edgedb-{instance_name}
 
-ExecStart={server_path} --data-dir=${{EDGEDATA}} --runstate-dir=%t/edgedb-{instance_name} --port={port}
+ExecStart={server_path} --data-dir=${{EDGEDATA}} --runstate-dir=%t/edgedb-{instance_name} --port={port}{bind_args}
 ExecReload=/bin/kill -HUP ${{MAINPID}}
 KillMode=mixed
 KillSignal=SIGINT
@@ -307,6 +320,9 @@ WantedBy=multi-user.target
         directory=path.display(),
         server_path=get_server_path(meta.slot.as_ref()).display(),
         port=meta.port,
+        bind_args=meta.bind_addresses.iter()
+            .map(|addr| format!(" --bind-address={}", addr))
+            .collect::<String>(),
         userinfo=if system {
             "User=edgedb\n\
              Group=edgedb"
@@ -326,7 +342,7 @@ fn unit_dir(system: bool) -> anyhow::Result<PathBuf> {
     }
 }
 
-fn unit_name(name: &str) -> String {
+pub fn unit_name(name: &str) -> String {
     format!("edgedb-server@{}.service", name)
 }
 
@@ -401,6 +417,42 @@ pub fn systemd_status(name: &str, system: bool) -> Service {
     }
 }
 
+/// Problems of the systemd user session which prevent the service
+/// from starting or keep it from running
+pub fn start_problems(name: &str, problems: &mut Vec<Problem>) {
+    let manager = unix::storage_dir(name)
+        .and_then(|dir| read_metadata(&dir))
+        .ok()
+        .and_then(|meta| meta.service_manager);
+    if !matches!(manager, None | Some(ServiceManager::Systemd)) {
+        return;
+    }
+    let unit = format!("edgedb-server@{}", name);
+    let result = process::get_text(Command::new("systemctl")
+        .arg("--user").arg("show").arg(&unit).arg("--property=Result"));
+    if matches!(result.as_ref().map(|r| r.trim()),
+                Ok("Result=start-limit-hit"))
+    {
+        problems.push(Problem::new(format!(
+            "service of instance {:?} was restarted too many times \
+             and systemd refuses to start it", name))
+            .fix("Reset failed state of the service".into(), false,
+                 move || process::run(Command::new("systemctl")
+                    .arg("--user").arg("reset-failed").arg(&unit))));
+    }
+    let user = whoami::username();
+    let linger = process::get_text(Command::new("loginctl")
+        .arg("show-user").arg(&user).arg("--property=Linger"));
+    if matches!(linger.as_ref().map(|r| r.trim()), Ok("Linger=no")) {
+        problems.push(Problem::new(format!(
+            "lingering is disabled for user {:?}, so services are \
+             stopped when the user logs out", user))
+            .fix("Enable lingering for the user".into(), false,
+                 move || process::run(Command::new("loginctl")
+                    .arg("enable-linger").arg(&user))));
+    }
+}
+
 pub fn all_instances<'x>(method: &'x dyn Method)
     -> anyhow::Result<Vec<InstanceRef<'x>>>
 {
@@ -449,58 +501,74 @@ fn systemd_is_not_found_error(e: &str) -> bool {
     e.contains(".service does not exist")
 }
 
-pub fn destroy(options: &Destroy) -> anyhow::Result<()> {
+/// Stops, disables and removes systemd service of the instance, returns
+/// `false` if there is no such service
+pub fn remove_systemd_service(name: &str,
+    not_found_error: &mut Option<String>)
+    -> anyhow::Result<bool>
+{
     let system = false;
     let mut found = false;
-    let name = &options.name;
     let svc_name = format!("edgedb-server@{}", name);
     log::info!(target: "edgedb::server::destroy",
         "Stopping service {}", svc_name);
-    let mut not_found_error = None;
     let mut cmd = Command::new("systemctl");
     cmd.arg("--user");
     cmd.arg("stop");
     cmd.arg(&svc_name);
-    match process::run_or_stderr(&mut cmd)? {
-        Ok(()) => found = true,
-        Err(e) if systemd_is_not_found_error(&e) => {
-            not_found_error = Some(e);
+    if dry_run::run(&cmd) {
+        match process::run_or_stderr(&mut cmd)? {
+            Ok(()) => found = true,
+            Err(e) if systemd_is_not_found_error(&e) => {
+                *not_found_error = Some(e);
+            }
+            Err(e) => {
+                Err(anyhow::anyhow!("Error running {:?}: {}", cmd, e))?
+            }
         }
-        Err(e) => Err(anyhow::anyhow!("Error running {:?}: {}", cmd, e))?,
     }
 
     let mut cmd = Command::new("systemctl");
     cmd.arg("--user");
     cmd.arg("disable");
     cmd.arg(&svc_name);
-    match process::run_or_stderr(&mut cmd)? {
-        Ok(()) => found = true,
-        Err(e) if systemd_is_not_found_error(&e) => {
-            not_found_error = Some(e);
+    if dry_run::run(&cmd) {
+        match process::run_or_stderr(&mut cmd)? {
+            Ok(()) => found = true,
+            Err(e) if systemd_is_not_found_error(&e) => {
+                *not_found_error = Some(e);
+            }
+            Err(e) => {
+                Err(anyhow::anyhow!("Error running {:?}: {}", cmd, e))?
+            }
         }
-        Err(e) => Err(anyhow::anyhow!("Error running {:?}: {}", cmd, e))?,
     }
 
     let svc_path = systemd_service_path(name, system)?;
     if svc_path.exists() {
         found = true;
-        fs::remove_file(&svc_path)?;
+        if dry_run::perform(format_args!("remove service file {}",
+                                         svc_path.display())) {
+            fs::remove_file(&svc_path)?;
+        }
     }
+    Ok(found)
+}
 
-    let dir = unix::storage_dir(name)?;
-    if dir.exists() {
-        found = true;
-        log::info!(target: "edgedb::server::destroy",
-            "Removing data directory {}", dir.display());
-        fs::remove_dir_all(&dir)?;
-    }
-    let credentials = credentials::path(&options.name)?;
-    if credentials.exists() {
-        found = true;
-        log::info!(target: "edgedb::server::destroy",
-            "Removing credentials file {}", credentials.display());
-        fs::remove_file(&credentials)?;
-    }
+pub fn destroy(options: &Destroy) -> anyhow::Result<()> {
+    let name = &options.name;
+    let mut not_found_error = None;
+    let manager = unix::storage_dir(name)
+        .and_then(|dir| read_metadata(&dir))
+        .ok()
+        .and_then(|meta| meta.service_manager);
+    let mut found = match manager {
+        None | Some(ServiceManager::Systemd) => {
+            remove_systemd_service(name, &mut not_found_error)?
+        }
+        Some(_) => service::backend(manager).remove(name)?,
+    };
+    found |= unix::remove_instance_files(name, options.purge)?;
     if found {
         Ok(())
     } else if let Some(e) = not_found_error {
@@ -11,13 +11,15 @@ use fn_error_context::context;
 use once_cell::unsync::OnceCell;
 use serde::Serialize;
 
-use crate::credentials::{self, get_connector};
+use crate::credentials::get_connector;
+use crate::dry_run;
 use crate::platform::{get_current_uid, home_dir};
 use crate::process;
 use crate::server::control::read_metadata;
-use crate::server::detect::{ARCH, Lazy, VersionQuery};
+use crate::server::detect::{Lazy, VersionQuery, server_arch};
 use crate::server::distribution::{DistributionRef, Distribution, MajorVersion};
 use crate::server::docker::DockerCandidate;
+use crate::server::doctor::Problem;
 use crate::server::errors::InstanceNotFound;
 use crate::server::init::{self, Storage};
 use crate::server::install::{self, Operation, Command};
@@ -28,6 +30,7 @@ use crate::server::options::{StartConf};
 use crate::server::os_trait::{CurrentOs, Method, Instance, InstanceRef};
 use crate::server::package::{PackageMethod, Package};
 use crate::server::package::{self, PackageCandidate, RepositoryInfo};
+use crate::server::portable;
 use crate::server::remote;
 use crate::server::status::{Service, Status};
 use crate::server::unix;
@@ -71,7 +74,7 @@ impl CurrentOs for Macos {
         let version_supported = self.get_repo(false)?
             .map(|repo| repo.packages.iter().any(|p| {
                 (p.basename == "edgedb" || p.basename == "edgedb-server")
-                && p.architecture == ARCH
+                && p.matches_arch()
             }))
             .unwrap_or(false);
         Ok(InstallationMethods {
@@ -120,14 +123,14 @@ impl Macos {
             self.nightly_repo.get_or_try_init(|| {
                 task::block_on(remote::get_json_opt(
                     &format!("https://packages.edgedb.com/archive/\
-                        .jsonindexes/macos-{}.nightly.json", ARCH),
+                        .jsonindexes/macos-{}.nightly.json", server_arch()),
                     "failed to fetch repository index"))
             }).map(|opt| opt.as_ref())
         } else {
             self.stable_repo.get_or_try_init(|| {
                 Ok(task::block_on(remote::get_json_opt(
                     &format!("https://packages.edgedb.com/archive/\
-                        .jsonindexes/macos-{}.json", ARCH),
+                        .jsonindexes/macos-{}.json", server_arch()),
                     "failed to fetch repository index"))?
                 .map(|mut repo: RepositoryInfo| {
                     repo.packages
@@ -159,13 +162,14 @@ impl<'os> Method for PackageMethod<'os, Macos> {
         let url = if settings.distribution.major_version().is_nightly() {
             format!("https://packages.edgedb.com/archive/\
                 macos-{arch}.nightly/{name}",
-                arch=ARCH, name=package_name)
+                arch=server_arch(), name=package_name)
         } else {
             format!("https://packages.edgedb.com/archive/\
                 macos-{arch}/{name}",
-                arch=ARCH, name=package_name)
+                arch=server_arch(), name=package_name)
         };
-        task::block_on(remote::get_file(&pkg_path, &url))
+        task::block_on(remote::get_verified_file(&pkg_path, &url,
+                                                 pkg.sha256.as_deref()))
             .context("failed to download package")?;
 
         let operations = vec![
@@ -207,7 +211,10 @@ impl<'os> Method for PackageMethod<'os, Macos> {
         Ok(self.os.get_repo(nightly)?
             .map(|x| {
                 x.packages.iter()
-                .filter(|p| p.basename == "edgedb-server" && p.slot.is_some())
+                .filter(|p| {
+                    p.basename == "edgedb-server" && p.slot.is_some()
+                    && p.matches_arch()
+                })
                 .map(|p| p.into())
                 .collect()
             }).unwrap_or_else(Vec::new))
@@ -272,6 +279,7 @@ impl<'os> Method for PackageMethod<'os, Macos> {
                     },
                     version: Version(version.to_string()),
                     slot: major.to_string(),
+                    sha256: None,
                 }.into_ref());
             }
             Ok(result)
@@ -338,28 +346,22 @@ impl<'os> Method for PackageMethod<'os, Macos> {
         log::info!(target: "edgedb::server::destroy",
             "Unloading service");
         let unit_path = unit_path(&options.name)?;
-        process::run(&mut StdCommand::new("launchctl")
-            .arg("unload").arg(&unit_path))?;
-        if unit_path.exists() {
-            found = true;
-            log::info!(target: "edgedb::server::destroy",
-                "Removing unit file {}", unit_path.display());
-            fs::remove_file(unit_path)?;
-        }
-        let dir = unix::storage_dir(&options.name)?;
-        if dir.exists() {
-            found = true;
-            log::info!(target: "edgedb::server::destroy",
-                "Removing data directory {}", dir.display());
-            fs::remove_dir_all(&dir)?;
+        let mut cmd = StdCommand::new("launchctl");
+        cmd.arg("unload").arg(&unit_path);
+        if dry_run::run(&cmd) {
+            process::run(&mut cmd)?;
         }
-        let credentials = credentials::path(&options.name)?;
-        if credentials.exists() {
+        if unit_path.exists() {
             found = true;
-            log::info!(target: "edgedb::server::destroy",
-                "Removing credentials file {}", credentials.display());
-            fs::remove_file(&credentials)?;
+            if dry_run::perform(format_args!("remove unit file {}",
+                                             unit_path.display())) {
+                log::info!(target: "edgedb::server::destroy",
+                    "Removing unit file {}", unit_path.display());
+                fs::remove_file(unit_path)?;
+            }
         }
+        found |= unix::remove_instance_files(&options.name,
+                                            options.purge)?;
         if found {
             Ok(())
         } else {
@@ -390,6 +392,9 @@ impl LocalInstance<'_> {
     fn socket_dir(&self) -> anyhow::Result<PathBuf> {
         Ok(runtime_dir(&self.name)?)
     }
+    fn is_portable(&self) -> bool {
+        self.get_meta().map(|m| m.portable).unwrap_or(false)
+    }
 }
 
 impl<'a> Instance for LocalInstance<'a> {
@@ -420,6 +425,10 @@ impl<'a> Instance for LocalInstance<'a> {
         Ok(self.get_meta()?.start_conf)
     }
     fn get_status(&self) -> Status {
+        if self.is_portable() {
+            let service = portable::service_status(&self.name);
+            return unix::status(&self.name, &self.path, false, service);
+        }
         let system = false;
         let service = launchctl_status(&self.name, system,
             // TODO
@@ -432,6 +441,8 @@ impl<'a> Instance for LocalInstance<'a> {
     fn start(&self, options: &Start) -> anyhow::Result<()> {
         if options.foreground {
             process::run(&mut self.get_command()?)?;
+        } else if self.is_portable() {
+            portable::no_service(&self.name)?;
         } else {
             process::run(&mut StdCommand::new("launchctl")
                 .arg("load").arg("-w")
@@ -440,12 +451,18 @@ impl<'a> Instance for LocalInstance<'a> {
         Ok(())
     }
     fn stop(&self, _options: &Stop) -> anyhow::Result<()> {
+        if self.is_portable() {
+            return portable::stop(&self.name);
+        }
         process::run(&mut StdCommand::new("launchctl")
             .arg("unload")
             .arg(&self.unit_path()?))?;
         Ok(())
     }
     fn restart(&self, _options: &Restart) -> anyhow::Result<()> {
+        if self.is_portable() {
+            return portable::no_service(&self.name);
+        }
         process::run(&mut StdCommand::new("launchctl")
             .arg("kickstart")
             .arg("-k")
@@ -453,6 +470,9 @@ impl<'a> Instance for LocalInstance<'a> {
         Ok(())
     }
     fn service_status(&self) -> anyhow::Result<()> {
+        if self.is_portable() {
+            return portable::no_service(&self.name);
+        }
         process::exit_from(&mut StdCommand::new("launchctl")
             .arg("print")
             .arg(self.launchd_name()))?;
@@ -477,6 +497,9 @@ impl<'a> Instance for LocalInstance<'a> {
         let socket_dir = self.socket_dir()?;
         let mut cmd = StdCommand::new(get_server_path(&self.get_slot()?));
         cmd.arg("--port").arg(self.get_meta()?.port.to_string());
+        for addr in &self.get_meta()?.bind_addresses {
+            cmd.arg("--bind-address").arg(addr);
+        }
         cmd.arg("--data-dir").arg(&self.path);
         cmd.arg("--runstate-dir").arg(&socket_dir);
         Ok(cmd)
@@ -501,6 +524,9 @@ impl<'a> Instance for LocalInstance<'a> {
         unix::revert(self, metadata)
     }
     fn logs(&self, options: &Logs) -> anyhow::Result<()> {
+        if self.is_portable() {
+            return portable::no_service(&self.name);
+        }
         let mut cmd = StdCommand::new("tail");
         if let Some(n) = options.tail {
             cmd.arg("-n").arg(n.to_string());
@@ -563,7 +589,7 @@ fn plist_data(name: &str, meta: &Metadata)
         <string>{server_path}</string>
         <string>--data-dir={directory}</string>
         <string>--runstate-dir={runtime_dir}</string>
-        <string>--port={port}</string>
+        <string>--port={port}</string>{bind_args}
     </array>
 
     <key>RunAtLoad</key>
@@ -594,6 +620,10 @@ fn plist_data(name: &str, meta: &Metadata)
             StartConf::Manual => "<true/>",
         },
         port=meta.port,
+        bind_args=meta.bind_addresses.iter()
+            .map(|addr| format!(
+                "\n        <string>--bind-address={}</string>", addr))
+            .collect::<String>(),
         userinfo=if system {
             "<key>UserName</key><string>edgedb</string>"
         } else {
@@ -677,6 +707,29 @@ pub fn create_launchctl_service(name: &str, meta: &Metadata)
     Ok(())
 }
 
+/// Problems of launchd which prevent the service from starting
+pub fn start_problems(name: &str, problems: &mut Vec<Problem>) {
+    let launchd_name = format!("gui/{}/edgedb-server-{}",
+                               get_current_uid(), name);
+    let loaded = process::get_text(&mut StdCommand::new("launchctl")
+        .arg("print").arg(&launchd_name)).is_ok();
+    let running = matches!(launchctl_status(name, false, &StatusCache::new()),
+                           Service::Running {..});
+    if loaded && !running {
+        if let Ok(unit_path) = unit_path(name) {
+            problems.push(Problem::new(format!(
+                "service of instance {:?} is loaded but not running, \
+                 launchctl doesn't load it again", name))
+                .hint(format!("See the log at {}",
+                    log_file(name).map(|p| p.display().to_string())
+                    .unwrap_or_else(|_| "~/.edgedb/logs".into())))
+                .fix("Unload the service".into(), false,
+                     move || process::run(&mut StdCommand::new("launchctl")
+                        .arg("unload").arg(&unit_path))));
+        }
+    }
+}
+
 fn unit_path(name: &str) -> anyhow::Result<PathBuf> {
     let plist = format!("com.edgedb.edgedb-server-{}.plist", &name);
     Ok(home_dir()?.join("Library/LaunchAgents").join(plist))
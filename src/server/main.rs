@@ -1,13 +1,19 @@
 use crate::server::options::{ServerCommand, Command};
 
+use crate::server::cleanup;
 use crate::server::control;
+use crate::server::credentials_bundle;
 use crate::server::destroy;
 use crate::server::detect;
+use crate::server::export_state;
 use crate::server::info;
 use crate::server::init;
 use crate::server::install;
+use crate::server::kubernetes;
 use crate::server::list_versions;
 use crate::server::reset_password;
+#[cfg(unix)] use crate::server::run_temp;
+use crate::server::set_port;
 use crate::server::uninstall;
 use crate::server::upgrade;
 
@@ -18,12 +24,24 @@ pub fn main(cmd: &ServerCommand) -> Result<(), anyhow::Error> {
     match &cmd.subcommand {
         Install(c) => install::install(c),
         Uninstall(c) => uninstall::uninstall(c),
+        Cleanup(c) => cleanup::cleanup(c),
         Init(c) => init::init(c),
         Destroy(c) => destroy::destroy(c),
+        #[cfg(unix)]
+        RunTemp(c) => run_temp::run_temp(c),
+        #[cfg(not(unix))]
+        RunTemp(_) => anyhow::bail!("temporary instances are \
+                                     not supported on this platform"),
         ListVersions(c) => list_versions::list_versions(c),
         Instance(c) => control::instance_command(c),
         Upgrade(c) => upgrade::upgrade(c),
+        SetPort(c) => set_port::set_port(c),
+        SetListenAddress(c) => set_port::set_listen_address(c),
         ResetPassword(c) => reset_password::reset_password(c),
+        ExportCredentials(c) => credentials_bundle::export_credentials(c),
+        ImportCredentials(c) => credentials_bundle::import_credentials(c),
+        GenerateK8s(c) => kubernetes::generate(c),
+        ExportState(c) => export_state::export_state(c),
         Info(c) => info::info(c),
         _Detect(c) => detect::main(c),
     }
@@ -5,7 +5,7 @@ use serde_json::Value;
 use crate::server::version::Version;
 use crate::server::distribution::{MajorVersion};
 use crate::server::methods::InstallMethod;
-use crate::server::options::StartConf;
+use crate::server::options::{StartConf, ServiceManager};
 
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
@@ -17,6 +17,12 @@ pub struct Metadata {
     pub method: InstallMethod,
     pub port: u16,
     pub start_conf: StartConf,
+    /// Addresses to listen on, server's default is used when empty
+    pub bind_addresses: Vec<String>,
+    /// Instance has no service and is run by `edgedb server run`
+    pub portable: bool,
+    /// Service manager on Linux, systemd is used if not set
+    pub service_manager: Option<ServiceManager>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -30,6 +36,12 @@ pub struct MetadataV2 {
     method: InstallMethod,
     port: u16,
     start_conf: StartConf,
+    #[serde(default, skip_serializing_if="Vec::is_empty")]
+    bind_addresses: Vec<String>,
+    #[serde(default, skip_serializing_if="is_false")]
+    portable: bool,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    service_manager: Option<ServiceManager>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -47,6 +59,10 @@ fn two() -> u16 {
     2
 }
 
+fn is_false(val: &bool) -> bool {
+    !*val
+}
+
 impl<'de> Deserialize<'de> for Metadata {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -85,6 +101,9 @@ impl From<MetadataV1> for Metadata {
             method: m.method,
             port: m.port,
             start_conf: m.start_conf,
+            bind_addresses: Vec::new(),
+            portable: false,
+            service_manager: None,
         }
     }
 }
@@ -98,6 +117,9 @@ impl From<MetadataV2> for Metadata {
             method: m.method,
             port: m.port,
             start_conf: m.start_conf,
+            bind_addresses: m.bind_addresses,
+            portable: m.portable,
+            service_manager: m.service_manager,
         }
     }
 }
@@ -112,6 +134,9 @@ impl From<Metadata> for MetadataV2 {
             method: m.method,
             port: m.port,
             start_conf: m.start_conf,
+            bind_addresses: m.bind_addresses,
+            portable: m.portable,
+            service_manager: m.service_manager,
         }
     }
 }
@@ -136,6 +161,9 @@ mod test {
             method: InstallMethod::Package,
             port: 10700,
             start_conf: StartConf::Auto,
+            bind_addresses: Vec::new(),
+            portable: false,
+            service_manager: None,
         });
 
         assert_eq!(serde_json::from_str::<Metadata>(r###"
@@ -148,6 +176,9 @@ mod test {
             method: InstallMethod::Package,
             port: 10700,
             start_conf: StartConf::Auto,
+            bind_addresses: Vec::new(),
+            portable: false,
+            service_manager: None,
         });
     }
 
@@ -160,6 +191,9 @@ mod test {
             method: InstallMethod::Package,
             port: 10700,
             start_conf: StartConf::Auto,
+            bind_addresses: Vec::new(),
+            portable: false,
+            service_manager: None,
         }).unwrap(), r###"{
   "format": 2,
   "version": "1-alpha5",
@@ -176,6 +210,9 @@ mod test {
             method: InstallMethod::Package,
             port: 10700,
             start_conf: StartConf::Auto,
+            bind_addresses: Vec::new(),
+            portable: false,
+            service_manager: None,
         }).unwrap(), r###"{
   "format": 2,
   "version": "nightly",
@@ -186,4 +223,22 @@ mod test {
   "start_conf": "Auto"
 }"###);
     }
+
+    #[test]
+    fn portable_metadata() {
+        let meta = Metadata {
+            version: MajorVersion::Stable(Version("1-beta2".into())),
+            current_version: None,
+            slot: Some("1-beta2".into()),
+            method: InstallMethod::Package,
+            port: 10700,
+            start_conf: StartConf::Manual,
+            bind_addresses: Vec::new(),
+            portable: true,
+            service_manager: None,
+        };
+        let text = serde_json::to_string(&meta).unwrap();
+        assert!(text.contains(r#""portable":true"#));
+        assert_eq!(serde_json::from_str::<Metadata>(&text).unwrap(), meta);
+    }
 }
@@ -7,6 +7,7 @@ pub mod distribution;
 pub mod remote;
 pub mod version;
 pub mod os_trait;
+pub mod service;
 mod debian_like;
 
 // OSs
@@ -15,6 +16,7 @@ mod linux;
 mod debian;
 mod ubuntu;
 mod centos;
+mod archive;
 mod macos;
 mod windows;
 mod unknown_os;
@@ -24,16 +26,27 @@ mod docker;
 pub mod package;
 
 // commands
+mod cleanup;
 mod control;
+mod credentials_bundle;
 pub mod destroy;
+pub mod doctor;
 pub mod errors;
+mod export_state;
 mod info;
 pub mod init;
+mod init_wizard;
 pub mod install;
+mod kubernetes;
 mod list_versions;
+mod portable;
 mod reset_password;
 mod revert;
+#[cfg(unix)] mod run_temp;
+mod set_port;
+mod start_repair;
 mod status;
+pub mod trash;
 mod uninstall;
 mod upgrade;
 
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use clap::{Clap, AppSettings, ArgSettings, ValueHint};
@@ -24,6 +25,8 @@ pub enum InstanceCommand {
     Stop(Stop),
     /// Restart an instance
     Restart(Restart),
+    /// Run an instance created with `--no-service` in the foreground
+    Run(Run),
     /// Status of an instance
     Status(Status),
     /// Show logs of an instance
@@ -38,18 +41,37 @@ pub enum Command {
     Install(Install),
     /// Uninstall edgedb-server
     Uninstall(Uninstall),
+    /// Remove unused server versions, destroyed instances kept in the trash
+    /// and stale temporary files
+    Cleanup(Cleanup),
     /// List available and installed versions of the server
     ListVersions(ListVersions),
     /// Initialize a new server instance
     Init(Init),
     /// Destroy a server instance and remove the data stored
     Destroy(Destroy),
+    /// Run a temporary instance which is destroyed on exit
+    RunTemp(RunTemp),
     #[clap(flatten)]
     Instance(InstanceCommand),
     /// Upgrade installations and instances
     Upgrade(Upgrade),
+    /// Change the port an instance listens on
+    SetPort(SetPort),
+    /// Change addresses an instance listens on
+    SetListenAddress(SetListenAddress),
     /// Reset password for a user in the instance
     ResetPassword(ResetPassword),
+    /// Export credentials of an instance into a passphrase-protected file
+    ExportCredentials(ExportCredentials),
+    /// Import credentials exported by `export-credentials`
+    ImportCredentials(ImportCredentials),
+    /// Print Kubernetes manifests deploying the version and settings of an
+    /// instance to a cluster
+    #[clap(name="generate-k8s")]
+    GenerateK8s(GenerateK8s),
+    /// Describe all local instances in a machine-readable form
+    ExportState(ExportState),
     /// Show server information
     Info(Info),
     /// Show system introspection debug info
@@ -88,6 +110,17 @@ pub struct Uninstall {
     pub verbose: bool,
 }
 
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Cleanup {
+    /// Only show what would be removed
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Remove without asking for confirmation
+    #[clap(long)]
+    pub non_interactive: bool,
+}
+
 #[derive(Clap, Debug, Clone)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub struct ListVersions {
@@ -111,6 +144,16 @@ pub enum StartConf {
     Manual,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum ServiceManager {
+    Systemd,
+    Supervisord,
+    Openrc,
+    Runit,
+    Rcd,
+}
+
 #[derive(Clap, Debug, Clone)]
 pub struct Init {
     /// Database server instance name
@@ -132,6 +175,18 @@ pub struct Init {
     #[clap(long, default_value="auto",
            possible_values=&["auto", "manual"][..])]
     pub start_conf: StartConf,
+    /// Do not create a systemd/launchd service for the instance. The server
+    /// is started by `edgedb server run` instead (useful in containers and
+    /// CI where no service manager is available)
+    #[clap(long)]
+    pub no_service: bool,
+    /// Service manager to register the instance with (Linux and FreeBSD).
+    /// Detected from the running init system by default
+    #[clap(long, conflicts_with="no-service",
+           possible_values=&[
+               "systemd", "supervisord", "openrc", "runit", "rcd",
+           ][..])]
+    pub service_manager: Option<ServiceManager>,
 
     /// Default database name (created during initialization, and saved in
     /// credentials file)
@@ -158,6 +213,37 @@ pub struct Init {
     pub upgrade_marker: Option<String>,
 }
 
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct RunTemp {
+    #[clap(long)]
+    pub nightly: bool,
+    #[clap(long, conflicts_with="nightly")]
+    pub version: Option<Version<String>>,
+    /// Port to listen on (a free port is picked by default)
+    #[clap(long)]
+    pub port: Option<u16>,
+    /// Command to run against the instance, connection parameters are
+    /// passed in `EDGEDB_*` environment variables. Without a command the
+    /// instance runs until interrupted by Ctrl+C
+    #[clap(last=true)]
+    pub command: Vec<String>,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Undo {
+    #[clap(subcommand)]
+    pub subcommand: UndoCommand,
+}
+
+#[derive(Clap, Debug, Clone)]
+pub enum UndoCommand {
+    /// Restore data directory and credentials of the most recently
+    /// destroyed local instance (kept for 7 days)
+    LastDestroy,
+}
+
 #[derive(Clap, Debug, Clone)]
 pub struct Destroy {
     /// Database server instance name to destroy
@@ -171,6 +257,13 @@ pub struct Destroy {
     /// Force destroy even if instance is referred to by a project
     #[clap(long)]
     pub force: bool,
+
+    /// Remove the data directory instead of moving it to the trash.
+    /// Trashed data can be restored by `edgedb undo last-destroy` but
+    /// keeps using disk space until `edgedb server cleanup` or for up to
+    /// 7 days
+    #[clap(long)]
+    pub purge: bool,
 }
 
 #[derive(Clap, Debug, Clone)]
@@ -210,6 +303,18 @@ pub struct Restart {
     pub name: String,
 }
 
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Run {
+    /// Database server instance name
+    #[clap(validator(instance_name_opt))]
+    #[clap(value_hint=ValueHint::Other)]  // TODO complete instance name
+    pub name: String,
+    /// Exit when the server fails instead of restarting it
+    #[clap(long)]
+    pub no_restart: bool,
+}
+
 #[derive(Clap, Debug, Clone)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub struct Status {
@@ -338,6 +443,118 @@ pub struct ResetPassword {
     pub quiet: bool,
 }
 
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct ExportCredentials {
+    /// Database server instance name
+    #[clap(validator(instance_name_opt))]
+    #[clap(value_hint=ValueHint::Other)]  // TODO complete instance name
+    pub name: String,
+    /// File to write to. Default is `<name>.edgedb-credentials`
+    #[clap(short='o', long, value_hint=ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+    /// Read a passphrase from stdin rather than from the terminal
+    #[clap(long)]
+    pub passphrase_from_stdin: bool,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct ImportCredentials {
+    /// File written by `edgedb server export-credentials`
+    #[clap(value_hint=ValueHint::FilePath)]
+    pub file: PathBuf,
+    /// Save credentials under this instance name instead of the original one
+    #[clap(long, validator(instance_name_opt))]
+    pub name: Option<String>,
+    /// Replace existing credentials of the instance
+    #[clap(long)]
+    pub overwrite: bool,
+    /// Read a passphrase from stdin rather than from the terminal
+    #[clap(long)]
+    pub passphrase_from_stdin: bool,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct GenerateK8s {
+    /// Database server instance name
+    #[clap(validator(instance_name_opt))]
+    #[clap(value_hint=ValueHint::Other)]  // TODO complete instance name
+    pub name: String,
+    /// Emit a StatefulSet, a Service and a Secret, or a values file for a
+    /// Helm chart
+    #[clap(long, default_value="manifests",
+           possible_values=&["manifests", "helm"][..])]
+    pub format: K8sFormat,
+    /// Kubernetes namespace of the resources
+    #[clap(long, default_value="default")]
+    pub namespace: String,
+    /// Size of the persistent volume for the data
+    #[clap(long, default_value="10Gi")]
+    pub storage: String,
+    /// File to write to instead of stdout
+    #[clap(short='o', long, value_hint=ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum K8sFormat {
+    Manifests,
+    Helm,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct ExportState {
+    /// Output format
+    #[clap(long, default_value="json",
+           possible_values=&["json", "terraform"][..])]
+    pub format: StateFormat,
+    /// Compare current state with a previous JSON export and print the
+    /// differences instead. Exits with status 1 if anything has changed
+    #[clap(long, value_hint=ValueHint::FilePath)]
+    pub diff: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateFormat {
+    Json,
+    Terraform,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct SetPort {
+    /// Name of the instance
+    #[clap(validator(instance_name_opt))]
+    #[clap(value_hint=ValueHint::Other)]  // TODO complete instance name
+    pub name: String,
+    /// New port number
+    pub port: u16,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct SetListenAddress {
+    /// Name of the instance
+    #[clap(validator(instance_name_opt))]
+    #[clap(value_hint=ValueHint::Other)]  // TODO complete instance name
+    pub name: String,
+    /// Addresses to listen on. Use `default` to reset to the server's
+    /// default (usually localhost only)
+    #[clap(required=true, min_values=1)]
+    pub addresses: Vec<String>,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Doctor {
+    /// Only report problems, don't offer to fix them
+    #[clap(long)]
+    pub non_interactive: bool,
+}
+
 #[derive(Clap, Debug, Clone)]
 #[clap(setting=AppSettings::DisableVersionFlag)]
 pub struct Info {
@@ -390,6 +607,64 @@ impl fmt::Display for StartConf {
     }
 }
 
+impl FromStr for ServiceManager {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<ServiceManager> {
+        match s {
+            "systemd" => Ok(ServiceManager::Systemd),
+            "supervisord" => Ok(ServiceManager::Supervisord),
+            "openrc" => Ok(ServiceManager::Openrc),
+            "runit" => Ok(ServiceManager::Runit),
+            "rcd" => Ok(ServiceManager::Rcd),
+            _ => anyhow::bail!("Unsupported service manager, \
+                options: `systemd`, `supervisord`, `openrc`, `runit`, \
+                `rcd`"),
+        }
+    }
+}
+
+impl ServiceManager {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ServiceManager::Systemd => "systemd",
+            ServiceManager::Supervisord => "supervisord",
+            ServiceManager::Openrc => "openrc",
+            ServiceManager::Runit => "runit",
+            ServiceManager::Rcd => "rcd",
+        }
+    }
+}
+
+impl fmt::Display for ServiceManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl FromStr for K8sFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<K8sFormat> {
+        match s {
+            "manifests" => Ok(K8sFormat::Manifests),
+            "helm" => Ok(K8sFormat::Helm),
+            _ => anyhow::bail!("Unsupported format, \
+                options: `manifests`, `helm`"),
+        }
+    }
+}
+
+impl FromStr for StateFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<StateFormat> {
+        match s {
+            "json" => Ok(StateFormat::Json),
+            "terraform" => Ok(StateFormat::Terraform),
+            _ => anyhow::bail!("Unsupported format, \
+                options: `json`, `terraform`"),
+        }
+    }
+}
+
 fn instance_name_opt(name: &str) -> Result<(), String> {
     if is_valid_name(&name) {
         return Ok(())
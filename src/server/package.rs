@@ -1,7 +1,7 @@
 use serde::{Serialize, Deserialize};
 
 use crate::server::version::Version;
-use crate::server::detect::{Lazy, VersionQuery};
+use crate::server::detect::{self, Lazy, VersionQuery};
 use crate::server::os_trait::{CurrentOs, PreciseVersion};
 use crate::server::distribution::{Distribution, DistributionRef, MajorVersion};
 
@@ -36,6 +36,8 @@ pub struct PackageInfo {
     pub revision: String,
     pub architecture: String,
     pub installref: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug)]
@@ -43,6 +45,8 @@ pub struct Package {
     pub major_version: MajorVersion,
     pub version: Version<String>,
     pub slot: String,
+    /// Checksum of the package file, if known from the repository index
+    pub sha256: Option<String>,
 }
 
 impl<'a> Into<DistributionRef> for &'a PackageInfo {
@@ -57,6 +61,7 @@ impl<'a> Into<DistributionRef> for &'a PackageInfo {
             major_version,
             version: Version(format!("{}-{}", self.version, self.revision)),
             slot: slot.as_ref().to_owned(),
+            sha256: self.sha256.clone(),
         }.into_ref()
     }
 }
@@ -113,7 +118,7 @@ impl PackageCandidate {
 fn version_matches(package: &PackageInfo, version: &VersionQuery) -> bool {
     use VersionQuery::*;
 
-    if package.slot.is_none() ||
+    if package.slot.is_none() || !package.matches_arch() ||
         (package.basename != "edgedb" && package.basename != "edgedb-server")
     {
         return false;
@@ -152,6 +157,7 @@ pub fn find_version(haystack: &RepositoryInfo, ver: &VersionQuery)
             },
             version: Version(format!("{}-{}", target.version, target.revision)),
             slot: slot.as_ref().to_owned(),
+            sha256: target.sha256.clone(),
         }.into_ref())
     } else {
         anyhow::bail!("Version {} not found", ver)
@@ -159,6 +165,10 @@ pub fn find_version(haystack: &RepositoryInfo, ver: &VersionQuery)
 }
 
 impl PackageInfo {
+    /// Package is built for the architecture servers are installed for
+    pub fn matches_arch(&self) -> bool {
+        self.architecture == detect::server_arch()
+    }
     pub fn is_nightly(&self) -> bool {
         return self.version.as_ref().contains(".dev")
     }
@@ -0,0 +1,198 @@
+//! Instances created with `--no-service` are not managed by systemd or
+//! launchd. `edgedb server run` supervises the server process instead
+//! and keeps its own pid in `<data_dir>/../<name>.pid`.
+use std::fs;
+use std::path::PathBuf;
+
+use crate::hint::HintExt;
+use crate::process;
+use crate::server::status::Service;
+use crate::server::unix::base_data_dir;
+
+#[cfg(unix)] pub use self::imp::{run, stop};
+
+
+pub fn pid_file(name: &str) -> anyhow::Result<PathBuf> {
+    Ok(base_data_dir()?.join(format!("{}.pid", name)))
+}
+
+/// Returns pid of the `edgedb server run` process if it's running
+pub fn read_pid(name: &str) -> Option<u32> {
+    let path = pid_file(name).ok()?;
+    let pid = fs::read_to_string(&path).ok()?.trim().parse().ok()?;
+    if process::exists(pid) {
+        Some(pid)
+    } else {
+        None
+    }
+}
+
+pub fn service_status(name: &str) -> Service {
+    match read_pid(name) {
+        Some(pid) => Service::Running { pid },
+        None => Service::Failed { exit_code: None },
+    }
+}
+
+/// Error for service commands called on an instance without a service
+pub fn no_service(name: &str) -> anyhow::Result<()> {
+    return Err(anyhow::anyhow!(
+        "instance {:?} was created with `--no-service`", name))
+        .with_hint(|| format!(
+            "Use `edgedb server run {0}` to run the server in the \
+             foreground, its logs are printed to the terminal. \
+             `edgedb server stop {0}` stops it.",
+            name.escape_default()))?;
+}
+
+#[cfg(not(unix))]
+pub fn run(_inst: &crate::server::os_trait::InstanceRef,
+    _options: &crate::server::options::Run)
+    -> anyhow::Result<()>
+{
+    anyhow::bail!("portable instances are not supported on this platform");
+}
+
+#[cfg(not(unix))]
+pub fn stop(_name: &str) -> anyhow::Result<()> {
+    anyhow::bail!("portable instances are not supported on this platform");
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use std::process::{Child, ExitStatus};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use anyhow::Context;
+    use signal::Signal::{SIGINT, SIGTERM, SIGCHLD};
+    use signal::trap::Trap;
+
+    use crate::hint::HintExt;
+    use crate::process;
+    use crate::server::control::read_metadata;
+    use crate::server::options::Run;
+    use crate::server::os_trait::InstanceRef;
+    use crate::server::unix::storage_dir;
+
+    use super::{pid_file, read_pid};
+
+    /// Server exiting sooner than this after start is considered broken
+    /// and is not restarted
+    const MIN_UPTIME: Duration = Duration::from_secs(10);
+    const RESTART_DELAY: Duration = Duration::from_secs(1);
+    const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+    struct PidFile(PathBuf);
+
+    enum Exit {
+        Interrupted,
+        Exited(ExitStatus),
+    }
+
+    impl PidFile {
+        fn create(name: &str) -> anyhow::Result<PidFile> {
+            let path = pid_file(name)?;
+            fs::write(&path, std::process::id().to_string())
+                .with_context(|| format!("cannot write {:?}", path))?;
+            Ok(PidFile(path))
+        }
+    }
+
+    impl Drop for PidFile {
+        fn drop(&mut self) {
+            fs::remove_file(&self.0).map_err(|e| {
+                log::warn!("Cannot remove pid file {:?}: {}", self.0, e);
+            }).ok();
+        }
+    }
+
+    fn wait_server(trap: &mut Trap, child: &mut Child)
+        -> anyhow::Result<Exit>
+    {
+        for sig in trap {
+            match sig {
+                SIGINT | SIGTERM => {
+                    log::info!("Interrupted by {:?}. Stopping server", sig);
+                    let pid = child.id() as i32;
+                    if unsafe { libc::kill(pid, sig as libc::c_int) } != 0 {
+                        log::debug!("Error signalling process: {}",
+                            io::Error::last_os_error());
+                    }
+                    child.wait()?;
+                    return Ok(Exit::Interrupted);
+                }
+                _ => {}
+            }
+            if let Some(status) = child.try_wait()? {
+                return Ok(Exit::Exited(status));
+            }
+        }
+        unreachable!();
+    }
+
+    pub fn run(inst: &InstanceRef, options: &Run) -> anyhow::Result<()> {
+        let name = inst.name();
+        let meta = read_metadata(&storage_dir(name)?)?;
+        if !meta.portable {
+            return Err(anyhow::anyhow!(
+                "instance {:?} is managed by a service", name))
+                .hint("Use `edgedb server start` to start it \
+                       or `edgedb server start --foreground` \
+                       to run it in the terminal")?;
+        }
+        if let Some(pid) = read_pid(name) {
+            anyhow::bail!("instance {:?} is already running, pid {}",
+                          name, pid);
+        }
+        let _pid_file = PidFile::create(name)?;
+        let mut trap = Trap::trap(&[SIGINT, SIGTERM, SIGCHLD]);
+        loop {
+            let mut cmd = inst.get_command()?;
+            log::info!("Running server: {:?}", cmd);
+            let mut child = cmd.spawn()
+                .with_context(|| format!("error running server {:?}", cmd))?;
+            let started = Instant::now();
+            let status = match wait_server(&mut trap, &mut child)? {
+                Exit::Interrupted => return Ok(()),
+                Exit::Exited(status) if status.success() => return Ok(()),
+                Exit::Exited(status) => status,
+            };
+            if options.no_restart {
+                anyhow::bail!("server exited: {}", status);
+            }
+            if started.elapsed() < MIN_UPTIME {
+                anyhow::bail!("server failed right after start: {}. \
+                               Not restarting", status);
+            }
+            log::warn!("Server exited: {}. Restarting...", status);
+            thread::sleep(RESTART_DELAY);
+        }
+    }
+
+    pub fn stop(name: &str) -> anyhow::Result<()> {
+        let pid = match read_pid(name) {
+            Some(pid) => pid,
+            None => {
+                log::info!("Instance {:?} is not running", name);
+                return Ok(());
+            }
+        };
+        if unsafe { libc::kill(pid as i32, libc::SIGTERM) } != 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("cannot stop process {}", pid));
+        }
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while process::exists(pid) {
+            if Instant::now() > deadline {
+                anyhow::bail!("process {} is still running after {:?}",
+                              pid, STOP_TIMEOUT);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,17 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 use async_std::fs;
 use async_std::io;
 use fn_error_context::context;
 use serde::de::DeserializeOwned;
+use sha2::{Sha256, Digest};
+
+use crate::platform::home_dir;
 
 
 pub const USER_AGENT: &str = "edgedb";
+const DOWNLOAD_ATTEMPTS: u32 = 3;
 
 #[derive(Debug, thiserror::Error)]
 #[error("HTTP error: {0}")]
@@ -125,3 +129,102 @@ pub async fn get_file(dest: impl AsRef<Path>, url: &str)
                                  url, dest.display()))?;
     Ok(())
 }
+
+/// Cache of verified downloads, files are named by their checksums
+pub fn downloads_dir() -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".edgedb").join("cache").join("downloads"))
+}
+
+fn file_sha256(path: &Path) -> anyhow::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Downloads the rest of the partially downloaded file
+async fn continue_download(part: &Path, url: &str) -> anyhow::Result<()> {
+    let offset = match fs::metadata(part).await {
+        Ok(meta) => meta.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+        Err(e) => Err(e)?,
+    };
+    let mut request = surf::get(url).header("User-Agent", USER_AGENT);
+    if offset > 0 {
+        log::info!("Resuming download of {} from byte {}", url, offset);
+        request = request.header("Range", format!("bytes={}-", offset));
+    }
+    let response = request.await.map_err(HttpError).url_context(url)?;
+    let append = match u16::from(response.status()) {
+        206 => true,
+        200 => false,
+        // the range starts at the end of the file: it's complete
+        416 if offset > 0 => return Ok(()),
+        _ => return Err(HttpFailure(response)).url_context(url),
+    };
+    let file = fs::OpenOptions::new()
+        .create(true).write(true).append(append).truncate(!append)
+        .open(part).await
+        .with_context(|| format!("writing {:?}", part.display()))?;
+    io::copy(response, file).await
+        .with_context(|| format!("downloading {:?} -> {:?}",
+                                 url, part.display()))?;
+    Ok(())
+}
+
+/// Downloads the file verifying its SHA-256 checksum (if known)
+///
+/// Interrupted downloads are resumed using range requests. Verified files
+/// are kept in the cache by their checksum, so installing the same
+/// package again doesn't download it.
+#[context("failed to download file at URL: {}", url)]
+pub async fn get_verified_file(dest: impl AsRef<Path>, url: &str,
+    sha256: Option<&str>)
+    -> Result<(), anyhow::Error>
+{
+    let dest = dest.as_ref();
+    let sha256 = match sha256 {
+        Some(sha256) => sha256.to_lowercase(),
+        None => {
+            log::warn!("No checksum known for {}, \
+                        downloading without verification", url);
+            return get_file(dest, url).await;
+        }
+    };
+    // the checksum comes from the remote index and is used as a file name
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("invalid sha256 checksum {:?} for {}", sha256, url);
+    }
+    let dir = downloads_dir()?;
+    fs::create_dir_all(&dir).await?;
+    let cached = dir.join(&sha256);
+    if fs::metadata(&cached).await.is_ok() {
+        if file_sha256(&cached)? == sha256 {
+            log::info!("Using cached {}", cached.display());
+            fs::copy(&cached, dest).await?;
+            return Ok(());
+        }
+        fs::remove_file(&cached).await?;
+    }
+    let part = dir.join(format!("{}.part", sha256));
+    let mut attempt = 1;
+    loop {
+        match continue_download(&part, url).await {
+            Ok(()) => break,
+            Err(e) if attempt < DOWNLOAD_ATTEMPTS => {
+                log::warn!("Download interrupted: {:#}. Retrying...", e);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let actual = file_sha256(&part)?;
+    if actual != sha256 {
+        fs::remove_file(&part).await.ok();
+        anyhow::bail!("checksum mismatch: expected sha256 {}, got {}",
+                      sha256, actual);
+    }
+    fs::rename(&part, &cached).await?;
+    fs::copy(&cached, dest).await?;
+    Ok(())
+}
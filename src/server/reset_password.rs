@@ -30,7 +30,7 @@ pub fn generate_password() -> String {
 }
 
 #[context("error reading credentials at {}", path.display())]
-fn read_credentials(path: &Path) -> anyhow::Result<Credentials> {
+pub fn read_credentials(path: &Path) -> anyhow::Result<Credentials> {
     let data = fs::read(&path)?;
     Ok(serde_json::from_slice(&data)?)
 }
@@ -0,0 +1,141 @@
+use std::fs;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_std::task;
+use edgedb_client as client;
+use edgeql_parser::helpers::quote_string;
+use signal::Signal::{SIGINT, SIGTERM, SIGCHLD};
+use signal::trap::Trap;
+
+use crate::commands::ExitCode;
+use crate::process::ProcessGuard;
+use crate::server::detect::{self, VersionQuery};
+use crate::server::init::find_distribution;
+use crate::server::methods::InstallMethod;
+use crate::server::options::RunTemp;
+use crate::server::package::Package;
+use crate::server::reset_password::{generate_password, password_hash};
+use crate::server::unix;
+
+
+fn free_port() -> anyhow::Result<u16> {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .context("cannot find a free port")?;
+    Ok(listener.local_addr()?.port())
+}
+
+fn set_password(socket: PathBuf, password: &str) -> anyhow::Result<()> {
+    let mut conn_params = client::Builder::new();
+    conn_params.user("edgedb");
+    conn_params.database("edgedb");
+    conn_params.unix_addr(socket);
+    // server bootstraps the empty data directory first, which takes a while
+    conn_params.wait_until_available(Duration::from_secs(120));
+    task::block_on(async {
+        let mut cli = conn_params.connect().await?;
+        cli.execute(&format!(r###"
+            ALTER ROLE edgedb {{
+                SET password_hash := {password_hash};
+            }};
+            "###,
+            password_hash=quote_string(&password_hash(password)),
+        )).await?;
+        Ok(())
+    })
+}
+
+fn wait_interrupt(trap: &mut Trap, server: &mut ProcessGuard)
+    -> anyhow::Result<()>
+{
+    for sig in trap {
+        match sig {
+            SIGINT | SIGTERM => return Ok(()),
+            _ => {}
+        }
+        if let Some(status) = server.try_wait()? {
+            anyhow::bail!("server exited unexpectedly: {}", status);
+        }
+    }
+    unreachable!();
+}
+
+fn run_command(command: &[String], port: u16, password: &str, dsn: &str)
+    -> anyhow::Result<()>
+{
+    let mut cmd = Command::new(&command[0]);
+    cmd.args(&command[1..]);
+    cmd.env("EDGEDB_DSN", dsn);
+    cmd.env("EDGEDB_HOST", "localhost");
+    cmd.env("EDGEDB_PORT", port.to_string());
+    cmd.env("EDGEDB_USER", "edgedb");
+    cmd.env("EDGEDB_PASSWORD", password);
+    cmd.env("EDGEDB_DATABASE", "edgedb");
+    log::info!("Running {:?}", cmd);
+    let status = cmd.status()
+        .with_context(|| format!("error running {:?}", cmd))?;
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(ExitCode::new(code).into()),
+        None => anyhow::bail!("process {:?} failed: {}", cmd, status),
+    }
+}
+
+pub fn run_temp(options: &RunTemp) -> anyhow::Result<()> {
+    let version_query = VersionQuery::new(
+        options.nightly, options.version.as_ref());
+    let current_os = detect::current_os()?;
+    let avail_methods = current_os.get_available_methods()?;
+    let (distr, _, _) = find_distribution(
+        &*current_os, &avail_methods,
+        &version_query, &Some(InstallMethod::Package))?;
+    let pkg = distr.downcast_ref::<Package>()
+        .context("invalid unix package")?;
+    let port = match options.port {
+        Some(port) => port,
+        None => free_port()?,
+    };
+    let dir = tempfile::Builder::new().prefix("edgedb-temp-").tempdir()
+        .context("cannot create temporary directory")?;
+    let data_dir = dir.path().join("data");
+    let runstate_dir = dir.path().join("run");
+    fs::create_dir_all(&runstate_dir)
+        .with_context(|| format!("failed to create {}",
+                                 runstate_dir.display()))?;
+
+    // Trap is held until cleanup is done, so that Ctrl+C doesn't leave
+    // the server and its data directory behind
+    let mut trap = Trap::trap(&[SIGINT, SIGTERM, SIGCHLD]);
+    let mut cmd = Command::new(unix::server_path(pkg));
+    cmd.arg("--port").arg(port.to_string());
+    cmd.arg("--data-dir").arg(&data_dir);
+    cmd.arg("--runstate-dir").arg(&runstate_dir);
+    cmd.arg("--log-level=warn");
+    eprintln!("Starting temporary EdgeDB {} instance...", distr.version());
+    log::debug!("Running server: {:?}", cmd);
+    let mut server = ProcessGuard::run(&mut cmd)
+        .with_context(|| format!("error running server {:?}", cmd))?;
+
+    let password = generate_password();
+    let socket = runstate_dir.join(format!(".s.EDGEDB.admin.{}", port));
+    let result = set_password(socket, &password).and_then(|()| {
+        let dsn = format!("edgedb://edgedb:{}@localhost:{}/edgedb",
+                          password, port);
+        if options.command.is_empty() {
+            println!("{}", dsn);
+            eprintln!("Instance is running. \
+                       Press Ctrl+C to stop and destroy it.");
+            wait_interrupt(&mut trap, &mut server)
+        } else {
+            run_command(&options.command, port, &password, &dsn)
+        }
+    });
+
+    drop(server);
+    dir.close().context("cannot remove temporary instance data")?;
+    drop(trap);
+    result
+}
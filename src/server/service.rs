@@ -0,0 +1,922 @@
+//! Service managers for package instances on Linux and FreeBSD
+//!
+//! By default instances are systemd user services. On distributions
+//! without systemd, instances are registered as system services of
+//! supervisord, OpenRC or runit, which run the server as the current user.
+//! On FreeBSD instances are rc.d services supervised by `daemon(8)`.
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+
+use crate::dry_run;
+use crate::platform::{home_dir, get_current_uid};
+use crate::process;
+use crate::server::install::{Operation, Command as PrivCommand};
+use crate::server::linux;
+use crate::server::metadata::Metadata;
+use crate::server::options::{Logs, StartConf, ServiceManager};
+use crate::server::status::Service;
+use crate::server::unix::{self, Unix};
+
+
+pub trait ServiceBackend: fmt::Debug {
+    /// Writes or updates the service definition
+    fn create(&self, name: &str, meta: &Metadata) -> anyhow::Result<()>;
+    fn exists(&self, name: &str) -> bool;
+    fn start(&self, name: &str) -> anyhow::Result<()>;
+    fn stop(&self, name: &str) -> anyhow::Result<()>;
+    fn restart(&self, name: &str) -> anyhow::Result<()>;
+    fn status(&self, name: &str) -> Service;
+    /// Prints status as reported by the service manager and exits
+    fn print_status(&self, name: &str) -> anyhow::Result<()>;
+    fn logs(&self, name: &str, logs: &Logs) -> anyhow::Result<()>;
+    /// Stops and removes the service, returns `false` if there was none
+    fn remove(&self, name: &str) -> anyhow::Result<bool>;
+    /// Directory where server puts its sockets
+    fn runstate_dir(&self, name: &str) -> anyhow::Result<PathBuf>;
+}
+
+#[derive(Debug)]
+pub struct Systemd;
+
+#[derive(Debug)]
+pub struct Supervisord;
+
+#[derive(Debug)]
+pub struct OpenRc;
+
+#[derive(Debug)]
+pub struct Runit;
+
+#[derive(Debug)]
+pub struct Rcd;
+
+struct ServerCommand {
+    user: String,
+    home: PathBuf,
+    server: PathBuf,
+    args: Vec<String>,
+    log_file: PathBuf,
+    autostart: bool,
+}
+
+
+pub fn backend(manager: Option<ServiceManager>) -> Box<dyn ServiceBackend> {
+    match manager.unwrap_or(ServiceManager::Systemd) {
+        ServiceManager::Systemd => Box::new(Systemd),
+        ServiceManager::Supervisord => Box::new(Supervisord),
+        ServiceManager::Openrc => Box::new(OpenRc),
+        ServiceManager::Runit => Box::new(Runit),
+        ServiceManager::Rcd => Box::new(Rcd),
+    }
+}
+
+/// Detects service manager by the running init system
+pub fn detect() -> ServiceManager {
+    if cfg!(target_os="freebsd") {
+        ServiceManager::Rcd
+    } else if Path::new("/run/systemd/system").exists() {
+        ServiceManager::Systemd
+    } else if Path::new("/run/openrc").exists() {
+        ServiceManager::Openrc
+    } else if Path::new("/run/runit").exists() ||
+              Path::new("/etc/runit").exists()
+    {
+        ServiceManager::Runit
+    } else if which::which("supervisorctl").is_ok() {
+        ServiceManager::Supervisord
+    } else {
+        ServiceManager::Systemd
+    }
+}
+
+/// Returns service manager to record in the metadata of a new instance
+pub fn choose(manager: Option<ServiceManager>)
+    -> anyhow::Result<Option<ServiceManager>>
+{
+    if cfg!(target_os="linux") || cfg!(target_os="freebsd") {
+        let manager = manager.unwrap_or_else(detect);
+        if (manager == ServiceManager::Rcd) != cfg!(target_os="freebsd") {
+            anyhow::bail!("`--service-manager={}` is not supported \
+                           on this platform", manager);
+        }
+        log::info!("Using {} to manage the instance", manager);
+        Ok(Some(manager))
+    } else if let Some(manager) = manager {
+        anyhow::bail!("`--service-manager={}` is only supported \
+                       on Linux and FreeBSD", manager);
+    } else {
+        Ok(None)
+    }
+}
+
+fn perform(operations: Vec<Operation>) -> anyhow::Result<()> {
+    Unix::new().perform(operations, "service management",
+                        "edgedb server")
+}
+
+fn log_file(name: &str) -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".edgedb").join("logs")
+       .join(format!("{}.log", name)))
+}
+
+fn tail_log(name: &str, logs: &Logs) -> anyhow::Result<()> {
+    let mut cmd = Command::new("tail");
+    if let Some(n) = logs.tail {
+        cmd.arg("-n").arg(n.to_string());
+    }
+    if logs.follow {
+        cmd.arg("-F");
+    }
+    cmd.arg(log_file(name)?);
+    process::run(&mut cmd)
+}
+
+fn service_name(name: &str) -> String {
+    format!("edgedb-server-{}", name)
+}
+
+fn server_command(backend: &dyn ServiceBackend, name: &str, meta: &Metadata)
+    -> anyhow::Result<ServerCommand>
+{
+    let runstate_dir = backend.runstate_dir(name)?;
+    let log_file = log_file(name)?;
+    // directories are owned by the user, so create them before
+    // the service manager runs anything as root
+    fs::create_dir_all(&runstate_dir)
+        .with_context(|| format!("cannot create {:?}", runstate_dir))?;
+    fs::create_dir_all(log_file.parent().expect("log dir is not root"))?;
+    let mut args = vec![
+        format!("--data-dir={}", unix::storage_dir(name)?.display()),
+        format!("--runstate-dir={}", runstate_dir.display()),
+        format!("--port={}", meta.port),
+    ];
+    for addr in &meta.bind_addresses {
+        args.push(format!("--bind-address={}", addr));
+    }
+    Ok(ServerCommand {
+        user: whoami::username(),
+        home: home_dir()?,
+        server: linux::get_server_path(meta.slot.as_ref()),
+        args,
+        log_file,
+        autostart: meta.start_conf == StartConf::Auto,
+    })
+}
+
+fn quote(value: impl fmt::Display) -> String {
+    shell_words::quote(&value.to_string()).into_owned()
+}
+
+fn user_runstate_dir(name: &str) -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".edgedb").join("run").join(name))
+}
+
+impl ServiceBackend for Systemd {
+    fn create(&self, name: &str, meta: &Metadata) -> anyhow::Result<()> {
+        linux::create_systemd_service(name, meta)
+    }
+    fn exists(&self, name: &str) -> bool {
+        linux::systemd_service_path(name, false)
+            .map(|p| p.exists())
+            .unwrap_or(false)
+    }
+    fn start(&self, name: &str) -> anyhow::Result<()> {
+        process::run(Command::new("systemctl")
+            .arg("--user")
+            .arg("start")
+            .arg(format!("edgedb-server@{}", name)))
+    }
+    fn stop(&self, name: &str) -> anyhow::Result<()> {
+        process::run(Command::new("systemctl")
+            .arg("--user")
+            .arg("stop")
+            .arg(format!("edgedb-server@{}", name)))
+    }
+    fn restart(&self, name: &str) -> anyhow::Result<()> {
+        process::run(Command::new("systemctl")
+            .arg("--user")
+            .arg("restart")
+            .arg(format!("edgedb-server@{}", name)))
+    }
+    fn status(&self, name: &str) -> Service {
+        linux::systemd_status(name, false)
+    }
+    fn print_status(&self, name: &str) -> anyhow::Result<()> {
+        process::exit_from(Command::new("systemctl")
+            .arg("--user")
+            .arg("status")
+            .arg(format!("edgedb-server@{}", name)))
+    }
+    fn logs(&self, name: &str, logs: &Logs) -> anyhow::Result<()> {
+        let mut cmd = Command::new("journalctl");
+        cmd.arg("--user-unit").arg(linux::unit_name(name));
+        if let Some(n) = logs.tail  {
+            cmd.arg(format!("--lines={}", n));
+        }
+        if logs.follow {
+            cmd.arg("--follow");
+        }
+        process::run(&mut cmd)
+    }
+    fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        linux::remove_systemd_service(name, &mut None)
+    }
+    fn runstate_dir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        Ok(dirs::runtime_dir()
+            .unwrap_or_else(|| {
+                Path::new("/run/user").join(get_current_uid().to_string())
+            })
+            .join(format!("edgedb-{}", name)))
+    }
+}
+
+impl Supervisord {
+    fn config_path(&self, name: &str) -> PathBuf {
+        // Debian-like distributions use `conf.d/*.conf`,
+        // RedHat-like ones `supervisord.d/*.ini`
+        let debian_dir = Path::new("/etc/supervisor/conf.d");
+        if debian_dir.exists() {
+            debian_dir.join(format!("{}.conf", service_name(name)))
+        } else {
+            Path::new("/etc/supervisord.d")
+                .join(format!("{}.ini", service_name(name)))
+        }
+    }
+    fn ctl(&self, command: &str, name: &str) -> anyhow::Result<()> {
+        perform(vec![
+            Operation::PrivilegedCmd(PrivCommand::new("supervisorctl")
+                .arg(command).arg(service_name(name))),
+        ])
+    }
+}
+
+fn supervisord_config(name: &str, cmd: &ServerCommand) -> String {
+    format!(r###"
+[program:{service}]
+command={server} {args}
+user={user}
+environment=HOME="{home}"
+autostart={autostart}
+autorestart=true
+stopsignal=INT
+stopwaitsecs=60
+redirect_stderr=true
+stdout_logfile={log_file}
+"###,
+        service=service_name(name),
+        server=cmd.server.display(),
+        args=cmd.args.iter().map(quote).collect::<Vec<_>>().join(" "),
+        user=cmd.user,
+        home=cmd.home.display(),
+        autostart=cmd.autostart,
+        log_file=cmd.log_file.display(),
+    )
+}
+
+impl ServiceBackend for Supervisord {
+    fn create(&self, name: &str, meta: &Metadata) -> anyhow::Result<()> {
+        let cmd = server_command(self, name, meta)?;
+        perform(vec![
+            Operation::WritePrivilegedFile {
+                path: self.config_path(name),
+                data: supervisord_config(name, &cmd).into(),
+            },
+            Operation::PrivilegedCmd(PrivCommand::new("supervisorctl")
+                .arg("update")),
+        ])
+    }
+    fn exists(&self, name: &str) -> bool {
+        self.config_path(name).exists()
+    }
+    fn start(&self, name: &str) -> anyhow::Result<()> {
+        self.ctl("start", name)
+    }
+    fn stop(&self, name: &str) -> anyhow::Result<()> {
+        self.ctl("stop", name)
+    }
+    fn restart(&self, name: &str) -> anyhow::Result<()> {
+        self.ctl("restart", name)
+    }
+    fn status(&self, name: &str) -> Service {
+        // exit code is non-zero for stopped programs, so only output is
+        // checked
+        let out = match Command::new("supervisorctl")
+            .arg("status").arg(service_name(name)).output()
+        {
+            Ok(out) => String::from_utf8_lossy(&out.stdout).into_owned(),
+            Err(e) => return Service::Inactive {
+                error: format!("cannot determine service status: {}", e),
+            },
+        };
+        parse_supervisord_status(&out)
+    }
+    fn print_status(&self, name: &str) -> anyhow::Result<()> {
+        process::exit_from(Command::new("supervisorctl")
+            .arg("status").arg(service_name(name)))
+    }
+    fn logs(&self, name: &str, logs: &Logs) -> anyhow::Result<()> {
+        tail_log(name, logs)
+    }
+    fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        let path = self.config_path(name);
+        if !path.exists() {
+            return Ok(false);
+        }
+        if dry_run::perform(format_args!("remove supervisord program {}",
+                                         path.display())) {
+            // `update` stops and removes programs without config
+            perform(vec![
+                Operation::PrivilegedCmd(PrivCommand::new("rm")
+                    .arg("-f").arg(&path)),
+                Operation::PrivilegedCmd(PrivCommand::new("supervisorctl")
+                    .arg("update")),
+            ])?;
+        }
+        Ok(true)
+    }
+    fn runstate_dir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        user_runstate_dir(name)
+    }
+}
+
+fn parse_supervisord_status(output: &str) -> Service {
+    // edgedb-server-x   RUNNING   pid 123, uptime 0:01:02
+    let mut words = output.split_whitespace().skip(1);
+    match words.next() {
+        Some("RUNNING") => {
+            let pid = words.skip_while(|w| *w != "pid").nth(1)
+                .and_then(|pid| pid.trim_end_matches(',').parse().ok());
+            match pid {
+                Some(pid) => Service::Running { pid },
+                None => Service::Inactive {
+                    error: format!("cannot parse status {:?}", output.trim()),
+                },
+            }
+        }
+        Some("STOPPED") | Some("EXITED") | Some("FATAL")
+        | Some("BACKOFF") => {
+            Service::Failed { exit_code: None }
+        }
+        _ => Service::Inactive { error: output.trim().into() },
+    }
+}
+
+impl OpenRc {
+    fn script_path(&self, name: &str) -> PathBuf {
+        Path::new("/etc/init.d").join(service_name(name))
+    }
+    fn pid_file(&self, name: &str) -> PathBuf {
+        Path::new("/run").join(format!("{}.pid", service_name(name)))
+    }
+    fn rc_service(&self, command: &str, name: &str) -> anyhow::Result<()> {
+        perform(vec![
+            Operation::PrivilegedCmd(PrivCommand::new("rc-service")
+                .arg(service_name(name)).arg(command)),
+        ])
+    }
+    fn in_default_runlevel(&self, name: &str) -> bool {
+        let service = service_name(name);
+        process::get_text(Command::new("rc-update")
+                          .arg("show").arg("default"))
+            .map(|text| text.lines().any(|line| {
+                line.split('|').next().map(|s| s.trim()) == Some(&service)
+            }))
+            .unwrap_or(false)
+    }
+}
+
+fn openrc_script(name: &str, pid_file: &Path, cmd: &ServerCommand)
+    -> String
+{
+    format!(r###"#!/sbin/openrc-run
+description="EdgeDB Database Service, instance {name}"
+
+command={server}
+command_args={args}
+command_user={user}
+command_background=true
+pidfile={pid_file}
+output_log={log_file}
+error_log={log_file}
+retry="SIGINT/60"
+export HOME={home}
+
+depend() {{
+    need net
+    after syslog
+}}
+"###,
+        name=name,
+        server=quote(cmd.server.display()),
+        args=quote(cmd.args.iter().map(quote).collect::<Vec<_>>().join(" ")),
+        user=quote(&cmd.user),
+        pid_file=quote(pid_file.display()),
+        log_file=quote(cmd.log_file.display()),
+        home=quote(cmd.home.display()),
+    )
+}
+
+impl ServiceBackend for OpenRc {
+    fn create(&self, name: &str, meta: &Metadata) -> anyhow::Result<()> {
+        let cmd = server_command(self, name, meta)?;
+        let path = self.script_path(name);
+        let mut operations = vec![
+            Operation::WritePrivilegedFile {
+                path: path.clone(),
+                data: openrc_script(name, &self.pid_file(name), &cmd).into(),
+            },
+            Operation::PrivilegedCmd(PrivCommand::new("chmod")
+                .arg("755").arg(&path)),
+        ];
+        if cmd.autostart && !self.in_default_runlevel(name) {
+            operations.push(Operation::PrivilegedCmd(
+                PrivCommand::new("rc-update")
+                .arg("add").arg(service_name(name)).arg("default")));
+        }
+        perform(operations)
+    }
+    fn exists(&self, name: &str) -> bool {
+        self.script_path(name).exists()
+    }
+    fn start(&self, name: &str) -> anyhow::Result<()> {
+        self.rc_service("start", name)
+    }
+    fn stop(&self, name: &str) -> anyhow::Result<()> {
+        self.rc_service("stop", name)
+    }
+    fn restart(&self, name: &str) -> anyhow::Result<()> {
+        self.rc_service("restart", name)
+    }
+    fn status(&self, name: &str) -> Service {
+        let out = match Command::new("rc-service")
+            .arg(service_name(name)).arg("status").output()
+        {
+            Ok(out) => String::from_utf8_lossy(&out.stdout).into_owned(),
+            Err(e) => return Service::Inactive {
+                error: format!("cannot determine service status: {}", e),
+            },
+        };
+        if out.contains("status: started") {
+            let pid = fs::read_to_string(self.pid_file(name)).ok()
+                .and_then(|pid| pid.trim().parse().ok());
+            match pid {
+                Some(pid) => Service::Running { pid },
+                None => Service::Inactive {
+                    error: "cannot read pid file".into(),
+                },
+            }
+        } else if out.contains("status: stopped") ||
+                  out.contains("status: crashed")
+        {
+            Service::Failed { exit_code: None }
+        } else {
+            Service::Inactive { error: out.trim().into() }
+        }
+    }
+    fn print_status(&self, name: &str) -> anyhow::Result<()> {
+        process::exit_from(Command::new("rc-service")
+            .arg(service_name(name)).arg("status"))
+    }
+    fn logs(&self, name: &str, logs: &Logs) -> anyhow::Result<()> {
+        tail_log(name, logs)
+    }
+    fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        let path = self.script_path(name);
+        if !path.exists() {
+            return Ok(false);
+        }
+        if dry_run::perform(format_args!("remove OpenRC service {}",
+                                         path.display())) {
+            let mut operations = vec![
+                Operation::PrivilegedCmd(PrivCommand::new("rc-service")
+                    .arg(service_name(name))
+                    .arg("--ifstarted").arg("stop")),
+            ];
+            if self.in_default_runlevel(name) {
+                operations.push(Operation::PrivilegedCmd(
+                    PrivCommand::new("rc-update")
+                    .arg("del").arg(service_name(name)).arg("default")));
+            }
+            operations.push(Operation::PrivilegedCmd(
+                PrivCommand::new("rm").arg("-f").arg(&path)));
+            perform(operations)?;
+        }
+        Ok(true)
+    }
+    fn runstate_dir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        user_runstate_dir(name)
+    }
+}
+
+impl Runit {
+    fn service_dir(&self, name: &str) -> PathBuf {
+        Path::new("/etc/sv").join(service_name(name))
+    }
+    /// Directory scanned by `runsvdir`, differs between distributions
+    fn enabled_dir(&self) -> PathBuf {
+        if let Some(dir) = std::env::var_os("SVDIR") {
+            return dir.into();
+        }
+        ["/var/service", "/etc/service", "/service"].iter()
+            .map(Path::new)
+            .find(|p| p.exists())
+            .unwrap_or(Path::new("/var/service"))
+            .into()
+    }
+    fn enabled_path(&self, name: &str) -> PathBuf {
+        self.enabled_dir().join(service_name(name))
+    }
+    fn sv(&self, command: &str, name: &str) -> anyhow::Result<()> {
+        perform(vec![
+            Operation::PrivilegedCmd(PrivCommand::new("sv")
+                .arg(command).arg(self.enabled_path(name))),
+        ])
+    }
+}
+
+fn runit_script(cmd: &ServerCommand) -> String {
+    format!(r###"#!/bin/sh
+exec 2>&1
+exec chpst -u {user} env HOME={home} {server} {args} >>{log_file}
+"###,
+        user=quote(&cmd.user),
+        home=quote(cmd.home.display()),
+        server=quote(cmd.server.display()),
+        args=cmd.args.iter().map(quote).collect::<Vec<_>>().join(" "),
+        log_file=quote(cmd.log_file.display()),
+    )
+}
+
+impl ServiceBackend for Runit {
+    fn create(&self, name: &str, meta: &Metadata) -> anyhow::Result<()> {
+        let cmd = server_command(self, name, meta)?;
+        let dir = self.service_dir(name);
+        let run = dir.join("run");
+        let down = dir.join("down");
+        let mut operations = vec![
+            Operation::PrivilegedCmd(PrivCommand::new("mkdir")
+                .arg("-p").arg(&dir)),
+            Operation::WritePrivilegedFile {
+                path: run.clone(),
+                data: runit_script(&cmd).into(),
+            },
+            Operation::PrivilegedCmd(PrivCommand::new("chmod")
+                .arg("755").arg(&run)),
+        ];
+        // `down` file keeps runit from starting the service automatically
+        if cmd.autostart {
+            operations.push(Operation::PrivilegedCmd(
+                PrivCommand::new("rm").arg("-f").arg(&down)));
+        } else {
+            operations.push(Operation::WritePrivilegedFile {
+                path: down,
+                data: Vec::new(),
+            });
+        }
+        operations.push(Operation::PrivilegedCmd(PrivCommand::new("ln")
+            .arg("-sfn").arg(&dir).arg(self.enabled_path(name))));
+        perform(operations)
+    }
+    fn exists(&self, name: &str) -> bool {
+        self.service_dir(name).join("run").exists()
+    }
+    fn start(&self, name: &str) -> anyhow::Result<()> {
+        self.sv("start", name)
+    }
+    fn stop(&self, name: &str) -> anyhow::Result<()> {
+        self.sv("stop", name)
+    }
+    fn restart(&self, name: &str) -> anyhow::Result<()> {
+        self.sv("restart", name)
+    }
+    fn status(&self, name: &str) -> Service {
+        match process::get_text(Command::new("sv")
+                                .arg("status").arg(self.enabled_path(name)))
+        {
+            Ok(text) => parse_runit_status(&text),
+            Err(e) => Service::Inactive {
+                error: format!("cannot determine service status: {:#}", e),
+            },
+        }
+    }
+    fn print_status(&self, name: &str) -> anyhow::Result<()> {
+        process::exit_from(Command::new("sv")
+            .arg("status").arg(self.enabled_path(name)))
+    }
+    fn logs(&self, name: &str, logs: &Logs) -> anyhow::Result<()> {
+        tail_log(name, logs)
+    }
+    fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        let dir = self.service_dir(name);
+        let link = self.enabled_path(name);
+        if !dir.exists() && !link.exists() {
+            return Ok(false);
+        }
+        if dry_run::perform(format_args!("remove runit service {}",
+                                         dir.display())) {
+            let mut operations = Vec::new();
+            if link.exists() {
+                operations.push(Operation::PrivilegedCmd(
+                    PrivCommand::new("sv").arg("-w").arg("60")
+                    .arg("force-stop").arg(&link)));
+            }
+            operations.push(Operation::PrivilegedCmd(PrivCommand::new("rm")
+                .arg("-f").arg(&link)));
+            operations.push(Operation::PrivilegedCmd(PrivCommand::new("rm")
+                .arg("-rf").arg(&dir)));
+            perform(operations)?;
+        }
+        Ok(true)
+    }
+    fn runstate_dir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        user_runstate_dir(name)
+    }
+}
+
+fn parse_runit_status(output: &str) -> Service {
+    // run: /var/service/edgedb-server-x: (pid 123) 45s
+    // down: /var/service/edgedb-server-x: 3s, normally up
+    if output.starts_with("run:") {
+        let pid = output.split("(pid ").nth(1)
+            .and_then(|rest| rest.split(')').next())
+            .and_then(|pid| pid.parse().ok());
+        match pid {
+            Some(pid) => Service::Running { pid },
+            None => Service::Inactive {
+                error: format!("cannot parse status {:?}", output.trim()),
+            },
+        }
+    } else if output.starts_with("down:") || output.starts_with("finish:") {
+        Service::Failed { exit_code: None }
+    } else {
+        Service::Inactive { error: output.trim().into() }
+    }
+}
+
+impl Rcd {
+    /// rc.d variable names can't contain dashes
+    fn rc_name(&self, name: &str) -> String {
+        format!("edgedb_server_{}", name)
+    }
+    fn script_path(&self, name: &str) -> PathBuf {
+        Path::new("/usr/local/etc/rc.d").join(self.rc_name(name))
+    }
+    fn pid_file(&self, name: &str) -> PathBuf {
+        Path::new("/var/run").join(format!("{}.pid", self.rc_name(name)))
+    }
+    fn enable_var(&self, name: &str) -> String {
+        format!("{}_enable", self.rc_name(name))
+    }
+    fn service(&self, command: &str, name: &str) -> anyhow::Result<()> {
+        perform(vec![
+            Operation::PrivilegedCmd(PrivCommand::new("service")
+                .arg(self.rc_name(name)).arg(command)),
+        ])
+    }
+    fn is_enabled(&self, name: &str) -> bool {
+        process::get_text(Command::new("sysrc")
+                          .arg("-n").arg(self.enable_var(name)))
+            .map(|value| value.trim().eq_ignore_ascii_case("yes"))
+            .unwrap_or(false)
+    }
+}
+
+fn rcd_script(rc_name: &str, name: &str, pid_file: &Path,
+              cmd: &ServerCommand)
+    -> String
+{
+    let mut daemon_args = vec![
+        "-r".into(),
+        "-P".into(), pid_file.display().to_string(),
+        "-u".into(), cmd.user.clone(),
+        "-o".into(), cmd.log_file.display().to_string(),
+        cmd.server.display().to_string(),
+    ];
+    daemon_args.extend(cmd.args.iter().cloned());
+    format!(r###"#!/bin/sh
+#
+# PROVIDE: {rc_name}
+# REQUIRE: LOGIN
+# KEYWORD: shutdown
+
+. /etc/rc.subr
+
+name={rc_name}
+rcvar={rc_name}_enable
+desc="EdgeDB Database Service, instance {name}"
+
+load_rc_config $name
+: ${{{rc_name}_enable:="NO"}}
+
+pidfile={pid_file}
+procname=/usr/sbin/daemon
+command=/usr/sbin/daemon
+command_args={args}
+export HOME={home}
+
+run_rc_command "$1"
+"###,
+        rc_name=rc_name,
+        name=name,
+        pid_file=quote(pid_file.display()),
+        args=quote(daemon_args.iter().map(quote)
+                   .collect::<Vec<_>>().join(" ")),
+        home=quote(cmd.home.display()),
+    )
+}
+
+impl ServiceBackend for Rcd {
+    fn create(&self, name: &str, meta: &Metadata) -> anyhow::Result<()> {
+        let cmd = server_command(self, name, meta)?;
+        let path = self.script_path(name);
+        let script = rcd_script(&self.rc_name(name), name,
+                                &self.pid_file(name), &cmd);
+        let enable = if cmd.autostart { "YES" } else { "NO" };
+        perform(vec![
+            Operation::WritePrivilegedFile {
+                path: path.clone(),
+                data: script.into(),
+            },
+            Operation::PrivilegedCmd(PrivCommand::new("chmod")
+                .arg("755").arg(&path)),
+            Operation::PrivilegedCmd(PrivCommand::new("sysrc")
+                .arg(format!("{}={}", self.enable_var(name), enable))),
+        ])
+    }
+    fn exists(&self, name: &str) -> bool {
+        self.script_path(name).exists()
+    }
+    // `one*` commands work regardless of the `_enable` variable
+    fn start(&self, name: &str) -> anyhow::Result<()> {
+        self.service("onestart", name)
+    }
+    fn stop(&self, name: &str) -> anyhow::Result<()> {
+        self.service("onestop", name)
+    }
+    fn restart(&self, name: &str) -> anyhow::Result<()> {
+        self.service("onerestart", name)
+    }
+    fn status(&self, name: &str) -> Service {
+        // exit code is non-zero for stopped services, so only output is
+        // checked
+        let out = match Command::new("service")
+            .arg(self.rc_name(name)).arg("onestatus").output()
+        {
+            Ok(out) => String::from_utf8_lossy(&out.stdout).into_owned(),
+            Err(e) => return Service::Inactive {
+                error: format!("cannot determine service status: {}", e),
+            },
+        };
+        parse_rcd_status(&out)
+    }
+    fn print_status(&self, name: &str) -> anyhow::Result<()> {
+        process::exit_from(Command::new("service")
+            .arg(self.rc_name(name)).arg("onestatus"))
+    }
+    fn logs(&self, name: &str, logs: &Logs) -> anyhow::Result<()> {
+        tail_log(name, logs)
+    }
+    fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        let path = self.script_path(name);
+        if !path.exists() {
+            return Ok(false);
+        }
+        if dry_run::perform(format_args!("remove rc.d service {}",
+                                         path.display())) {
+            let mut operations = Vec::new();
+            if matches!(self.status(name), Service::Running {..}) {
+                operations.push(Operation::PrivilegedCmd(
+                    PrivCommand::new("service")
+                    .arg(self.rc_name(name)).arg("onestop")));
+            }
+            if self.is_enabled(name) {
+                operations.push(Operation::PrivilegedCmd(
+                    PrivCommand::new("sysrc")
+                    .arg("-x").arg(self.enable_var(name))));
+            }
+            operations.push(Operation::PrivilegedCmd(
+                PrivCommand::new("rm").arg("-f").arg(&path)));
+            perform(operations)?;
+        }
+        Ok(true)
+    }
+    fn runstate_dir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        user_runstate_dir(name)
+    }
+}
+
+fn parse_rcd_status(output: &str) -> Service {
+    // edgedb_server_x is running as pid 123.
+    // edgedb_server_x is not running.
+    if output.contains(" is running as pid ") {
+        let pid = output.split(" is running as pid ").nth(1)
+            .and_then(|rest| rest.trim().trim_end_matches('.').parse().ok());
+        match pid {
+            Some(pid) => Service::Running { pid },
+            None => Service::Inactive {
+                error: format!("cannot parse status {:?}", output.trim()),
+            },
+        }
+    } else if output.contains(" is not running") {
+        Service::Failed { exit_code: None }
+    } else {
+        Service::Inactive { error: output.trim().into() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use crate::server::status::Service;
+    use super::{ServerCommand, parse_supervisord_status, parse_runit_status};
+    use super::{parse_rcd_status};
+    use super::{openrc_script, runit_script, supervisord_config, rcd_script};
+
+    fn command() -> ServerCommand {
+        ServerCommand {
+            user: "alice".into(),
+            home: PathBuf::from("/home/alice"),
+            server: PathBuf::from("/usr/bin/edgedb-server-1-beta2"),
+            args: vec![
+                "--data-dir=/home/alice/.local/share/edgedb/data/x".into(),
+                "--port=10700".into(),
+            ],
+            log_file: PathBuf::from("/home/alice/.edgedb/logs/x.log"),
+            autostart: true,
+        }
+    }
+
+    fn line_words(text: &str, prefix: &str) -> Vec<String> {
+        let line = text.lines().find(|l| l.starts_with(prefix))
+            .expect("line exists");
+        shell_words::split(&line[prefix.len()..]).unwrap()
+    }
+
+    #[test]
+    fn service_files() {
+        let cmd = command();
+        let config = supervisord_config("x", &cmd);
+        assert_eq!(line_words(&config, "command="), vec![
+            "/usr/bin/edgedb-server-1-beta2",
+            "--data-dir=/home/alice/.local/share/edgedb/data/x",
+            "--port=10700",
+        ]);
+        assert!(config.contains("\nuser=alice\n"));
+        assert!(config.contains("\nautostart=true\n"));
+
+        let script = openrc_script("x", Path::new("/run/x.pid"), &cmd);
+        assert!(script.starts_with("#!/sbin/openrc-run\n"));
+        let args = line_words(&script, "command_args=");
+        assert_eq!(shell_words::split(&args[0]).unwrap(), vec![
+            "--data-dir=/home/alice/.local/share/edgedb/data/x",
+            "--port=10700",
+        ]);
+
+        assert_eq!(line_words(&runit_script(&cmd), "exec chpst "), vec![
+            "-u", "alice", "env", "HOME=/home/alice",
+            "/usr/bin/edgedb-server-1-beta2",
+            "--data-dir=/home/alice/.local/share/edgedb/data/x",
+            "--port=10700",
+            ">>/home/alice/.edgedb/logs/x.log",
+        ]);
+
+        let script = rcd_script("edgedb_server_x", "x",
+                                Path::new("/var/run/x.pid"), &cmd);
+        assert!(script.contains("\nrcvar=edgedb_server_x_enable\n"));
+        let args = line_words(&script, "command_args=");
+        assert_eq!(shell_words::split(&args[0]).unwrap(), vec![
+            "-r", "-P", "/var/run/x.pid", "-u", "alice",
+            "-o", "/home/alice/.edgedb/logs/x.log",
+            "/usr/bin/edgedb-server-1-beta2",
+            "--data-dir=/home/alice/.local/share/edgedb/data/x",
+            "--port=10700",
+        ]);
+    }
+
+    #[test]
+    fn statuses() {
+        assert!(matches!(parse_supervisord_status(
+            "edgedb-server-x   RUNNING   pid 123, uptime 0:01:02\n"),
+            Service::Running { pid: 123 }));
+        assert!(matches!(parse_supervisord_status(
+            "edgedb-server-x   STOPPED   Not started\n"),
+            Service::Failed { exit_code: None }));
+        assert!(matches!(parse_runit_status(
+            "run: /var/service/edgedb-server-x: (pid 123) 45s\n"),
+            Service::Running { pid: 123 }));
+        assert!(matches!(parse_runit_status(
+            "down: /var/service/edgedb-server-x: 3s, normally up\n"),
+            Service::Failed { exit_code: None }));
+        assert!(matches!(parse_rcd_status(
+            "edgedb_server_x is running as pid 123.\n"),
+            Service::Running { pid: 123 }));
+        assert!(matches!(parse_rcd_status(
+            "edgedb_server_x is not running.\n"),
+            Service::Failed { exit_code: None }));
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use crate::credentials;
+use crate::hint::HintExt;
+use crate::server::control::{get_instance, read_metadata};
+use crate::server::detect;
+use crate::server::init;
+use crate::server::metadata::Metadata;
+use crate::server::methods::InstallMethod;
+use crate::server::options::{SetPort, SetListenAddress, Restart};
+use crate::server::os_trait::InstanceRef;
+use crate::server::reset_password::{read_credentials, write_credentials};
+use crate::server::status::{self, Service, Port};
+use crate::server::unix;
+
+
+fn check_port(name: &str, port: u16, instances: &[InstanceRef])
+    -> anyhow::Result<()>
+{
+    for (other, other_port) in init::read_ports()? {
+        if other != name && other_port == port {
+            return Err(anyhow::anyhow!(
+                "port {} is reserved for instance {:?}", port, other))
+                .hint("Choose another port or change the port \
+                       of that instance first")?;
+        }
+    }
+    for inst in instances {
+        if inst.name() == name {
+            continue;
+        }
+        if let Ok(other_port) = inst.get_port() {
+            if other_port == port {
+                return Err(anyhow::anyhow!(
+                    "port {} is used by instance {:?}", port, inst.name()))
+                    .hint("Choose another port or change the port \
+                           of that instance first")?;
+            }
+        }
+    }
+    match status::probe_port(&Err(anyhow::anyhow!("no metadata")),
+                             &Some(port))
+    {
+        Port::Occupied => {
+            anyhow::bail!("port {} is occupied by another process", port);
+        }
+        Port::Refused | Port::Unknown => {}
+    }
+    Ok(())
+}
+
+fn update_instance(inst: &InstanceRef, mut update: impl FnMut(&mut Metadata))
+    -> anyhow::Result<Metadata>
+{
+    let status = inst.get_status();
+    if status.method != InstallMethod::Package {
+        anyhow::bail!("changing network settings is only supported \
+                       for package installations, instance {:?} is \
+                       installed using {}",
+                       inst.name(), status.method.title());
+    }
+    let dir = unix::storage_dir(inst.name())?;
+    let mut meta = read_metadata(&dir)?;
+    update(&mut meta);
+    unix::write_metadata(&dir.join("metadata.json"), &meta)?;
+    unix::create_user_service(inst.name(), &meta)?;
+    if matches!(status.service, Service::Running {..}) {
+        inst.restart(&Restart { name: inst.name().into() })?;
+    }
+    Ok(meta)
+}
+
+pub fn set_port(options: &SetPort) -> anyhow::Result<()> {
+    let os = detect::current_os()?;
+    let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
+    let inst = get_instance(&methods, &options.name)?;
+    if inst.get_port()? == options.port {
+        eprintln!("Instance {:?} already uses port {}",
+                  options.name, options.port);
+        return Ok(());
+    }
+    let mut instances = Vec::new();
+    for meth in methods.values() {
+        instances.extend(meth.all_instances()?);
+    }
+    check_port(&options.name, options.port, &instances)?;
+
+    update_instance(&inst, |meta| meta.port = options.port)?;
+    init::set_port(&options.name, options.port)?;
+    let cred_path = credentials::path(&options.name)?;
+    if cred_path.exists() {
+        let mut creds = read_credentials(&cred_path)?;
+        creds.port = options.port;
+        write_credentials(&cred_path, &creds)?;
+    }
+    eprintln!("Instance {:?} now listens on port {}",
+              options.name, options.port);
+    Ok(())
+}
+
+pub fn set_listen_address(options: &SetListenAddress) -> anyhow::Result<()> {
+    let addresses = if options.addresses == ["default"] {
+        Vec::new()
+    } else {
+        for addr in &options.addresses {
+            if addr.parse::<IpAddr>().is_err() && addr != "localhost" {
+                anyhow::bail!("invalid address {:?}, \
+                               an IP address is expected", addr);
+            }
+        }
+        // keep the order given on the command line
+        let mut seen = HashSet::new();
+        options.addresses.iter()
+            .filter(|a| seen.insert(&a[..]))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let os = detect::current_os()?;
+    let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
+    let inst = get_instance(&methods, &options.name)?;
+    update_instance(&inst, |meta| meta.bind_addresses = addresses.clone())?;
+    if addresses.is_empty() {
+        eprintln!("Instance {:?} now listens on the default address",
+                  options.name);
+    } else {
+        eprintln!("Instance {:?} now listens on {}",
+                  options.name, addresses.join(", "));
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Analysis of the failures of `edgedb server start`
+//!
+//! When the service manager fails to start an instance, common causes are
+//! detected and fixes are offered in the same way `edgedb server doctor`
+//! does. The instance is started again if anything has been fixed.
+use std::fs;
+use std::path::Path;
+
+use crate::commands::ExitCode;
+use crate::process;
+use crate::server::doctor::{self, Problem};
+use crate::server::init::Storage;
+use crate::server::linux;
+use crate::server::macos;
+use crate::server::methods::InstallMethod;
+use crate::server::options::Start;
+use crate::server::os_trait::InstanceRef;
+use crate::server::status::Status;
+
+
+/// PostgreSQL refuses to start if the pid file of a killed server is left
+fn check_pid_file(data_dir: &Path, problems: &mut Vec<Problem>) {
+    let path = data_dir.join("postmaster.pid");
+    let pid = match fs::read_to_string(&path) {
+        Ok(text) => text.lines().next().and_then(|l| l.trim().parse().ok()),
+        Err(_) => return,
+    };
+    match pid {
+        Some(pid) if process::exists(pid) => {}
+        _ => {
+            problems.push(Problem::new(format!(
+                "stale pid file {} is left by a server which \
+                 is not running anymore", path.display()))
+                .fix(format!("Remove {}", path.display()), false,
+                     move || Ok(fs::remove_file(&path)?)));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn check_permissions(data_dir: &Path, problems: &mut Vec<Problem>) {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use crate::platform::get_current_uid;
+
+    let meta = match fs::metadata(data_dir) {
+        Ok(meta) => meta,
+        Err(_) => return,
+    };
+    if meta.uid() != get_current_uid() {
+        problems.push(Problem::new(format!(
+            "data directory {} is owned by another user",
+            data_dir.display()))
+            .hint(format!("Run `sudo chown -R {} {}`",
+                whoami::username(), data_dir.display())));
+    } else if meta.mode() & 0o077 != 0 {
+        let dir = data_dir.to_path_buf();
+        problems.push(Problem::new(format!(
+            "data directory {} is accessible by other users, \
+             the server refuses to use it", data_dir.display()))
+            .fix("Restrict permissions of the data directory".into(), false,
+                 move || Ok(fs::set_permissions(&dir,
+                    fs::Permissions::from_mode(0o700))?)));
+    }
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_data_dir: &Path, _problems: &mut Vec<Problem>) {
+}
+
+fn find_problems(status: &Status) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    doctor::check_instance(status, &mut problems);
+    if status.method != InstallMethod::Package {
+        return problems;
+    }
+    if let Storage::UserDir(data_dir) = &status.storage {
+        check_pid_file(data_dir, &mut problems);
+        check_permissions(data_dir, &mut problems);
+    }
+    if cfg!(target_os="linux") {
+        linux::start_problems(&status.name, &mut problems);
+    } else if cfg!(target_os="macos") {
+        macos::start_problems(&status.name, &mut problems);
+    }
+    problems
+}
+
+pub fn start(inst: &InstanceRef, options: &Start) -> anyhow::Result<()> {
+    let error = match inst.start(options) {
+        Ok(()) => return Ok(()),
+        Err(e) if options.foreground => return Err(e),
+        Err(e) => e,
+    };
+    let problems = find_problems(&inst.get_status());
+    if problems.is_empty() {
+        return Err(error);
+    }
+    eprintln!("edgedb error: {:#}", error);
+    eprintln!("Found {} possible cause(s) of the failure:", problems.len());
+    let non_interactive = !atty::is(atty::Stream::Stdin);
+    if doctor::offer_fixes(problems, non_interactive)? == 0 {
+        return Err(ExitCode::new(1))?;
+    }
+    eprintln!("Starting instance {:?} again", inst.name());
+    inst.start(options)
+}
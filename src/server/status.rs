@@ -111,10 +111,14 @@ impl Status {
                 println!("inactive");
             }
         }
-        println!("  Service/Container: {}", match self.service_exists {
-            true => "exists",
-            false => "NOT FOUND",
-        });
+        let portable = self.metadata.as_ref()
+            .map(|m| m.portable).unwrap_or(false);
+        println!("  Service/Container: {}",
+            match (self.service_exists, portable) {
+                (_, true) => "none (run by `edgedb server run`)",
+                (true, false) => "exists",
+                (false, false) => "NOT FOUND",
+            });
         println!("  Credentials: {}", match self.credentials_file_exists {
             true => "exist",
             false => "NOT FOUND",
@@ -0,0 +1,241 @@
+//! Files of destroyed instances which can be restored by `edgedb undo`
+//!
+//! Each destroyed instance gets a directory in the trash which contains
+//! the moved files and `manifest.json` listing their original locations.
+//! Entries older than `RETENTION_DAYS` are removed on the next destroy or
+//! by `edgedb server cleanup`, until then they keep using the disk space.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use fn_error_context::context;
+use serde::{Serialize, Deserialize};
+
+use crate::server::control::read_metadata;
+use crate::server::options::{Undo, UndoCommand};
+use crate::server::unix;
+
+
+pub const RETENTION_DAYS: u64 = 7;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    instance: String,
+    /// Unix timestamp of destroy
+    destroyed_at: u64,
+    items: Vec<Item>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Item {
+    original: PathBuf,
+    /// File name in the trash entry directory
+    stored: String,
+}
+
+/// Trash entry which is created on the first moved file
+pub struct Entry {
+    dir: Option<PathBuf>,
+    manifest: Manifest,
+}
+
+fn trash_dir() -> anyhow::Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Can't determine data directory"))?
+        .join("edgedb").join("trash"))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[context("error writing {}", dir.join("manifest.json").display())]
+fn write_manifest(dir: &Path, manifest: &Manifest) -> anyhow::Result<()> {
+    let path = dir.join("manifest.json");
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_vec_pretty(manifest)?)?;
+    fs::rename(&tmp_path, &path)?;
+    Ok(())
+}
+
+fn read_entries() -> anyhow::Result<Vec<(PathBuf, Manifest)>> {
+    let dir = trash_dir()?;
+    let list = match fs::read_dir(&dir) {
+        Ok(list) => list,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(Vec::new());
+        }
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!("error reading {}", dir.display())
+            });
+        }
+    };
+    let mut entries = Vec::new();
+    for item in list {
+        let path = item?.path();
+        let manifest = match fs::read(path.join("manifest.json")) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) => {
+                log::warn!("Skipping trash entry {}: {}",
+                           path.display(), e);
+                continue;
+            }
+        };
+        entries.push((path, manifest));
+    }
+    entries.sort_by_key(|(_, manifest)| manifest.destroyed_at);
+    Ok(entries)
+}
+
+/// Directories of all trash entries, oldest first
+pub fn entry_dirs() -> anyhow::Result<Vec<PathBuf>> {
+    Ok(read_entries()?.into_iter().map(|(path, _)| path).collect())
+}
+
+fn copy_all(src: &Path, dest: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(src)?;
+    if meta.file_type().is_symlink() {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(fs::read_link(src)?, dest)?;
+        #[cfg(not(unix))]
+        fs::copy(src, dest)?;
+    } else if meta.is_dir() {
+        fs::create_dir(dest)?;
+        for item in fs::read_dir(src)? {
+            let item = item?;
+            copy_all(&item.path(), &dest.join(item.file_name()))?;
+        }
+        fs::set_permissions(dest, meta.permissions())?;
+    } else {
+        fs::copy(src, dest)?;
+    }
+    Ok(())
+}
+
+fn remove_all(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Renames the path, copying it if the destination is on another
+/// filesystem
+fn move_path(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    match fs::rename(src, dest) {
+        Ok(()) => Ok(()),
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            log::debug!("Copying {} to another filesystem", src.display());
+            if let Err(e) = copy_all(src, dest) {
+                remove_all(dest).ok();
+                return Err(e.into());
+            }
+            remove_all(src)?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Removes entries which are kept longer than `RETENTION_DAYS`
+fn purge_expired() -> anyhow::Result<()> {
+    let deadline = now().saturating_sub(RETENTION_DAYS * 24 * 3600);
+    for (path, manifest) in read_entries()? {
+        if manifest.destroyed_at < deadline {
+            log::info!("Removing expired trash entry {}", path.display());
+            fs::remove_dir_all(&path)?;
+        }
+    }
+    Ok(())
+}
+
+impl Entry {
+    pub fn new(instance: &str) -> Entry {
+        Entry {
+            dir: None,
+            manifest: Manifest {
+                instance: instance.into(),
+                destroyed_at: now(),
+                items: Vec::new(),
+            },
+        }
+    }
+    /// Moves file or directory into the trash
+    #[context("cannot move {} to trash", path.display())]
+    pub fn keep(&mut self, path: &Path) -> anyhow::Result<()> {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => {
+                purge_expired()
+                    .map_err(|e| log::warn!("Cannot clean trash: {:#}", e))
+                    .ok();
+                let dir = trash_dir()?.join(format!("{}-{}",
+                    self.manifest.destroyed_at, self.manifest.instance));
+                fs::create_dir_all(&dir)?;
+                self.dir = Some(dir.clone());
+                dir
+            }
+        };
+        let stored = format!("{}-{}", self.manifest.items.len(),
+            path.file_name().and_then(|n| n.to_str()).unwrap_or("item"));
+        move_path(path, &dir.join(&stored))?;
+        self.manifest.items.push(Item {
+            original: path.into(),
+            stored,
+        });
+        write_manifest(&dir, &self.manifest)
+    }
+}
+
+fn restore(dir: &Path, manifest: &Manifest) -> anyhow::Result<()> {
+    for item in &manifest.items {
+        if item.original.exists() {
+            anyhow::bail!("cannot restore {}: the path already exists",
+                          item.original.display());
+        }
+    }
+    for item in &manifest.items {
+        if let Some(parent) = item.original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_path(&dir.join(&item.stored), &item.original)
+            .with_context(|| format!("cannot restore {}",
+                                     item.original.display()))?;
+        eprintln!("Restored {}", item.original.display());
+    }
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+fn undo_last_destroy() -> anyhow::Result<()> {
+    let (dir, manifest) = match read_entries()?.pop() {
+        Some(entry) => entry,
+        None => anyhow::bail!("No destroyed instances in the last {} days",
+                              RETENTION_DAYS),
+    };
+    let name = &manifest.instance;
+    restore(&dir, &manifest)?;
+    let metadata = read_metadata(&unix::storage_dir(name)?)
+        .context("cannot read metadata of the restored instance")?;
+    unix::create_user_service(name, &metadata)
+        .map_err(|e| {
+            log::warn!("Cannot create service: {:#}", e);
+            eprintln!("Run `edgedb doctor` to recreate the service \
+                       of the instance");
+        }).ok();
+    eprintln!("Instance {:?} is restored. \
+               Run `edgedb server start {}` to start it.",
+               name, name.escape_default());
+    Ok(())
+}
+
+pub fn undo(cmd: &Undo) -> anyhow::Result<()> {
+    match cmd.subcommand {
+        UndoCommand::LastDestroy => undo_last_destroy(),
+    }
+}
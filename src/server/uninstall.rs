@@ -1,9 +1,23 @@
 use crate::commands::ExitCode;
 use crate::server::detect::{self, VersionQuery};
 use crate::server::options::Uninstall;
-use crate::server::distribution::MajorVersion;
+use crate::server::distribution::{DistributionRef, MajorVersion};
+use crate::server::version::Version;
 
 
+/// Returns true if the distribution is used by an instance of the version
+pub fn is_used_by(distr: &DistributionRef, major: &MajorVersion,
+    exact: Option<&Version<String>>)
+    -> bool
+{
+    match (distr.major_version(), major) {
+        (MajorVersion::Nightly, MajorVersion::Nightly)
+        => Some(distr.version()) == exact,
+        (MajorVersion::Stable(a), MajorVersion::Stable(b)) => a == b,
+        _ => false,
+    }
+}
+
 pub fn uninstall(options: &Uninstall) -> Result<(), anyhow::Error> {
     let os = detect::current_os()?;
     let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
@@ -19,13 +33,7 @@ pub fn uninstall(options: &Uninstall) -> Result<(), anyhow::Error> {
             let major = inst.get_version()?;
             let exact = inst.get_current_version()?;
             candidates.retain(|cand| {
-                let del = match (cand.major_version(), major) {
-                    (MajorVersion::Nightly, MajorVersion::Nightly)
-                    => Some(cand.version()) == exact,
-                    (MajorVersion::Stable(a), MajorVersion::Stable(b))
-                    => a == b,
-                    _ => false,
-                };
+                let del = is_used_by(cand, major, exact);
                 if del && !options.unused {
                     log::warn!("Version {} is used by {:?}",
                         cand.version(), inst.name());
@@ -11,6 +11,8 @@ use linked_hash_map::LinkedHashMap;
 use fn_error_context::context;
 
 use crate::commands::ExitCode;
+use crate::credentials;
+use crate::dry_run;
 use crate::process::ProcessGuard;
 use crate::platform::{Uid, home_dir, get_current_uid};
 use crate::server::control::read_metadata;
@@ -24,10 +26,13 @@ use crate::server::macos;
 use crate::server::metadata::Metadata;
 use crate::server::methods::InstallMethod;
 use crate::server::options::{self, Start, Upgrade, StartConf, Stop};
+use crate::server::options::ServiceManager;
 use crate::server::os_trait::{Method, Instance, InstanceRef};
 use crate::server::package::Package;
+use crate::server::service;
 use crate::server::status::{Service, Status, DataDirectory};
 use crate::server::status::{read_upgrade, backup_status, probe_port};
+use crate::server::trash;
 use crate::server::upgrade;
 use crate::server::version::Version;
 
@@ -97,6 +102,14 @@ impl Unix {
 }
 
 
+pub fn server_path(pkg: &Package) -> PathBuf {
+    if cfg!(target_os="macos") {
+        macos::get_server_path(&pkg.slot)
+    } else {
+        linux::get_server_path(Some(&pkg.slot))
+    }
+}
+
 pub fn bootstrap(method: &dyn Method, settings: &init::Settings)
     -> anyhow::Result<()>
 {
@@ -109,11 +122,7 @@ pub fn bootstrap(method: &dyn Method, settings: &init::Settings)
 
     let pkg = settings.distribution.downcast_ref::<Package>()
         .context("invalid unix package")?;
-    let mut cmd = Command::new(if cfg!(target_os="macos") {
-        macos::get_server_path(&pkg.slot)
-    } else {
-        linux::get_server_path(Some(&pkg.slot))
-    });
+    let mut cmd = Command::new(server_path(pkg));
     cmd.arg("--bootstrap-only");
     cmd.arg("--log-level=warn");
     cmd.arg("--data-dir").arg(&dir);
@@ -154,7 +163,12 @@ pub fn bootstrap(method: &dyn Method, settings: &init::Settings)
                     format!("error running server {:?}", cmd))?;
             init_credentials(&settings, &inst)?;
             drop(child);
-            if settings.start_conf == StartConf::Manual && res.is_ok() {
+            if settings.portable {
+                println!("Bootstrap complete. To run the server:\n  \
+                          edgedb server run {}",
+                          settings.name.escape_default());
+            } else if settings.start_conf == StartConf::Manual && res.is_ok()
+            {
                 println!("Bootstrap complete. To start the server:\n  \
                           edgedb server start {}",
                           settings.name.escape_default());
@@ -165,16 +179,42 @@ pub fn bootstrap(method: &dyn Method, settings: &init::Settings)
     Ok(())
 }
 
-fn create_user_service(name: &str, meta: &Metadata) -> anyhow::Result<()> {
-    if cfg!(target_os="macos") {
+pub fn create_user_service(name: &str, meta: &Metadata) -> anyhow::Result<()> {
+    if meta.portable {
+        log::debug!("Instance {:?} is portable, skipping service", name);
+        Ok(())
+    } else if cfg!(target_os="macos") {
         macos::create_launchctl_service(&name, &meta)
-    } else if cfg!(target_os="linux") {
-        linux::create_systemd_service(&name, &meta)
+    } else if cfg!(target_os="linux") || cfg!(target_os="freebsd") {
+        service::backend(meta.service_manager).create(&name, &meta)
     } else {
         anyhow::bail!("unsupported OS");
     }
 }
 
+/// Keeps addresses configured by `edgedb server set-listen-address`
+fn bind_addresses(name: &str) -> Vec<String> {
+    storage_dir(name)
+        .and_then(|dir| read_metadata(&dir))
+        .map(|meta| meta.bind_addresses)
+        .unwrap_or_default()
+}
+
+/// Keeps instances created with `--no-service` portable after upgrade
+pub fn is_portable(name: &str) -> bool {
+    storage_dir(name)
+        .and_then(|dir| read_metadata(&dir))
+        .map(|meta| meta.portable)
+        .unwrap_or(false)
+}
+
+fn service_manager(name: &str) -> Option<ServiceManager> {
+    storage_dir(name)
+        .and_then(|dir| read_metadata(&dir))
+        .ok()
+        .and_then(|meta| meta.service_manager)
+}
+
 #[context("failed to write upgrade marker {}", path.display())]
 fn write_upgrade(path: &Path, data: &str) -> anyhow::Result<()> {
     fs::write(path, data.as_bytes())?;
@@ -182,7 +222,9 @@ fn write_upgrade(path: &Path, data: &str) -> anyhow::Result<()> {
 }
 
 #[context("failed to write metadata file {}", path.display())]
-fn write_metadata(path: &Path, metadata: &Metadata) -> anyhow::Result<()> {
+pub fn write_metadata(path: &Path, metadata: &Metadata)
+    -> anyhow::Result<()>
+{
     let tmp_path = path.with_extension("tmp");
     fs::remove_file(&tmp_path).ok();
     fs::write(&tmp_path, serde_json::to_vec_pretty(&metadata)?)?;
@@ -196,6 +238,53 @@ pub fn storage_dir(name: &str) -> anyhow::Result<PathBuf> {
         .join("edgedb").join("data").join(name))
 }
 
+/// Moves data directory and credentials file to the trash, or removes
+/// them if `purge` is set, returns `false` if none of them exist
+pub fn remove_instance_files(name: &str, purge: bool)
+    -> anyhow::Result<bool>
+{
+    let mut found = false;
+    let mut trash = trash::Entry::new(name);
+    let dir = storage_dir(name)?;
+    if dir.exists() {
+        found = true;
+        if purge {
+            if dry_run::perform(format_args!("remove data directory {}",
+                                             dir.display())) {
+                log::info!(target: "edgedb::server::destroy",
+                    "Removing data directory {}", dir.display());
+                fs::remove_dir_all(&dir)?;
+            }
+        } else if dry_run::perform(format_args!(
+            "move data directory {} to trash", dir.display()))
+        {
+            log::info!(target: "edgedb::server::destroy",
+                "Moving data directory {} to trash", dir.display());
+            trash.keep(&dir)?;
+        }
+    }
+    let credentials = credentials::path(name)?;
+    if credentials.exists() {
+        found = true;
+        if purge {
+            if dry_run::perform(format_args!("remove credentials file {}",
+                                             credentials.display())) {
+                log::info!(target: "edgedb::server::destroy",
+                    "Removing credentials file {}", credentials.display());
+                fs::remove_file(&credentials)?;
+            }
+        } else if dry_run::perform(format_args!(
+            "move credentials file {} to trash", credentials.display()))
+        {
+            log::info!(target: "edgedb::server::destroy",
+                "Moving credentials file {} to trash",
+                credentials.display());
+            trash.keep(&credentials)?;
+        }
+    }
+    Ok(found)
+}
+
 pub fn storage(system: bool, name: &str) -> anyhow::Result<Storage> {
     assert!(!system);
     Ok(Storage::UserDir(storage_dir(name)?))
@@ -380,6 +469,9 @@ fn do_minor_upgrade(method: &dyn Method, options: &Upgrade)
                 method: method.name(),
                 port: inst.get_port()?,
                 start_conf: inst.get_start_conf()?,
+                bind_addresses: bind_addresses(inst.name()),
+                portable: is_portable(inst.name()),
+                service_manager: service_manager(inst.name()),
             };
             let metapath = storage_dir(inst.name())?.join("metadata.json");
             write_metadata(&metapath, &new_meta)?;
@@ -466,6 +558,9 @@ fn do_nightly_upgrade(method: &dyn Method, options: &Upgrade)
             method: method.name(),
             port: inst.get_port()?,
             start_conf: inst.get_start_conf()?,
+            bind_addresses: bind_addresses(inst.name()),
+            portable: is_portable(inst.name()),
+            service_manager: service_manager(inst.name()),
         };
         upgrade::dump_and_stop(inst.as_ref(), &dump_path)?;
         let upgrade_meta = upgrade::UpgradeMeta {
@@ -629,6 +724,9 @@ fn do_instance_upgrade(method: &dyn Method,
         method: method.name(),
         port: inst.get_port()?,
         start_conf: inst.get_start_conf()?,
+        bind_addresses: bind_addresses(inst.name()),
+        portable: is_portable(inst.name()),
+        service_manager: service_manager(inst.name()),
     };
     upgrade::dump_and_stop(inst.as_ref(), &dump_path)?;
 
@@ -17,6 +17,7 @@ use crate::server::options::{Upgrade, Start, Stop};
 use crate::server::os_trait::{Method, Instance};
 use crate::server::upgrade;
 use crate::server::version::Version;
+use crate::verbosity::Verbosity;
 
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -105,8 +106,12 @@ pub async fn dump_instance(inst: &dyn Instance, destination: &Path,
         command_line: true,
         styler: None,
         conn_params: Connector::new(Ok(conn_params)),
+        verbosity: Verbosity::Normal,
     };
-    commands::dump_all(&mut cli, &options, destination.as_ref()).await?;
+    // upgrade must not proceed with a partial dump
+    commands::dump_all(&mut cli, &options, destination.as_ref(), false,
+                       true, None)
+        .await?;
     Ok(())
 }
 
@@ -125,12 +130,16 @@ pub async fn restore_instance(inst: &dyn Instance,
         command_line: true,
         styler: None,
         conn_params: Connector::new(Ok(conn_params)),
+        verbosity: Verbosity::Normal,
     };
     commands::restore_all(&mut cli, &options, &Restore {
         path: path.into(),
         all: true,
         allow_non_empty: false,
         verbose: false,
+        rename_module: Vec::new(),
+        skip_type: Vec::new(),
+        jobs: 1,
     }).await?;
     Ok(())
 }
@@ -0,0 +1,125 @@
+//! Interactive setup of the command-line tool settings
+//!
+//! Offered when the interactive shell is started and there is no user
+//! config yet. Answers are written into the user's `cli.toml`, which
+//! also marks the setup as done, even if it was skipped.
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context;
+
+use crate::config;
+use crate::print::style::ColorTheme;
+use crate::question;
+use crate::repl::OutputMode;
+use crate::self_install;
+use crate::telemetry;
+
+
+const HEADER: &str = "\
+# Settings of the EdgeDB command-line tool.
+# Run `edgedb config doctor` to see the effective settings.
+";
+
+pub fn is_first_run() -> bool {
+    config::path().map(|path| !path.exists()).unwrap_or(false)
+        && atty::is(atty::Stream::Stdout)
+}
+
+/// Runs the setup on the first start of the shell, failures and
+/// interruptions don't prevent the shell from starting
+pub fn first_run() {
+    if let Err(e) = _first_run() {
+        eprintln!("edgedb error: {:#}", e);
+        eprintln!("Run `edgedb config setup` to finish the setup later.");
+    }
+}
+
+fn _first_run() -> anyhow::Result<()> {
+    println!("Welcome to EdgeDB!");
+    let q = question::Confirm::new(
+        "Would you like to adjust settings of the command-line tool now?");
+    if !q.ask()? {
+        let path = save(&[])?;
+        println!("Default settings are used. \
+                  Run `edgedb config setup` to change them \
+                  or edit {}", path.display());
+        return Ok(());
+    }
+    ask_and_save()
+}
+
+/// Implementation of `edgedb config setup`
+pub fn setup() -> anyhow::Result<()> {
+    ask_and_save()
+}
+
+fn ask_and_save() -> anyhow::Result<()> {
+    let format = question::Numeric::new(
+        "Which output format to use for query results by default?")
+        .option("Human-readable (tables and indented objects)",
+                OutputMode::Default)
+        .option("JSON", OutputMode::Json)
+        .option("JSON, one element per line", OutputMode::JsonElements)
+        .option("Tab-separated", OutputMode::TabSeparated)
+        .ask()?;
+    let theme = question::Numeric::new(
+        "Which color theme suits your terminal?")
+        .option("Dark background", ColorTheme::Dark)
+        .option("Light background", ColorTheme::Light)
+        .ask()?;
+    let version_check = question::Confirm::new(
+        "Check for new versions of the tool and the server once a day?")
+        .ask()?;
+    let telemetry = if question::Confirm::new(
+        "Record anonymous usage statistics locally (names of commands and \
+         classes of errors, never queries or arguments)? \
+         They are never sent anywhere, see `edgedb telemetry status`.")
+        .ask()?
+    {
+        telemetry::Mode::LocalOnly
+    } else {
+        telemetry::Mode::Disabled
+    };
+    let completions = question::Confirm::new(
+        "Install shell completions for bash, zsh and fish?")
+        .ask()?;
+
+    let path = save(&[
+        ("output-format", format.as_str().into()),
+        ("color-theme", theme.as_str().into()),
+        ("version-check", version_check.into()),
+        ("telemetry", telemetry.as_str().into()),
+    ])?;
+    println!("Settings are saved to {}", path.display());
+    if completions {
+        self_install::write_completions_home()?;
+        println!("Shell completions are installed. \
+                  They are enabled in new shell sessions.");
+    }
+    Ok(())
+}
+
+/// Updates settings in the user's `cli.toml` keeping the other ones
+pub fn save(settings: &[(&str, toml::Value)]) -> anyhow::Result<PathBuf> {
+    let path = config::path()?;
+    let mut table = if path.exists() {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {:?}", path))?;
+        toml::from_str(&text)
+            .with_context(|| format!("cannot parse {:?}", path))?
+    } else {
+        toml::value::Table::new()
+    };
+    for (name, value) in settings {
+        table.insert((*name).into(), value.clone());
+    }
+    let text = toml::to_string(&toml::Value::Table(table))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create {:?}", dir))?;
+    }
+    fs::write(&path, format!("{}{}", HEADER, text))
+        .with_context(|| format!("cannot write {:?}", path))?;
+    Ok(path)
+}
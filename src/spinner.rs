@@ -0,0 +1,107 @@
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_std::stream::Stream;
+use async_std::task;
+use colorful::Colorful;
+
+use crate::output_style;
+
+
+const FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const DELAY: Duration = Duration::from_millis(500);
+const TICK: Duration = Duration::from_millis(100);
+
+struct State {
+    stopped: bool,
+    shown: bool,
+}
+
+/// Status line with elapsed time shown on stderr while waiting
+///
+/// Nothing is displayed for fast operations or when stderr is not
+/// a terminal. The line is cleared on `stop()` or on drop. With plain
+/// output style a single line of text is printed instead.
+pub struct Spinner {
+    state: Option<Arc<Mutex<State>>>,
+}
+
+pub struct StopOnData<S> {
+    stream: S,
+    spinner: Spinner,
+}
+
+impl Spinner {
+    pub fn start(message: &'static str) -> Spinner {
+        let plain = output_style::is_plain();
+        if !plain && !output_style::animations(atty::Stream::Stderr) {
+            return Spinner { state: None };
+        }
+        let state = Arc::new(Mutex::new(State {
+            stopped: false,
+            shown: false,
+        }));
+        let task_state = state.clone();
+        let start = Instant::now();
+        task::spawn(async move {
+            task::sleep(DELAY).await;
+            if plain {
+                let state = task_state.lock().expect("not poisoned");
+                if !state.stopped {
+                    eprintln!("{}...", message);
+                }
+                return;
+            }
+            for frame in FRAMES.iter().cycle() {
+                {
+                    let mut state = task_state.lock().expect("not poisoned");
+                    if state.stopped {
+                        break;
+                    }
+                    state.shown = true;
+                    eprint!("\r{}", format!("{} {} {:.1}s",
+                        frame, message, start.elapsed().as_secs_f64())
+                        .dark_gray());
+                    io::stderr().flush().ok();
+                }
+                task::sleep(TICK).await;
+            }
+        });
+        Spinner { state: Some(state) }
+    }
+    pub fn stop(&mut self) {
+        if let Some(state) = self.state.take() {
+            let mut state = state.lock().expect("not poisoned");
+            state.stopped = true;
+            if state.shown {
+                eprint!("\r\x1b[K");
+                io::stderr().flush().ok();
+            }
+        }
+    }
+    /// Stops spinner as soon as the stream yields anything
+    pub fn stop_on_data<S>(self, stream: S) -> StopOnData<S> {
+        StopOnData { stream, spinner: self }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<S: Stream + Unpin> Stream for StopOnData<S> {
+    type Item = S::Item;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>)
+        -> task::Poll<Option<S::Item>>
+    {
+        let res = Pin::new(&mut self.stream).poll_next(cx);
+        if res.is_ready() {
+            self.spinner.stop();
+        }
+        res
+    }
+}
@@ -3,8 +3,13 @@ use prettytable::format::{FormatBuilder, LinePosition, LineSeparator};
 use prettytable::format::{Alignment, TableFormat};
 use prettytable::{Table, Row, Cell, Attr};
 
+use crate::output_style;
+
 
 pub static FORMAT: Lazy<TableFormat> = Lazy::new(|| {
+    if output_style::is_plain() {
+        return FormatBuilder::new().padding(0, 2).build();
+    }
     FormatBuilder::new()
     .column_separator('│')
     .borders('│')
@@ -28,6 +33,9 @@ pub static FORMAT: Lazy<TableFormat> = Lazy::new(|| {
 });
 
 pub fn header_cell(title: &str) -> Cell {
+    if output_style::is_plain() {
+        return Cell::new(title);
+    }
     Cell::new_align(title, Alignment::CENTER)
         .with_style(Attr::Dim)
 }
@@ -0,0 +1,339 @@
+//! Anonymous usage statistics, disabled unless enabled explicitly
+//!
+//! Only names of the built-in commands and classes of errors are counted:
+//! no arguments, query text, instance names or paths are recorded.
+//! Statistics are only stored in a file for the user's own inspection,
+//! nothing is ever sent anywhere.
+//!
+//! The setting is only read from the user and system configuration, a
+//! project can't enable it for everyone who clones the repository.
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, Duration};
+
+use clap::{Clap, IntoApp};
+use edgedb_protocol::error_response::ErrorResponse;
+use fn_error_context::context;
+use prettytable::{Table, Row, Cell};
+use serde::{Serialize, Deserialize};
+
+use crate::aliases;
+use crate::bug;
+use crate::commands::ExitCode;
+use crate::options::{Options, RawOptions};
+use crate::platform::{home_dir, tmp_file_path};
+use crate::server::remote;
+use crate::setup_wizard;
+use crate::table;
+
+
+/// A lock older than this is left by a crashed process
+const STALE_LOCK: Duration = Duration::from_secs(10);
+
+#[derive(Clap, Clone, Debug)]
+pub struct Telemetry {
+    #[clap(subcommand)]
+    pub subcommand: TelemetryCommand,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum TelemetryCommand {
+    /// Show whether statistics are collected, and the statistics recorded
+    /// so far
+    Status,
+    /// Start recording anonymous usage statistics locally
+    Enable,
+    /// Stop recording usage statistics
+    Disable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Disabled,
+    /// Statistics are recorded in a local file
+    LocalOnly,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Counters {
+    commands: BTreeMap<String, u64>,
+    errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Stats {
+    #[serde(with="humantime_serde")]
+    since: SystemTime,
+    total: Counters,
+}
+
+/// Exclusive lock on the statistics file, released on drop
+struct Lock {
+    path: PathBuf,
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Mode> {
+        match s {
+            "disabled" => Ok(Mode::Disabled),
+            "local-only" => Ok(Mode::LocalOnly),
+            _ => anyhow::bail!("Unsupported telemetry mode, \
+                options: `disabled`, `local-only`"),
+        }
+    }
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Disabled => "disabled",
+            Mode::LocalOnly => "local-only",
+        }
+    }
+}
+
+impl Counters {
+    fn add(&mut self, command: &str, error: Option<&str>) {
+        *self.commands.entry(command.into()).or_insert(0) += 1;
+        if let Some(error) = error {
+            *self.errors.entry(error.into()).or_insert(0) += 1;
+        }
+    }
+}
+
+impl Lock {
+    /// Returns `None` if another process holds the lock
+    fn acquire(stats: &Path) -> anyhow::Result<Option<Lock>> {
+        let path = stats.with_extension("lock");
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        for _ in 0..2 {
+            match fs::OpenOptions::new().write(true).create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Some(Lock { path })),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&path)?.modified()?
+                        .elapsed().map(|age| age > STALE_LOCK)
+                        .unwrap_or(false);
+                    if !stale {
+                        return Ok(None);
+                    }
+                    fs::remove_file(&path).ok();
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path)
+            .map_err(|e| log::info!("Cannot remove {}: {}",
+                                    self.path.display(), e))
+            .ok();
+    }
+}
+
+/// Names of the built-in subcommands, skipping all the arguments
+pub fn command_name(args: &[OsString]) -> Vec<String> {
+    let mut app = RawOptions::into_app();
+    let mut names = Vec::new();
+    let mut args = args;
+    while let Some(pos) = aliases::subcommand_pos(&app, args) {
+        let sub = args[pos].to_str()
+            .and_then(|name| {
+                app.get_subcommands().find(|c| c.get_name() == name)
+            })
+            .cloned();
+        match sub {
+            Some(sub) => {
+                names.push(sub.get_name().to_string());
+                app = sub;
+                args = &args[pos..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+fn error_class(err: &anyhow::Error) -> String {
+    for item in err.chain() {
+        if let Some(e) = item.downcast_ref::<ErrorResponse>() {
+            return format!("server-0x{:08x}", e.code);
+        } else if item.is::<bug::Bug>() {
+            return "bug".into();
+        } else if item.is::<ExitCode>() {
+            return "exit-code".into();
+        } else if item.is::<remote::HttpError>() ||
+            item.is::<remote::HttpFailure>()
+        {
+            return "http".into();
+        } else if item.is::<io::Error>() {
+            return "io".into();
+        }
+    }
+    "other".into()
+}
+
+fn stats_path() -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".edgedb").join("telemetry").join("stats.json"))
+}
+
+fn read_stats(path: &Path) -> anyhow::Result<Option<Stats>> {
+    match fs::File::open(path) {
+        Ok(file) => Ok(Some(serde_json::from_reader(file)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[context("error writing {}", path.display())]
+fn write_stats(path: &Path, stats: &Stats) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = tmp_file_path(path);
+    serde_json::to_writer_pretty(fs::File::create(&tmp_path)?, stats)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn _record(command: &str, error: Option<&str>) -> anyhow::Result<()> {
+    let path = stats_path()?;
+    let _lock = match Lock::acquire(&path)? {
+        Some(lock) => lock,
+        None => {
+            // a concurrent command is being recorded, losing a count is
+            // better than delaying the exit
+            log::info!("Statistics are locked, skipping");
+            return Ok(());
+        }
+    };
+    let mut stats = read_stats(&path)
+        .map_err(|e| log::info!("Cannot read {}: {:#}", path.display(), e))
+        .ok().flatten()
+        .unwrap_or_else(|| Stats {
+            since: SystemTime::now(),
+            total: Counters::default(),
+        });
+    stats.total.add(command, error);
+    write_stats(&path, &stats)
+}
+
+/// Name of the command as counted in statistics
+pub fn command(options: &Options) -> String {
+    if options.subcommand.is_some() {
+        options.command_name.join(" ")
+    } else if options.interactive {
+        "shell".into()
+    } else {
+        "query".into()
+    }
+}
+
+/// Counts the command and the class of its error, if statistics are
+/// enabled. Never fails
+pub fn record(mode: Mode, command: &str, result: &anyhow::Result<()>) {
+    if mode == Mode::Disabled {
+        return;
+    }
+    let error = result.as_ref().err().map(error_class);
+    if let Err(e) = _record(command, error.as_deref()) {
+        log::info!("Cannot record usage statistics: {:#}", e);
+    }
+}
+
+fn print_counters(title: &str, counters: &BTreeMap<String, u64>) {
+    let mut table = Table::new();
+    table.set_format(*table::FORMAT);
+    table.set_titles(Row::new([title, "Count"]
+        .iter().map(|x| table::header_cell(x)).collect()));
+    for (name, count) in counters {
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+    table.printstd();
+}
+
+fn status(options: &Options) -> anyhow::Result<()> {
+    let config = &options.config;
+    println!("Telemetry: {} (from {})",
+             config.telemetry.as_str(), config.source("telemetry"));
+    let path = stats_path()?;
+    let stats = match read_stats(&path)? {
+        Some(stats) => stats,
+        None => {
+            println!("No statistics recorded");
+            return Ok(());
+        }
+    };
+    println!("Statistics recorded since {} in {}",
+             humantime::format_rfc3339_seconds(stats.since),
+             path.display());
+    print_counters("Command", &stats.total.commands);
+    if !stats.total.errors.is_empty() {
+        print_counters("Error", &stats.total.errors);
+    }
+    Ok(())
+}
+
+fn set_mode(mode: Mode) -> anyhow::Result<()> {
+    let path = setup_wizard::save(&[("telemetry", mode.as_str().into())])?;
+    println!("Telemetry is {} in {}", mode.as_str(), path.display());
+    Ok(())
+}
+
+pub fn main(options: &Options, cmd: &Telemetry) -> anyhow::Result<()> {
+    match &cmd.subcommand {
+        TelemetryCommand::Status => status(options),
+        TelemetryCommand::Enable => set_mode(Mode::LocalOnly),
+        TelemetryCommand::Disable => {
+            set_mode(Mode::Disabled)?;
+            if let Ok(path) = stats_path() {
+                if path.exists() {
+                    println!("Statistics recorded so far are kept in {}, \
+                              remove the file if they aren't needed",
+                             path.display());
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use super::command_name;
+
+    fn name(args: &[&str]) -> String {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        command_name(&args).join(" ")
+    }
+
+    #[test]
+    fn command_names() {
+        assert_eq!(name(&["edgedb", "create-migration", "--non-interactive"]),
+                   "create-migration");
+        assert_eq!(name(&["edgedb", "project", "init", "--link"]),
+                   "project init");
+        assert_eq!(name(&["edgedb", "-I", "inst", "server", "start", "x"]),
+                   "server start");
+        assert_eq!(name(&["edgedb", "query", "SELECT 1"]), "query");
+        assert_eq!(name(&["edgedb", "--json"]), "");
+        assert_eq!(name(&["edgedb", "my-alias", "migrate"]), "");
+    }
+}
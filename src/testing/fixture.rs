@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use edgedb_protocol::value::Value;
+
+use crate::classify;
+use crate::commands::{self, dump, restore, recreate_database};
+use crate::commands::parser::{Dump, Restore};
+use crate::dry_run;
+use crate::hint::HintExt;
+use crate::options::Options;
+use crate::question;
+use crate::testing::options::{FixtureCommand, FixtureAction, Fixture};
+use crate::testing::options::FixtureReset;
+
+
+// Servers supported by this tool have no copy-on-write database clones,
+// so fixtures are plain dumps
+fn dump_path(fixture: &Fixture) -> anyhow::Result<PathBuf> {
+    let valid = !fixture.name.is_empty() && fixture.name.chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(anyhow::anyhow!("invalid fixture name {:?}",
+                                   fixture.name))
+            .hint("use letters, digits, `_` and `-`")?;
+    }
+    Ok(fixture.dir.join(format!("{}.dump", fixture.name)))
+}
+
+fn command_options(options: &Options) -> commands::Options {
+    commands::Options {
+        command_line: true,
+        styler: None,
+        conn_params: options.conn_params.clone(),
+        verbosity: options.verbosity,
+    }
+}
+
+async fn save(fixture: &Fixture, options: &Options) -> anyhow::Result<()> {
+    let path = dump_path(fixture)?;
+    std::fs::create_dir_all(&fixture.dir)
+        .with_context(|| format!("cannot create {}",
+                                 fixture.dir.display()))?;
+    let mut cli = options.conn_params.connect().await?;
+    dump(&mut cli, &command_options(options), &Dump {
+        path: path.clone(),
+        all: false,
+        parallel: false,
+        format: None,
+        anonymize: None,
+    }).await?;
+    if options.verbosity.show_progress() {
+        eprintln!("Saved fixture {:?} to {}", fixture.name, path.display());
+    }
+    Ok(())
+}
+
+async fn reset(cmd: &FixtureReset, options: &Options)
+    -> anyhow::Result<()>
+{
+    classify::guard_command("test fixture reset")?;
+    let fixture = &cmd.fixture;
+    let path = dump_path(fixture)?;
+    if !path.exists() {
+        return Err(anyhow::anyhow!("fixture {:?} is not found in {}",
+                                   fixture.name, fixture.dir.display()))
+            .hint("save it first with `edgedb test fixture save`")?;
+    }
+    let cmdopt = command_options(options);
+    let mut cli = options.conn_params.connect().await?;
+    let database = cli.query_row::<String>(
+        "SELECT sys::get_current_database()",
+        &Value::empty_tuple()).await?;
+    if !dry_run::perform(format_args!(
+        "drop database {:?} and restore fixture {:?}",
+        database, fixture.name))
+    {
+        return Ok(());
+    }
+    if !cmd.non_interactive {
+        let q = question::Confirm::new_dangerous(format!(
+            "Drop database {:?} and replace it with fixture {:?}?",
+            database, fixture.name));
+        if !q.ask()? {
+            eprintln!("Canceled");
+            return Ok(());
+        }
+    }
+    recreate_database(&cmdopt, cli, &database).await?;
+    let mut cli = options.conn_params.connect().await?;
+    restore(&mut cli, &cmdopt, &Restore {
+        path: path.clone(),
+        all: false,
+        allow_non_empty: false,
+        verbose: false,
+        rename_module: Vec::new(),
+        skip_type: Vec::new(),
+        jobs: 1,
+    }).await?;
+    if options.verbosity.show_progress() {
+        eprintln!("Database {:?} is reset to fixture {:?}",
+                  database, fixture.name);
+    }
+    Ok(())
+}
+
+pub async fn run(cmd: &FixtureCommand, options: &Options)
+    -> anyhow::Result<()>
+{
+    use FixtureAction::*;
+
+    match &cmd.subcommand {
+        Save(c) => save(c, options).await,
+        Reset(c) => reset(c, options).await,
+    }
+}
@@ -0,0 +1,16 @@
+use async_std::task;
+
+use crate::options::Options;
+use crate::testing::options::{TestCommand, Command};
+use crate::testing::fixture;
+use crate::testing::snapshots;
+
+
+pub fn main(cmd: &TestCommand, options: &Options) -> anyhow::Result<()> {
+    use Command::*;
+
+    match &cmd.subcommand {
+        Snapshots(c) => task::block_on(snapshots::run(c, options)),
+        Fixture(c) => task::block_on(fixture::run(c, options)),
+    }
+}
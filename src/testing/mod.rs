@@ -0,0 +1,7 @@
+pub mod options;
+
+mod fixture;
+mod main;
+mod snapshots;
+
+pub use main::main;
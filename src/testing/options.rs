@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use clap::{Clap, AppSettings, ValueHint};
+
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct TestCommand {
+    #[clap(subcommand)]
+    pub subcommand: Command,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum Command {
+    /// Run queries from a directory and compare output to saved snapshots
+    Snapshots(Snapshots),
+    /// Save the database and restore it between test runs
+    Fixture(FixtureCommand),
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Snapshots {
+    /// Directory containing `*.edgeql` files. Snapshot of the output of
+    /// each file is stored next to it with the `.snap` extension.
+    #[clap(value_hint=ValueHint::DirPath, default_value="./dbschema/tests")]
+    pub dir: PathBuf,
+
+    /// Write current output to snapshot files instead of comparing
+    #[clap(long)]
+    pub accept: bool,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct FixtureCommand {
+    #[clap(subcommand)]
+    pub subcommand: FixtureAction,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum FixtureAction {
+    /// Save schema and data of the current database
+    Save(Fixture),
+    /// Replace the current database with the saved schema and data
+    Reset(FixtureReset),
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct Fixture {
+    /// Name of the fixture
+    pub name: String,
+
+    /// Directory where fixtures are stored as dump files
+    #[clap(long, value_hint=ValueHint::DirPath,
+           default_value="./dbschema/fixtures")]
+    pub dir: PathBuf,
+}
+
+#[derive(Clap, Debug, Clone)]
+#[clap(setting=AppSettings::DisableVersionFlag)]
+pub struct FixtureReset {
+    #[clap(flatten)]
+    pub fixture: Fixture,
+
+    /// Do not ask for confirmation before dropping the database
+    #[clap(long)]
+    pub non_interactive: bool,
+}
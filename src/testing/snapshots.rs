@@ -0,0 +1,212 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use colorful::Colorful;
+use edgedb_client::client::Connection;
+use edgedb_client::errors::NoResultExpected;
+use edgedb_client::reader::ReadError;
+use edgedb_protocol::server_message::{ErrorResponse, TransactionState};
+use edgedb_protocol::value::Value;
+use edgeql_parser::preparser::{self, full_statement};
+
+use crate::commands::ExitCode;
+use crate::hint::HintExt;
+use crate::options::Options;
+use crate::print::{self, PrintError};
+use crate::testing::options::Snapshots;
+
+
+const SNAPSHOT_EXT: &str = "snap";
+
+enum Outcome {
+    Passed,
+    Failed,
+    Missing,
+    Written,
+}
+
+fn find_query_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    for item in fs::read_dir(dir)
+        .with_context(|| format!("cannot read directory {:?}", dir))?
+    {
+        let path = item?.path();
+        if path.extension().map(|x| x == "edgeql").unwrap_or(false) {
+            result.push(path);
+        }
+    }
+    result.sort();
+    Ok(result)
+}
+
+fn statements(text: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut tail = text;
+    while !preparser::is_empty(tail) {
+        let len = full_statement(tail.as_bytes(), None)
+            .unwrap_or(tail.len());
+        let stmt = &tail[..len];
+        tail = &tail[len..];
+        if !preparser::is_empty(stmt) {
+            result.push(stmt.trim());
+        }
+    }
+    return result;
+}
+
+async fn run_statement(cli: &mut Connection, stmt: &str, cfg: &print::Config)
+    -> anyhow::Result<String>
+{
+    let items = match cli.query_dynamic(stmt, &Value::empty_tuple()).await {
+        Ok(items) => items,
+        Err(e) => {
+            if let Some(e) = e.downcast_ref::<NoResultExpected>() {
+                return Ok(format!("OK: {}\n",
+                    String::from_utf8_lossy(&e.completion_message)));
+            }
+            if let Some(e) = e.downcast_ref::<ErrorResponse>() {
+                return Ok(format!("ERROR: {}\n", e.display(false)));
+            }
+            return Err(e);
+        }
+    };
+    match print::native_to_string(items, cfg).await {
+        Ok(text) => Ok(text + "\n"),
+        Err(PrintError::StreamErr {
+            source: ReadError::RequestError { ref error, .. },
+            ..
+        }) => Ok(format!("ERROR: {}\n", error)),
+        Err(e) => anyhow::bail!("cannot format query result: {:#}", e),
+    }
+}
+
+/// Runs each statement in a savepoint, so that an error doesn't abort
+/// the following ones
+async fn run_statements(cli: &mut Connection, text: &str,
+    cfg: &print::Config)
+    -> anyhow::Result<String>
+{
+    let mut output = String::with_capacity(text.len() * 2);
+    for stmt in statements(text) {
+        for line in stmt.lines() {
+            output.push_str("> ");
+            output.push_str(line);
+            output.push('\n');
+        }
+        cli.execute("DECLARE SAVEPOINT snapshot_statement").await?;
+        output.push_str(&run_statement(cli, stmt, cfg).await?);
+        if matches!(cli.transaction_state(),
+                    TransactionState::InFailedTransaction)
+        {
+            cli.execute("ROLLBACK TO SAVEPOINT snapshot_statement").await?;
+        } else {
+            cli.execute("RELEASE SAVEPOINT snapshot_statement").await?;
+        }
+    }
+    Ok(output)
+}
+
+/// Runs all statements of the file in a transaction that is rolled back,
+/// so snapshot tests don't modify the database
+async fn run_file(cli: &mut Connection, path: &Path) -> anyhow::Result<String>
+{
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("cannot read {:?}", path))?;
+    let mut cfg = print::Config::new();
+    cfg.colors(false);
+    cfg.max_width(80);
+
+    cli.execute("START TRANSACTION").await?;
+    let result = run_statements(cli, &text, &cfg).await;
+    // the transaction is rolled back on errors too, unless the connection
+    // is lost, in which case the server drops it
+    if cli.is_consistent() {
+        cli.execute("ROLLBACK").await?;
+    }
+    result
+}
+
+fn print_mismatch(expected: &str, actual: &str) {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    let prefix = expected.iter().zip(&actual)
+        .take_while(|(a, b)| a == b).count();
+    let suffix = expected[prefix..].iter().rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b).count();
+    for line in &expected[prefix..expected.len()-suffix] {
+        eprintln!("{}", format!("- {}", line).red());
+    }
+    for line in &actual[prefix..actual.len()-suffix] {
+        eprintln!("{}", format!("+ {}", line).green());
+    }
+}
+
+async fn check_file(cli: &mut Connection, path: &Path, accept: bool)
+    -> anyhow::Result<Outcome>
+{
+    let output = run_file(cli, path).await?;
+    let snapshot = path.with_extension(SNAPSHOT_EXT);
+    let expected = match fs::read_to_string(&snapshot) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => Err(e).with_context(|| {
+            format!("cannot read snapshot {:?}", snapshot)
+        })?,
+    };
+    if expected.as_ref() == Some(&output) {
+        return Ok(Outcome::Passed);
+    }
+    if accept {
+        fs::write(&snapshot, &output)
+            .with_context(|| format!("cannot write {:?}", snapshot))?;
+        return Ok(Outcome::Written);
+    }
+    match expected {
+        Some(expected) => {
+            print_mismatch(&expected, &output);
+            Ok(Outcome::Failed)
+        }
+        None => Ok(Outcome::Missing),
+    }
+}
+
+pub async fn run(cmd: &Snapshots, options: &Options) -> anyhow::Result<()> {
+    let files = find_query_files(&cmd.dir)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("no `*.edgeql` files found in {:?}",
+                                   cmd.dir))
+            .hint("put queries to test into `*.edgeql` files")?;
+    }
+    let mut cli = options.conn_params.connect().await?;
+    let mut passed = 0;
+    let mut failed = 0;
+    let mut written = 0;
+    for path in &files {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        match check_file(&mut cli, path, cmd.accept).await? {
+            Outcome::Passed => {
+                passed += 1;
+            }
+            Outcome::Written => {
+                eprintln!("{:?}: snapshot updated", name);
+                written += 1;
+            }
+            Outcome::Failed => {
+                eprintln!("{:?}: {}", name, "FAILED".red());
+                failed += 1;
+            }
+            Outcome::Missing => {
+                eprintln!("{:?}: {} (use `--accept` to create it)",
+                          name, "no snapshot".red());
+                failed += 1;
+            }
+        }
+    }
+    eprintln!("{} passed, {} failed, {} updated", passed, failed, written);
+    if failed > 0 {
+        return Err(ExitCode::new(1))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,142 @@
+//! Suggestions for mistyped names of commands and instances
+//!
+//! When both stdin and stdout are terminals the user is asked whether the
+//! closest valid name should be used instead. Otherwise names are only
+//! suggested in the error message.
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use clap::{AppSettings, IntoApp};
+
+use crate::aliases;
+use crate::credentials;
+use crate::hint::HintExt;
+use crate::options::RawOptions;
+use crate::question;
+
+
+/// Closest candidate by edit distance, if it's close enough to be a typo
+pub fn closest<'x>(name: &str, candidates: impl Iterator<Item=&'x str>)
+    -> Option<&'x str>
+{
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|c| (strsim::damerau_levenshtein(
+            &name.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c)
+}
+
+fn confirm(question: String) -> bool {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+        return false;
+    }
+    question::Confirm::new(question).ask()
+        .map_err(|e| log::debug!("Cannot ask for confirmation: {:#}", e))
+        .unwrap_or(false)
+}
+
+/// Replaces unknown subcommands by the closest known ones if the user
+/// confirms, unconfirmed ones are left to be reported by the parser
+///
+/// `aliases` are only considered for the top-level command.
+pub fn fix_command(mut args: Vec<OsString>,
+                   aliases: &BTreeMap<String, String>)
+    -> Vec<OsString>
+{
+    let mut app = RawOptions::into_app();
+    let mut command = String::from("edgedb");
+    let mut start = 0;
+    while let Some(pos) = aliases::subcommand_pos(&app, &args[start..]) {
+        let pos = start + pos;
+        let name = match args[pos].to_str() {
+            Some(name) => name.to_string(),
+            None => break,
+        };
+        let mut sub = app.get_subcommands()
+            .find(|c| c.get_name() == name)
+            .cloned();
+        if sub.is_none() {
+            if start == 0 && aliases.contains_key(&name) {
+                break;
+            }
+            let mut candidates = app.get_subcommands()
+                .filter(|c| !c.is_set(AppSettings::Hidden))
+                .map(|c| c.get_name())
+                .collect::<Vec<_>>();
+            if start == 0 {
+                candidates.extend(aliases.keys().map(|k| &k[..]));
+            }
+            let found = match closest(&name, candidates.into_iter()) {
+                Some(found) => found.to_string(),
+                None => break,
+            };
+            if !confirm(format!("Unknown command `{} {}`, \
+                                 run `{} {}` instead?",
+                                command, name, command, found)) {
+                break;
+            }
+            args[pos] = found.clone().into();
+            sub = app.get_subcommands()
+                .find(|c| c.get_name() == found)
+                .cloned();
+        }
+        // alias is expanded later
+        let sub = match sub {
+            Some(sub) => sub,
+            None => break,
+        };
+        command = format!("{} {}", command, sub.get_name());
+        app = sub;
+        start = pos;
+    }
+    args
+}
+
+/// Known instance with the name closest to `name`
+pub fn similar_instance(name: &str) -> Option<String> {
+    let names = credentials::all_instance_names()
+        .map_err(|e| log::debug!("Cannot list instances: {:#}", e))
+        .ok()?;
+    closest(name, names.iter().map(|n| &n[..])).map(|n| n.to_string())
+}
+
+/// Name of the instance to connect to: `name` itself if there are
+/// credentials for it, or the closest known name confirmed by the user
+pub fn instance(name: &str) -> anyhow::Result<String> {
+    if credentials::path(name)?.exists() {
+        return Ok(name.into());
+    }
+    let found = match similar_instance(name) {
+        Some(found) => found,
+        // missing credentials are reported when reading them
+        None => return Ok(name.into()),
+    };
+    if confirm(format!("Unknown instance `{}`, use `{}` instead?",
+                       name, found)) {
+        return Ok(found);
+    }
+    Err(anyhow::anyhow!("unknown instance {:?}", name))
+        .with_hint(|| format!("did you mean `{}`?", found))
+        .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod test {
+    use super::closest;
+
+    fn find(name: &str) -> Option<&'static str> {
+        closest(name, ["migrate", "migration", "dump", "restore"]
+            .iter().copied())
+    }
+
+    #[test]
+    fn closest_names() {
+        assert_eq!(find("migarte"), Some("migrate"));
+        assert_eq!(find("migratoin"), Some("migration"));
+        assert_eq!(find("DUMP"), Some("dump"));
+        assert_eq!(find("dmp"), Some("dump"));
+        assert_eq!(find("list"), None);
+    }
+}
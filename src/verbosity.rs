@@ -0,0 +1,65 @@
+//! How much the commands print besides their data
+//!
+//! `-q` hides spinners, progress bars, status messages and hints, while
+//! data and errors are still printed. `-v` adds details, including info
+//! messages of the logger. The policy is passed to commands in their
+//! options, commands with their own `--quiet` or `--verbose` flags
+//! combine them with the global one using `with_flags()`.
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl Default for Verbosity {
+    fn default() -> Verbosity {
+        Verbosity::Normal
+    }
+}
+
+impl Verbosity {
+    pub fn new(quiet: bool, verbose: bool) -> Verbosity {
+        Verbosity::Normal.with_flags(quiet, verbose)
+    }
+    /// Applies flags of the command, which take precedence
+    pub fn with_flags(self, quiet: bool, verbose: bool) -> Verbosity {
+        if quiet {
+            Verbosity::Quiet
+        } else if verbose {
+            Verbosity::Verbose
+        } else {
+            self
+        }
+    }
+    /// Spinners, progress bars and messages about what was done
+    pub fn show_progress(self) -> bool {
+        self > Verbosity::Quiet
+    }
+    /// Suggestions which are not part of an error
+    pub fn show_hints(self) -> bool {
+        self > Verbosity::Quiet
+    }
+    /// Additional details of the operation
+    pub fn show_details(self) -> bool {
+        self == Verbosity::Verbose
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Verbosity;
+
+    #[test]
+    fn command_flags() {
+        let quiet = Verbosity::new(true, false);
+        assert!(!quiet.show_progress());
+        assert!(quiet.with_flags(false, true).show_details());
+        assert_eq!(Verbosity::new(false, true).with_flags(true, false),
+                   Verbosity::Quiet);
+        assert_eq!(Verbosity::new(false, false).with_flags(false, false),
+                   Verbosity::Normal);
+    }
+}
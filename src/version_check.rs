@@ -1,17 +1,36 @@
-use std::env;
+//! Checks for newer versions of the tool and of the EdgeDB server
+//!
+//! The check is opt-in (`version-check = true` in `cli.toml`). Remote
+//! indexes are fetched in a background thread, and notices are printed
+//! from the cached data, so the check never delays the command being run.
+//! The cache expires a day after a successful fetch; if the fetch fails or
+//! the process exits before it's done, the next command retries it.
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::thread;
 use std::time::{SystemTime, Duration};
 
+use clap::Clap;
 use fn_error_context::context;
 use rand::{thread_rng, Rng};
 use serde::{Serialize, Deserialize};
 
-use crate::platform::home_dir;
+use crate::platform::{home_dir, tmp_file_path};
+use crate::project;
+use crate::server::detect;
+use crate::server::distribution::MajorVersion;
 use crate::server::version::Version;
 use crate::self_upgrade;
 
 
+#[derive(Clap, Clone, Debug)]
+pub struct ShowVersion {
+    /// Compare to the latest released versions of the tool and of the
+    /// server used by the current project
+    #[clap(long)]
+    pub check: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Cache {
     #[serde(with="humantime_serde")]
@@ -19,26 +38,54 @@ struct Cache {
     #[serde(with="humantime_serde")]
     expires: SystemTime,
     version: Option<Version<String>>,
+    /// Latest stable major version of the server
+    #[serde(default)]
+    server_version: Option<Version<String>>,
 }
 
-fn cache_age() -> Duration {
-    Duration::from_secs(thread_rng().gen_range(16*3600..32*3600))
+#[derive(Debug, Default)]
+struct Latest {
+    cli: Option<Version<String>>,
+    server: Option<Version<String>>,
 }
 
-fn negative_cache_age() -> Duration {
-    Duration::from_secs(thread_rng().gen_range(6*3600..12*3600))
+fn cache_age() -> Duration {
+    Duration::from_secs(thread_rng().gen_range(24*3600..32*3600))
 }
 
+fn cache_path(dir: &Path) -> PathBuf {
+    dir.join("version_check.json")
+}
 
 fn read_cache(dir: &Path) -> anyhow::Result<Cache> {
-    let file = fs::File::open(dir.join("version_check.json"))?;
+    let file = fs::File::open(cache_path(dir))?;
     Ok(serde_json::from_reader(file)?)
 }
 
 #[context("error writing {}/version_check.json", dir.display())]
 fn write_cache(dir: &Path, data: &Cache) -> anyhow::Result<()> {
-    let file = fs::File::create(dir.join("version_check.json"))?;
-    Ok(serde_json::to_writer_pretty(file, data)?)
+    // the background thread may be interrupted by the exit of the process
+    let path = cache_path(dir);
+    let tmp_path = tmp_file_path(&path);
+    let file = fs::File::create(&tmp_path)?;
+    serde_json::to_writer_pretty(file, data)?;
+    fs::rename(&tmp_path, &path)?;
+    Ok(())
+}
+
+fn current_version() -> Version<String> {
+    Version(env!("CARGO_PKG_VERSION").into())
+}
+
+fn project_server_version() -> anyhow::Result<Option<Version<String>>> {
+    let dir = match project::project_dir_opt(None)? {
+        Some(dir) => dir,
+        None => return Ok(None),
+    };
+    let config = project::config::read(&dir.join("edgedb.toml"))?;
+    Ok(config.edgedb.server_version.as_ref()
+        .and_then(|v| v.as_stable())
+        .cloned())
 }
 
 fn newer_warning(ver: &Version<String>) {
@@ -54,51 +101,112 @@ fn newer_warning(ver: &Version<String>) {
     }
 }
 
-fn _check(cache_dir: &Path) -> anyhow::Result<()> {
-    match read_cache(cache_dir) {
-        Ok(cache) if cache.expires > SystemTime::now() => {
-            log::debug!("Cached version {:?}", cache.version);
-            if let Some(ver) = cache.version {
-                if Version(env!("CARGO_PKG_VERSION").into()) < ver {
-                    newer_warning(&ver);
+fn newer_server_warning(ver: &Version<String>, project: &Version<String>) {
+    log::warn!(
+        "EdgeDB {} is available (the project uses {}). \
+            Run `edgedb server list-versions` for details",
+        ver.num(), project.num());
+}
+
+fn notify(cache: &Cache) {
+    if let Some(ver) = &cache.version {
+        if current_version() < *ver {
+            newer_warning(ver);
+            // a single notice per command
+            return;
+        }
+    }
+    if let Some(ver) = &cache.server_version {
+        match project_server_version() {
+            Ok(Some(project)) if project < *ver => {
+                newer_server_warning(ver, &project);
+            }
+            Ok(_) => {}
+            Err(e) => log::debug!("Cannot read project version: {:#}", e),
+        }
+    }
+}
+
+fn latest_server() -> anyhow::Result<Option<Version<String>>> {
+    let os = detect::current_os()?;
+    let methods = os.get_available_methods()?.instantiate_all(&*os, true)?;
+    let mut max = None;
+    for method in methods.values() {
+        for distr in method.all_versions(false)? {
+            if let MajorVersion::Stable(v) = distr.major_version() {
+                if max.as_ref().map(|m| m < v).unwrap_or(true) {
+                    max = Some(v.clone());
                 }
             }
-            return Ok(());
         }
-        Ok(_) => {},
-        Err(e) => {
-            log::debug!("Error reading cache: {}", e);
+    }
+    Ok(max)
+}
+
+fn fetch(max_wait: Duration) -> Latest {
+    let mut latest = Latest::default();
+    match self_upgrade::get_repo(max_wait) {
+        Ok(repo) => {
+            latest.cli = repo.packages.iter()
+                .filter(|pkg| pkg.basename == "edgedb-cli")
+                .map(|pkg| &pkg.version)
+                .max()
+                .cloned();
         }
+        Err(e) => log::info!("Error while checking for updates: {}", e),
+    }
+    match latest_server() {
+        Ok(ver) => latest.server = ver,
+        Err(e) => log::info!("Error while checking server versions: {}", e),
+    }
+    log::debug!("Remote versions {:?}", latest);
+    latest
+}
+
+fn refresh(cache_dir: &Path, cache: Cache) -> anyhow::Result<()> {
+    let latest = fetch(Duration::from_secs(10));
+    if latest.cli.is_none() && latest.server.is_none() {
+        anyhow::bail!("no versions fetched");
     }
+    // versions which failed to fetch are kept from the previous check
     let timestamp = SystemTime::now();
-    let repo = match self_upgrade::get_repo(Duration::from_secs(1)) {
-        Ok(repo) => repo,
+    write_cache(cache_dir, &Cache {
+        timestamp,
+        expires: timestamp + cache_age(),
+        version: latest.cli.or(cache.version),
+        server_version: latest.server.or(cache.server_version),
+    })
+}
+
+fn _check(cache_dir: &Path) -> anyhow::Result<()> {
+    let cache = match read_cache(cache_dir) {
+        Ok(cache) => {
+            log::debug!("Cached versions {:?} {:?}",
+                        cache.version, cache.server_version);
+            notify(&cache);
+            if cache.expires > SystemTime::now() {
+                return Ok(());
+            }
+            cache
+        }
         Err(e) => {
-            log::info!("Error while checking for updates: {}", e);
-            write_cache(cache_dir, &Cache {
-                timestamp,
-                expires: timestamp + negative_cache_age(),
+            log::debug!("Error reading cache: {}", e);
+            Cache {
+                timestamp: SystemTime::now(),
+                expires: SystemTime::now(),
                 version: None,
-            })?;
-            return Ok(());
+                server_version: None,
+            }
         }
     };
-    let max = repo.packages.iter()
-        .filter(|pkg| pkg.basename == "edgedb-cli")
-        .map(|pkg| &pkg.version)
-        .max();
-    if let Some(ver) = &max {
-        if Version(env!("CARGO_PKG_VERSION").into()) < **ver {
-            newer_warning(&ver);
+    // The expiry is only advanced by a successful refresh. Stale versions
+    // are kept until then.
+    let cache_dir = cache_dir.to_path_buf();
+    thread::spawn(move || {
+        if let Err(e) = refresh(&cache_dir, cache) {
+            log::info!("Cannot check for updates: {:#}", e);
         }
-    }
-    log::debug!("Remote version {:?}", max);
-    write_cache(cache_dir, &Cache {
-        timestamp,
-        expires: timestamp +
-            if max.is_some() { cache_age() } else { negative_cache_age() },
-        version: max.cloned(),
-    })?;
+    });
     Ok(())
 }
 
@@ -108,15 +216,12 @@ fn cache_dir() -> anyhow::Result<PathBuf> {
     Ok(dir)
 }
 
-pub fn check(no_version_check_opt: bool) {
-    if no_version_check_opt {
-        log::debug!("Skipping version check due to --no-version-check");
-        return;
-    }
-    if env::var_os("EDGEDB_NO_VERSION_CHECK")
-        .map(|x| !x.is_empty()).unwrap_or(false)
-    {
-        log::debug!("Skipping version check due to EDGEDB_NO_VERSION_CHECK");
+/// `version-check` setting, `--no-version-check` and
+/// `EDGEDB_NO_VERSION_CHECK` are merged into `no_version_check` by the
+/// config layers
+pub fn check(no_version_check: bool) {
+    if no_version_check {
+        log::debug!("Skipping version check as configured");
         return;
     }
     let dir = match cache_dir() {
@@ -133,3 +238,55 @@ pub fn check(no_version_check_opt: bool) {
         }
     }
 }
+
+/// Implementation of `edgedb version`
+pub fn show_version(options: &ShowVersion) -> anyhow::Result<()> {
+    if !options.check {
+        println!("EdgeDB CLI {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let latest = fetch(Duration::from_secs(30));
+    // explicit check updates the cache used for the notices too
+    if let Ok(dir) = cache_dir() {
+        let timestamp = SystemTime::now();
+        write_cache(&dir, &Cache {
+            timestamp,
+            expires: timestamp + cache_age(),
+            version: latest.cli.clone(),
+            server_version: latest.server.clone(),
+        }).map_err(|e| log::info!("{:#}", e)).ok();
+    }
+
+    let current = current_version();
+    match &latest.cli {
+        Some(ver) if current < *ver => {
+            println!("EdgeDB CLI {} (newer version {} exists)",
+                     current, ver);
+            if self_upgrade::can_upgrade() {
+                println!("  To upgrade run `edgedb self-upgrade`");
+            }
+        }
+        Some(_) => println!("EdgeDB CLI {} (up to date)", current),
+        None => println!("EdgeDB CLI {} (cannot fetch latest version)",
+                         current),
+    }
+    match project_server_version()? {
+        Some(project) => match &latest.server {
+            Some(ver) if project < *ver => {
+                println!("Project server {} (EdgeDB {} is available)",
+                         project.num(), ver.num());
+            }
+            Some(_) => println!("Project server {} (up to date)",
+                                project.num()),
+            None => println!("Project server {} \
+                              (cannot fetch latest version)",
+                             project.num()),
+        },
+        None => {
+            if let Some(ver) = &latest.server {
+                println!("Latest server: EdgeDB {}", ver.num());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Warnings and notices sent by the server along with the results
+//!
+//! Deprecations, implicit casts and similar messages don't fail the
+//! query. They are printed to stderr, hidden or turned into errors
+//! depending on `--warnings`.
+use std::str::FromStr;
+
+use colorful::Colorful;
+use edgedb_protocol::server_message::{LogMessage, MessageSeverity};
+use once_cell::sync::OnceCell;
+
+use crate::log_levels;
+use crate::output_style;
+
+
+static POLICY: OnceCell<Policy> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    Show,
+    Hide,
+    /// Warnings fail the statement, notices are shown
+    Error,
+}
+
+/// Warning escalated to an error by `--warnings=error`
+#[derive(Debug, thiserror::Error)]
+#[error("server warning: {0}")]
+pub struct ServerWarning(pub String);
+
+impl FromStr for Policy {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Policy> {
+        match s {
+            "show" => Ok(Policy::Show),
+            "hide" => Ok(Policy::Hide),
+            "error" => Ok(Policy::Error),
+            _ => anyhow::bail!("Unsupported warnings policy, \
+                options: `show`, `hide`, `error`"),
+        }
+    }
+}
+
+pub fn init(policy: Policy) {
+    POLICY.set(policy).ok();
+}
+
+fn policy() -> Policy {
+    POLICY.get().copied().unwrap_or(Policy::Show)
+}
+
+fn label(severity: &MessageSeverity) -> Option<&'static str> {
+    match severity {
+        MessageSeverity::Warning => Some("warning"),
+        MessageSeverity::Notice => Some("notice"),
+        MessageSeverity::Info => Some("info"),
+        // debug and unknown messages are only useful with `RUST_LOG`
+        _ => None,
+    }
+}
+
+fn print(label: &str, msg: &LogMessage) {
+    let prefix = format!("server {}:", label);
+    let prefix = if !output_style::colors(atty::Stream::Stderr) {
+        prefix
+    } else if label == "warning" {
+        prefix.yellow().bold().to_string()
+    } else {
+        prefix.blue().to_string()
+    };
+    eprintln!("{} {}", prefix, msg.text);
+}
+
+/// Reports the message according to the policy, returns error only for
+/// warnings with `--warnings=error`
+pub fn handle(msg: &LogMessage) -> Result<(), ServerWarning> {
+    log::debug!("Server message {:?} (code 0x{:08x}): {}",
+                msg.severity, msg.code, msg.text);
+    let label = match label(&msg.severity) {
+        Some(label) => label,
+        None => return Ok(()),
+    };
+    let is_warning = matches!(msg.severity, MessageSeverity::Warning);
+    match policy() {
+        Policy::Hide => Ok(()),
+        Policy::Error if is_warning => Err(ServerWarning(msg.text.clone())),
+        Policy::Show | Policy::Error => {
+            if is_warning {
+                log_levels::count_warning();
+            }
+            print(label, msg);
+            Ok(())
+        }
+    }
+}
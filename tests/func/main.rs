@@ -22,6 +22,8 @@ mod configure;
 mod non_interactive;
 #[cfg(not(windows))]
 mod migrations;
+#[cfg(not(windows))]
+mod snapshots;
 
 // for some reason rexpect doesn't work on macos
 // and also something wrong on musl libc
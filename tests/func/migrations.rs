@@ -325,7 +325,7 @@ fn modified2_interactive() -> anyhow::Result<()> {
         cmd.arg("create-migration");
         cmd.arg("--schema-dir=tests/migrations/db1/modified2");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("y").unwrap();
     cmd.exp_string("Created \
         tests/migrations/db1/modified2/migrations/00002.edgeql, \
@@ -376,15 +376,15 @@ fn modified3_interactive() -> anyhow::Result<()> {
         cmd.arg("create-migration");
         cmd.arg("--schema-dir=tests/migrations/db1/modified3");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("back").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
     cmd.exp_string("Created").unwrap();
 
@@ -419,9 +419,9 @@ fn prompt_id() -> anyhow::Result<()> {
         cmd.arg("create-migration");
         cmd.arg("--schema-dir=tests/migrations/db2");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
     // on pre-prompt_id version this would require an extra prompt
     cmd.exp_string("extra DDL statements").unwrap();
@@ -450,7 +450,7 @@ fn input_required() -> anyhow::Result<()> {
         cmd.arg("create-migration");
         cmd.arg("--schema-dir=tests/migrations/db3");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
     cmd.exp_string("cast_expr>").unwrap();
     cmd.send_line(".foo[IS Child2]").unwrap();
@@ -462,7 +462,7 @@ fn input_required() -> anyhow::Result<()> {
         cmd.arg("create-migration");
         cmd.arg("--schema-dir=tests/migrations/db3");
     });
-    cmd.exp_string("[y,n,l,c,b,s,q,?]").unwrap();
+    cmd.exp_string("[y,n,l,c,a,b,s,d,q,?]").unwrap();
     cmd.send_line("yes").unwrap();
     cmd.exp_string("cast_expr>").unwrap();
     cmd.send_line(".foo[IS Child2] # comment").unwrap();
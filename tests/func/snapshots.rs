@@ -0,0 +1,41 @@
+use std::fs;
+
+use crate::SERVER;
+
+
+#[test]
+fn error_in_the_middle() {
+    let dir = "./tmp/snapshots_01";
+    fs::create_dir_all(dir).expect("can create directory");
+    fs::write(format!("{}/errors.edgeql", dir),
+              "SELECT 1;\nSELECT <int64>'x';\nSELECT 2;\n")
+        .expect("can write query file");
+    SERVER.admin_cmd().arg("test").arg("snapshots").arg(dir).arg("--accept")
+        .assert().success();
+    let snapshot = fs::read_to_string(format!("{}/errors.snap", dir))
+        .expect("snapshot is written");
+    assert!(snapshot.contains("> SELECT <int64>'x';\nERROR: "), "{}",
+            snapshot);
+    assert!(snapshot.ends_with("> SELECT 2;\n{2}\n"), "{}", snapshot);
+    SERVER.admin_cmd().arg("test").arg("snapshots").arg(dir)
+        .assert().success();
+}
+
+#[test]
+fn rolled_back() {
+    SERVER.admin_cmd().arg("create-database").arg("snapshots_02")
+        .assert().success();
+    let dir = "./tmp/snapshots_02";
+    fs::create_dir_all(dir).expect("can create directory");
+    fs::write(format!("{}/ddl.edgeql", dir),
+              "CREATE TYPE Snapshot;\nSELECT <int64>'x';\nINSERT Snapshot;\n")
+        .expect("can write query file");
+    SERVER.database_cmd("snapshots_02")
+        .arg("test").arg("snapshots").arg(dir).arg("--accept")
+        .assert().success();
+    SERVER.database_cmd("snapshots_02").arg("query")
+        .arg("SELECT count((SELECT schema::ObjectType \
+              FILTER .name = 'default::Snapshot'))")
+        .assert().success()
+        .stdout("0\n");
+}